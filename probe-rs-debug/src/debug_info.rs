@@ -1,6 +1,6 @@
 use super::{
     DebugError, DebugRegisters, StackFrame, VariableCache,
-    exception_handling::ExceptionInterface,
+    exception_handling::{ExceptionInterface, sparc},
     function_die::{Die, FunctionDie},
     get_object_reference,
     unit_info::UnitInfo,
@@ -13,8 +13,8 @@ use gimli::{
 };
 use object::read::{Object, ObjectSection};
 use probe_rs::{
-    CoreRegister, Error, InstructionSet, MemoryInterface, RegisterDataType, RegisterRole,
-    RegisterValue, UnwindRule,
+    CoreInterface, CoreRegister, Error, InstructionSet, MemoryInterface, RegisterDataType,
+    RegisterRole, RegisterValue, UnwindRule,
 };
 use std::{
     borrow, cmp::Ordering, num::NonZeroU64, ops::ControlFlow, path::Path, rc::Rc, str::from_utf8,
@@ -555,6 +555,36 @@ impl DebugInfo {
         )
     }
 
+    /// The address the function at the current program counter of a SPARC core returns to.
+    ///
+    /// A SPARC call returns to the instruction after its delay slot, eight bytes after the call
+    /// instruction in `%i7`, or in `%o7` if the function did not save the register window.
+    pub(crate) fn sparc_return_address(
+        &self,
+        core: &mut impl CoreInterface,
+    ) -> Result<u64, DebugError> {
+        let mut registers = DebugRegisters::from_core(core);
+        let program_counter = registers
+            .get_program_counter()
+            .and_then(|register| register.value)
+            .ok_or_else(|| DebugError::Other("The program counter is unknown".to_string()))?;
+        let mut unwind_context = Box::new(gimli::UnwindContext::new());
+        let unwind_info = get_unwind_info(
+            &mut unwind_context,
+            &self.frame_section,
+            program_counter.try_into()?,
+        );
+        sparc::unwind_frame(&mut registers, unwind_info.ok(), core)?;
+        let call = registers
+            .get_program_counter()
+            .and_then(|register| register.value)
+            .ok_or_else(|| {
+                DebugError::Other("Unable to determine the return address".to_string())
+            })?;
+        let call: u64 = call.try_into()?;
+        Ok(call + 8)
+    }
+
     pub(crate) fn unwind_impl(
         &self,
         initial_registers: DebugRegisters,
@@ -655,6 +685,27 @@ impl DebugInfo {
             // we need to keep a copy of the current frame's registers that can be used to resolve [DWARF](https://dwarfstd.org) expressions.
             let callee_frame_registers = unwind_registers.clone();
 
+            // SPARC functions save the registers of their caller by switching the register window,
+            // which the call frame information cannot describe, so they are unwound through the
+            // register windows instead.
+            if instruction_set == Some(InstructionSet::Sparc) {
+                let unwind_info = unwind_info.as_ref().ok().copied();
+                if let Err(error) = sparc::unwind_frame(&mut unwind_registers, unwind_info, memory)
+                {
+                    tracing::error!("{:?}", &error);
+                    if let Some(first_frame) = stack_frames.first_mut() {
+                        first_frame.function_name =
+                            format!("{} : ERROR : {error}", first_frame.function_name);
+                    };
+                    break;
+                }
+                if callee_frame_registers == unwind_registers {
+                    tracing::debug!("No change, preventing infinite loop");
+                    break;
+                }
+                continue 'unwind;
+            }
+
             // PART 2-a: get the `gimli::FrameDescriptorEntry` for the program counter
             // and then the unwind info associated with this row.
             let unwind_info = match unwind_info {
@@ -968,6 +1019,150 @@ impl DebugInfo {
         self.endianness
     }
 
+    /// The address of the static variable `name`, if it is stored at a fixed address.
+    pub fn static_variable_address(&self, name: &str) -> Option<u64> {
+        for unit_info in &self.unit_infos {
+            let mut entries = unit_info.unit.entries();
+            while let Ok(Some((_, entry))) = entries.next_dfs() {
+                if entry.tag() != gimli::DW_TAG_variable {
+                    continue;
+                }
+                let Ok(Some(gimli::AttributeValue::Exprloc(expression))) =
+                    entry.attr_value(gimli::DW_AT_location)
+                else {
+                    continue;
+                };
+                // The definition of a variable declared elsewhere refers to the declaration
+                // for its name.
+                let has_name = self.has_name(unit_info, entry, name)
+                    || entry
+                        .attr(gimli::DW_AT_specification)
+                        .ok()
+                        .flatten()
+                        .and_then(|attr| {
+                            self.resolve_die_reference_with_unit(&attr, unit_info).ok()
+                        })
+                        .is_some_and(|(unit_info, declaration)| {
+                            self.has_name(unit_info, &declaration, name)
+                        });
+                if !has_name {
+                    continue;
+                }
+                let mut operations = expression.operations(unit_info.unit.encoding());
+                if let Ok(Some(gimli::Operation::Address { address })) = operations.next() {
+                    return Some(address);
+                }
+            }
+        }
+        None
+    }
+
+    /// The offset of `member` in the structure with the tag or typedef name `type_name`.
+    pub fn struct_member_offset(&self, type_name: &str, member: &str) -> Option<u64> {
+        let (unit_info, offset) = self.find_struct(type_name)?;
+        let mut tree = unit_info.unit.entries_tree(Some(offset)).ok()?;
+        let root = tree.root().ok()?;
+        let mut children = root.children();
+        while let Ok(Some(child)) = children.next() {
+            let entry = child.entry();
+            if entry.tag() != gimli::DW_TAG_member || !self.has_name(unit_info, entry, member) {
+                continue;
+            }
+            return match entry.attr_value(gimli::DW_AT_data_member_location).ok()? {
+                // DWARF 2 describes the offset with an expression.
+                Some(gimli::AttributeValue::Exprloc(expression)) => {
+                    match expression
+                        .operations(unit_info.unit.encoding())
+                        .next()
+                        .ok()?
+                    {
+                        Some(gimli::Operation::PlusConstant { value }) => Some(value),
+                        _ => None,
+                    }
+                }
+                Some(value) => value.udata_value(),
+                // The members of a union all start at its beginning.
+                None => Some(0),
+            };
+        }
+        None
+    }
+
+    /// The size in bytes of the structure with the tag or typedef name `type_name`.
+    pub fn struct_byte_size(&self, type_name: &str) -> Option<u64> {
+        let (unit_info, offset) = self.find_struct(type_name)?;
+        let entry = unit_info.unit.entry(offset).ok()?;
+        entry
+            .attr_value(gimli::DW_AT_byte_size)
+            .ok()
+            .flatten()?
+            .udata_value()
+    }
+
+    /// The definition of the structure with the tag or typedef name `type_name`.
+    fn find_struct(&self, type_name: &str) -> Option<(&UnitInfo, gimli::UnitOffset)> {
+        for unit_info in &self.unit_infos {
+            let mut entries = unit_info.unit.entries();
+            while let Ok(Some((_, entry))) = entries.next_dfs() {
+                if !matches!(
+                    entry.tag(),
+                    gimli::DW_TAG_structure_type | gimli::DW_TAG_typedef
+                ) || !self.has_name(unit_info, entry, type_name)
+                {
+                    continue;
+                }
+                if let Some(definition) = self.resolve_struct(unit_info, entry.offset()) {
+                    return Some(definition);
+                }
+            }
+        }
+        None
+    }
+
+    /// Follow typedefs and qualifiers from the type at `offset` to a structure definition.
+    ///
+    /// Returns `None` for other types, and for declarations of structures that are defined in
+    /// another unit.
+    fn resolve_struct<'debug_info>(
+        &'debug_info self,
+        mut unit_info: &'debug_info UnitInfo,
+        mut offset: gimli::UnitOffset,
+    ) -> Option<(&'debug_info UnitInfo, gimli::UnitOffset)> {
+        // Limit the number of hops, in case of a malformed, cyclic, type chain.
+        for _ in 0..8 {
+            let entry = unit_info.unit.entry(offset).ok()?;
+            match entry.tag() {
+                gimli::DW_TAG_structure_type => {
+                    let is_declaration = matches!(
+                        entry.attr_value(gimli::DW_AT_declaration),
+                        Ok(Some(gimli::AttributeValue::Flag(true)))
+                    );
+                    return (!is_declaration).then_some((unit_info, offset));
+                }
+                gimli::DW_TAG_typedef | gimli::DW_TAG_const_type | gimli::DW_TAG_volatile_type => {
+                    let attr = entry.attr(gimli::DW_AT_type).ok().flatten()?;
+                    let (type_unit_info, type_entry) = self
+                        .resolve_die_reference_with_unit(&attr, unit_info)
+                        .ok()?;
+                    unit_info = type_unit_info;
+                    offset = type_entry.offset();
+                }
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    /// Whether the `DW_AT_name` of the DIE is `name`.
+    fn has_name(&self, unit_info: &UnitInfo, entry: &Die, name: &str) -> bool {
+        entry
+            .attr_value(gimli::DW_AT_name)
+            .ok()
+            .flatten()
+            .and_then(|value| self.dwarf.attr_string(&unit_info.unit, value).ok())
+            .is_some_and(|value| *value == *name.as_bytes())
+    }
+
     /// Returns the UnitInfo and DIE for the given attribute.
     pub(crate) fn resolve_die_reference_with_unit<'debug_info, 'unit_info>(
         &'debug_info self,
@@ -2190,4 +2385,31 @@ mod test {
         // we assume that it is the same as the canonical frame address.
         assert_eq!(value, Some(RegisterValue::U32(0x200)));
     }
+
+    #[test]
+    fn static_variable_address() {
+        let debug_info = load_test_elf_as_debug_info("debug-unwind-tests/RP2040_full_unwind.elf");
+
+        assert_eq!(debug_info.static_variable_address("I"), Some(0x2000_0000));
+        assert_eq!(debug_info.static_variable_address("NOT_A_VARIABLE"), None);
+    }
+
+    #[test]
+    fn struct_layout() {
+        let debug_info = load_test_elf_as_debug_info("debug-unwind-tests/RP2040_full_unwind.elf");
+
+        assert_eq!(debug_info.struct_byte_size("Formatter"), Some(0x24));
+        assert_eq!(
+            debug_info.struct_member_offset("Formatter", "fill"),
+            Some(0x10)
+        );
+        assert_eq!(
+            debug_info.struct_member_offset("Formatter", "buf"),
+            Some(0x14)
+        );
+        assert_eq!(
+            debug_info.struct_member_offset("Formatter", "nothing"),
+            None
+        );
+    }
 }
@@ -1,9 +1,9 @@
 use super::{DebugError, VerifiedBreakpoint, debug_info::DebugInfo};
 use probe_rs::{
-    CoreInterface, CoreStatus, Error, HaltReason,
+    CoreInterface, CoreStatus, Error, HaltReason, InstructionSet,
     architecture::{
-        arm::ArmError, riscv::communication_interface::RiscvError,
-        xtensa::communication_interface::XtensaError,
+        arm::ArmError, leon3::communication_interface::Leon3Error,
+        riscv::communication_interface::RiscvError, xtensa::communication_interface::XtensaError,
     },
 };
 use std::{ops::RangeInclusive, time::Duration};
@@ -53,20 +53,20 @@ impl SteppingMode {
             }
         };
         let origin_program_counter = program_counter;
-        let mut return_address = core.read_core_reg(core.return_address().id())?.try_into()?;
+        if let SteppingMode::StepInstruction = self {
+            // First deal with the the fast/easy case.
+            program_counter = core.step()?.pc;
+            core_status = core.status()?;
+            return Ok((core_status, program_counter));
+        }
+        let mut return_address = return_address(core, debug_info)?;
 
         // Sometimes the target program_counter is at a location where the debug_info program row data does not contain valid statements for halt points.
         // When DebugError::NoValidHaltLocation happens, we will step to the next instruction and try again(until we can reasonably expect to have passed out of an epilogue), before giving up.
         let mut target_address: Option<u64> = None;
         for _ in 0..10 {
             let post_step_target = match self {
-                SteppingMode::StepInstruction => {
-                    // First deal with the the fast/easy case.
-                    program_counter = core.step()?.pc;
-                    core_status = core.status()?;
-                    return Ok((core_status, program_counter));
-                }
-                SteppingMode::BreakPoint => {
+                SteppingMode::StepInstruction | SteppingMode::BreakPoint => {
                     self.get_halt_location(core, debug_info, program_counter, None)
                 }
                 SteppingMode::IntoStatement
@@ -93,8 +93,7 @@ impl SteppingMode {
                                 "Incomplete stepping information @{program_counter:#010X}: {message}"
                             );
                             program_counter = core.step()?.pc;
-                            return_address =
-                                core.read_core_reg(core.return_address().id())?.try_into()?;
+                            return_address = self::return_address(core, debug_info)?;
                             continue;
                         }
                         other_error => {
@@ -111,7 +110,16 @@ impl SteppingMode {
         }
 
         (core_status, program_counter) = match target_address {
-            Some(target_address) => {
+            Some(mut target_address) => {
+                // A statement can start in the delay slot of a call, which executes before the
+                // called function. Stepping over the call halts where the call returns to.
+                if matches!(self, SteppingMode::OverStatement)
+                    && core.instruction_set()? == InstructionSet::Sparc
+                    && in_sparc_call_delay_slot(core, target_address)
+                {
+                    target_address += 4;
+                }
+
                 tracing::debug!(
                     "Preparing to step ({:20?}): \n\tfrom: {:?} @ {:#010X} \n\t  to: {:?} @ {:#010X}",
                     self,
@@ -316,6 +324,40 @@ impl SteppingMode {
     }
 }
 
+/// The address the function at the current program counter returns to.
+///
+/// On SPARC, this is not the return address register, which holds the address of the call
+/// instruction, but the instruction after the delay slot of the call.
+fn return_address(
+    core: &mut impl CoreInterface,
+    debug_info: &DebugInfo,
+) -> Result<u64, DebugError> {
+    let return_address = core.read_core_reg(core.return_address().id())?.try_into()?;
+    if core.instruction_set()? == InstructionSet::Sparc {
+        return Ok(debug_info
+            .sparc_return_address(core)
+            .unwrap_or_else(|error| {
+                tracing::debug!("Assuming the register window was saved: {error}");
+                return_address + 8
+            }));
+    }
+    Ok(return_address)
+}
+
+/// Returns whether the instruction at `address` is in the delay slot of a SPARC `call`, or of
+/// a `jmpl` that links the return address.
+fn in_sparc_call_delay_slot(core: &mut impl CoreInterface, address: u64) -> bool {
+    let Some(Ok(word)) = address
+        .checked_sub(4)
+        .map(|previous| core.read_word_32(previous))
+    else {
+        return false;
+    };
+    let call = word >> 30 == 1;
+    let linking_jmpl = word >> 30 == 2 && (word >> 19) & 0x3F == 0x38 && (word >> 25) & 0x1F != 0;
+    call || linking_jmpl
+}
+
 /// Run the target to the desired address. If available, we will use a breakpoint, otherwise we will use single step.
 /// Returns the program counter at the end of the step, when any of the following conditions are met:
 /// - We reach the `target_address_range.end()` (inclusive)
@@ -369,6 +411,7 @@ fn run_to_address(
                     Error::Arm(ArmError::Timeout)
                         | Error::Riscv(RiscvError::Timeout)
                         | Error::Xtensa(XtensaError::Timeout)
+                        | Error::Leon3(Leon3Error::Timeout)
                 ) {
                     // This is not a quick step and halt operation. Notify the user that we are not going to wait any longer, and then return the current program counter so that the debugger can show the user where the forced halt happened.
                     tracing::error!(
@@ -18,6 +18,7 @@ pub(crate) mod armv6m_armv7m_shared;
 pub(crate) mod armv7m;
 pub(crate) mod armv8m;
 pub(crate) mod riscv;
+pub(crate) mod sparc;
 pub(crate) mod xtensa;
 
 /// Creates a new exception interface for the [`CoreType`] at hand.
@@ -30,7 +31,7 @@ pub fn exception_handler_for_core(core_type: CoreType) -> Box<dyn ExceptionInter
         CoreType::Xtensa => Box::new(xtensa::XtensaExceptionHandler),
         CoreType::Riscv => Box::new(riscv::RiscvExceptionHandler),
         CoreType::Armv7a | CoreType::Armv8a => Box::new(UnimplementedExceptionHandler),
-        CoreType::Sparc => Box::new(UnimplementedExceptionHandler),
+        CoreType::Sparc => Box::new(sparc::SparcExceptionHandler),
     }
 }
 
@@ -0,0 +1,325 @@
+use std::ops::ControlFlow;
+
+use gimli::{CfaRule, UnwindTableRow};
+
+use crate::{
+    DebugError, DebugRegisters, StackFrame, debug_info::GimliReaderOffset,
+    exception_handling::ExceptionInterface,
+};
+
+use probe_rs::{InstructionSet, MemoryInterface, RegisterRole, RegisterValue};
+
+/// The DWARF register numbers of the SPARC integer registers: %g0-%g7 are 0-7, %o0-%o7 are
+/// 8-15, %l0-%l7 are 16-23 and %i0-%i7 are 24-31.
+const O0: u16 = 8;
+const O6: u16 = 14;
+const O7: u16 = 15;
+const L0: u16 = 16;
+const I0: u16 = 24;
+const I6: u16 = 30;
+const I7: u16 = 31;
+
+pub struct SparcExceptionHandler;
+
+impl ExceptionInterface for SparcExceptionHandler {
+    fn unwind_without_debuginfo(
+        &self,
+        unwind_registers: &mut DebugRegisters,
+        _frame_pc: u64,
+        _stack_frames: &[StackFrame],
+        _instruction_set: Option<InstructionSet>,
+        memory: &mut dyn MemoryInterface,
+    ) -> ControlFlow<Option<DebugError>> {
+        match unwind_frame(unwind_registers, None, memory) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(error) => ControlFlow::Break(Some(error)),
+        }
+    }
+}
+
+/// Unwind the registers of the calling frame through the SPARC register windows.
+///
+/// A function that executed `save` runs in a new register window: the outs of the caller are
+/// its ins, the stack pointer of the caller is its frame pointer `%i6`, and the caller
+/// continues after the call instruction at `%i7`. The locals and ins of the caller are read
+/// from the register save area at the stack pointer of the caller, where the window overflow
+/// trap handler or [`probe_rs::CoreInterface::spill_registers`] stored them.
+///
+/// A leaf function, or a function before its `save`, still runs in the window of the caller,
+/// with the return address in `%o7`. The DWARF call frame information tells the two apart:
+/// the CFA is based on the stack pointer `%o6` until the window is saved. GCC describes the
+/// saved window with `DW_CFA_GNU_window_save`, which cannot be evaluated, so frames without
+/// usable call frame information, `unwind_info` being `None`, are assumed to have saved it.
+///
+/// A trap switches to a new window without `save` and without a call. The caller of a trap
+/// window is the interrupted code, which continues at the PC saved in the window.
+pub(crate) fn unwind_frame(
+    unwind_registers: &mut DebugRegisters,
+    unwind_info: Option<&UnwindTableRow<GimliReaderOffset>>,
+    memory: &mut dyn MemoryInterface,
+) -> Result<(), DebugError> {
+    let windowed = match unwind_info.map(|row| row.cfa()) {
+        Some(CfaRule::RegisterAndOffset { register, .. }) => register.0 != O6,
+        _ => true,
+    };
+
+    if !windowed {
+        let return_address = value(unwind_registers, O7);
+        set(unwind_registers, O7, None);
+        set_program_counter(unwind_registers, return_address);
+        return Ok(());
+    }
+
+    let trapped_pc = trap_window_pc(unwind_registers);
+    let Some(return_address) = restore_window(unwind_registers, memory, trapped_pc.is_some())?
+    else {
+        // The outermost frame clears its frame pointer, which ends the unwind.
+        set_program_counter(unwind_registers, None);
+        return Ok(());
+    };
+    if trapped_pc.is_some() {
+        set_program_counter(unwind_registers, trapped_pc);
+        return Ok(());
+    }
+
+    // A trap handler that saved a window of its own returns into the trap window, which does
+    // not have a return address. It is skipped in favour of the interrupted code.
+    if let Some(pc) = trap_window_pc(unwind_registers)
+        && matches!(restore_window(unwind_registers, memory, true), Ok(Some(_)))
+    {
+        set_program_counter(unwind_registers, Some(pc));
+        return Ok(());
+    }
+
+    set_program_counter(unwind_registers, Some(return_address));
+    Ok(())
+}
+
+/// Replace the registers of a window that executed `save` with those of the previous window,
+/// returning the return address in `%i7`, or `None` if the window is the outermost one.
+///
+/// The registers are only changed if the previous window was found. The outs of a trap window
+/// are stale, so its stack pointer is not checked against its frame pointer.
+fn restore_window(
+    unwind_registers: &mut DebugRegisters,
+    memory: &mut dyn MemoryInterface,
+    trap_window: bool,
+) -> Result<Option<u32>, DebugError> {
+    let Some(frame_pointer) = value(unwind_registers, I6) else {
+        return Err(DebugError::Other(
+            "The frame pointer is unknown, cannot unwind the register window".to_string(),
+        ));
+    };
+    let stack_pointer = if trap_window {
+        0
+    } else {
+        value(unwind_registers, O6).unwrap_or(0)
+    };
+    if frame_pointer == 0 || frame_pointer % 8 != 0 || frame_pointer <= stack_pointer {
+        return Ok(None);
+    }
+
+    let ins: Vec<Option<u32>> = (I0..=I7).map(|id| value(unwind_registers, id)).collect();
+    let mut save_area = [0; 16];
+    memory.read_32(u64::from(frame_pointer), &mut save_area)?;
+
+    for (n, value) in ins.iter().enumerate() {
+        set(unwind_registers, O0 + n as u16, *value);
+    }
+    for (n, value) in save_area.iter().enumerate() {
+        set(unwind_registers, L0 + n as u16, Some(*value));
+    }
+    Ok(ins[7])
+}
+
+/// The PC of the interrupted code if the window is the one a trap switched to.
+///
+/// Taking a trap stores the PC and nPC of the interrupted instruction in `%l1` and `%l2` of
+/// the new window, and the trap table entry reads the PSR into `%l0`, by convention. The
+/// window is recognized by the PSR, which is in supervisor mode with traps disabled.
+fn trap_window_pc(unwind_registers: &DebugRegisters) -> Option<u32> {
+    const PSR_S: u32 = 1 << 7;
+    const PSR_ET: u32 = 1 << 5;
+    const PSR_RESERVED: u32 = 0x000F_C000;
+
+    let psr = value(unwind_registers, L0)?;
+    let pc = value(unwind_registers, L0 + 1)?;
+    let npc = value(unwind_registers, L0 + 2)?;
+    let is_trap_window = psr & (PSR_S | PSR_ET | PSR_RESERVED) == PSR_S
+        && pc != 0
+        && npc != 0
+        && (pc | npc) % 4 == 0;
+    is_trap_window.then_some(pc)
+}
+
+/// The value of the register with the DWARF register number `dwarf_id`.
+fn value(unwind_registers: &DebugRegisters, dwarf_id: u16) -> Option<u32> {
+    unwind_registers
+        .get_register_by_dwarf_id(dwarf_id)
+        .and_then(|register| register.value)
+        .and_then(|value| TryInto::<u32>::try_into(value).ok())
+}
+
+/// Set the register with the DWARF register number `dwarf_id`.
+fn set(unwind_registers: &mut DebugRegisters, dwarf_id: u16, value: Option<u32>) {
+    if let Some(register) = unwind_registers
+        .0
+        .iter_mut()
+        .find(|register| register.dwarf_id == Some(dwarf_id))
+    {
+        register.value = value.map(RegisterValue::from);
+    }
+}
+
+/// Set the program counter of the calling frame to the call instruction at the return
+/// address, so that it is attributed to the line of the call.
+fn set_program_counter(unwind_registers: &mut DebugRegisters, return_address: Option<u32>) {
+    if let Ok(program_counter) =
+        unwind_registers.get_register_mut_by_role(&RegisterRole::ProgramCounter)
+    {
+        program_counter.value = return_address
+            .filter(|address| *address != 0)
+            .map(RegisterValue::from);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use probe_rs::{
+        RegisterRole, RegisterValue, architecture::leon3::registers::LEON3_CORE_REGISTERS,
+        test::MockMemory,
+    };
+
+    use super::unwind_frame;
+    use crate::{DebugRegister, DebugRegisters};
+
+    /// Registers of a frame whose `%rN` is `values[N]`.
+    fn registers(values: &[u32; 32], pc: u32) -> DebugRegisters {
+        DebugRegisters(
+            LEON3_CORE_REGISTERS
+                .core_registers()
+                .enumerate()
+                .map(|(dwarf_id, core_register)| DebugRegister {
+                    core_register,
+                    dwarf_id: (dwarf_id < 32).then_some(dwarf_id as u16),
+                    value: Some(RegisterValue::U32(
+                        values.get(dwarf_id).copied().unwrap_or(pc),
+                    )),
+                })
+                .collect(),
+        )
+    }
+
+    fn value(registers: &DebugRegisters, role: RegisterRole) -> Option<u32> {
+        registers
+            .get_register_value_by_role(&role)
+            .ok()
+            .map(|value| value as u32)
+    }
+
+    #[test]
+    fn unwind_saved_window() {
+        let mut values = [0; 32];
+        values[1] = 0x1111; // %g1
+        values[14] = 0x4000_0f00; // %sp
+        values[30] = 0x4000_0f60; // %fp
+        values[31] = 0x4000_1234; // %i7
+        values[24] = 0x2424; // %i0
+        let mut registers = registers(&values, 0x4000_2000);
+
+        let save_area: Vec<u32> = (0..16).map(|n| 0x100 + n).collect();
+        let mut memory = MockMemory::new();
+        memory.add_word_range(0x4000_0f60, &save_area);
+
+        unwind_frame(&mut registers, None, &mut memory).unwrap();
+
+        assert_eq!(
+            value(&registers, RegisterRole::ProgramCounter),
+            Some(0x4000_1234)
+        );
+        assert_eq!(value(&registers, RegisterRole::Core("r1")), Some(0x1111));
+        assert_eq!(value(&registers, RegisterRole::Core("r8")), Some(0x2424));
+        assert_eq!(
+            value(&registers, RegisterRole::Core("r14")),
+            Some(0x4000_0f60)
+        );
+        assert_eq!(value(&registers, RegisterRole::Core("r16")), Some(0x100));
+        assert_eq!(value(&registers, RegisterRole::Core("r30")), Some(0x10e));
+        assert_eq!(value(&registers, RegisterRole::Core("r31")), Some(0x10f));
+    }
+
+    #[test]
+    fn unwind_trap_window() {
+        let mut values = [0; 32];
+        values[14] = 0x5000_0000; // stale %sp
+        values[16] = 0xF300_00C2; // %l0, the PSR
+        values[17] = 0x4000_2468; // %l1, the PC
+        values[18] = 0x4000_246C; // %l2, the nPC
+        values[30] = 0x4000_0f60; // %fp
+        values[31] = 0x4000_1234; // %i7
+        let mut registers = registers(&values, 0x0000_0050);
+
+        let save_area: Vec<u32> = (0..16).map(|n| 0x100 + n).collect();
+        let mut memory = MockMemory::new();
+        memory.add_word_range(0x4000_0f60, &save_area);
+
+        unwind_frame(&mut registers, None, &mut memory).unwrap();
+
+        assert_eq!(
+            value(&registers, RegisterRole::ProgramCounter),
+            Some(0x4000_2468)
+        );
+        assert_eq!(
+            value(&registers, RegisterRole::Core("r15")),
+            Some(0x4000_1234)
+        );
+        assert_eq!(value(&registers, RegisterRole::Core("r16")), Some(0x100));
+    }
+
+    #[test]
+    fn unwind_skips_trap_window_of_saving_handler() {
+        let mut values = [0; 32];
+        values[14] = 0x4000_0e00; // %sp
+        values[30] = 0x4000_0e60; // %fp
+        values[31] = 0x4000_3000; // %i7, stale in the trap window
+        let mut registers = registers(&values, 0x4000_4000);
+
+        // The trap window, with %l0-%l2 holding the PSR, PC and nPC, and %i6 the stack
+        // pointer of the interrupted code.
+        let mut trap_window: Vec<u32> = (0..16).map(|n| 0x200 + n).collect();
+        trap_window[0] = 0xF300_00C2;
+        trap_window[1] = 0x4000_2468;
+        trap_window[2] = 0x4000_246C;
+        trap_window[14] = 0x4000_0f60;
+        let interrupted: Vec<u32> = (0..16).map(|n| 0x100 + n).collect();
+        let mut memory = MockMemory::new();
+        memory.add_word_range(0x4000_0e60, &trap_window);
+        memory.add_word_range(0x4000_0f60, &interrupted);
+
+        unwind_frame(&mut registers, None, &mut memory).unwrap();
+
+        assert_eq!(
+            value(&registers, RegisterRole::ProgramCounter),
+            Some(0x4000_2468)
+        );
+        assert_eq!(
+            value(&registers, RegisterRole::Core("r14")),
+            Some(0x4000_0f60)
+        );
+        assert_eq!(value(&registers, RegisterRole::Core("r16")), Some(0x100));
+    }
+
+    #[test]
+    fn unwind_stops_at_outermost_frame() {
+        let mut values = [0; 32];
+        values[14] = 0x4000_0f00; // %sp
+        values[31] = 0x4000_1234; // %i7
+        let mut registers = registers(&values, 0x4000_2000);
+
+        unwind_frame(&mut registers, None, &mut MockMemory::new()).unwrap();
+
+        assert_eq!(value(&registers, RegisterRole::ProgramCounter), None);
+    }
+}
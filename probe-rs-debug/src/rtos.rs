@@ -0,0 +1,59 @@
+//! This module (and its children) contains the implementation of the [`RtosInterface`] for the
+//! real-time operating systems the debugger is aware of.
+
+use probe_rs::MemoryInterface;
+use probe_rs_target::CoreType;
+
+use super::{DebugError, DebugInfo, DebugRegisters};
+
+pub(crate) mod rtems;
+
+/// Creates the [`RtosInterface`] for the operating system the program binary is built with, or
+/// `None` if the binary does not use an operating system the debugger is aware of.
+pub fn rtos_for_binary(
+    debug_info: &DebugInfo,
+    core_type: CoreType,
+) -> Option<Box<dyn RtosInterface>> {
+    match core_type {
+        CoreType::Sparc => {
+            rtems::Rtems::detect(debug_info).map(|rtems| Box::new(rtems) as Box<dyn RtosInterface>)
+        }
+        _ => None,
+    }
+}
+
+/// A thread of the operating system running on the target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtosThread {
+    /// The identifier the operating system assigned to the thread.
+    pub id: u64,
+    /// The name of the thread, which may be empty.
+    pub name: String,
+    /// A human readable description of the scheduling state of the thread.
+    pub state: String,
+    /// The index of the processor executing the thread, if the thread is executing.
+    pub executing_on: Option<usize>,
+    /// The address of the thread control block.
+    pub(crate) control_block: u64,
+}
+
+/// A generic interface to the threads of an operating system.
+pub trait RtosInterface {
+    /// The name of the operating system.
+    fn name(&self) -> &'static str;
+
+    /// All threads that currently exist on the target.
+    fn threads(&self, memory: &mut dyn MemoryInterface) -> Result<Vec<RtosThread>, DebugError>;
+
+    /// The registers of a thread that is not executing, restored from the context the operating
+    /// system saved when it switched away from the thread.
+    ///
+    /// The `core_registers` supply the register definitions. Registers that are not part of
+    /// the saved context have no value.
+    fn thread_registers(
+        &self,
+        thread: &RtosThread,
+        core_registers: &DebugRegisters,
+        memory: &mut dyn MemoryInterface,
+    ) -> Result<DebugRegisters, DebugError>;
+}
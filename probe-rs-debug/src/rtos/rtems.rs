@@ -0,0 +1,382 @@
+//! Threads of RTEMS 5 and 6 on SPARC.
+//!
+//! The thread control blocks of each API are found through the local table of its object
+//! information, and the executing thread of each processor through the per-CPU information.
+//! The layout of these structures depends on the configuration RTEMS was built with, so it is
+//! taken from the debug information, except for the SPARC context, whose layout is fixed by
+//! the context switch code.
+
+use probe_rs::{MemoryInterface, RegisterRole, RegisterValue};
+
+use crate::{
+    DebugError, DebugInfo, DebugRegisters,
+    rtos::{RtosInterface, RtosThread},
+};
+
+/// The object information of the internal, Classic API and POSIX API threads.
+const THREAD_INFORMATION: &[&str] = &[
+    "_Thread_Information",
+    "_RTEMS_tasks_Information",
+    "_POSIX_Threads_Information",
+];
+
+/// The maximum length of a string name that is read from the target.
+const MAXIMUM_NAME_LENGTH: usize = 32;
+
+/// The DWARF register numbers of the registers in the SPARC `Context_Control`.
+const G0: u16 = 0;
+const G5: u16 = 5;
+const G7: u16 = 7;
+const O6: u16 = 14;
+const O7: u16 = 15;
+const L0: u16 = 16;
+const I7: u16 = 31;
+
+/// The words of the SPARC `Context_Control` that hold registers: `%g5`, `%g7`, the locals,
+/// the ins, `%o6` and `%o7`, in this order.
+const CONTEXT_WORDS: usize = 20;
+
+/// The RTEMS thread structures, as described by the debug information of the program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Rtems {
+    /// The addresses of the object information of the thread APIs the program uses.
+    information: Vec<u64>,
+    /// The offset of `maximum_id` in `Objects_Information`.
+    maximum_id: u64,
+    /// The offset of `local_table` in `Objects_Information`.
+    local_table: u64,
+    /// The index of the first object in the local table, which is 1 before RTEMS 6.
+    first_index: u64,
+    /// The offset and size of the field of `Objects_Information` that is non-zero if the
+    /// objects have string names: `is_string` before RTEMS 6, and `name_length` since.
+    string_names: (u64, usize),
+    /// The offset of `Object.id` in `Thread_Control`.
+    object_id: u64,
+    /// The offset of `Object.name` in `Thread_Control`.
+    object_name: u64,
+    /// The offset of `current_state` in `Thread_Control`.
+    current_state: u64,
+    /// The offset of `Registers` in `Thread_Control`.
+    registers: u64,
+    /// The address of `_Per_CPU_Information`.
+    per_cpu: u64,
+    /// The size of an entry of `_Per_CPU_Information`.
+    per_cpu_size: u64,
+    /// The offset of `executing` in `Per_CPU_Control`.
+    executing: u64,
+    /// The address of the processor count of SMP configurations.
+    processor_count: Option<u64>,
+}
+
+impl Rtems {
+    /// Find the RTEMS thread structures in the debug information.
+    pub(crate) fn detect(debug_info: &DebugInfo) -> Option<Self> {
+        let information: Vec<u64> = THREAD_INFORMATION
+            .iter()
+            .filter_map(|name| debug_info.static_variable_address(name))
+            .collect();
+        if information.is_empty() {
+            return None;
+        }
+
+        let rtems = Self::layout(debug_info, information);
+        if rtems.is_none() {
+            tracing::warn!(
+                "The program uses RTEMS, but the debug information does not describe its thread structures"
+            );
+        }
+        rtems
+    }
+
+    fn layout(debug_info: &DebugInfo, information: Vec<u64>) -> Option<Self> {
+        let offset = |type_name, member| debug_info.struct_member_offset(type_name, member);
+
+        // RTEMS 6 removed `maximum`, and the unused first entry of the local table with it.
+        let first_index = match offset("Objects_Information", "maximum") {
+            Some(_) => 1,
+            None => 0,
+        };
+        let string_names = match offset("Objects_Information", "is_string") {
+            Some(is_string) => (is_string, 1),
+            None => (offset("Objects_Information", "name_length")?, 2),
+        };
+        let object = offset("Thread_Control", "Object")?;
+
+        Some(Self {
+            information,
+            maximum_id: offset("Objects_Information", "maximum_id")?,
+            local_table: offset("Objects_Information", "local_table")?,
+            first_index,
+            string_names,
+            object_id: object + offset("Objects_Control", "id")?,
+            object_name: object + offset("Objects_Control", "name")?,
+            current_state: offset("Thread_Control", "current_state")?,
+            registers: offset("Thread_Control", "Registers")?,
+            per_cpu: debug_info.static_variable_address("_Per_CPU_Information")?,
+            per_cpu_size: debug_info
+                .struct_byte_size("Per_CPU_Control_envelope")
+                .or_else(|| debug_info.struct_byte_size("Per_CPU_Control"))?,
+            executing: offset("Per_CPU_Control", "executing")?,
+            processor_count: debug_info
+                .static_variable_address("_SMP_Processor_maximum")
+                .or_else(|| debug_info.static_variable_address("_SMP_Processor_count")),
+        })
+    }
+
+    /// The thread control blocks of the threads executing on each processor.
+    fn executing(&self, memory: &mut dyn MemoryInterface) -> Result<Vec<u64>, DebugError> {
+        let processors = match self.processor_count {
+            Some(address) => memory.read_word_32(address)?.clamp(1, 32),
+            None => 1,
+        };
+        (0..u64::from(processors))
+            .map(|processor| {
+                let address = self.per_cpu + processor * self.per_cpu_size + self.executing;
+                Ok(u64::from(memory.read_word_32(address)?))
+            })
+            .collect()
+    }
+
+    fn read_name(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        information: u64,
+        control_block: u64,
+    ) -> Result<String, DebugError> {
+        let (offset, size) = self.string_names;
+        let mut string_names = [0; 2];
+        memory.read_8(information + offset, &mut string_names[..size])?;
+        let name = memory.read_word_32(control_block + self.object_name)?;
+
+        if string_names == [0, 0] {
+            return Ok(name_from_u32(name));
+        }
+        if name == 0 {
+            return Ok(String::new());
+        }
+        let mut bytes = [0; MAXIMUM_NAME_LENGTH];
+        memory.read_8(u64::from(name), &mut bytes)?;
+        let length = bytes.iter().position(|&c| c == 0).unwrap_or(bytes.len());
+        Ok(String::from_utf8_lossy(&bytes[..length]).into_owned())
+    }
+}
+
+impl RtosInterface for Rtems {
+    fn name(&self) -> &'static str {
+        "RTEMS"
+    }
+
+    fn threads(&self, memory: &mut dyn MemoryInterface) -> Result<Vec<RtosThread>, DebugError> {
+        let executing = self.executing(memory)?;
+        let mut threads = Vec::new();
+
+        for &information in &self.information {
+            let maximum = memory.read_word_32(information + self.maximum_id)? & 0xFFFF;
+            let local_table = memory.read_word_32(information + self.local_table)?;
+            if maximum == 0 || local_table == 0 {
+                continue;
+            }
+
+            let mut table = vec![0; maximum as usize];
+            memory.read_32(u64::from(local_table) + 4 * self.first_index, &mut table)?;
+
+            // Unused entries of the local table are null.
+            for control_block in table.into_iter().filter(|&entry| entry != 0) {
+                let control_block = u64::from(control_block);
+                let id = memory.read_word_32(control_block + self.object_id)?;
+                let state = memory.read_word_32(control_block + self.current_state)?;
+                threads.push(RtosThread {
+                    id: u64::from(id),
+                    name: self.read_name(memory, information, control_block)?,
+                    state: describe_state(state),
+                    executing_on: executing.iter().position(|&thread| thread == control_block),
+                    control_block,
+                });
+            }
+        }
+
+        Ok(threads)
+    }
+
+    fn thread_registers(
+        &self,
+        thread: &RtosThread,
+        core_registers: &DebugRegisters,
+        memory: &mut dyn MemoryInterface,
+    ) -> Result<DebugRegisters, DebugError> {
+        let mut context = [0; CONTEXT_WORDS];
+        memory.read_32(thread.control_block + self.registers, &mut context)?;
+        Ok(registers_from_context(&context, core_registers))
+    }
+}
+
+/// The registers of a thread whose context was saved by `_CPU_Context_switch`.
+///
+/// The context switch is a leaf function, so the context holds the window of its caller, and
+/// the thread continues after the call, at `%o7 + 8`. The context switch flushed the other
+/// windows of the thread to its stack, where the unwinder finds them.
+fn registers_from_context(
+    context: &[u32; CONTEXT_WORDS],
+    core_registers: &DebugRegisters,
+) -> DebugRegisters {
+    let mut registers = core_registers.clone();
+    for register in &mut registers.0 {
+        let value = match register.dwarf_id {
+            Some(G0) => Some(0),
+            Some(G5) => Some(context[0]),
+            Some(G7) => Some(context[1]),
+            Some(id @ L0..=I7) => Some(context[2 + usize::from(id - L0)]),
+            Some(O6) => Some(context[18]),
+            Some(O7) => Some(context[19]),
+            _ if register
+                .core_register
+                .register_has_role(RegisterRole::ProgramCounter) =>
+            {
+                Some(context[19].wrapping_add(8))
+            }
+            _ => None,
+        };
+        register.value = value.map(RegisterValue::from);
+    }
+    registers
+}
+
+/// An object name of four characters, as built by `rtems_build_name`.
+fn name_from_u32(name: u32) -> String {
+    let name: String = name
+        .to_be_bytes()
+        .into_iter()
+        .filter(|c| c.is_ascii_graphic() || *c == b' ')
+        .map(char::from)
+        .collect();
+    name.trim_end().to_string()
+}
+
+/// The state of a thread, which is ready if no state flags are set, and blocked otherwise.
+///
+/// The meaning of the flags changed between RTEMS versions, so they are not decoded.
+fn describe_state(state: u32) -> String {
+    if state == 0 {
+        "ready".to_string()
+    } else {
+        format!("blocked ({state:#010x})")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use probe_rs::{
+        RegisterRole, RegisterValue, architecture::leon3::registers::LEON3_CORE_REGISTERS,
+        test::MockMemory,
+    };
+
+    use super::{CONTEXT_WORDS, Rtems, name_from_u32, registers_from_context};
+    use crate::{
+        DebugRegister, DebugRegisters,
+        rtos::{RtosInterface, RtosThread},
+    };
+
+    fn core_registers() -> DebugRegisters {
+        DebugRegisters(
+            LEON3_CORE_REGISTERS
+                .core_registers()
+                .enumerate()
+                .map(|(dwarf_id, core_register)| DebugRegister {
+                    core_register,
+                    dwarf_id: (dwarf_id < 32).then_some(dwarf_id as u16),
+                    value: Some(RegisterValue::U32(0xDEAD_BEEF)),
+                })
+                .collect(),
+        )
+    }
+
+    /// The layout of a single-processor RTEMS 6 build.
+    fn rtems() -> Rtems {
+        Rtems {
+            information: vec![0x4000_1000],
+            maximum_id: 0,
+            local_table: 4,
+            first_index: 0,
+            string_names: (0x12, 2),
+            object_id: 0x08,
+            object_name: 0x0C,
+            current_state: 0x1C,
+            registers: 0x40,
+            per_cpu: 0x4000_2000,
+            per_cpu_size: 0x80,
+            executing: 0x18,
+            processor_count: None,
+        }
+    }
+
+    #[test]
+    fn threads() {
+        let mut memory = MockMemory::new();
+        // `Objects_Information` with three entries, one of them unused, and u32 names.
+        memory.add_word_range(0x4000_1000, &[0x0A01_0003, 0x4000_1100, 0, 0, 0]);
+        memory.add_word_range(0x4000_1100, &[0x4000_3000, 0, 0x4000_3100]);
+        // `Per_CPU_Control`
+        memory.add_word_range(0x4000_2018, &[0x4000_3100]);
+        // `Thread_Control`
+        memory.add_word_range(
+            0x4000_3008,
+            &[0x0A01_0001, 0x4944_4C45, 0, 0, 0, 0x0002_0000],
+        );
+        memory.add_word_range(0x4000_3108, &[0x0A01_0003, 0x5549_3120, 0, 0, 0, 0]);
+
+        let threads = rtems().threads(&mut memory).unwrap();
+
+        assert_eq!(
+            threads,
+            vec![
+                RtosThread {
+                    id: 0x0A01_0001,
+                    name: "IDLE".to_string(),
+                    state: "blocked (0x00020000)".to_string(),
+                    executing_on: None,
+                    control_block: 0x4000_3000,
+                },
+                RtosThread {
+                    id: 0x0A01_0003,
+                    name: "UI1".to_string(),
+                    state: "ready".to_string(),
+                    executing_on: Some(0),
+                    control_block: 0x4000_3100,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn saved_context() {
+        let context: [u32; CONTEXT_WORDS] = std::array::from_fn(|n| 0x100 + n as u32);
+
+        let registers = registers_from_context(&context, &core_registers());
+
+        let value = |role| {
+            registers
+                .get_register_value_by_role(&role)
+                .ok()
+                .map(|value| value as u32)
+        };
+        assert_eq!(value(RegisterRole::Core("g0")), Some(0));
+        assert_eq!(value(RegisterRole::Core("g1")), None);
+        assert_eq!(value(RegisterRole::Core("g5")), Some(0x100));
+        assert_eq!(value(RegisterRole::Core("g7")), Some(0x101));
+        assert_eq!(value(RegisterRole::Core("r8")), None);
+        assert_eq!(value(RegisterRole::Core("r14")), Some(0x112));
+        assert_eq!(value(RegisterRole::Core("r15")), Some(0x113));
+        assert_eq!(value(RegisterRole::Core("l0")), Some(0x102));
+        assert_eq!(value(RegisterRole::Core("r31")), Some(0x111));
+        assert_eq!(value(RegisterRole::ProgramCounter), Some(0x11B));
+    }
+
+    #[test]
+    fn object_names() {
+        assert_eq!(name_from_u32(0x4944_4C45), "IDLE");
+        assert_eq!(name_from_u32(0x5549_3100), "UI1");
+        assert_eq!(name_from_u32(0), "");
+    }
+}
@@ -184,7 +184,7 @@ pub enum CoreAccessOptions {
     /// Xtensa specific options
     Xtensa(XtensaCoreAccessOptions),
     /// Leon3 specific options
-    Leon3(Leon3CoreAccessOptions),
+    Leon3(Box<Leon3CoreAccessOptions>),
 }
 
 /// An address for AP accesses
@@ -249,7 +249,236 @@ pub struct XtensaCoreAccessOptions {
 /// The data required to access a Leon3 core
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Leon3CoreAccessOptions {
-    // TODO(darsor): add AHBJTAG register addresses and IR length?
-    /// The JTAG TAP index of the core's debug module
+    /// The index of the AHBJTAG TAP on the scan chain, for chains with more than one TAP.
+    /// Defaults to the first TAP.
     pub jtag_tap: Option<usize>,
+
+    /// The AHBJTAG instruction codes of the TAP selected by `jtag_tap`. Overrides the
+    /// `ahbjtag` configuration of the target's JTAG description.
+    #[serde(default)]
+    pub ahbjtag: Option<AhbJtag>,
+
+    /// The base address of the debug support unit. Overrides the address found in the
+    /// plug&play area, for designs with a broken plug&play ROM.
+    #[serde(default)]
+    pub dsu_base: Option<u64>,
+
+    /// The address of the AHB plug&play area, if it was moved from the default `0xFFFFF000`.
+    #[serde(default)]
+    pub plugnplay_base: Option<u64>,
+
+    /// The index of the debug support unit controlling the core, for designs with more than
+    /// one. Debug support units are numbered in plug&play order, defaults to the first one.
+    #[serde(default)]
+    pub dsu: Option<usize>,
+
+    /// How long to wait for a single AHBJTAG word transaction to complete, in milliseconds.
+    /// Defaults to 2 seconds.
+    #[serde(default)]
+    pub ahbjtag_timeout: Option<u32>,
+
+    /// The IP address, or host name, of a GRETH Ethernet debug communication link (EDCL),
+    /// with an optional UDP port. If set, the system bus is accessed over Ethernet instead
+    /// of AHBJTAG.
+    #[serde(default)]
+    pub edcl: Option<String>,
+
+    /// A PCI device, like a GRPCI2 target, whose BARs map the AHB bus. If set, the system
+    /// bus is accessed through the BARs instead of AHBJTAG.
+    #[serde(default)]
+    pub pci: Option<Leon3PciAccess>,
+
+    /// The USB device of a GRUSBDC debug communication link (DCL), as `VID:PID[:serial]`.
+    /// If set, the system bus is accessed over USB instead of AHBJTAG.
+    #[serde(default)]
+    pub usbdcl: Option<String>,
+
+    /// Register writes that configure the memory controller, like MCTRL or FTMCTRL, so the
+    /// SRAM and SDRAM can be accessed. Applied in order when connecting and after each reset.
+    #[serde(default)]
+    pub memory_controller_init: Vec<Leon3RegisterWrite>,
+
+    /// The SDRAM configuration of the MCTRL or FTMCTRL memory controller, programmed before
+    /// an image is downloaded to RAM.
+    #[serde(default)]
+    pub sdram: Option<Leon3SdramConfig>,
+
+    /// The kind of memory in the PROM area of the memory controller, which selects how it is
+    /// programmed. Defaults to CFI flash.
+    #[serde(default)]
+    pub prom: Leon3PromMemory,
+
+    /// How the core is started after an image is downloaded to RAM.
+    #[serde(default)]
+    pub ram_boot: Option<Leon3RamBoot>,
+
+    /// The debug links to try when attaching, for systems that can be reached over several.
+    /// Overrides the `edcl`, `pci` and `usbdcl` options, which select a single link.
+    #[serde(default)]
+    pub debug_links: Vec<Leon3DebugLink>,
+
+    /// The name of the chip whose built-in debug sequence is used, like `GR712RC`, for
+    /// boards and FPGA designs whose target name does not start with the chip name.
+    #[serde(default)]
+    pub debug_sequence: Option<String>,
+
+    /// How the system is reset. Defaults to the reset of the debug sequence.
+    #[serde(default)]
+    pub reset: Leon3ResetStrategy,
+
+    /// The address the core starts executing from after reset, set by the `rstaddr` VHDL
+    /// generic or the reset vector input of the core. Defaults to the reset address of the
+    /// debug sequence, `0x0` for most chips.
+    #[serde(default)]
+    pub reset_address: Option<u32>,
+
+    /// The units to ungate of each GRCLKGATE clock gating unit, in plug&play order, as a bit
+    /// mask of its clock enable register. Peripherals that are clock gated at boot do not
+    /// respond to the debug link until they are ungated.
+    #[serde(default)]
+    pub ungate_clocks: Vec<u32>,
+
+    /// The software trap number of semihosting calls, `n` of the `ta n` instruction the
+    /// firmware issues them with. Semihosting is disabled if not set.
+    #[serde(default)]
+    pub semihosting_trap: Option<u8>,
+}
+
+/// A debug link of a LEON3 system, tried when attaching.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Leon3DebugLink {
+    /// How the debug link accesses the system bus.
+    pub link: Leon3DebugLinkKind,
+
+    /// Links with a higher priority are tried first, links with the same priority in the
+    /// listed order.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// How a debug link accesses the AHB bus of a LEON3 system.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Leon3DebugLinkKind {
+    /// AHBJTAG, through the JTAG probe.
+    Ahbjtag,
+    /// The AHBUART serial debug link, on the serial port `PORT[:BAUD]`.
+    Ahbuart(String),
+    /// The Ethernet debug communication link of a GRETH, at an IP address or host name with
+    /// an optional UDP port.
+    Edcl(String),
+    /// A PCI device whose BARs map the AHB bus.
+    Pci(Leon3PciAccess),
+    /// The USB debug communication link of a GRUSBDC, as `VID:PID[:serial]`.
+    Usbdcl(String),
+}
+
+/// How a LEON3 core is started after an image is downloaded to RAM.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Leon3RamBoot {
+    /// The entry point of the image the core runs, for AMP configurations in which each core
+    /// runs its own image. The first core defaults to the entry point of the downloaded image
+    /// and is started by the caller, other cores are started at their entry point. Other
+    /// cores without an entry point stay halted.
+    #[serde(default)]
+    pub entry: Option<u64>,
+
+    /// The initial stack pointer. Defaults to the end of the RAM region holding the entry
+    /// point.
+    #[serde(default)]
+    pub stack: Option<u64>,
+}
+
+/// The kind of memory in the PROM area of a LEON3 MCTRL or FTMCTRL memory controller.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Leon3PromMemory {
+    /// Parallel NOR flash, identified with a CFI query and erased before programming.
+    #[default]
+    CfiFlash,
+    /// MRAM, which is written like SRAM.
+    Mram,
+    /// Parallel EEPROM, which is written a page at a time and polled until each write
+    /// cycle completes.
+    Eeprom(Leon3EepromConfig),
+}
+
+/// The write timing of a parallel EEPROM in the PROM area of a LEON3 memory controller.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Leon3EepromConfig {
+    /// The size of the write pages in bytes. Each bus word is written on its own if not set.
+    #[serde(default)]
+    pub page_size: Option<u32>,
+
+    /// The maximum duration of a write cycle in milliseconds. Defaults to 10 ms.
+    #[serde(default)]
+    pub write_timeout: Option<u32>,
+}
+
+/// The SDRAM configuration of a LEON3 MCTRL or FTMCTRL memory controller.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Leon3SdramConfig {
+    /// The CAS latency in clock cycles, 2 or 3.
+    pub cas_latency: u8,
+
+    /// The number of columns of the SDRAM: 256, 512, 1024, 2048, or 4096 with 512 MiB banks.
+    pub column_size: u32,
+
+    /// The refresh reload value: the number of clock cycles between refreshes, minus one.
+    pub refresh_reload: u32,
+
+    /// The size of each SDRAM bank in MiB, a power of two from 4 to 512. Keeps the size
+    /// configured in the memory controller if not set.
+    #[serde(default)]
+    pub bank_size: Option<u32>,
+}
+
+/// How a LEON3 system is reset.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Leon3ResetStrategy {
+    /// The soft core reset of the debug sequence, which emulates a processor reset through
+    /// the debug support unit for most chips. Peripherals are not reset.
+    #[default]
+    Sequence,
+    /// Pulse the reset line of the probe, which resets the whole board. Only available with
+    /// the AHBJTAG debug link.
+    Probe,
+    /// Write a reset controller register of the board, like a reset generator or a watchdog
+    /// that is forced to expire.
+    Register(Leon3RegisterWrite),
+}
+
+/// A write to a memory mapped register of a LEON3 system.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Leon3RegisterWrite {
+    /// The address of the register.
+    pub address: u64,
+
+    /// The value to write.
+    pub value: u32,
+
+    /// The bits of the register to change. If set, the other bits keep their current value.
+    #[serde(default)]
+    pub mask: Option<u32>,
+}
+
+/// PCI access to the AHB bus of a LEON3 system.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Leon3PciAccess {
+    /// The PCI address of the device, like `0000:01:00.0`.
+    pub device: String,
+
+    /// The BARs of the device and the AHB address ranges they map.
+    pub windows: Vec<Leon3PciWindow>,
+}
+
+/// A PCI BAR mapping a range of the AHB bus.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Leon3PciWindow {
+    /// The index of the BAR.
+    pub bar: u8,
+
+    /// The AHB address mapped to the start of the BAR.
+    pub ahb_base: u64,
 }
@@ -20,7 +20,9 @@ pub(crate) mod serialize;
 
 pub use chip::{
     AhbJtag, ApAddress, ArmCoreAccessOptions, Chip, Core, CoreAccessOptions, Jtag,
-    Leon3CoreAccessOptions, RiscvCoreAccessOptions, RiscvJtagTunnel, ScanChainElement,
+    Leon3CoreAccessOptions, Leon3DebugLink, Leon3DebugLinkKind, Leon3EepromConfig, Leon3PciAccess,
+    Leon3PciWindow, Leon3PromMemory, Leon3RamBoot, Leon3RegisterWrite, Leon3ResetStrategy,
+    Leon3SdramConfig, RiscvCoreAccessOptions, RiscvJtagTunnel, ScanChainElement,
     XtensaCoreAccessOptions,
 };
 pub use chip_family::{
@@ -7,10 +7,13 @@ pub mod complete;
 pub mod dap_server;
 pub mod debug;
 pub mod download;
+pub mod dsu_trace;
 pub mod erase;
 pub mod gdb_server;
 pub mod info;
 pub mod itm;
+pub mod l3stat;
+pub mod leon3_cache;
 pub mod list;
 pub mod mi;
 pub mod profile;
@@ -21,4 +24,5 @@ pub mod run;
 pub mod serve;
 pub mod trace;
 pub mod verify;
+pub mod watch;
 pub mod write;
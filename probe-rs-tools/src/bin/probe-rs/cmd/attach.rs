@@ -48,6 +48,7 @@ impl Cmd {
                 catch_hardfault: !self.run.run_options.no_catch_hardfault,
                 rtt_client: Some(client_handle),
                 semihosting_options,
+                uart_console: self.run.run_options.uart_console,
             },
             self.run.shared_options.always_print_stacktrace,
             &mut target_output_files,
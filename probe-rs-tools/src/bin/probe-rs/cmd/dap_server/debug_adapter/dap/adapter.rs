@@ -984,12 +984,56 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
                 );
         }
 
-        // TODO: Implement actual thread resolution. For now, we just use the core id as the thread id.
-
-        let threads = vec![Thread {
+        // Without RTOS awareness, the core is the only thread.
+        let core_thread = Thread {
             id: target_core.id() as i64,
             name: target_core.core_data.target_name.clone(),
-        }];
+        };
+        let mut threads = vec![];
+
+        // The threads of an RTOS can only be listed consistently while the core is halted.
+        if target_core.core_data.last_known_status.is_halted()
+            && let Some(rtos) = &target_core.core_data.rtos
+        {
+            match rtos.threads(&mut target_core.core) {
+                Ok(rtos_threads) => {
+                    let core_index = target_core.core_data.core_index;
+                    for rtos_thread in rtos_threads {
+                        // The executing thread is the thread of the core, as reported in the
+                        // `stopped` events.
+                        let executing = rtos_thread.executing_on == Some(core_index);
+                        let state = match rtos_thread.executing_on {
+                            Some(processor) => format!("executing on processor {processor}"),
+                            None => rtos_thread.state,
+                        };
+                        let thread = Thread {
+                            id: if executing {
+                                core_thread.id
+                            } else {
+                                rtos_thread.id as i64
+                            },
+                            name: format!(
+                                "{} ({:#010x}, {state})",
+                                rtos_thread.name, rtos_thread.id
+                            ),
+                        };
+                        if executing {
+                            threads.insert(0, thread);
+                        } else {
+                            threads.push(thread);
+                        }
+                    }
+                }
+                Err(error) => {
+                    tracing::warn!("Failed to read the {} threads: {error}", rtos.name());
+                }
+            }
+        }
+
+        // Before the RTOS starts multitasking, none of its threads is executing.
+        if !threads.iter().any(|thread| thread.id == core_thread.id) {
+            threads.insert(0, core_thread);
+        }
         self.send_response(request, Ok(Some(ThreadsResponseBody { threads })))
     }
 
@@ -1016,8 +1060,13 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
 
         let arguments: StackTraceArguments = get_arguments(self, request)?;
 
+        let thread_frames = match target_core.thread_stack_frames(arguments.thread_id) {
+            Ok(thread_frames) => thread_frames,
+            Err(error) => return self.send_response::<()>(request, Err(&error)),
+        };
+
         // Determine the correct 'slice' of available [StackFrame]s to serve up ...
-        let total_frames = target_core.core_data.stack_frames.len() as i64;
+        let total_frames = thread_frames.len() as i64;
 
         // The DAP spec says that the `levels` is optional if `None` or `Some(0)`, then all available frames should be returned.
         let mut levels = arguments.levels.unwrap_or(0);
@@ -1041,10 +1090,8 @@ impl<P: ProtocolAdapter> DebugAdapter<P> {
             start_frame + levels
         } as usize;
 
-        let Some(frames) = target_core
-            .core_data
-            .stack_frames
-            .get(first_frame..last_frame)
+        let Some(frames) =
+            target_core.core_data.stack_frames[thread_frames].get(first_frame..last_frame)
         else {
             return self.send_response::<()>(
                 request,
@@ -29,7 +29,10 @@ static BREAK: ReplCommand = ReplCommand {
     help_text: "Sets a breakpoint specified location, or next instruction if unspecified.",
     requires_target_halted: false,
     sub_commands: &[],
-    args: &[ReplCommandArgs::Optional("*address")],
+    args: &[
+        ReplCommandArgs::Optional("*address"),
+        ReplCommandArgs::Optional("thread <id>"),
+    ],
     handler: create_breakpoint,
 };
 
@@ -72,6 +75,25 @@ fn create_breakpoint(
             "Invalid parameters {command_arguments:?}. See the `help` command for more information."
         )));
     };
+    // Like gdb, a breakpoint can be restricted to a thread of the RTOS.
+    let thread = match (input_arguments.next(), input_arguments.next()) {
+        (None, _) => None,
+        (Some("thread"), Some(thread)) if target_core.core_data.rtos.is_some() => {
+            Some(parse_int::parse::<u64>(thread).map_err(|error| {
+                DebuggerError::UserMessage(format!("Invalid thread id {thread:?}: {error}"))
+            })?)
+        }
+        (Some("thread"), Some(_)) => {
+            return Err(DebuggerError::UserMessage(
+                "Thread-specific breakpoints require an RTOS the debugger is aware of.".to_string(),
+            ));
+        }
+        _ => {
+            return Err(DebuggerError::UserMessage(format!(
+                "Invalid parameters {command_arguments:?}. See the `help` command for more information."
+            )));
+        }
+    };
 
     let result = set_instruction_breakpoint(
         InstructionBreakpoint {
@@ -96,6 +118,20 @@ fn create_breakpoint(
             body: None,
         };
     if result.verified {
+        if let Some(thread) = thread
+            && let Some(address) = result.instruction_reference.as_deref()
+            && let Ok(MemoryAddress(address)) = address.try_into()
+            && let Some(breakpoint) = target_core
+                .core_data
+                .breakpoints
+                .iter_mut()
+                .find(|breakpoint| breakpoint.address == address)
+        {
+            breakpoint.thread = Some(thread);
+            if let Some(message) = response.message.as_mut() {
+                message.push_str(&format!(" Only thread {thread:#010x} halts here."));
+            }
+        }
         // The caller will catch this event body and use it to synch the UI breakpoint list.
         response.body = serde_json::to_value(BreakpointEventBody {
             breakpoint: result,
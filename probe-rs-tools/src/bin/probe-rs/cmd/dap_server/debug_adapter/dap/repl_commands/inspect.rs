@@ -49,7 +49,7 @@ static EXAMINE: ReplCommand = ReplCommand {
 #[distributed_slice(REPL_COMMANDS)]
 static DUMP: ReplCommand = ReplCommand {
     command: "dump",
-    help_text: "Create a core dump at a target location. Specify memory ranges to dump, or leave blank to dump in-scope memory regions.",
+    help_text: "Create a core dump at a target location. Specify memory ranges to dump, or leave blank to dump in-scope memory regions. A path ending in `.elf` stores an ELF core file that GDB can open.",
     requires_target_halted: true,
     sub_commands: &[],
     args: &[
@@ -221,7 +221,15 @@ fn dump_core(
     } else {
         format!("(Includes memory ranges: {range_string})")
     };
-    CoreDump::dump_core(&mut target_core.core, ranges)?.store(location)?;
+    let core_dump = CoreDump::dump_core(&mut target_core.core, ranges)?;
+    if location
+        .extension()
+        .is_some_and(|extension| extension == "elf")
+    {
+        core_dump.store_elf(location)?;
+    } else {
+        core_dump.store(location)?;
+    }
 
     Ok(Response {
         command: "dump".to_string(),
@@ -348,7 +348,7 @@ mod test {
             &[
                 (
                     "break ",
-                    "break [*address]: Sets a breakpoint specified location, or next instruction if unspecified.",
+                    "break [*address] [thread <id>]: Sets a breakpoint specified location, or next instruction if unspecified.",
                 ),
                 (
                     "bt ",
@@ -362,14 +362,14 @@ mod test {
             "br",
             &[(
                 "break ",
-                "break [*address]: Sets a breakpoint specified location, or next instruction if unspecified.",
+                "break [*address] [thread <id>]: Sets a breakpoint specified location, or next instruction if unspecified.",
             )],
         );
         assert_completion_result(
             "break",
             &[(
                 "break ",
-                "break [*address]: Sets a breakpoint specified location, or next instruction if unspecified.",
+                "break [*address] [thread <id>]: Sets a breakpoint specified location, or next instruction if unspecified.",
             )],
         );
         assert_completion_result(
@@ -101,7 +101,8 @@ pub(crate) fn disassemble_target_memory(
         | InstructionSet::RV32C
         | InstructionSet::RV32
         | InstructionSet::A32
-        | InstructionSet::A64 => (),
+        | InstructionSet::A64
+        | InstructionSet::Sparc => (),
         _ => return Err(DebuggerError::Unimplemented), // e.g. Xtensa.
     };
 
@@ -164,7 +165,10 @@ pub(crate) fn disassemble_target_memory(
     let mut disassembled_instructions: Vec<DisassembledInstruction> = vec![];
     let mut maybe_previous_source_location = None;
     let mut maybe_reference_instruction_index = None;
-    let convert_endianness = target_core.core_data.debug_info.endianness() == RunTimeEndian::Big;
+    // Capstone decodes SPARC instructions from a big-endian byte stream, so they are passed
+    // on in memory order.
+    let convert_endianness = target_core.core_data.debug_info.endianness() == RunTimeEndian::Big
+        && instruction_set != InstructionSet::Sparc;
 
     let mut instruction_pointer = start_from_address;
     'instruction_loop: while instruction_pointer < read_until_address {
@@ -31,9 +31,11 @@ use probe_rs::{
 };
 use probe_rs::{Core, CoreStatus, HaltReason, rtt::ScanRegion};
 use probe_rs_debug::VerifiedBreakpoint;
+use probe_rs_debug::rtos::RtosInterface;
 use probe_rs_debug::{
     ColumnType, ObjectRef, VariableCache, debug_info::DebugInfo, stack_frame::StackFrameInfo,
 };
+use probe_rs_debug::{DebugRegisters, exception_handler_for_core};
 use time::UtcOffset;
 use typed_path::TypedPath;
 
@@ -54,6 +56,12 @@ pub struct CoreData {
     pub static_variables: Option<VariableCache>,
     pub core_peripherals: Option<SvdCache>,
     pub stack_frames: Vec<probe_rs_debug::stack_frame::StackFrame>,
+    /// The operating system the program runs on, if the debugger is aware of its threads.
+    pub rtos: Option<Box<dyn RtosInterface>>,
+    /// The ranges of [`CoreData::stack_frames`] holding the frames of RTOS threads that are not
+    /// executing, indexed by the DAP thread id. They are unwound on request, after the frames
+    /// of the core.
+    pub thread_stack_frames: HashMap<i64, Range<usize>>,
     pub breakpoints: Vec<session_data::ActiveBreakpoint>,
     pub rtt_scan_ranges: ScanRegion,
     pub rtt_connection: Option<debug_rtt::RttConnection>,
@@ -154,6 +162,14 @@ impl CoreHandle<'_> {
                 // is not handled or indicates that the core should halt.
             }
 
+            CoreStatus::Halted(HaltReason::Breakpoint(_)) if self.halted_in_other_thread()? => {
+                // A thread-specific breakpoint was hit by another thread, which continues
+                // without notifying the client.
+                self.core.run()?;
+                self.core_data.last_known_status = CoreStatus::Running;
+                return Ok(CoreStatus::Running);
+            }
+
             CoreStatus::Halted(_) => self.notify_halted(debug_adapter, status)?,
             CoreStatus::LockedUp => {
                 let (_, description) = status.short_long_status(None);
@@ -172,6 +188,91 @@ impl CoreHandle<'_> {
         Ok(status)
     }
 
+    /// Whether the core halted at a breakpoint that is restricted to an RTOS thread, while
+    /// another thread is executing.
+    fn halted_in_other_thread(&mut self) -> Result<bool, DebuggerError> {
+        let program_counter: u64 = self.core.read_core_reg(self.core.program_counter())?;
+        let Some(thread) = self
+            .find_breakpoint_in_cache(program_counter)
+            .and_then(|(_, breakpoint)| breakpoint.thread)
+        else {
+            return Ok(false);
+        };
+        let Some(rtos) = &self.core_data.rtos else {
+            return Ok(false);
+        };
+
+        let core_index = self.core_data.core_index;
+        let executing = rtos
+            .threads(&mut self.core)?
+            .into_iter()
+            .find(|rtos_thread| rtos_thread.executing_on == Some(core_index));
+        Ok(executing.is_some_and(|rtos_thread| rtos_thread.id != thread))
+    }
+
+    /// The range of [`CoreData::stack_frames`] holding the frames of the DAP thread `thread_id`.
+    ///
+    /// The thread of the core is the thread executing on it. The frames of other RTOS threads
+    /// are unwound from their saved registers when they are first requested.
+    pub(crate) fn thread_stack_frames(
+        &mut self,
+        thread_id: i64,
+    ) -> Result<Range<usize>, DebuggerError> {
+        if let Some(frames) = self.core_data.thread_stack_frames.get(&thread_id) {
+            return Ok(frames.clone());
+        }
+        if thread_id == self.id() as i64 {
+            let end = self
+                .core_data
+                .thread_stack_frames
+                .values()
+                .map(|frames| frames.start)
+                .min()
+                .unwrap_or(self.core_data.stack_frames.len());
+            return Ok(0..end);
+        }
+
+        let Some(rtos) = &self.core_data.rtos else {
+            return Err(DebuggerError::Other(anyhow!("Unknown thread {thread_id}")));
+        };
+        let Some(thread) = rtos
+            .threads(&mut self.core)?
+            .into_iter()
+            .find(|rtos_thread| rtos_thread.id as i64 == thread_id)
+        else {
+            return Err(DebuggerError::Other(anyhow!(
+                "The {} thread {thread_id:#x} no longer exists",
+                rtos.name()
+            )));
+        };
+        if let Some(processor) = thread.executing_on {
+            return Err(DebuggerError::Other(anyhow!(
+                "The thread {:?} is executing on processor {processor}, which is not debugged",
+                thread.name
+            )));
+        }
+
+        let core_registers = DebugRegisters::from_core(&mut self.core);
+        let registers = rtos.thread_registers(&thread, &core_registers, &mut self.core)?;
+        let exception_interface = exception_handler_for_core(self.core.core_type());
+        let instruction_set = self.core.instruction_set().ok();
+        let frames = self.core_data.debug_info.unwind(
+            &mut self.core,
+            registers,
+            exception_interface.as_ref(),
+            instruction_set,
+            500,
+        )?;
+
+        let start = self.core_data.stack_frames.len();
+        self.core_data.stack_frames.extend(frames);
+        let frames = start..self.core_data.stack_frames.len();
+        self.core_data
+            .thread_stack_frames
+            .insert(thread_id, frames.clone());
+        Ok(frames)
+    }
+
     /// Search available [`probe_rs::debug::StackFrame`]'s for the given `id`
     pub(crate) fn get_stackframe(
         &self,
@@ -336,6 +437,7 @@ impl CoreHandle<'_> {
             .push(session_data::ActiveBreakpoint {
                 breakpoint_type,
                 address,
+                thread: None,
             });
         Ok(())
     }
@@ -30,6 +30,7 @@ use probe_rs::{
 };
 use probe_rs_debug::{
     DebugRegisters, SourceLocation, debug_info::DebugInfo, exception_handler_for_core,
+    rtos::rtos_for_binary,
 };
 use std::{any::Any, collections::HashMap, env::set_current_dir, time::Duration};
 use time::UtcOffset;
@@ -60,6 +61,9 @@ pub(crate) enum SourceLocationScope {
 pub struct ActiveBreakpoint {
     pub(crate) breakpoint_type: BreakpointType,
     pub(crate) address: u64,
+    /// The id of the RTOS thread the breakpoint is restricted to. Other threads continue when
+    /// they hit the breakpoint.
+    pub(crate) thread: Option<u64>,
 }
 
 /// SessionData is designed to be similar to [probe_rs::Session], in as much that it provides handles to the [CoreHandle] instances for each of the available [probe_rs::Core] involved in the debug session.
@@ -171,6 +175,14 @@ impl SessionData {
 
             // Load debug info first, which also validates the accessibility of the elf.
             let debug_info = debug_info_from_binary(core_configuration)?;
+            let rtos = target_session
+                .list_cores()
+                .into_iter()
+                .find(|(core_index, _)| *core_index == core_configuration.core_index)
+                .and_then(|(_, core_type)| rtos_for_binary(&debug_info, core_type));
+            if let Some(rtos) = &rtos {
+                tracing::info!("Detected {} in the program binary.", rtos.name());
+            }
 
             let mut repl_commands = REPL_COMMANDS.to_vec();
             let mut test_data: Box<dyn Any> = Box::new(());
@@ -202,6 +214,8 @@ impl SessionData {
                 static_variables: None,
                 core_peripherals: None,
                 stack_frames: vec![],
+                rtos,
+                thread_stack_frames: HashMap::new(),
                 breakpoints: vec![],
                 rtt_scan_ranges: ScanRegion::Ranges(vec![]),
                 rtt_connection: None,
@@ -439,6 +453,7 @@ impl SessionData {
                         Some(target_core.core_data.debug_info.create_static_scope_cache());
                 }
 
+                target_core.core_data.thread_stack_frames.clear();
                 target_core.core_data.stack_frames = target_core.core_data.debug_info.unwind(
                     &mut target_core.core,
                     initial_registers,
@@ -0,0 +1,202 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use probe_rs::architecture::leon3::trace::{AhbTraceEntry, InstructionTraceEntry, TraceClock};
+use probe_rs::config::Registry;
+use probe_rs::probe::list::Lister;
+use serde_json::json;
+use time::{OffsetDateTime, UtcOffset, format_description::well_known::Rfc3339};
+
+use crate::CoreOptions;
+use crate::util::common_options::ProbeOptions;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    shared: CoreOptions,
+
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    /// The trace buffer to export.
+    #[clap(value_enum)]
+    buffer: TraceBuffer,
+
+    /// The frequency of the system clock in Hz. Derived from the prescaler of the GPTIMER,
+    /// assuming timer ticks at 1 MHz, if not set.
+    #[clap(long)]
+    frequency: Option<u64>,
+
+    /// The format of the exported entries.
+    #[clap(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// The file to write the entries to, instead of stdout.
+    #[clap(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum TraceBuffer {
+    /// The instruction trace buffer of the core. The core is halted to read it.
+    Instruction,
+    /// The AHB trace buffer, which is read while the core runs.
+    Ahb,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// One line per entry, starting with the timestamp.
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+impl Cmd {
+    pub fn run(
+        self,
+        registry: &mut Registry,
+        lister: &Lister,
+        utc_offset: UtcOffset,
+    ) -> anyhow::Result<()> {
+        let (mut session, _probe_options) = self.common.simple_attach(registry, lister)?;
+
+        let mut output: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(std::io::stdout().lock()),
+        };
+        let timestamp = |clock: &TraceClock, time_tag: u32| {
+            format_timestamp(clock.timestamp(time_tag), utc_offset)
+        };
+
+        match self.buffer {
+            TraceBuffer::Instruction => {
+                let was_running = {
+                    let mut core = session.core(self.shared.core)?;
+                    let was_running = !core.core_halted()?;
+                    core.halt(Duration::from_millis(100))?;
+                    was_running
+                };
+                // The time tag stopped when the core halted, so the reference is read right
+                // away.
+                let mut leon3 = session.get_leon3_core(self.shared.core)?;
+                let clock = leon3.trace_clock(self.frequency)?;
+                let entries = leon3.read_instruction_trace()?;
+                drop(leon3);
+                if was_running {
+                    session.core(self.shared.core)?.run()?;
+                }
+
+                for entry in entries {
+                    let timestamp = timestamp(&clock, entry.time);
+                    write_instruction(&mut output, self.format, &timestamp, &entry)?;
+                }
+            }
+            TraceBuffer::Ahb => {
+                let mut leon3 = session.get_leon3_core(self.shared.core)?;
+                let entries = leon3.read_ahb_trace()?;
+                let clock = leon3.trace_clock(self.frequency)?;
+
+                for entry in entries {
+                    let timestamp = timestamp(&clock, entry.time);
+                    write_ahb(&mut output, self.format, &timestamp, &entry)?;
+                }
+            }
+        }
+
+        output.flush()?;
+        Ok(())
+    }
+}
+
+fn format_timestamp(time: SystemTime, utc_offset: UtcOffset) -> String {
+    OffsetDateTime::from(time)
+        .to_offset(utc_offset)
+        .format(&Rfc3339)
+        .expect("Formatting a timestamp as RFC 3339 cannot fail")
+}
+
+fn write_instruction(
+    output: &mut dyn Write,
+    format: Format,
+    timestamp: &str,
+    entry: &InstructionTraceEntry,
+) -> anyhow::Result<()> {
+    match format {
+        Format::Text => {
+            let mut flags = String::new();
+            if entry.trap {
+                flags.push_str(" trap");
+            }
+            if entry.error_mode {
+                flags.push_str(" error-mode");
+            }
+            if entry.multi_cycle {
+                flags.push_str(" multi-cycle");
+            }
+            writeln!(
+                output,
+                "{timestamp} {:#010x} pc {:#010x} instruction {:#010x} result {:#010x}{flags}",
+                entry.time, entry.pc, entry.instruction, entry.result
+            )?;
+        }
+        Format::Json => {
+            let value = json!({
+                "timestamp": timestamp,
+                "time_tag": entry.time,
+                "pc": entry.pc,
+                "instruction": entry.instruction,
+                "result": entry.result,
+                "trap": entry.trap,
+                "error_mode": entry.error_mode,
+                "multi_cycle": entry.multi_cycle,
+            });
+            writeln!(output, "{value}")?;
+        }
+    }
+    Ok(())
+}
+
+fn write_ahb(
+    output: &mut dyn Write,
+    format: Format,
+    timestamp: &str,
+    entry: &AhbTraceEntry,
+) -> anyhow::Result<()> {
+    match format {
+        Format::Text => {
+            let direction = if entry.write { "write" } else { "read" };
+            let watchpoint = if entry.watchpoint_hit {
+                " watchpoint"
+            } else {
+                ""
+            };
+            writeln!(
+                output,
+                "{timestamp} {:#010x} master {} {direction} {:#010x} data {:#010x} size {} response {}{watchpoint}",
+                entry.time, entry.master, entry.address, entry.data, entry.size, entry.response
+            )?;
+        }
+        Format::Json => {
+            let value = json!({
+                "timestamp": timestamp,
+                "time_tag": entry.time,
+                "master": entry.master,
+                "write": entry.write,
+                "address": entry.address,
+                "data": entry.data,
+                "size": entry.size,
+                "trans": entry.trans,
+                "burst": entry.burst,
+                "response": entry.response,
+                "locked": entry.locked,
+                "irq": entry.irq,
+                "watchpoint_hit": entry.watchpoint_hit,
+            });
+            writeln!(output, "{value}")?;
+        }
+    }
+    Ok(())
+}
@@ -1,6 +1,6 @@
 use crate::{
     rpc::{client::RpcClient, functions::flash::EraseCommand},
-    util::{cli, common_options::ProbeOptions, flash::CliProgressBars},
+    util::{cli, common_options::ProbeOptions, flash::CliProgressBars, parse_u64},
 };
 
 #[derive(clap::Parser)]
@@ -14,6 +14,15 @@ pub struct Cmd {
     /// Whether to read the RTT output from the flash loader, if available.
     #[clap(long)]
     pub read_flasher_rtt: bool,
+
+    /// The start of the address range to erase. Only the sectors inside the range are erased.
+    /// Erases all nonvolatile memory if not given.
+    #[clap(long, value_parser = parse_u64, requires = "end_address")]
+    pub start_address: Option<u64>,
+
+    /// The end of the address range to erase, exclusive.
+    #[clap(long, value_parser = parse_u64, requires = "start_address")]
+    pub end_address: Option<u64>,
 }
 
 impl Cmd {
@@ -26,16 +35,17 @@ impl Cmd {
             Some(CliProgressBars::new())
         };
 
+        let command = match (self.start_address, self.end_address) {
+            (Some(start), Some(end)) => EraseCommand::Range { start, end },
+            _ => EraseCommand::All,
+        };
+
         session
-            .erase(
-                EraseCommand::All,
-                self.read_flasher_rtt,
-                async move |event| {
-                    if let Some(pb) = pb.as_ref() {
-                        pb.handle(event);
-                    }
-                },
-            )
+            .erase(command, self.read_flasher_rtt, async move |event| {
+                if let Some(pb) = pb.as_ref() {
+                    pb.handle(event);
+                }
+            })
             .await?;
 
         Ok(())
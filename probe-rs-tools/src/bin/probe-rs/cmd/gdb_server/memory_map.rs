@@ -0,0 +1,104 @@
+//! The memory map sent to GDB through `qXfer:memory-map:read`.
+#![cfg_attr(not(test), expect(dead_code))]
+
+use std::fmt::Write as _;
+
+use probe_rs::config::MemoryRegion;
+
+/// The GDB memory type of `region`.
+///
+/// GDB has no type for device memory, and refuses to write to `rom` regions. Writable generic
+/// regions, like the peripheral I/O areas found in the LEON plug&play area, are therefore
+/// described as `ram`. Non-volatile memory is described as `rom`, as it is programmed by
+/// probe-rs and not through GDB.
+fn memory_type(region: &MemoryRegion) -> &'static str {
+    match region {
+        MemoryRegion::Ram(_) => "ram",
+        MemoryRegion::Generic(region) if region.is_writable() => "ram",
+        MemoryRegion::Generic(_) | MemoryRegion::Nvm(_) => "rom",
+    }
+}
+
+/// The GDB memory map of the regions accessible by the core `core_name`, ordered by address.
+pub(crate) fn gdb_memory_map(regions: &[MemoryRegion], core_name: &str) -> String {
+    let mut regions = regions
+        .iter()
+        .filter(|region| region.cores().iter().any(|core| core == core_name))
+        .collect::<Vec<_>>();
+    regions.sort_by_key(|region| region.address_range().start);
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\"?>\n\
+         <!DOCTYPE memory-map PUBLIC \"+//IDN gnu.org//DTD GDB Memory Map V1.0//EN\" \
+         \"http://sourceware.org/gdb/gdb-memory-map.dtd\">\n\
+         <memory-map>\n",
+    );
+    for region in regions {
+        let range = region.address_range();
+        writeln!(
+            xml,
+            "<memory type=\"{}\" start=\"{:#x}\" length=\"{:#x}\"/>",
+            memory_type(region),
+            range.start,
+            range.end - range.start
+        )
+        .unwrap();
+    }
+    xml.push_str("</memory-map>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use probe_rs::config::{GenericRegion, MemoryAccess, Registry};
+
+    use super::*;
+
+    #[test]
+    fn leon3_io_areas_are_writable() {
+        let registry = Registry::from_builtin_families();
+        let target = registry.get_target_by_name("GR712RC").unwrap();
+        let mut regions = target.memory_map.clone();
+        // The APB area of the AHB/APB bridge, as added from the plug&play area on attach.
+        regions.push(MemoryRegion::Generic(GenericRegion {
+            name: Some("AHB/APB Bridge I/O".to_string()),
+            range: 0x8000_0000..0x8010_0000,
+            cores: vec!["cpu0".to_string(), "cpu1".to_string()],
+            access: Some(MemoryAccess {
+                execute: false,
+                ..Default::default()
+            }),
+        }));
+
+        let xml = gdb_memory_map(&regions, "cpu0");
+        assert_eq!(
+            xml.lines().skip(2).collect::<Vec<_>>(),
+            [
+                "<memory-map>",
+                "<memory type=\"rom\" start=\"0x0\" length=\"0x20000000\"/>",
+                "<memory type=\"ram\" start=\"0x20000000\" length=\"0x20000000\"/>",
+                "<memory type=\"ram\" start=\"0x40000000\" length=\"0x40000000\"/>",
+                "<memory type=\"ram\" start=\"0x80000000\" length=\"0x100000\"/>",
+                "<memory type=\"ram\" start=\"0xa0000000\" length=\"0x30000\"/>",
+                "</memory-map>",
+            ]
+        );
+    }
+
+    #[test]
+    fn read_only_generic_regions_are_rom() {
+        let regions = [MemoryRegion::Generic(GenericRegion {
+            name: None,
+            range: 0x1000..0x2000,
+            cores: vec!["core0".to_string()],
+            access: Some(MemoryAccess {
+                write: false,
+                ..Default::default()
+            }),
+        })];
+
+        let xml = gdb_memory_map(&regions, "core0");
+        assert!(xml.contains("<memory type=\"rom\" start=\"0x1000\" length=\"0x1000\"/>"));
+        assert!(!gdb_memory_map(&regions, "core1").contains("<memory "));
+    }
+}
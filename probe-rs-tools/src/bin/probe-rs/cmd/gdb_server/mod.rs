@@ -1,6 +1,8 @@
 //! GDB server
 
 mod arch;
+mod memory_map;
+mod sparc;
 mod stub;
 mod target;
 
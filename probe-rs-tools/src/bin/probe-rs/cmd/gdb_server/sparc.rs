@@ -0,0 +1,226 @@
+//! SPARC V8 (LEON) register layout, as expected by GDB.
+//!
+//! GDB numbers the SPARC32 registers as follows:
+//!
+//! | Number | Registers   |
+//! |--------|-------------|
+//! | 0-7    | `g0`-`g7`   |
+//! | 8-15   | `o0`-`o7`   |
+//! | 16-23  | `l0`-`l7`   |
+//! | 24-31  | `i0`-`i7`   |
+//! | 32-63  | `f0`-`f31`  |
+//! | 64-71  | `y`, `psr`, `wim`, `tbr`, `pc`, `npc`, `fsr`, `csr` |
+//!
+//! All registers are 32 bits wide and sent big-endian in `g`/`G`/`p`/`P` packets.
+#![cfg_attr(not(test), expect(dead_code))]
+
+use std::fmt::Write as _;
+
+use probe_rs::architecture::leon3::registers::{FpuReg, IuCoreReg, IuSpecialReg, Leon3RegisterId};
+
+/// The number of registers in a `g` packet.
+pub(crate) const NUM_REGISTERS: usize = 72;
+
+/// The first floating-point register.
+const FIRST_FPU_REGISTER: usize = 32;
+
+/// The first special purpose register.
+const FIRST_SPECIAL_REGISTER: usize = 64;
+
+/// The special purpose registers, in GDB order.
+const SPECIAL_REGISTERS: [(IuSpecialReg, &str); 8] = [
+    (IuSpecialReg::Y, "y"),
+    (IuSpecialReg::PSR, "psr"),
+    (IuSpecialReg::WIM, "wim"),
+    (IuSpecialReg::TBR, "tbr"),
+    (IuSpecialReg::PC, "pc"),
+    (IuSpecialReg::NPC, "npc"),
+    (IuSpecialReg::FSR, "fsr"),
+    (IuSpecialReg::CPSR, "csr"),
+];
+
+/// The register with the GDB register number `regnum`.
+pub(crate) fn register_id(regnum: usize) -> Option<Leon3RegisterId> {
+    match regnum {
+        0..FIRST_FPU_REGISTER => Some(Leon3RegisterId::IuCore(IuCoreReg::from_index(regnum as u8))),
+        FIRST_FPU_REGISTER..FIRST_SPECIAL_REGISTER => Some(Leon3RegisterId::Fpu(FpuReg::F(
+            (regnum - FIRST_FPU_REGISTER) as u8,
+        ))),
+        FIRST_SPECIAL_REGISTER..NUM_REGISTERS => Some(Leon3RegisterId::IuSpecial(
+            SPECIAL_REGISTERS[regnum - FIRST_SPECIAL_REGISTER].0,
+        )),
+        _ => None,
+    }
+}
+
+/// The GDB register number of `id`, or `None` for registers GDB does not know, like the
+/// ancillary state registers, double-precision registers and windows other than the current
+/// one.
+pub(crate) fn gdb_regnum(id: Leon3RegisterId) -> Option<usize> {
+    match id {
+        Leon3RegisterId::IuCore(reg) => Some(reg.index() as usize),
+        Leon3RegisterId::Fpu(FpuReg::F(n)) => Some(FIRST_FPU_REGISTER + n as usize),
+        Leon3RegisterId::IuSpecial(reg) => SPECIAL_REGISTERS
+            .iter()
+            .position(|(special, _)| {
+                std::mem::discriminant(special) == std::mem::discriminant(&reg)
+            })
+            .map(|index| FIRST_SPECIAL_REGISTER + index),
+        Leon3RegisterId::Fpu(FpuReg::D(_)) | Leon3RegisterId::IuWindow { .. } => None,
+    }
+}
+
+/// The name GDB uses for register `regnum`.
+fn register_name(regnum: usize) -> String {
+    match regnum {
+        0..FIRST_FPU_REGISTER => {
+            let prefix = ["g", "o", "l", "i"][regnum / 8];
+            format!("{prefix}{}", regnum % 8)
+        }
+        FIRST_FPU_REGISTER..FIRST_SPECIAL_REGISTER => format!("f{}", regnum - FIRST_FPU_REGISTER),
+        _ => SPECIAL_REGISTERS[regnum - FIRST_SPECIAL_REGISTER]
+            .1
+            .to_string(),
+    }
+}
+
+/// The GDB type of register `regnum`.
+fn register_type(regnum: usize) -> &'static str {
+    match regnum {
+        // %sp and %fp
+        14 | 30 => "data_ptr",
+        FIRST_FPU_REGISTER..FIRST_SPECIAL_REGISTER => "ieee_single",
+        // pc and npc
+        68 | 69 => "code_ptr",
+        _ => "uint32",
+    }
+}
+
+/// The target description sent to GDB through `qXfer:features:read:target.xml`.
+pub(crate) fn target_description() -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\"?>\n\
+         <!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n\
+         <target version=\"1.0\">\n\
+         <architecture>sparc</architecture>\n",
+    );
+
+    for (feature, registers) in [
+        ("org.gnu.gdb.sparc.cpu", 0..FIRST_FPU_REGISTER),
+        (
+            "org.gnu.gdb.sparc.fpu",
+            FIRST_FPU_REGISTER..FIRST_SPECIAL_REGISTER,
+        ),
+        (
+            "org.gnu.gdb.sparc.cp0",
+            FIRST_SPECIAL_REGISTER..NUM_REGISTERS,
+        ),
+    ] {
+        writeln!(xml, "<feature name=\"{feature}\">").unwrap();
+        for regnum in registers {
+            writeln!(
+                xml,
+                "<reg name=\"{}\" bitsize=\"32\" type=\"{}\" regnum=\"{regnum}\"/>",
+                register_name(regnum),
+                register_type(regnum)
+            )
+            .unwrap();
+        }
+        xml.push_str("</feature>\n");
+    }
+
+    xml.push_str("</target>\n");
+    xml
+}
+
+/// Pack register values for a `g` or `p` packet, in GDB register number order.
+pub(crate) fn pack_registers(values: &[u32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect()
+}
+
+/// Unpack the register values of a `G` or `P` packet, in GDB register number order.
+///
+/// Returns `None` if the data is not a whole number of registers.
+pub(crate) fn unpack_registers(bytes: &[u8]) -> Option<Vec<u32>> {
+    if !bytes.len().is_multiple_of(4) {
+        return None;
+    }
+    Some(
+        bytes
+            .chunks_exact(4)
+            .map(|word| u32::from_be_bytes(word.try_into().unwrap()))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use probe_rs::RegisterId;
+
+    use super::*;
+
+    #[test]
+    fn register_numbers_round_trip() {
+        for regnum in 0..NUM_REGISTERS {
+            let id = register_id(regnum).unwrap();
+            assert_eq!(gdb_regnum(id), Some(regnum), "{id:?}");
+        }
+        assert!(register_id(NUM_REGISTERS).is_none());
+    }
+
+    #[test]
+    fn register_numbers_match_gdb() {
+        let expected = [
+            (0, Leon3RegisterId::IuCore(IuCoreReg::G(0))),
+            (14, Leon3RegisterId::IuCore(IuCoreReg::O(6))),
+            (23, Leon3RegisterId::IuCore(IuCoreReg::L(7))),
+            (31, Leon3RegisterId::IuCore(IuCoreReg::I(7))),
+            (33, Leon3RegisterId::Fpu(FpuReg::F(1))),
+            (64, Leon3RegisterId::IuSpecial(IuSpecialReg::Y)),
+            (65, Leon3RegisterId::IuSpecial(IuSpecialReg::PSR)),
+            (68, Leon3RegisterId::IuSpecial(IuSpecialReg::PC)),
+            (69, Leon3RegisterId::IuSpecial(IuSpecialReg::NPC)),
+            (70, Leon3RegisterId::IuSpecial(IuSpecialReg::FSR)),
+            (71, Leon3RegisterId::IuSpecial(IuSpecialReg::CPSR)),
+        ];
+        for (regnum, id) in expected {
+            assert_eq!(
+                RegisterId::from(register_id(regnum).unwrap()),
+                RegisterId::from(id)
+            );
+        }
+        assert_eq!(
+            gdb_regnum(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(17))),
+            None
+        );
+        assert_eq!(gdb_regnum(Leon3RegisterId::Fpu(FpuReg::D(0))), None);
+    }
+
+    #[test]
+    fn registers_are_big_endian() {
+        let bytes = pack_registers(&[0x1234_5678, 0x4000_0000]);
+        assert_eq!(bytes, [0x12, 0x34, 0x56, 0x78, 0x40, 0x00, 0x00, 0x00]);
+        assert_eq!(
+            unpack_registers(&bytes),
+            Some(vec![0x1234_5678, 0x4000_0000])
+        );
+        assert_eq!(unpack_registers(&bytes[..3]), None);
+    }
+
+    #[test]
+    fn target_description_lists_all_registers() {
+        let xml = target_description();
+        assert!(xml.contains("<architecture>sparc</architecture>"));
+        assert!(xml.contains("<reg name=\"g0\" bitsize=\"32\" type=\"uint32\" regnum=\"0\"/>"));
+        assert!(xml.contains("<reg name=\"o6\" bitsize=\"32\" type=\"data_ptr\" regnum=\"14\"/>"));
+        assert!(
+            xml.contains("<reg name=\"f31\" bitsize=\"32\" type=\"ieee_single\" regnum=\"63\"/>")
+        );
+        assert!(xml.contains("<reg name=\"pc\" bitsize=\"32\" type=\"code_ptr\" regnum=\"68\"/>"));
+        assert!(xml.contains("<reg name=\"csr\" bitsize=\"32\" type=\"uint32\" regnum=\"71\"/>"));
+        assert_eq!(xml.matches("<reg ").count(), NUM_REGISTERS);
+    }
+}
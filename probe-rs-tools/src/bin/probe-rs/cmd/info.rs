@@ -16,7 +16,7 @@ use crate::{
         client::RpcClient,
         functions::info::{
             ApInfo, ComponentTreeNode, DebugPortInfo, DebugPortInfoNode, DebugPortVersion,
-            InfoEvent, MinDpSupport, TargetInfoRequest,
+            InfoEvent, MinDpSupport, PlugnPlayRecordInfo, TargetInfoRequest,
         },
     },
     util::{cli::select_probe, common_options::ProbeOptions},
@@ -67,12 +67,17 @@ impl Cmd {
                 speed: self.common.speed,
                 connect_under_reset: self.common.connect_under_reset,
                 dry_run: self.common.dry_run,
+                chip: self.common.chip.clone(),
             };
 
             let result = client
                 .info(req, async |message| {
-                    let is_success =
-                        matches!(message, InfoEvent::Idcode { .. } | InfoEvent::ArmDp(_));
+                    let is_success = matches!(
+                        message,
+                        InfoEvent::Idcode { .. }
+                            | InfoEvent::ArmDp(_)
+                            | InfoEvent::Leon3PlugnPlay(_)
+                    );
 
                     if matches!(message, InfoEvent::Message(_)) {
                         successes.push(message.clone());
@@ -168,6 +173,16 @@ impl std::fmt::Display for InfoEvent {
             InfoEvent::ArmDp(dp_info) => {
                 writeln!(f, "{dp_info}")
             }
+            InfoEvent::Leon3PlugnPlay(records) => {
+                let mut tree = Tree::new("LEON3 AMBA plug&play configuration:".to_string());
+                if records.is_empty() {
+                    tree.push(Tree::new("No devices found.".to_string()));
+                }
+                for record in records {
+                    tree.push(plugnplay_record_tree(record));
+                }
+                writeln!(f, "{tree}")
+            }
         }
     }
 }
@@ -289,6 +304,28 @@ impl Display for DebugPortInfo {
     }
 }
 
+fn plugnplay_record_tree(record: &PlugnPlayRecordInfo) -> Tree<String> {
+    let mut node = Tree::new(format!(
+        "{}: {} [{}] (Vendor: {:#04x}, Device: {:#05x}, Version: {})",
+        record.bus,
+        record.name,
+        record.category,
+        record.vendor_id,
+        record.device_id,
+        record.version
+    ));
+    for space in &record.address_spaces {
+        node.push(Tree::new(format!(
+            "{}: {:#010x} - {:#010x}",
+            space.kind, space.start, space.end
+        )));
+    }
+    if record.irq != 0 {
+        node.push(Tree::new(format!("IRQ: {}", record.irq)));
+    }
+    node
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
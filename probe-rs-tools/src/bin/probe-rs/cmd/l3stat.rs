@@ -0,0 +1,143 @@
+use std::thread::sleep;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Context;
+use probe_rs::architecture::leon3::l3stat::{CounterConfig, ModeFilter, StatisticsEvent};
+use probe_rs::config::Registry;
+use probe_rs::probe::list::Lister;
+
+use crate::CoreOptions;
+use crate::util::common_options::ProbeOptions;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    shared: CoreOptions,
+
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    /// The event to count, by GRMON name (e.g. `icmiss`, `dcmiss`, `icnt`, `ahbutil`) or event ID.
+    /// Repeat the option to count several events, one counter per event.
+    #[clap(long = "event", required = true)]
+    events: Vec<StatisticsEvent>,
+
+    /// The processor whose events are counted, or the AHB master for the per-master AHB events.
+    #[clap(long, default_value_t = 0)]
+    source: u8,
+
+    /// The processor mode in which the events are counted.
+    #[clap(long, value_enum, default_value_t = Mode::All)]
+    mode: Mode,
+
+    /// The index of the statistics unit to use, in plug&play order.
+    #[clap(long, default_value_t = 0)]
+    unit: usize,
+
+    /// The time between two samples, in milliseconds.
+    #[clap(long, default_value_t = 1000)]
+    interval: u64,
+
+    /// The number of samples to take before stopping the counters. Samples until interrupted if
+    /// not set.
+    #[clap(long)]
+    samples: Option<usize>,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    All,
+    User,
+    Supervisor,
+}
+
+impl From<Mode> for ModeFilter {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::All => ModeFilter::All,
+            Mode::User => ModeFilter::User,
+            Mode::Supervisor => ModeFilter::Supervisor,
+        }
+    }
+}
+
+impl Cmd {
+    pub fn run(self, registry: &mut Registry, lister: &Lister) -> anyhow::Result<()> {
+        let (mut session, _probe_options) = self.common.simple_attach(registry, lister)?;
+
+        let units = session
+            .get_leon3_core(self.shared.core)?
+            .statistics_units()?;
+        let unit = units.get(self.unit).copied().with_context(|| {
+            format!(
+                "The target has no statistics unit with index {} ({} found)",
+                self.unit,
+                units.len()
+            )
+        })?;
+        anyhow::ensure!(
+            self.events.len() <= unit.counters(),
+            "{} events were requested, but the statistics unit has {} counters",
+            self.events.len(),
+            unit.counters()
+        );
+
+        let mut core = session.core(self.shared.core)?;
+
+        println!(
+            "Statistics unit at {:#010x}: {} counters, {} processors",
+            unit.base_address(),
+            unit.counters(),
+            unit.processors()
+        );
+
+        for (counter, &event) in self.events.iter().enumerate() {
+            unit.configure(
+                &mut core,
+                counter,
+                CounterConfig {
+                    event,
+                    source: self.source,
+                    mode: self.mode.into(),
+                },
+            )?;
+        }
+
+        let interval = Duration::from_millis(self.interval);
+        let mut previous = vec![0; self.events.len()];
+        let mut last_sample = Instant::now();
+        let mut taken = 0;
+
+        while self.samples.is_none_or(|samples| taken < samples) {
+            sleep(interval.saturating_sub(last_sample.elapsed()));
+
+            let values = unit.sample(&mut core)?;
+            let elapsed = last_sample.elapsed().as_secs_f64();
+            last_sample = Instant::now();
+            taken += 1;
+
+            println!();
+            println!(
+                "{:<10} {:>12} {:>12} {:>14}",
+                "event", "count", "delta", "per second"
+            );
+            for ((event, value), previous) in self.events.iter().zip(values).zip(&mut previous) {
+                // The counters are 32 bits wide and wrap around.
+                let delta = value.wrapping_sub(*previous);
+                *previous = value;
+                println!(
+                    "{:<10} {value:>12} {delta:>12} {:>14.0}",
+                    event.to_string(),
+                    f64::from(delta) / elapsed
+                );
+            }
+        }
+
+        for counter in 0..self.events.len() {
+            unit.disable(&mut core, counter)?;
+        }
+
+        Ok(())
+    }
+}
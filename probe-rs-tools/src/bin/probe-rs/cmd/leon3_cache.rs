@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use probe_rs::architecture::leon3::cache::{CacheConfiguration, CacheKind, CacheSnapshot, Ccr};
+use probe_rs::config::Registry;
+use probe_rs::probe::list::Lister;
+
+use crate::CoreOptions;
+use crate::util::common_options::ProbeOptions;
+use crate::util::parse_u32;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    shared: CoreOptions,
+
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    /// The cache to inspect. Both caches are inspected if not set.
+    #[clap(long, value_enum)]
+    cache: Option<Cache>,
+
+    /// The start of the address range whose cache lines are dumped. Only the cache control and
+    /// configuration registers are printed if not set.
+    #[clap(long, value_parser = parse_u32)]
+    address: Option<u32>,
+
+    /// The size of the address range whose cache lines are dumped, in bytes.
+    #[clap(long, value_parser = parse_u32, default_value_t = 4)]
+    size: u32,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Cache {
+    Instruction,
+    Data,
+}
+
+impl From<Cache> for CacheKind {
+    fn from(cache: Cache) -> Self {
+        match cache {
+            Cache::Instruction => CacheKind::Instruction,
+            Cache::Data => CacheKind::Data,
+        }
+    }
+}
+
+impl Cmd {
+    pub fn run(self, registry: &mut Registry, lister: &Lister) -> anyhow::Result<()> {
+        let (mut session, _probe_options) = self.common.simple_attach(registry, lister)?;
+
+        let kinds = match self.cache {
+            Some(cache) => vec![cache.into()],
+            None => vec![CacheKind::Instruction, CacheKind::Data],
+        };
+
+        // The cache registers and diagnostic ASIs are only accessible while the core is halted.
+        let was_running = {
+            let mut core = session.core(self.shared.core)?;
+            let was_running = !core.core_halted()?;
+            core.halt(Duration::from_millis(100))?;
+            was_running
+        };
+
+        let mut leon3 = session.get_leon3_core(self.shared.core)?;
+        let ccr = leon3.read_cache_control()?;
+        let mut caches = Vec::with_capacity(kinds.len());
+        for kind in kinds {
+            let configuration = leon3.cache_configuration(kind)?;
+            let snapshot = match self.address {
+                Some(_) => Some(leon3.read_cache(kind)?),
+                None => None,
+            };
+            caches.push((kind, configuration, snapshot));
+        }
+        drop(leon3);
+        if was_running {
+            session.core(self.shared.core)?.run()?;
+        }
+
+        print_control(&ccr);
+        for (kind, configuration, snapshot) in caches {
+            println!();
+            print_configuration(kind, &configuration);
+            if let (Some(address), Some(snapshot)) = (self.address, snapshot) {
+                print_lines(&snapshot, address, self.size);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn cache_name(kind: CacheKind) -> &'static str {
+    match kind {
+        CacheKind::Instruction => "Instruction cache",
+        CacheKind::Data => "Data cache",
+    }
+}
+
+fn print_control(ccr: &Ccr) {
+    println!("Cache control register: {:#010x}", u32::from(*ccr));
+    for kind in [CacheKind::Instruction, CacheKind::Data] {
+        println!(
+            "  {}: {:?}{}",
+            cache_name(kind),
+            ccr.cache_state(kind),
+            if ccr.freeze_on_interrupt(kind) {
+                ", frozen on interrupt"
+            } else {
+                ""
+            }
+        );
+    }
+    println!("  Data cache snooping: {}", ccr.ds());
+    println!("  Instruction burst fetch: {}", ccr.ib());
+    println!(
+        "  Error counters: ITE={} IDE={} DTE={} DDE={}",
+        ccr.ite(),
+        ccr.ide(),
+        ccr.dte(),
+        ccr.dde()
+    );
+}
+
+fn print_configuration(kind: CacheKind, configuration: &CacheConfiguration) {
+    println!(
+        "{}: {} KiB, {} way(s) of {} KiB, {}-byte lines, {:?}",
+        cache_name(kind),
+        configuration.size() / 1024,
+        configuration.ways,
+        configuration.way_size / 1024,
+        configuration.line_size,
+        configuration.replacement
+    );
+    println!(
+        "  Locking: {}, snooping: {}, MMU: {}",
+        configuration.locking, configuration.snooping, configuration.mmu
+    );
+}
+
+fn print_lines(snapshot: &CacheSnapshot, address: u32, size: u32) {
+    let line_size = snapshot.configuration.line_size;
+    let first = address & !(line_size - 1);
+    let end = address.saturating_add(size.max(1));
+
+    let mut found = false;
+    for line_address in (first..end).step_by(line_size as usize) {
+        for line in snapshot.lines_for(line_address) {
+            found = true;
+            let words = line
+                .data
+                .iter()
+                .enumerate()
+                .map(|(word, data)| {
+                    if line.valid & (1 << word) != 0 {
+                        format!("{data:08x}")
+                    } else {
+                        "--------".to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            println!(
+                "  {:#010x} way {} index {:>4}{}{}: {}",
+                line.address,
+                line.way,
+                line.index,
+                if line.locked { " locked" } else { "" },
+                if line.lrr { " lrr" } else { "" },
+                words
+            );
+        }
+    }
+    if !found {
+        println!("  No valid lines for {first:#010x}..{end:#010x}");
+    }
+}
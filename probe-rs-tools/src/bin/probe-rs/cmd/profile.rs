@@ -1,5 +1,6 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use std::time::Instant;
 
@@ -8,6 +9,7 @@ use anyhow::anyhow;
 use itm::TracePacket;
 use probe_rs::config::Registry;
 use probe_rs::{
+    Core,
     architecture::arm::{
         SwoConfig,
         component::{Dwt, TraceSink, enable_tracing, find_component},
@@ -16,6 +18,7 @@ use probe_rs::{
     },
     probe::list::Lister,
 };
+use probe_rs_debug::{DebugInfo, DebugRegisters, exception_handler_for_core};
 
 use crate::util::flash::{build_loader, run_flash_download};
 use tracing::info;
@@ -39,6 +42,10 @@ pub struct ProfileCmd {
     /// Limit the number of entries to output
     #[clap(long, default_value_t = 25)]
     limit: usize,
+    /// Write the samples as folded stacks to this file, the input format of flamegraph tools
+    /// like `inferno-flamegraph`
+    #[clap(long)]
+    folded: Option<PathBuf>,
     /// Profile Method
     #[clap(subcommand)]
     method: ProfileMethod,
@@ -61,6 +68,13 @@ pub enum ProfileMethod {
     /// Use the DWT_PCSR to profile the chip (ARM only)
     #[clap(name = "pcsr")]
     Pcsr,
+    /// Read the PC from the instruction trace buffer of the DSU while the core runs (LEON3 only)
+    #[clap(name = "dsu")]
+    Dsu {
+        /// Halt the core for each sample to unwind up to this many calling functions
+        #[clap(long, default_value_t = 0)]
+        frames: usize,
+    },
 }
 
 impl std::fmt::Display for ProfileMethod {
@@ -109,7 +123,9 @@ impl ProfileCmd {
 
         let start = Instant::now();
         let mut reads = 0;
-        let mut samples: HashMap<u32, u64> = HashMap::with_capacity(256 * (self.duration as usize));
+        // Each sample is a stack of addresses, the sampled PC first.
+        let mut samples: HashMap<Vec<u32>, u64> =
+            HashMap::with_capacity(256 * (self.duration as usize));
         let duration = Duration::from_secs(self.duration);
         info!("Profiling...");
 
@@ -123,7 +139,7 @@ impl ProfileCmd {
                 loop {
                     core.halt(Duration::from_millis(10))?;
                     let pc: u32 = core.read_core_reg(pc_reg)?;
-                    *samples.entry(pc).or_insert(1) += 1;
+                    *samples.entry(vec![pc]).or_default() += 1;
                     reads += 1;
                     core.run()?;
                     if start.elapsed() > duration {
@@ -143,7 +159,7 @@ impl ProfileCmd {
 
                 while start.elapsed() <= duration {
                     let pc = dwt.read_pcsr()?;
-                    *samples.entry(pc).or_insert(1) += 1;
+                    *samples.entry(vec![pc]).or_default() += 1;
                     reads += 1;
                 }
             }
@@ -166,7 +182,7 @@ impl ProfileCmd {
 
                 for packet in iter {
                     if let TracePacket::PCSample { pc: Some(pc) } = packet? {
-                        *samples.entry(pc).or_insert(1) += 1;
+                        *samples.entry(vec![pc]).or_default() += 1;
                         reads += 1;
                     }
                     if start.elapsed() > duration {
@@ -174,22 +190,71 @@ impl ProfileCmd {
                     }
                 }
             }
+            ProfileMethod::Dsu { frames } => {
+                session.core(self.core)?.halt(Duration::from_millis(100))?;
+                let sampler = session.get_leon3_core(self.core)?.pc_sampler()?;
+                let debug_info = match frames {
+                    0 => None,
+                    _ => Some(DebugInfo::from_file(file_location)?),
+                };
+
+                let mut core = session.core(self.core)?;
+                core.run()?;
+                while start.elapsed() <= duration {
+                    let stack = match &debug_info {
+                        None => vec![sampler.sample(&mut core)?],
+                        Some(debug_info) => {
+                            core.halt(Duration::from_millis(10))?;
+                            let stack = unwind_stack(&mut core, debug_info, frames);
+                            core.run()?;
+                            stack?
+                        }
+                    };
+                    *samples.entry(stack).or_default() += 1;
+                    reads += 1;
+                }
+                drop(core);
+                session
+                    .get_leon3_core(self.core)?
+                    .set_instruction_trace(false)?;
+            }
+        }
+
+        if let Some(path) = &self.folded {
+            let mut folded = String::new();
+            for (stack, count) in &samples {
+                let names = stack
+                    .iter()
+                    .rev()
+                    .map(|&address| symbol_name(&symbols, address))
+                    .collect::<Vec<_>>();
+                writeln!(folded, "{} {count}", names.join(";"))?;
+            }
+            std::fs::write(path, folded)?;
         }
 
-        let mut v = Vec::from_iter(samples);
+        // Aggregate the samples per function, and per line if the line info is printed.
+        let mut entries = HashMap::new();
+        for (stack, count) in &samples {
+            let address = stack[0];
+            let location = self.line_info.then(|| {
+                symbols
+                    .get_location(address as u64)
+                    .unwrap_or(("UNKNOWN", 0))
+            });
+            *entries
+                .entry((symbol_name(&symbols, address), location))
+                .or_insert(0) += count;
+        }
+
+        let mut v = Vec::from_iter(entries);
         // sort by frequency
         v.sort_by(|&(_, a), &(_, b)| b.cmp(&a));
 
         println!("Samples {reads}");
 
-        for (address, count) in v.into_iter().take(self.limit) {
-            let name = symbols
-                .get_name(address as u64)
-                .unwrap_or(format!("UNKNOWN - {address:08X}"));
-            if self.line_info {
-                let (file, num) = symbols
-                    .get_location(address as u64)
-                    .unwrap_or(("UNKNOWN", 0));
+        for ((name, location), count) in v.into_iter().take(self.limit) {
+            if let Some((file, num)) = location {
                 println!("{file}:{num}");
             }
             println!(
@@ -203,6 +268,43 @@ impl ProfileCmd {
     }
 }
 
+/// The name of the function at `address`, or the address itself if it is unknown.
+fn symbol_name(symbols: &Symbols, address: u32) -> String {
+    symbols
+        .get_name(address as u64)
+        .unwrap_or(format!("UNKNOWN - {address:08X}"))
+}
+
+/// The PCs of the halted core and up to `frames` of its callers.
+fn unwind_stack(
+    core: &mut Core,
+    debug_info: &DebugInfo,
+    frames: usize,
+) -> anyhow::Result<Vec<u32>> {
+    let registers = DebugRegisters::from_core(core);
+    let exception_interface = exception_handler_for_core(core.core_type());
+    let instruction_set = core.instruction_set().ok();
+    // Inlined functions share the PC of the function they are inlined into, so only the
+    // frames of the functions that were called are kept.
+    let mut stack = debug_info
+        .unwind(
+            core,
+            registers,
+            exception_interface.as_ref(),
+            instruction_set,
+            frames + 1,
+        )?
+        .into_iter()
+        .filter(|frame| !frame.is_inlined)
+        .filter_map(|frame| frame.pc.try_into().ok())
+        .collect::<Vec<u32>>();
+    if stack.is_empty() {
+        let pc_reg = core.program_counter();
+        stack.push(core.read_core_reg(pc_reg)?);
+    }
+    Ok(stack)
+}
+
 // Wrapper around addr2line that allows to look up function names
 pub(crate) struct Symbols {
     loader: Loader,
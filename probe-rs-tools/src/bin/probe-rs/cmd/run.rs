@@ -34,6 +34,10 @@ pub struct NormalRunOptions {
     /// Disable hardfault vector catch if its supported on the target.
     #[clap(long, help_heading = "RUN OPTIONS")]
     pub no_catch_hardfault: bool,
+    /// Print the output of the APBUART with this index, in plug&play order, by polling its
+    /// FIFO over the debug link. Only supported on LEON3 targets.
+    #[clap(long, help_heading = "RUN OPTIONS")]
+    pub uart_console: Option<u32>,
 }
 
 /// Options only used when in test run mode
@@ -273,6 +277,7 @@ impl Cmd {
                     catch_hardfault: !self.run_options.no_catch_hardfault,
                     rtt_client: Some(client_handle),
                     semihosting_options,
+                    uart_console: self.run_options.uart_console,
                 },
                 self.shared_options.always_print_stacktrace,
                 &mut target_output_files,
@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::Duration;
+
+use anyhow::Context;
+use probe_rs::config::Registry;
+use probe_rs::live_watch::LiveWatch;
+use probe_rs::probe::list::Lister;
+use probe_rs::{CoreInterface, Endian};
+use time::{OffsetDateTime, UtcOffset, macros::format_description};
+
+use crate::CoreOptions;
+use crate::util::{common_options::ProbeOptions, parse_u64};
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    shared: CoreOptions,
+
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    /// The ELF file to look up symbols in.
+    #[clap(long)]
+    elf: Option<PathBuf>,
+
+    /// The time between two reads of the locations, in milliseconds.
+    #[clap(long, default_value_t = 100)]
+    interval: u64,
+
+    /// The locations to watch: the name of a symbol in the ELF file, or an address with an
+    /// optional size in bytes, like `0x40001000:2`. The size defaults to 4 bytes.
+    #[clap(required = true)]
+    locations: Vec<String>,
+}
+
+impl Cmd {
+    pub fn run(
+        self,
+        registry: &mut Registry,
+        lister: &Lister,
+        utc_offset: UtcOffset,
+    ) -> anyhow::Result<()> {
+        let elf = self
+            .elf
+            .as_ref()
+            .map(|path| {
+                std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))
+            })
+            .transpose()?;
+
+        let mut watch = LiveWatch::new(Duration::from_millis(self.interval));
+        for location in &self.locations {
+            if location.starts_with(|c: char| c.is_ascii_digit()) {
+                let (address, size) = match location.split_once(':') {
+                    Some((address, size)) => (address, size.parse()?),
+                    None => (location.as_str(), 4),
+                };
+                watch.add(location, parse_u64(address)?, size);
+            } else {
+                let elf = elf
+                    .as_deref()
+                    .with_context(|| format!("Watching the symbol {location} needs --elf"))?;
+                watch.add_symbol(elf, location)?;
+            }
+        }
+
+        let (mut session, _probe_options) = self.common.simple_attach(registry, lister)?;
+        let mut core = session.core(self.shared.core)?;
+        let endian = core.endianness()?;
+
+        loop {
+            let changes = watch.poll(&mut core)?;
+            if !changes.is_empty() {
+                let timestamp = OffsetDateTime::now_utc()
+                    .to_offset(utc_offset)
+                    .format(format_description!(
+                        "[hour repr:24]:[minute]:[second].[subsecond digits:3]"
+                    ))
+                    .expect("Incorrect format string. This shouldn't happen.");
+                for change in changes {
+                    let value = format_value(&change.value, endian);
+                    match change.previous {
+                        Some(previous) => println!(
+                            "{timestamp} {} ({:#010x}): {} -> {value}",
+                            change.name,
+                            change.address,
+                            format_value(&previous, endian)
+                        ),
+                        None => println!(
+                            "{timestamp} {} ({:#010x}): {value}",
+                            change.name, change.address
+                        ),
+                    }
+                }
+            }
+            sleep(watch.time_to_next_poll());
+        }
+    }
+}
+
+/// Values of up to 8 bytes are shown as integers, larger ones as bytes.
+fn format_value(value: &[u8], endian: Endian) -> String {
+    if value.len() > 8 {
+        return format!("{value:02x?}");
+    }
+    let mut bytes = [0; 8];
+    match endian {
+        Endian::Little => {
+            bytes[..value.len()].copy_from_slice(value);
+            format!("{:#x}", u64::from_le_bytes(bytes))
+        }
+        Endian::Big => {
+            bytes[8 - value.len()..].copy_from_slice(value);
+            format!("{:#x}", u64::from_be_bytes(bytes))
+        }
+    }
+}
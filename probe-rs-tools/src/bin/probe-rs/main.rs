@@ -129,6 +129,12 @@ impl Cli {
             Subcommand::Erase(cmd) => cmd.run(client).await,
             Subcommand::Trace(cmd) => cmd.run(&mut *client.registry().await, &lister),
             Subcommand::Itm(cmd) => cmd.run(&mut *client.registry().await, &lister),
+            Subcommand::L3stat(cmd) => cmd.run(&mut *client.registry().await, &lister),
+            Subcommand::Leon3Cache(cmd) => cmd.run(&mut *client.registry().await, &lister),
+            Subcommand::Watch(cmd) => cmd.run(&mut *client.registry().await, &lister, utc_offset),
+            Subcommand::DsuTrace(cmd) => {
+                cmd.run(&mut *client.registry().await, &lister, utc_offset)
+            }
             Subcommand::Chip(cmd) => cmd.run(client).await,
             Subcommand::Benchmark(cmd) => cmd.run(&mut *client.registry().await, &lister),
             Subcommand::Profile(cmd) => cmd.run(&mut *client.registry().await, &lister),
@@ -179,9 +185,21 @@ enum Subcommand {
     /// Trace a memory location on the target
     #[clap(name = "trace")]
     Trace(cmd::trace::Cmd),
+    /// Watch memory locations or variables while the target runs
+    #[clap(name = "watch")]
+    Watch(cmd::watch::Cmd),
     /// Configure and monitor ITM trace packets from the target.
     #[clap(name = "itm")]
     Itm(cmd::itm::Cmd),
+    /// Sample the counters of the LEON statistics unit (L3STAT/L4STAT)
+    #[clap(name = "l3stat")]
+    L3stat(cmd::l3stat::Cmd),
+    /// Print the cache configuration of a LEON core and dump its cache lines for an address range
+    #[clap(name = "leon3-cache")]
+    Leon3Cache(cmd::leon3_cache::Cmd),
+    /// Export the instruction or AHB trace buffer of the LEON DSU with wall-clock timestamps
+    #[clap(name = "dsu-trace")]
+    DsuTrace(cmd::dsu_trace::Cmd),
     Chip(cmd::chip::Cmd),
     /// Measure the throughput of the selected debug probe
     Benchmark(cmd::benchmark::Cmd),
@@ -352,6 +370,10 @@ pub struct ElfCliOptions {
     /// considered when `elf` is selected as the format.
     #[clap(long, help_heading = "DOWNLOAD CONFIGURATION")]
     skip_section: Vec<String>,
+    /// Write the sections linked to RAM into the PROM of a LEON3 target, behind a boot loader
+    /// that configures the memory controller, copies them to RAM and starts the image.
+    #[clap(long, help_heading = "DOWNLOAD CONFIGURATION")]
+    prom_boot: bool,
 }
 
 #[derive(clap::Parser, Clone, Serialize, Deserialize, Debug, Default, Schema)]
@@ -392,6 +414,10 @@ pub enum FormatKind {
     #[value(aliases(["ihex", "intelhex"]))]
     Hex,
 
+    /// The image is in Motorola S-record format. For more information, see https://en.wikipedia.org/wiki/SREC_(file_format)
+    #[value(aliases(["s-record", "s19", "s28", "s37", "mot"]))]
+    Srec,
+
     /// The image is in the Executable and Linkable Format (ELF). For more information, see https://en.wikipedia.org/wiki/Executable_and_Linkable_Format
     Elf,
 
@@ -416,6 +442,7 @@ impl FormatKind {
             FormatKind::Target => unreachable!(),
             FormatKind::Bin => probe_rs::flashing::FormatKind::Bin,
             FormatKind::Hex => probe_rs::flashing::FormatKind::Hex,
+            FormatKind::Srec => probe_rs::flashing::FormatKind::Srec,
             FormatKind::Elf => probe_rs::flashing::FormatKind::Elf,
             FormatKind::Uf2 => probe_rs::flashing::FormatKind::Uf2,
             FormatKind::Idf => probe_rs::flashing::FormatKind::Idf,
@@ -417,6 +417,7 @@ pub struct EraseRequest {
 #[derive(Serialize, Deserialize, Schema)]
 pub enum EraseCommand {
     All,
+    Range { start: u64, end: u64 },
 }
 
 pub async fn erase(ctx: &mut RpcContext, _header: VarHeader, request: EraseRequest) -> NoResponse {
@@ -444,6 +445,13 @@ fn erase_impl(
         EraseCommand::All => {
             flashing::erase_all(&mut session, &mut progress, request.read_flasher_rtt)?
         }
+        EraseCommand::Range { start, end } => flashing::erase(
+            &mut session,
+            &mut progress,
+            start,
+            end,
+            request.read_flasher_rtt,
+        )?,
     }
 
     Ok(())
@@ -6,6 +6,7 @@ use anyhow::anyhow;
 use postcard_rpc::header::{VarHeader, VarSeq};
 use postcard_schema::{Schema, schema};
 use probe_rs::{
+    Architecture,
     architecture::{
         arm::{
             self, ApAddress, ApV2Address, ArmDebugInterface,
@@ -18,11 +19,13 @@ use probe_rs::{
             },
             sequences::DefaultArmSequence,
         },
+        leon3::plugnplay::{AddressSpaceKind, Bus, Record},
         riscv::communication_interface::RiscvCommunicationInterface,
         xtensa::communication_interface::{
             XtensaCommunicationInterface, XtensaDebugInterfaceState,
         },
     },
+    config::TargetSelector,
     probe::{Probe, WireProtocol as ProbeRsWireProtocol},
 };
 use serde::{Deserialize, Serialize};
@@ -44,12 +47,13 @@ pub struct TargetInfoRequest {
     pub dry_run: bool,
     pub target_sel: Option<u32>,
     pub protocol: WireProtocol,
+    pub chip: Option<String>,
 }
 
 impl From<&TargetInfoRequest> for ProbeOptions {
     fn from(request: &TargetInfoRequest) -> Self {
         ProbeOptions {
-            chip: None,
+            chip: request.chip.clone(),
             chip_description_path: None,
             protocol: match request.protocol {
                 WireProtocol::Jtag => Some(ProbeRsWireProtocol::Jtag),
@@ -75,6 +79,42 @@ pub async fn target_info(
 
     let probe = probe_options.attach_probe(&ctx.lister())?;
 
+    // LEON3 systems can only be inspected through the debug link described by the target.
+    if let TargetSelector::Specified(target) = probe_options.get_target_selector()?
+        && target.architecture() == Architecture::Sparc
+    {
+        if request.protocol != WireProtocol::Jtag {
+            ctx.publish::<TargetInfoDataTopic>(
+                VarSeq::Seq2(0),
+                &InfoEvent::ProtocolNotSupportedByArch {
+                    architecture: "LEON3".to_string(),
+                    protocol: request.protocol,
+                },
+            )
+            .await?;
+            return Ok(());
+        }
+
+        let result = probe_options
+            .attach_session(probe, TargetSelector::Specified(target))
+            .map_err(anyhow::Error::from)
+            .and_then(|session| {
+                let records = session.plugnplay_records()?;
+                Ok(records.iter().map(PlugnPlayRecordInfo::from).collect())
+            });
+        let event = match result {
+            Ok(records) => InfoEvent::Leon3PlugnPlay(records),
+            Err(error) => InfoEvent::Error {
+                architecture: "LEON3".to_string(),
+                error: format!("{error:?}"),
+            },
+        };
+        ctx.publish::<TargetInfoDataTopic>(VarSeq::Seq2(0), &event)
+            .await?;
+
+        return Ok(());
+    }
+
     if let Err(e) = try_show_info(
         ctx,
         probe,
@@ -121,6 +161,64 @@ pub enum InfoEvent {
         idcode: Option<u32>,
     },
     ArmDp(DebugPortInfo),
+    Leon3PlugnPlay(Vec<PlugnPlayRecordInfo>),
+}
+
+/// A device found in the AMBA plug&play area of a GRLIB design.
+#[derive(Clone, Debug, Serialize, Deserialize, Schema)]
+pub struct PlugnPlayRecordInfo {
+    /// The bus the device is connected to, e.g. `AHB Master`.
+    pub bus: String,
+    pub vendor_id: u8,
+    pub device_id: u16,
+    /// The human-readable name of the device.
+    pub name: String,
+    /// The kind of function the device provides, e.g. `Memory Controller`.
+    pub category: String,
+    pub version: u8,
+    pub irq: u8,
+    pub address_spaces: Vec<PlugnPlayAddressSpace>,
+}
+
+/// An address range of a device found in the AMBA plug&play area.
+#[derive(Clone, Debug, Serialize, Deserialize, Schema)]
+pub struct PlugnPlayAddressSpace {
+    /// The kind of the range, e.g. `AHB I/O`.
+    pub kind: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl From<&Record> for PlugnPlayRecordInfo {
+    fn from(record: &Record) -> Self {
+        Self {
+            bus: match record.bus {
+                Bus::AhbMaster => "AHB Master".to_string(),
+                Bus::AhbSlave => "AHB Slave".to_string(),
+                Bus::Apb { bridge } => format!("APB Slave (bridge {bridge:#010x})"),
+            },
+            vendor_id: record.vendor_id,
+            device_id: record.device_id,
+            name: record.device.to_string(),
+            category: record.device.category().to_string(),
+            version: record.version,
+            irq: record.irq,
+            address_spaces: record
+                .address_spaces
+                .iter()
+                .map(|space| PlugnPlayAddressSpace {
+                    kind: match space.kind {
+                        AddressSpaceKind::ApbIO => "APB",
+                        AddressSpaceKind::AhbMemory => "AHB",
+                        AddressSpaceKind::AhbIO => "AHB I/O",
+                    }
+                    .to_string(),
+                    start: space.addresses.start,
+                    end: space.addresses.end,
+                })
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Serialize, Deserialize, Schema)]
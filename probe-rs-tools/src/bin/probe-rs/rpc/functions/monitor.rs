@@ -17,7 +17,10 @@ use crate::{
 use anyhow::Context;
 use postcard_rpc::{header::VarHeader, server::Sender};
 use postcard_schema::Schema;
-use probe_rs::{BreakpointCause, Core, HaltReason, Session, semihosting::SemihostingCommand};
+use probe_rs::{
+    BreakpointCause, Core, HaltReason, Session, architecture::leon3::apbuart::ApbUart,
+    semihosting::SemihostingCommand,
+};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::{self, error::SendError};
 use tokio_util::sync::CancellationToken;
@@ -55,6 +58,8 @@ pub struct MonitorOptions {
     pub rtt_client: Option<Key<RttClient>>,
     /// Configure the support for semihosting.
     pub semihosting_options: SemihostingOptions,
+    /// The index of the APBUART whose output is polled over the debug link, on LEON3 targets.
+    pub uart_console: Option<u32>,
 }
 
 /// Monitor in normal run mode.
@@ -114,6 +119,10 @@ pub enum RttEvent {
         channel: u32,
         bytes: Vec<u8>,
     },
+    UartOutput {
+        uart: u32,
+        bytes: Vec<u8>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Schema)]
@@ -212,11 +221,33 @@ fn monitor_impl(
         },
     });
 
+    let uart_poller = match request.options.uart_console {
+        Some(index) => {
+            let mut session = shared_session.session_blocking();
+            let uart = session
+                .get_leon3_core(run_loop.core_id)?
+                .apb_uarts()
+                .get(index as usize)
+                .copied()
+                .with_context(|| format!("The target has no APBUART with index {index}"))?;
+            Some(UartPoller {
+                uart,
+                index,
+                sender: |message| {
+                    sender
+                        .send_rtt_event(message)
+                        .context("Failed to send UART event")
+                },
+            })
+        }
+        None => None,
+    };
+
     let exit_reason = run_loop.run_until(
         &shared_session,
         request.options.catch_hardfault,
         request.options.catch_reset,
-        poller,
+        (poller, uart_poller),
         None,
         |halt_reason, core| semihosting_sink.handle_halt(halt_reason, core),
     )?;
@@ -298,6 +329,44 @@ where
     }
 }
 
+/// Polls the transmitter FIFO of an APBUART in FIFO debug mode.
+pub struct UartPoller<S>
+where
+    S: FnMut(RttEvent) -> anyhow::Result<()>,
+{
+    pub uart: ApbUart,
+    pub index: u32,
+    pub sender: S,
+}
+
+impl<S> RunLoopPoller for UartPoller<S>
+where
+    S: FnMut(RttEvent) -> anyhow::Result<()>,
+{
+    fn start(&mut self, core: &mut Core<'_>) -> anyhow::Result<()> {
+        self.uart.enable_console(core)?;
+        Ok(())
+    }
+
+    fn poll(&mut self, core: &mut Core<'_>) -> anyhow::Result<Duration> {
+        let bytes = self.uart.read(core)?;
+        if bytes.is_empty() {
+            return Ok(Duration::from_millis(100));
+        }
+        (self.sender)(RttEvent::UartOutput {
+            uart: self.index,
+            bytes,
+        })?;
+        // The FIFO is small, keep draining it while the application writes.
+        Ok(Duration::ZERO)
+    }
+
+    fn exit(&mut self, core: &mut Core<'_>) -> anyhow::Result<()> {
+        self.uart.disable_console(core)?;
+        Ok(())
+    }
+}
+
 struct MonitorEventHandler<F: FnMut(SemihostingEvent)> {
     semihosting_file_manager: SemihostingFileManager,
     sender: F,
@@ -200,6 +200,27 @@ impl RunLoopPoller for NoopPoller {
     }
 }
 
+impl<A, B> RunLoopPoller for (A, B)
+where
+    A: RunLoopPoller,
+    B: RunLoopPoller,
+{
+    fn start(&mut self, core: &mut Core<'_>) -> Result<()> {
+        self.0.start(core)?;
+        self.1.start(core)
+    }
+
+    fn poll(&mut self, core: &mut Core<'_>) -> Result<Duration> {
+        Ok(self.0.poll(core)?.min(self.1.poll(core)?))
+    }
+
+    fn exit(&mut self, core: &mut Core<'_>) -> Result<()> {
+        let result = self.0.exit(core);
+        self.1.exit(core)?;
+        result
+    }
+}
+
 impl<T> RunLoopPoller for Option<T>
 where
     T: RunLoopPoller,
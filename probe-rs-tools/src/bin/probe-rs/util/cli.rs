@@ -853,6 +853,16 @@ async fn print_monitor_event(
                 )
                 .await;
         }
+        MonitorEvent::Rtt(RttEvent::UartOutput { uart, bytes }) => {
+            print!("{}", String::from_utf8_lossy(&bytes));
+
+            if let Some(remote_processor) = ChannelIdentifier::Unqualified(format!("apbuart{uart}"))
+                .find_in(target_output_files)
+            {
+                // Silently discarding output file errors
+                _ = remote_processor.write_all(&bytes).await;
+            };
+        }
         MonitorEvent::Semihosting(SemihostingEvent::Output { stream, data }) => {
             match stream.as_str() {
                 "stdout" => print!("{data}"),
@@ -123,8 +123,10 @@ pub fn build_loader(
             skip: format_options.bin_options.skip,
         }),
         FormatKind::Hex => Format::Hex,
+        FormatKind::Srec => Format::Srec,
         FormatKind::Elf => Format::Elf(ElfOptions {
             skip_sections: format_options.elf_options.skip_section,
+            prom_boot: format_options.elf_options.prom_boot,
         }),
         FormatKind::Uf2 => Format::Uf2,
         FormatKind::Idf => Format::Idf(IdfOptions {
@@ -7,6 +7,7 @@ use probe_rs::rtt::Error;
 use time::{OffsetDateTime, UtcOffset, macros::format_description};
 
 use std::{
+    borrow::Cow,
     fmt::{self, Write},
     sync::Arc,
 };
@@ -152,6 +153,7 @@ pub struct DefmtStateInner {
 
 impl DefmtStateInner {
     pub fn try_from_bytes(buffer: &[u8]) -> Result<Option<Self>, Error> {
+        let buffer = &*bitflags_values_to_le(buffer);
         let Some(table) =
             defmt_decoder::Table::parse(buffer).with_context(|| "Failed to parse defmt data")?
         else {
@@ -177,6 +179,46 @@ impl DefmtStateInner {
     }
 }
 
+/// Swap the byte order of the `defmt::bitflags!` values in the `.defmt` section of a big-endian
+/// ELF file, like SPARC firmware.
+///
+/// The values are `u128` statics in the byte order of the target, but `defmt-decoder` always
+/// reads them as little-endian. Little-endian files are returned unchanged.
+fn bitflags_values_to_le(buffer: &[u8]) -> Cow<'_, [u8]> {
+    let Ok(elf) = goblin::elf::Elf::parse(buffer) else {
+        return Cow::Borrowed(buffer);
+    };
+    if elf.little_endian {
+        return Cow::Borrowed(buffer);
+    }
+    let Some((defmt_index, defmt_header)) = elf
+        .section_headers
+        .iter()
+        .enumerate()
+        .find(|(_, header)| elf.shdr_strtab.get_at(header.sh_name) == Some(".defmt"))
+    else {
+        return Cow::Borrowed(buffer);
+    };
+
+    let mut swapped = buffer.to_vec();
+    for sym in elf.syms.iter().filter(|sym| sym.st_shndx == defmt_index) {
+        let Some(name) = elf.strtab.get_at(sym.st_name) else {
+            continue;
+        };
+        let is_bitflags_value = serde_json::from_str::<serde_json::Value>(name)
+            .is_ok_and(|symbol| symbol["tag"] == "defmt_bitflags_value");
+        if sym.st_size != 16 || !is_bitflags_value {
+            continue;
+        }
+        // Like `defmt-decoder`, use the address of the symbol as the offset in the section.
+        let start = defmt_header.sh_offset.saturating_add(sym.st_value) as usize;
+        if let Some(value) = swapped.get_mut(start..start.saturating_add(16)) {
+            value.reverse();
+        }
+    }
+    Cow::Owned(swapped)
+}
+
 /// defmt information common to all defmt channels.
 #[derive(Clone)]
 pub struct DefmtState {
@@ -300,3 +342,119 @@ impl DefmtProcessor {
         Ok(formatted_data)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHN_ABS: u16 = 0xfff1;
+
+    /// Build a big-endian SPARC ELF file with a `.defmt` section and the given symbols, each a
+    /// name, value, size and section index.
+    fn sparc_elf(defmt: &[u8], symbols: &[(&str, u32, u32, u16)]) -> Vec<u8> {
+        fn push_u16(out: &mut Vec<u8>, value: u16) {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        fn push_u32(out: &mut Vec<u8>, value: u32) {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        fn align(out: &mut Vec<u8>) {
+            out.resize(out.len().next_multiple_of(4), 0);
+        }
+
+        let mut strtab = vec![0];
+        let mut symtab = vec![0; 16];
+        for &(name, value, size, section) in symbols {
+            push_u32(&mut symtab, strtab.len() as u32);
+            push_u32(&mut symtab, value);
+            push_u32(&mut symtab, size);
+            // STB_GLOBAL, STT_OBJECT
+            symtab.extend_from_slice(&[0x11, 0]);
+            push_u16(&mut symtab, section);
+            strtab.extend_from_slice(name.as_bytes());
+            strtab.push(0);
+        }
+        let shstrtab = b"\0.defmt\0.symtab\0.strtab\0.shstrtab\0";
+
+        let mut elf = vec![0; 52];
+        let mut offsets = vec![];
+        for data in [defmt, &symtab, &strtab, shstrtab] {
+            align(&mut elf);
+            offsets.push(elf.len() as u32);
+            elf.extend_from_slice(data);
+        }
+        align(&mut elf);
+        let shoff = elf.len() as u32;
+
+        // Name, type, offset, size, link, info, alignment and entry size of the sections.
+        let sections = [
+            (0, 0, 0, 0, 0, 0, 0, 0),
+            (1, 1, offsets[0], defmt.len(), 0, 0, 1, 0),
+            (8, 2, offsets[1], symtab.len(), 3, 1, 4, 16),
+            (16, 3, offsets[2], strtab.len(), 0, 0, 1, 0),
+            (24, 3, offsets[3], shstrtab.len(), 0, 0, 1, 0),
+        ];
+        for (name, kind, offset, size, link, info, alignment, entry_size) in sections {
+            for value in [name, kind, 0, 0, offset, size as u32, link, info] {
+                push_u32(&mut elf, value);
+            }
+            push_u32(&mut elf, alignment);
+            push_u32(&mut elf, entry_size);
+        }
+
+        let mut header = b"\x7fELF\x01\x02\x01".to_vec();
+        header.resize(16, 0);
+        push_u16(&mut header, 1); // ET_REL
+        push_u16(&mut header, 2); // EM_SPARC
+        push_u32(&mut header, 1);
+        push_u32(&mut header, 0);
+        push_u32(&mut header, 0);
+        push_u32(&mut header, shoff);
+        push_u32(&mut header, 0);
+        for value in [52, 0, 0, 40, sections.len() as u16, 4] {
+            push_u16(&mut header, value);
+        }
+        elf[..52].copy_from_slice(&header);
+        elf
+    }
+
+    #[test]
+    fn decode_big_endian_sparc_elf() {
+        let bitflags_value = |flag: &str| {
+            format!(
+                r#"{{"package":"app","tag":"defmt_bitflags_value","data":"Flags::{flag}","disambiguator":"1","crate_name":"app"}}"#
+            )
+        };
+        let message = r#"{"package":"app","tag":"defmt_info","data":"value {=u32} flags {=u8:__internal_bitflags_Flags@app@1@app}","disambiguator":"2","crate_name":"app"}"#;
+        let flag_a = bitflags_value("0::A");
+        let flag_b = bitflags_value("1::B");
+
+        // The bitflags values are stored in the byte order of the target.
+        let mut defmt = vec![0; 16];
+        defmt.extend_from_slice(&1u128.to_be_bytes());
+        defmt.extend_from_slice(&2u128.to_be_bytes());
+        let elf = sparc_elf(
+            &defmt,
+            &[
+                ("_defmt_version_ = 4", 0, 0, SHN_ABS),
+                ("_defmt_encoding_ = raw", 0, 0, SHN_ABS),
+                (message, 1, 1, 1),
+                (&flag_a, 16, 16, 1),
+                (&flag_b, 32, 16, 1),
+            ],
+        );
+
+        let state = DefmtState::try_from_bytes(&elf).unwrap().unwrap();
+        // The file has no DWARF information.
+        assert!(state.as_ref().locs.is_none());
+
+        // The wire format is little-endian on every target.
+        let frame = [0x01, 0x00, 0x78, 0x56, 0x34, 0x12, 0x02];
+        let (frame, consumed) = state.table().decode(&frame).unwrap();
+        assert_eq!(consumed, 7);
+        assert_eq!(
+            frame.display_message().to_string(),
+            "value 305419896 flags B"
+        );
+    }
+}
@@ -1,3 +1,5 @@
+//! AHBJTAG debug link, giving access to the AHB bus through JTAG.
+
 use std::time::{Duration, Instant};
 
 use bitvec::{field::BitField as _, slice::BitSlice};
@@ -5,17 +7,31 @@ use itertools::{Itertools as _, Position};
 use scroll::Pread as _;
 
 use crate::{
-    MemoryInterface,
-    architecture::leon3::communication_interface::Leon3Error,
-    memory::{InvalidDataLengthError, MemoryNotAlignedError, valid_32bit_address},
-    probe::{DebugProbeError, Probe},
+    Error as ProbeRsError, MemoryInterface,
+    architecture::leon3::{
+        communication_interface::Leon3Error,
+        word_access::{WordAccess, read_unaligned, write_unaligned},
+    },
+    memory::{InvalidDataLengthError, valid_32bit_address},
+    probe::{
+        CommandQueue, CommandResult, DebugProbeError, DeferredResultIndex, JtagCommand,
+        JtagWriteCommand, Probe,
+    },
 };
 
 const ADATA_LEN: u32 = 35;
 const DDATA_LEN: u32 = 33;
 
-// TODO(darsor): make this configurable
-const JTAG_TIMEOUT: Duration = Duration::from_secs(2);
+/// The default timeout for a single word transaction, see [`AhbJtag::set_timeout`].
+const DEFAULT_JTAG_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Byte and half-word accesses of at least this many bytes are done with 32-bit
+/// sequential transfers. Shorter ones, like peripheral register accesses, keep their size.
+const MIN_BURST_BYTES: usize = 8;
+
+// TODO(darsor): first write transfer response always returns seq=0, which is not handled,
+//   so disable that logic for writes for now
+const CHECK_AHB_WRITE_FINISHED: bool = false;
 
 /// AHBJTAG driver used to access the AHB bus through JTAG.
 #[derive(Debug)]
@@ -23,9 +39,12 @@ pub struct AhbJtag {
     probe: Probe,
     config: probe_rs_target::AhbJtag,
     state: AhbJtagState,
+    timeout: Duration,
+    batched: bool,
 }
 
-#[derive(Debug)]
+/// Transaction state of the AHBJTAG driver.
+#[derive(Debug, Default)]
 pub struct AhbJtagState {
     current_transaction: Option<TransactionState>,
 }
@@ -38,6 +57,7 @@ struct TransactionState {
 }
 
 impl AhbJtagState {
+    /// Creates a new state without a pending transaction.
     pub fn new() -> Self {
         Self {
             current_transaction: None,
@@ -91,6 +111,14 @@ impl Seq {
             Seq::ContinuingTransaction => 1,
         }
     }
+
+    /// The SEQ flag for a word at the given position of a sequential transfer.
+    fn at(position: Position) -> Self {
+        match position {
+            Position::First | Position::Middle => Seq::ContinuingTransaction,
+            Position::Last | Position::Only => Seq::LastTransaction,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -157,14 +185,45 @@ enum TransactionOutcome {
 }
 
 impl AhbJtag {
+    /// Creates a new AHBJTAG driver using the given probe and register configuration.
     pub fn new(probe: Probe, config: probe_rs_target::AhbJtag) -> Self {
         Self {
             probe,
             config,
             state: AhbJtagState::new(),
+            timeout: DEFAULT_JTAG_TIMEOUT,
+            batched: true,
         }
     }
 
+    /// Returns how long to wait for a single word transaction to complete.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Sets how long to wait for a single word transaction to complete.
+    ///
+    /// Slow memories, like wait-stated PROMs, may need a longer timeout, while a shorter
+    /// timeout makes accesses to unmapped addresses fail faster.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Returns whether sequential transfers are sent to the probe as a single batch.
+    pub fn batched(&self) -> bool {
+        self.batched
+    }
+
+    /// Enables or disables batching of sequential transfers.
+    ///
+    /// When enabled (the default), all DDATA shifts of a sequential transfer are queued and
+    /// sent to the probe at once, which saves a round-trip per word on probes that implement
+    /// command queuing. When disabled, every word is shifted and checked individually.
+    pub fn set_batched(&mut self, batched: bool) {
+        self.batched = batched;
+    }
+
+    /// Returns the underlying probe.
     pub fn as_probe(&mut self) -> &mut Probe {
         &mut self.probe
     }
@@ -175,9 +234,7 @@ impl AhbJtag {
         kind: TransactionKind,
         size: TransactionSize,
     ) -> Result<(), DebugProbeError> {
-        let mut cmd = [0u8; 5];
-        cmd[0..4].copy_from_slice(&address.to_le_bytes());
-        cmd[4] = (kind.encode() << 2) | size.encode();
+        let cmd = encode_adata(address, kind, size);
         self.probe
             .try_as_jtag_probe()
             .expect("Should be JTAG probe")
@@ -191,8 +248,6 @@ impl AhbJtag {
         Ok(())
     }
 
-    // TODO(darsor): batch adata and ddata reads to improve speed
-    // TODO(darsor): subsequent ddata reads don't need to write IR again (write_register does)
     fn read_ddata_with_timeout(
         &mut self,
         seq: Seq,
@@ -209,10 +264,7 @@ impl AhbJtag {
                 unreachable!("reading DDATA before writing ADATA");
             }
         }
-        let shift_in = match seq {
-            Seq::LastTransaction => [0; 5],
-            Seq::ContinuingTransaction => [0, 0, 0, 0, 1],
-        };
+        let shift_in = [0, 0, 0, 0, seq.encode()];
 
         let start_time = Instant::now();
         loop {
@@ -226,15 +278,19 @@ impl AhbJtag {
             // interpret the result
             match self.transform_ddata_result(&result) {
                 TransactionOutcome::ReadDone(data) => {
-                    if seq == Seq::LastTransaction {
-                        self.state.current_transaction = None;
+                    match (seq, &mut self.state.current_transaction) {
+                        (Seq::LastTransaction, transaction) => *transaction = None,
+                        (Seq::ContinuingTransaction, Some(transaction)) => {
+                            transaction.address = transaction.address.wrapping_add(4);
+                        }
+                        (Seq::ContinuingTransaction, None) => unreachable!(),
                     }
                     tracing::debug!("Read DDATA: {data:?} ({seq:?})");
                     return Ok(data);
                 }
                 TransactionOutcome::Pending => {
                     if start_time.elapsed() > timeout {
-                        return Err(Leon3Error::Timeout);
+                        return Err(self.bus_error());
                     }
                 }
                 TransactionOutcome::WriteDone => unreachable!("Should be reading"),
@@ -264,10 +320,7 @@ impl AhbJtag {
         } else {
             unreachable!("writing DDATA before writing ADATA");
         }
-        let mut shift_in = match seq {
-            Seq::LastTransaction => [0; 5],
-            Seq::ContinuingTransaction => [0, 0, 0, 0, 1],
-        };
+        let mut shift_in = [0, 0, 0, 0, seq.encode()];
         shift_in[..4].copy_from_slice(&data.encode());
 
         let start_time = Instant::now();
@@ -298,22 +351,32 @@ impl AhbJtag {
         }
     }
 
-    fn transform_ddata_result(&self, response_bits: &BitSlice) -> TransactionOutcome {
-        // TODO(darsor): first write transfer response always returns seq=0, which is not handled,
-        //   so disable that logic for now
-        const CHECK_AHB_FINISHED: bool = false;
-        let seq = response_bits
-            .get(32)
-            .expect("AHBJTAG DDATA reponses should 33 bits");
-        if CHECK_AHB_FINISHED && !seq {
-            // transfer not yet complete
-            return TransactionOutcome::Pending;
+    /// Abandon the current read transaction after the AHB master did not complete it.
+    ///
+    /// The AHB master of the AHBJTAG never completes a transfer that received an ERROR
+    /// response, and the data shifted out of DDATA is not valid. The next access starts
+    /// with a fresh ADATA write, which resynchronizes the AHBJTAG state machine.
+    fn bus_error(&mut self) -> Leon3Error {
+        let transaction = self
+            .state
+            .current_transaction
+            .take()
+            .expect("DDATA accessed before writing ADATA");
+        tracing::warn!("AHB read at 0x{:08X} did not complete", transaction.address);
+        Leon3Error::BusError {
+            address: transaction.address,
         }
+    }
 
+    fn transform_ddata_result(&self, response_bits: &BitSlice) -> TransactionOutcome {
         let Some(transaction) = &self.state.current_transaction else {
             unreachable!("DDATA accessed before writing ADATA");
         };
 
+        if !transfer_complete(response_bits, transaction.kind) {
+            return TransactionOutcome::Pending;
+        }
+
         match transaction.kind {
             TransactionKind::Read => TransactionOutcome::ReadDone(match transaction.size {
                 TransactionSize::U32 => TransactionData::U32(response_bits[0..32].load_le()),
@@ -355,23 +418,78 @@ impl AhbJtag {
 
         // Sequential transfers should not cross a 1 kB boundary.
         // Process transfers in chunks within 1024-byte boundaries
-        for (chunk_idx, chunk) in &data
-            .iter_mut()
-            .enumerate()
-            .chunk_by(|(word_idx, _)| (address + *word_idx as u32 * 4) / 1024)
-        {
-            // write ADATA once for the chunk
-            let start_address = std::cmp::max(address, chunk_idx * 1024);
-            self.write_adata(start_address, TransactionKind::Read, TransactionSize::U32)?;
-
-            // read DDATA for each word in the chunk
-            for (position, (_idx, word)) in chunk.with_position() {
-                let seq = match position {
-                    Position::First | Position::Middle => Seq::ContinuingTransaction,
-                    Position::Last | Position::Only => Seq::LastTransaction,
-                };
-                *word = self.read_ddata_with_timeout(seq, timeout)?.as_u32();
+        let mut offset = 0;
+        while offset < data.len() {
+            let start_address = address + offset as u32 * 4;
+            let chunk_len = sequential_chunk_len(start_address, data.len() - offset);
+            let chunk = &mut data[offset..][..chunk_len];
+
+            if self.batched {
+                self.read32_batched(start_address, chunk, timeout)?;
+            } else {
+                self.write_adata(start_address, TransactionKind::Read, TransactionSize::U32)?;
+                self.read_ddata_words(chunk, timeout)?;
             }
+            offset += chunk_len;
+        }
+        Ok(())
+    }
+
+    /// Read the remaining words of the current sequential read, one DDATA shift at a time.
+    fn read_ddata_words(&mut self, words: &mut [u32], timeout: Duration) -> Result<(), Leon3Error> {
+        for (position, word) in words.iter_mut().with_position() {
+            *word = self
+                .read_ddata_with_timeout(Seq::at(position), timeout)?
+                .as_u32();
+        }
+        Ok(())
+    }
+
+    /// Read a sequential chunk of words, sending ADATA and all DDATA shifts as one batch.
+    ///
+    /// If a transfer was still pending when its DDATA was shifted, the sequence did not
+    /// advance, and the rest of the chunk is read one word at a time instead.
+    fn read32_batched(
+        &mut self,
+        address: u32,
+        words: &mut [u32],
+        timeout: Duration,
+    ) -> Result<(), Leon3Error> {
+        let mut queue = CommandQueue::<JtagCommand>::new();
+        let _adata = queue.schedule(self.adata_command(
+            address,
+            TransactionKind::Read,
+            TransactionSize::U32,
+        ));
+        let indices = (0..words.len())
+            .with_position()
+            .map(|(position, _)| {
+                queue.schedule(JtagWriteCommand {
+                    address: self.config.ddata_addr,
+                    data: vec![0, 0, 0, 0, Seq::at(position).encode()],
+                    len: DDATA_LEN,
+                    transform: |_, response_bits| {
+                        if !transfer_complete(response_bits, TransactionKind::Read) {
+                            return Err(Leon3Error::TransferPending.into());
+                        }
+                        Ok(CommandResult::U32(response_bits[0..32].load_le()))
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let completed = self.execute_batch(&queue, TransactionKind::Read, address, indices)?;
+        let num_completed = completed.len();
+        tracing::debug!(
+            "Read {num_completed} of {} words from 0x{address:08X} in one batch",
+            words.len()
+        );
+        for (word, result) in words.iter_mut().zip(completed) {
+            *word = result.into_u32();
+        }
+
+        if num_completed < words.len() {
+            self.read_ddata_words(&mut words[num_completed..], timeout)?;
         }
         Ok(())
     }
@@ -407,25 +525,151 @@ impl AhbJtag {
 
         // Sequential transfers should not cross a 1 kB boundary.
         // Process transfers in chunks within 1024-byte boundaries
-        for (chunk_idx, chunk) in &data
+        let mut offset = 0;
+        while offset < data.len() {
+            let start_address = address + offset as u32 * 4;
+            let chunk_len = sequential_chunk_len(start_address, data.len() - offset);
+            let chunk = &data[offset..][..chunk_len];
+
+            if self.batched {
+                self.write32_batched(start_address, chunk, timeout)?;
+            } else {
+                self.write_adata(start_address, TransactionKind::Write, TransactionSize::U32)?;
+                self.write_ddata_words(chunk, timeout)?;
+            }
+            offset += chunk_len;
+        }
+        Ok(())
+    }
+
+    /// Write the remaining words of the current sequential write, one DDATA shift at a time.
+    fn write_ddata_words(&mut self, words: &[u32], timeout: Duration) -> Result<(), Leon3Error> {
+        for (position, word) in words.iter().with_position() {
+            self.write_ddata_with_timeout(TransactionData::U32(*word), Seq::at(position), timeout)?;
+        }
+        Ok(())
+    }
+
+    /// Write a sequential chunk of words, sending ADATA and all DDATA shifts as one batch.
+    ///
+    /// If a transfer was still pending when the next DDATA was shifted, the sequence did not
+    /// advance, and the rest of the chunk is written one word at a time instead.
+    fn write32_batched(
+        &mut self,
+        address: u32,
+        words: &[u32],
+        timeout: Duration,
+    ) -> Result<(), Leon3Error> {
+        let mut queue = CommandQueue::<JtagCommand>::new();
+        let _adata = queue.schedule(self.adata_command(
+            address,
+            TransactionKind::Write,
+            TransactionSize::U32,
+        ));
+        let indices = words
             .iter()
-            .enumerate()
-            .chunk_by(|(word_idx, _)| (address + *word_idx as u32 * 4) / 1024)
+            .with_position()
+            .map(|(position, word)| {
+                let mut data = TransactionData::U32(*word).encode().to_vec();
+                data.push(Seq::at(position).encode());
+                queue.schedule(JtagWriteCommand {
+                    address: self.config.ddata_addr,
+                    data,
+                    len: DDATA_LEN,
+                    transform: |_, response_bits| {
+                        if !transfer_complete(response_bits, TransactionKind::Write) {
+                            return Err(Leon3Error::TransferPending.into());
+                        }
+                        Ok(CommandResult::None)
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let completed = self.execute_batch(&queue, TransactionKind::Write, address, indices)?;
+        let num_completed = completed.len();
+        tracing::debug!(
+            "Wrote {num_completed} of {} words to 0x{address:08X} in one batch",
+            words.len()
+        );
+
+        if num_completed < words.len() {
+            self.write_ddata_words(&words[num_completed..], timeout)?;
+        }
+        Ok(())
+    }
+
+    fn adata_command(
+        &self,
+        address: u32,
+        kind: TransactionKind,
+        size: TransactionSize,
+    ) -> JtagWriteCommand {
+        JtagWriteCommand {
+            address: self.config.adata_addr,
+            data: encode_adata(address, kind, size).to_vec(),
+            len: ADATA_LEN,
+            transform: |_, _| Ok(CommandResult::None),
+        }
+    }
+
+    /// Execute a batch of one ADATA write followed by sequential 32-bit DDATA shifts.
+    ///
+    /// Returns the results of the DDATA shifts that completed, in order. If a transfer was
+    /// still pending, the transaction state is left pointing at the first word that has to
+    /// be shifted again.
+    fn execute_batch(
+        &mut self,
+        queue: &CommandQueue<JtagCommand>,
+        kind: TransactionKind,
+        address: u32,
+        indices: Vec<DeferredResultIndex>,
+    ) -> Result<Vec<CommandResult>, Leon3Error> {
+        let num_words = indices.len();
+        let (mut results, error) = match self
+            .probe
+            .try_as_jtag_probe()
+            .expect("Should be JTAG probe")
+            .write_register_batch(queue)
         {
-            // write ADATA once for the chunk
-            let start_address = std::cmp::max(address, chunk_idx * 1024);
-            self.write_adata(start_address, TransactionKind::Write, TransactionSize::U32)?;
-
-            // write DDATA for each word in the chunk
-            for (position, (_idx, word)) in chunk.with_position() {
-                let seq = match position {
-                    Position::First | Position::Middle => Seq::ContinuingTransaction,
-                    Position::Last | Position::Only => Seq::LastTransaction,
+            Ok(results) => (results, None),
+            Err(e) => (e.results, Some(e.error)),
+        };
+
+        let mut completed = Vec::with_capacity(num_words);
+        for index in indices {
+            match results.take(index) {
+                Ok(result) => completed.push(result),
+                Err(_) => break,
+            }
+        }
+
+        match error {
+            None => self.state.current_transaction = None,
+            Some(ProbeRsError::Leon3(Leon3Error::TransferPending)) => {
+                // A batch never crosses a 1 kB boundary, so this only fails for a transfer
+                // that already ran past the end of the address space.
+                let Some(pending_address) = address.checked_add(completed.len() as u32 * 4) else {
+                    self.state.current_transaction = None;
+                    return Err(Leon3Error::OutOfBounds);
                 };
-                self.write_ddata_with_timeout(TransactionData::U32(*word), seq, timeout)?;
+                self.state.current_transaction = Some(TransactionState {
+                    size: TransactionSize::U32,
+                    kind,
+                    address: pending_address,
+                });
+            }
+            Some(error) => {
+                self.state.current_transaction = None;
+                return Err(match error {
+                    ProbeRsError::Leon3(error) => error,
+                    ProbeRsError::Probe(error) => Leon3Error::DebugProbe(error),
+                    other => Leon3Error::DebugProbe(DebugProbeError::Other(other.to_string())),
+                });
             }
         }
-        Ok(())
+
+        Ok(completed)
     }
 
     /// Write a single 16-bit word to the target at the given address.
@@ -453,7 +697,32 @@ impl AhbJtag {
     }
 }
 
-fn check_out_of_bounds(address: u32, num_bytes: usize) -> Result<(), Leon3Error> {
+fn encode_adata(address: u32, kind: TransactionKind, size: TransactionSize) -> [u8; 5] {
+    let mut cmd = [0u8; 5];
+    cmd[0..4].copy_from_slice(&address.to_le_bytes());
+    cmd[4] = (kind.encode() << 2) | size.encode();
+    cmd
+}
+
+/// Returns whether the AHB transfer of a DDATA response has completed.
+fn transfer_complete(response_bits: &BitSlice, kind: TransactionKind) -> bool {
+    let seq = response_bits
+        .get(32)
+        .expect("AHBJTAG DDATA reponses should 33 bits");
+    match kind {
+        TransactionKind::Read => *seq,
+        TransactionKind::Write => !CHECK_AHB_WRITE_FINISHED || *seq,
+    }
+}
+
+/// Returns how many words of a sequential transfer starting at `address` fit before the
+/// next 1 kB boundary.
+fn sequential_chunk_len(address: u32, remaining_words: usize) -> usize {
+    let words_to_boundary = (1024 - address % 1024) as usize / 4;
+    words_to_boundary.min(remaining_words)
+}
+
+pub(super) fn check_out_of_bounds(address: u32, num_bytes: usize) -> Result<(), Leon3Error> {
     if num_bytes > 0 {
         let num_bytes =
             u32::try_from(num_bytes).expect("Number of bytes to read should fit in u32");
@@ -466,14 +735,14 @@ fn check_out_of_bounds(address: u32, num_bytes: usize) -> Result<(), Leon3Error>
     }
 }
 
-fn check_alignment(address: u64, alignment: u64) -> Result<(), crate::Error> {
-    if !address.is_multiple_of(alignment) {
-        return Err(crate::Error::MemoryNotAligned(MemoryNotAlignedError {
-            address,
-            alignment: usize::try_from(alignment).expect("Alignment should fit in a usize"),
-        }));
+impl WordAccess for AhbJtag {
+    fn read_words(&mut self, address: u32, data: &mut [u32]) -> Result<(), Leon3Error> {
+        self.read32_with_timeout(address, data, self.timeout)
+    }
+
+    fn write_words(&mut self, address: u32, data: &[u32]) -> Result<(), Leon3Error> {
+        self.write32_with_timeout(address, data, self.timeout)
     }
-    Ok(())
 }
 
 impl MemoryInterface for AhbJtag {
@@ -482,14 +751,16 @@ impl MemoryInterface for AhbJtag {
     }
 
     fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), crate::Error> {
-        check_alignment(address, 8)?;
+        if !address.is_multiple_of(8) {
+            return read_unaligned(self, address, data);
+        }
         let address = valid_32bit_address(address)?;
         // SAFETY: Alignment transmute is sound between the u64 and u32 types
         let (prefix, data32, suffix) = unsafe { data.align_to_mut::<u32>() };
         assert_eq!(prefix.len(), 0);
         assert_eq!(suffix.len(), 0);
 
-        self.read32_with_timeout(address, data32, JTAG_TIMEOUT)?;
+        self.read32_with_timeout(address, data32, self.timeout)?;
 
         // For a big-endian host, data[0] has
         //   host address offset:  0   1   2   3   4   5   6   7
@@ -512,18 +783,30 @@ impl MemoryInterface for AhbJtag {
     }
 
     fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), crate::Error> {
-        check_alignment(address, 4)?;
+        if !address.is_multiple_of(4) {
+            return read_unaligned(self, address, data);
+        }
         let address = valid_32bit_address(address)?;
-        self.read32_with_timeout(address, data, JTAG_TIMEOUT)?;
+        self.read32_with_timeout(address, data, self.timeout)?;
         Ok(())
     }
 
     fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), crate::Error> {
-        check_alignment(address, 2)?;
+        if !address.is_multiple_of(2) {
+            return read_unaligned(self, address, data);
+        }
         let address = valid_32bit_address(address)?;
         check_out_of_bounds(address, data.len() * 2)?;
+        if data.len() * 2 >= MIN_BURST_BYTES {
+            let mut bytes = vec![0u8; data.len() * 2];
+            self.read_bytes(address, &mut bytes)?;
+            for (word16, bytes) in data.iter_mut().zip(bytes.chunks_exact(2)) {
+                *word16 = u16::from_be_bytes([bytes[0], bytes[1]]);
+            }
+            return Ok(());
+        }
         for (word_idx, word16) in data.iter_mut().enumerate() {
-            *word16 = self.read16_with_timeout(address + 2 * word_idx as u32, JTAG_TIMEOUT)?;
+            *word16 = self.read16_with_timeout(address + 2 * word_idx as u32, self.timeout)?;
         }
         Ok(())
     }
@@ -531,14 +814,20 @@ impl MemoryInterface for AhbJtag {
     fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
         let address = valid_32bit_address(address)?;
         check_out_of_bounds(address, data.len())?;
+        if data.len() >= MIN_BURST_BYTES {
+            self.read_bytes(address, data)?;
+            return Ok(());
+        }
         for (byte_idx, byte) in data.iter_mut().enumerate() {
-            *byte = self.read8_with_timeout(address + byte_idx as u32, JTAG_TIMEOUT)?;
+            *byte = self.read8_with_timeout(address + byte_idx as u32, self.timeout)?;
         }
         Ok(())
     }
 
     fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), crate::Error> {
-        check_alignment(address, 8)?;
+        if !address.is_multiple_of(8) {
+            return write_unaligned(self, address, data);
+        }
         let address = valid_32bit_address(address)?;
         // SAFETY: Alignment transmute is sound between the u64 and u32 types
         let (prefix, words32, suffix) = unsafe { data.align_to::<u32>() };
@@ -546,7 +835,7 @@ impl MemoryInterface for AhbJtag {
         assert_eq!(suffix.len(), 0);
         #[cfg(target_endian = "big")]
         {
-            self.write32_with_timeout(address, words32, JTAG_TIMEOUT)?;
+            self.write32_with_timeout(address, words32, self.timeout)?;
         }
         #[cfg(target_endian = "little")]
         {
@@ -557,24 +846,36 @@ impl MemoryInterface for AhbJtag {
                 buffer32_pair[0] = word32_pair[1];
                 buffer32_pair[1] = word32_pair[0];
             }
-            self.write32_with_timeout(address, &buffer, JTAG_TIMEOUT)?;
+            self.write32_with_timeout(address, &buffer, self.timeout)?;
         }
         Ok(())
     }
 
     fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), crate::Error> {
-        check_alignment(address, 4)?;
+        if !address.is_multiple_of(4) {
+            return write_unaligned(self, address, data);
+        }
         let address = valid_32bit_address(address)?;
-        self.write32_with_timeout(address, data, JTAG_TIMEOUT)?;
+        self.write32_with_timeout(address, data, self.timeout)?;
         Ok(())
     }
 
     fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), crate::Error> {
-        check_alignment(address, 2)?;
+        if !address.is_multiple_of(2) {
+            return write_unaligned(self, address, data);
+        }
         let address = valid_32bit_address(address)?;
         check_out_of_bounds(address, data.len() * 2)?;
+        if data.len() * 2 >= MIN_BURST_BYTES {
+            let bytes = data
+                .iter()
+                .flat_map(|word16| word16.to_be_bytes())
+                .collect::<Vec<_>>();
+            self.write_bytes(address, &bytes)?;
+            return Ok(());
+        }
         for (word_idx, word16) in data.iter().enumerate() {
-            self.write16_with_timeout(address + 2 * word_idx as u32, *word16, JTAG_TIMEOUT)?;
+            self.write16_with_timeout(address + 2 * word_idx as u32, *word16, self.timeout)?;
         }
         Ok(())
     }
@@ -582,8 +883,12 @@ impl MemoryInterface for AhbJtag {
     fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
         let address = valid_32bit_address(address)?;
         check_out_of_bounds(address, data.len())?;
+        if data.len() >= MIN_BURST_BYTES {
+            self.write_bytes(address, data)?;
+            return Ok(());
+        }
         for (byte_idx, byte) in data.iter().enumerate() {
-            self.write8_with_timeout(address + byte_idx as u32, *byte, JTAG_TIMEOUT)?;
+            self.write8_with_timeout(address + byte_idx as u32, *byte, self.timeout)?;
         }
         Ok(())
     }
@@ -650,3 +955,35 @@ impl MemoryInterface for AhbJtag {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::architecture::leon3::mock_ahbjtag::MockAhbJtag;
+
+    #[test]
+    fn sequential_chunks_stop_at_1k_boundary() {
+        assert_eq!(sequential_chunk_len(0x4000_0000, 1000), 256);
+        assert_eq!(sequential_chunk_len(0x4000_03F8, 1000), 2);
+        assert_eq!(sequential_chunk_len(0x4000_0100, 10), 10);
+        assert_eq!(sequential_chunk_len(0x4000_03FC, 1), 1);
+    }
+
+    #[test]
+    fn batch_errors_are_returned() {
+        let link = MockAhbJtag::new();
+        link.set_word(0x4000_0000, 0x1234_5678);
+        let mut ahbjtag = AhbJtag::new(link.probe(), MockAhbJtag::config());
+        let mut words = [0; 2];
+        ahbjtag.read_words(0x4000_0000, &mut words).unwrap();
+        assert_eq!(words, [0x1234_5678, 0]);
+
+        link.fail_batches("USB transfer failed");
+        let error = ahbjtag.read_words(0x4000_0000, &mut words).unwrap_err();
+        assert!(matches!(
+            error,
+            Leon3Error::DebugProbe(DebugProbeError::Other(_))
+        ));
+        assert!(ahbjtag.state.current_transaction.is_none());
+    }
+}
@@ -0,0 +1,319 @@
+//! AHBUART debug link, giving access to the AHB bus through the serial debug interface of
+//! GRLIB systems.
+//!
+//! The AHBUART detects the baud rate from two `0x55` synchronization bytes sent after it is
+//! opened. Each command starts with a control byte holding the direction and the number of
+//! words of a sequential transfer, followed by the AHB address. Write commands carry the data
+//! words, read commands are answered with the data words. All words are big-endian.
+
+use std::{
+    io::{Read as _, Write as _},
+    time::Duration,
+};
+
+use serialport::SerialPort;
+
+use crate::{
+    MemoryInterface,
+    architecture::leon3::{
+        ahbjtag::check_out_of_bounds,
+        communication_interface::Leon3Error,
+        word_access::{WordAccess, read_unaligned, write_unaligned},
+    },
+    memory::{InvalidDataLengthError, valid_32bit_address},
+};
+
+/// The baud rate used if none is given.
+pub const DEFAULT_AHBUART_BAUD_RATE: u32 = 115_200;
+
+/// The maximum number of words of a single command. Sequential transfers also must not
+/// cross a 1 kB boundary.
+const MAX_WORDS: usize = 64;
+
+/// The default time to wait for the response to a single read command.
+const DEFAULT_AHBUART_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The byte the AHBUART detects the baud rate from.
+const SYNC: u8 = 0x55;
+
+/// Errors of the AHBUART debug link.
+#[derive(thiserror::Error, Debug)]
+pub enum AhbUartError {
+    /// The serial port could not be parsed.
+    #[error("Invalid AHBUART port {0:?}, expected PORT[:BAUD]")]
+    InvalidPort(String),
+    /// Opening the serial port failed.
+    #[error("Failed to open the AHBUART serial port")]
+    Open(#[from] serialport::Error),
+    /// Sending or receiving data failed.
+    #[error("AHBUART transfer failed")]
+    Io(#[from] std::io::Error),
+}
+
+/// AHBUART driver used to access the AHB bus over a serial port.
+pub struct AhbUart {
+    port: Box<dyn SerialPort>,
+}
+
+impl std::fmt::Debug for AhbUart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AhbUart")
+            .field("port", &self.port.name())
+            .finish_non_exhaustive()
+    }
+}
+
+impl AhbUart {
+    /// Opens the AHBUART on the serial port `port`, given as `PORT[:BAUD]`.
+    pub fn open(port: &str) -> Result<Self, AhbUartError> {
+        let (name, baud_rate) = parse_port(port)?;
+        let mut port = serialport::new(name, baud_rate)
+            .timeout(DEFAULT_AHBUART_TIMEOUT)
+            .open()?;
+        port.write_all(&[SYNC, SYNC])?;
+        port.flush()?;
+        tracing::info!("Using AHBUART on {name} at {baud_rate} baud");
+
+        Ok(Self { port })
+    }
+
+    /// Returns how long to wait for the response to a single read command.
+    pub fn timeout(&self) -> Duration {
+        self.port.timeout()
+    }
+
+    /// Sets how long to wait for the response to a single read command.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        if let Err(error) = self.port.set_timeout(timeout) {
+            tracing::warn!("Failed to set the AHBUART timeout: {error}");
+        }
+    }
+
+    fn read_chunk(&mut self, address: u32, data: &mut [u32]) -> Result<(), AhbUartError> {
+        let command = encode_command(address, false, data.len());
+        self.port.write_all(&command)?;
+
+        let mut response = vec![0u8; data.len() * 4];
+        self.port.read_exact(&mut response)?;
+        for (word, bytes) in data.iter_mut().zip(response.chunks_exact(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, address: u32, data: &[u32]) -> Result<(), AhbUartError> {
+        let mut command = encode_command(address, true, data.len());
+        command.extend(data.iter().flat_map(|word| word.to_be_bytes()));
+        self.port.write_all(&command)?;
+        Ok(())
+    }
+}
+
+/// Splits `PORT[:BAUD]` into the port name and the baud rate.
+fn parse_port(port: &str) -> Result<(&str, u32), AhbUartError> {
+    let (name, baud_rate) = match port.rsplit_once(':') {
+        Some((name, baud_rate)) if !baud_rate.is_empty() => {
+            let baud_rate = baud_rate
+                .parse()
+                .map_err(|_| AhbUartError::InvalidPort(port.to_string()))?;
+            (name, baud_rate)
+        }
+        _ => (port, DEFAULT_AHBUART_BAUD_RATE),
+    };
+    if name.is_empty() {
+        return Err(AhbUartError::InvalidPort(port.to_string()));
+    }
+    Ok((name, baud_rate))
+}
+
+/// Encodes the control byte and address of a command for `num_words > 0` words.
+fn encode_command(address: u32, write: bool, num_words: usize) -> Vec<u8> {
+    let control = 0x80 | (u8::from(write) << 6) | (num_words as u8 - 1);
+    let mut command = Vec::with_capacity(5 + num_words * 4);
+    command.push(control);
+    command.extend_from_slice(&address.to_be_bytes());
+    command
+}
+
+/// Returns how many words of a sequential transfer starting at `address` fit into a command.
+fn chunk_len(address: u32, remaining_words: usize) -> usize {
+    let words_to_boundary = (1024 - address % 1024) as usize / 4;
+    words_to_boundary.min(remaining_words).min(MAX_WORDS)
+}
+
+impl WordAccess for AhbUart {
+    fn read_words(&mut self, address: u32, data: &mut [u32]) -> Result<(), Leon3Error> {
+        check_out_of_bounds(address, data.len() * 4)?;
+        let mut offset = 0;
+        while offset < data.len() {
+            let chunk_address = address + offset as u32 * 4;
+            let len = chunk_len(chunk_address, data.len() - offset);
+            self.read_chunk(chunk_address, &mut data[offset..][..len])?;
+            offset += len;
+        }
+        Ok(())
+    }
+
+    fn write_words(&mut self, address: u32, data: &[u32]) -> Result<(), Leon3Error> {
+        check_out_of_bounds(address, data.len() * 4)?;
+        let mut offset = 0;
+        while offset < data.len() {
+            let chunk_address = address + offset as u32 * 4;
+            let len = chunk_len(chunk_address, data.len() - offset);
+            self.write_chunk(chunk_address, &data[offset..][..len])?;
+            offset += len;
+        }
+        Ok(())
+    }
+}
+
+impl MemoryInterface for AhbUart {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        false
+    }
+
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(8) {
+            return read_unaligned(self, address, data);
+        }
+        let address = valid_32bit_address(address)?;
+        let mut words = vec![0u32; data.len() * 2];
+        self.read_words(address, &mut words)?;
+        for (word64, pair) in data.iter_mut().zip(words.chunks_exact(2)) {
+            // The target is big-endian, the word at the lower address holds the upper half.
+            *word64 = (u64::from(pair[0]) << 32) | u64::from(pair[1]);
+        }
+        Ok(())
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(4) {
+            return read_unaligned(self, address, data);
+        }
+        let address = valid_32bit_address(address)?;
+        self.read_words(address, data)?;
+        Ok(())
+    }
+
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), crate::Error> {
+        let mut bytes = vec![0u8; data.len() * 2];
+        self.read_8(address, &mut bytes)?;
+        for (word16, bytes) in data.iter_mut().zip(bytes.chunks_exact(2)) {
+            *word16 = u16::from_be_bytes([bytes[0], bytes[1]]);
+        }
+        Ok(())
+    }
+
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        let address = valid_32bit_address(address)?;
+        self.read_bytes(address, data)?;
+        Ok(())
+    }
+
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(8) {
+            return write_unaligned(self, address, data);
+        }
+        let address = valid_32bit_address(address)?;
+        let words = data
+            .iter()
+            .flat_map(|word64| [(word64 >> 32) as u32, *word64 as u32])
+            .collect::<Vec<_>>();
+        self.write_words(address, &words)?;
+        Ok(())
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(4) {
+            return write_unaligned(self, address, data);
+        }
+        let address = valid_32bit_address(address)?;
+        self.write_words(address, data)?;
+        Ok(())
+    }
+
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), crate::Error> {
+        let bytes = data
+            .iter()
+            .flat_map(|word16| word16.to_be_bytes())
+            .collect::<Vec<_>>();
+        self.write_8(address, &bytes)
+    }
+
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        let address = valid_32bit_address(address)?;
+        self.write_bytes(address, data)?;
+        Ok(())
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, crate::Error> {
+        Ok(true)
+    }
+
+    fn flush(&mut self) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn read_mem_64bit(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(8) {
+            return Err(InvalidDataLengthError::new("read_mem_64bit", 8).into());
+        }
+        self.read_8(address, data)
+    }
+
+    fn read_mem_32bit(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(4) {
+            return Err(InvalidDataLengthError::new("read_mem_32bit", 4).into());
+        }
+        self.read_8(address, data)
+    }
+
+    fn write_mem_64bit(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(8) {
+            return Err(InvalidDataLengthError::new("write_mem_64bit", 8).into());
+        }
+        self.write_8(address, data)
+    }
+
+    fn write_mem_32bit(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(4) {
+            return Err(InvalidDataLengthError::new("write_mem_32bit", 4).into());
+        }
+        self.write_8(address, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_commands() {
+        assert_eq!(
+            encode_command(0x4000_0100, false, 16),
+            [0x8F, 0x40, 0x00, 0x01, 0x00]
+        );
+        assert_eq!(
+            encode_command(0x9000_0000, true, 64),
+            [0xFF, 0x90, 0x00, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn chunks_stop_at_1k_boundary() {
+        assert_eq!(chunk_len(0x4000_0000, 1000), 64);
+        assert_eq!(chunk_len(0x4000_03F0, 1000), 4);
+        assert_eq!(chunk_len(0x4000_0000, 3), 3);
+    }
+
+    #[test]
+    fn parse_ports() {
+        assert_eq!(
+            parse_port("/dev/ttyUSB0").unwrap(),
+            ("/dev/ttyUSB0", DEFAULT_AHBUART_BAUD_RATE)
+        );
+        assert_eq!(parse_port("COM3:460800").unwrap(), ("COM3", 460_800));
+        assert!(parse_port("/dev/ttyUSB0:fast").is_err());
+        assert!(parse_port(":115200").is_err());
+    }
+}
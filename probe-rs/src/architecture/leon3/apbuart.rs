@@ -0,0 +1,149 @@
+//! A virtual console on an APBUART, whose FIFOs are accessed over the debug link.
+//!
+//! In FIFO debug mode the transmitter FIFO is not drained by the UART, but read through the
+//! FIFO debug register, and characters written to that register are put into the receiver
+//! FIFO. The application keeps using the UART as usual, while the debugger presents its
+//! output and input as a console, like the `-u` option of GRMON.
+
+use crate::{
+    MemoryInterface, MemoryMappedRegister,
+    architecture::leon3::{
+        communication_interface::{Leon3CommunicationInterface, Leon3Error},
+        plugnplay::{Device, GaislerDevice, Record},
+    },
+    memory_mapped_bitfield_register,
+};
+
+/// Offset of the FIFO debug register.
+const FIFO_DEBUG: u64 = 0x10;
+
+memory_mapped_bitfield_register! {
+    /// APBUART Status Register (GRLIB IP Core User's Manual, APBUART)
+    pub struct UartStatus(u32);
+    0x04, "uart_status",
+    impl From;
+    /// Receiver FIFO count (RCNT) - the number of characters in the receiver FIFO.
+    pub u8, rcnt, _: 31, 26;
+    /// Transmitter FIFO count (TCNT) - the number of characters in the transmitter FIFO.
+    pub u8, tcnt, _: 25, 20;
+    /// Receiver FIFO full (RF).
+    pub rf, _: 10;
+    /// Transmitter FIFO full (TF).
+    pub tf, _: 9;
+    /// Data ready (DR) - if set, the receiver holds a character.
+    pub dr, _: 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// APBUART Control Register (GRLIB IP Core User's Manual, APBUART)
+    pub struct UartControl(u32);
+    0x08, "uart_control",
+    impl From;
+    /// FIFOs available (FA) - if set, the UART has transmitter and receiver FIFOs.
+    pub fa, _: 31;
+    /// FIFO debug mode enable (DB) - if set, the transmitter FIFO is read, and the receiver
+    /// FIFO written, through the FIFO debug register.
+    pub db, set_db: 11;
+    /// Transmitter enable (TE).
+    pub te, set_te: 1;
+    /// Receiver enable (RE).
+    pub re, set_re: 0;
+}
+
+/// An APBUART used as a virtual console.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApbUart {
+    base: u64,
+}
+
+impl ApbUart {
+    /// All APBUARTs of the system, in plug&play order.
+    pub(crate) fn find_all(interface: &mut Leon3CommunicationInterface) -> Vec<Self> {
+        interface
+            .plugnplay()
+            .find_devices(Device::Gaisler(GaislerDevice::APBUART))
+            .filter_map(Record::base_address)
+            .map(|base| Self { base })
+            .collect()
+    }
+
+    /// The base address of the UART registers.
+    pub fn base_address(&self) -> u64 {
+        self.base
+    }
+
+    fn read_control(
+        &self,
+        memory: &mut (impl MemoryInterface + ?Sized),
+    ) -> Result<UartControl, crate::Error> {
+        Ok(UartControl(
+            memory.read_word_32(self.base + UartControl::ADDRESS_OFFSET)?,
+        ))
+    }
+
+    fn read_status(
+        &self,
+        memory: &mut (impl MemoryInterface + ?Sized),
+    ) -> Result<UartStatus, crate::Error> {
+        Ok(UartStatus(
+            memory.read_word_32(self.base + UartStatus::ADDRESS_OFFSET)?,
+        ))
+    }
+
+    /// Put the UART into FIFO debug mode, so that its output is kept for [`ApbUart::read`].
+    ///
+    /// The transmitter and receiver are enabled as well, in case the application has not set
+    /// up the UART yet. UARTs without FIFOs have no FIFO debug mode.
+    pub fn enable_console(
+        &self,
+        memory: &mut (impl MemoryInterface + ?Sized),
+    ) -> Result<(), crate::Error> {
+        let mut control = self.read_control(memory)?;
+        if !control.fa() {
+            return Err(Leon3Error::UartWithoutFifo(self.base).into());
+        }
+        control.set_db(true);
+        control.set_te(true);
+        control.set_re(true);
+        memory.write_word_32(self.base + UartControl::ADDRESS_OFFSET, control.into())
+    }
+
+    /// Leave FIFO debug mode, so that the UART transmits its output on the serial line again.
+    pub fn disable_console(
+        &self,
+        memory: &mut (impl MemoryInterface + ?Sized),
+    ) -> Result<(), crate::Error> {
+        let mut control = self.read_control(memory)?;
+        control.set_db(false);
+        memory.write_word_32(self.base + UartControl::ADDRESS_OFFSET, control.into())
+    }
+
+    /// Read the characters the application has written to the transmitter FIFO.
+    pub fn read(
+        &self,
+        memory: &mut (impl MemoryInterface + ?Sized),
+    ) -> Result<Vec<u8>, crate::Error> {
+        let count = self.read_status(memory)?.tcnt();
+        (0..count)
+            .map(|_| Ok(memory.read_word_32(self.base + FIFO_DEBUG)? as u8))
+            .collect()
+    }
+
+    /// Put characters into the receiver FIFO, for the application to read.
+    ///
+    /// Returns the number of characters written, which is less than `data.len()` if the
+    /// receiver FIFO filled up.
+    pub fn write(
+        &self,
+        memory: &mut (impl MemoryInterface + ?Sized),
+        data: &[u8],
+    ) -> Result<usize, crate::Error> {
+        for (written, &byte) in data.iter().enumerate() {
+            if self.read_status(memory)?.rf() {
+                return Ok(written);
+            }
+            memory.write_word_32(self.base + FIFO_DEBUG, u32::from(byte))?;
+        }
+        Ok(data.len())
+    }
+}
@@ -0,0 +1,297 @@
+//! Boot images that start an image linked to RAM from the PROM, like the ones built by the
+//! mkprom tools of the LEON toolchains.
+//!
+//! The boot loader at the start of the image runs from the reset address. It configures the
+//! memory controller, copies the sections of the image from the PROM to RAM and jumps to the
+//! entry point with the trap base register pointing at the trap table there. The sections
+//! follow the boot loader in the PROM.
+
+use probe_rs_target::Leon3RegisterWrite;
+
+use crate::architecture::leon3::{communication_interface::Leon3Error, mctrl::SdramBootSetup};
+
+/// The PSR of the boot loader: supervisor mode, all interrupts masked, traps disabled and
+/// the first register window.
+const BOOT_PSR: i32 = 0xFC0;
+
+/// The WIM of the boot loader, which marks the window after the first one invalid.
+const BOOT_WIM: i32 = 0b10;
+
+/// The size of the register window save area at the top of the initial stack.
+const MIN_STACK_FRAME: i32 = 96;
+
+const NOP: u32 = 0x0100_0000;
+
+// Opcodes of the instructions the boot loader uses.
+const OP3_ADD: u32 = 0x00;
+const OP3_AND: u32 = 0x01;
+const OP3_OR: u32 = 0x02;
+const OP3_SUB: u32 = 0x04;
+const OP3_ANDN: u32 = 0x05;
+const OP3_ANDCC: u32 = 0x11;
+const OP3_SUBCC: u32 = 0x14;
+const OP3_WRPSR: u32 = 0x31;
+const OP3_WRWIM: u32 = 0x32;
+const OP3_WRTBR: u32 = 0x33;
+const OP3_JMPL: u32 = 0x38;
+const OP3_FLUSH: u32 = 0x3B;
+const OP3_LD: u32 = 0x00;
+const OP3_ST: u32 = 0x04;
+
+// The registers the boot loader uses.
+const G0: u32 = 0;
+const G1: u32 = 1;
+const G2: u32 = 2;
+const G3: u32 = 3;
+const G4: u32 = 4;
+const O0: u32 = 8;
+const O1: u32 = 9;
+const O2: u32 = 10;
+const O3: u32 = 11;
+const SP: u32 = 14;
+const FP: u32 = 30;
+
+/// A section of the image, which the boot loader copies to RAM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BootSection<'data> {
+    /// The RAM address of the section.
+    pub(crate) address: u32,
+    /// The contents of the section.
+    pub(crate) data: &'data [u8],
+}
+
+/// The parameters of a boot image.
+#[derive(Debug, Clone)]
+pub(crate) struct BootImage<'data> {
+    /// The address of the PROM the image is written to, which is the reset address.
+    pub(crate) prom_base: u32,
+    /// The register writes that configure the memory controller.
+    pub(crate) memory_init: Vec<Leon3RegisterWrite>,
+    /// How to enable the SDRAM, if the target has any.
+    pub(crate) sdram: Option<SdramBootSetup>,
+    /// The sections copied to RAM.
+    pub(crate) sections: Vec<BootSection<'data>>,
+    /// The entry point of the image, where its trap table is.
+    pub(crate) entry: u32,
+    /// The end of the initial stack.
+    pub(crate) stack: u32,
+}
+
+impl BootImage<'_> {
+    /// Build the contents of the PROM: the boot loader followed by the sections.
+    pub(crate) fn build(&self) -> Result<Vec<u8>, Leon3Error> {
+        if let Some(section) = self
+            .sections
+            .iter()
+            .find(|section| section.address % 4 != 0)
+        {
+            return Err(Leon3Error::InvalidBootImage(format!(
+                "the section at {:#010x} is not word aligned",
+                section.address
+            )));
+        }
+
+        // The boot loader has the same size for any section addresses.
+        let size = self.boot_loader(&vec![0; self.sections.len()]).len() as u32 * 4;
+        let mut sources = vec![];
+        let mut end = self.prom_base + size;
+        for section in &self.sections {
+            sources.push(end);
+            end += padded_len(section.data);
+        }
+
+        let mut image: Vec<u8> = self
+            .boot_loader(&sources)
+            .into_iter()
+            .flat_map(u32::to_be_bytes)
+            .collect();
+        for section in &self.sections {
+            image.extend_from_slice(section.data);
+            image.resize(image.len().next_multiple_of(4), 0);
+        }
+        Ok(image)
+    }
+
+    /// The code of the boot loader, which copies each section from the PROM address in
+    /// `sources`.
+    fn boot_loader(&self, sources: &[u32]) -> Vec<u32> {
+        let mut code = vec![
+            arith_imm(OP3_WRPSR, G0, G0, BOOT_PSR),
+            arith_imm(OP3_WRWIM, G0, G0, BOOT_WIM),
+            NOP,
+            NOP,
+            NOP,
+        ];
+
+        let sdram_writes = self.sdram.iter().flat_map(|sdram| &sdram.writes);
+        for write in self.memory_init.iter().chain(sdram_writes) {
+            code.extend(set(write.address as u32, G1));
+            code.extend(set(write.value, G2));
+            if let Some(mask) = write.mask {
+                code.extend(set(mask, G4));
+                code.extend([
+                    memory(OP3_LD, G3, G1),
+                    arith(OP3_ANDN, G3, G3, G4),
+                    arith(OP3_AND, G2, G2, G4),
+                    arith(OP3_OR, G2, G3, G2),
+                ]);
+            }
+            code.push(memory(OP3_ST, G2, G1));
+        }
+
+        if let Some(sdram) = &self.sdram {
+            code.extend(set(sdram.mcfg2 as u32, G1));
+            code.extend(set(sdram.sdcmd_mask, G4));
+            for command in &sdram.commands {
+                code.extend(set(*command, G2));
+                code.extend([
+                    memory(OP3_LD, G3, G1),
+                    arith(OP3_ANDN, G3, G3, G4),
+                    arith(OP3_OR, G3, G3, G2),
+                    memory(OP3_ST, G3, G1),
+                    // Wait for the command to complete.
+                    memory(OP3_LD, G3, G1),
+                    arith(OP3_ANDCC, G0, G3, G4),
+                    bne(-2),
+                    NOP,
+                ]);
+            }
+        }
+
+        for (section, source) in self.sections.iter().zip(sources) {
+            code.extend(set(*source, O0));
+            code.extend(set(section.address, O1));
+            code.extend(set(padded_len(section.data), O2));
+            code.extend([
+                memory(OP3_LD, O3, O0),
+                arith_imm(OP3_ADD, O0, O0, 4),
+                memory(OP3_ST, O3, O1),
+                arith_imm(OP3_SUBCC, O2, O2, 4),
+                bne(-4),
+                arith_imm(OP3_ADD, O1, O1, 4),
+            ]);
+        }
+
+        code.push(arith(OP3_FLUSH, G0, G0, G0));
+        code.extend(set(self.stack & !0xF, FP));
+        code.push(arith_imm(OP3_SUB, SP, FP, MIN_STACK_FRAME));
+        code.extend(set(self.entry, G1));
+        code.extend([
+            arith(OP3_WRTBR, G0, G1, G0),
+            NOP,
+            NOP,
+            NOP,
+            arith_imm(OP3_JMPL, G0, G1, 0),
+            NOP,
+        ]);
+        code
+    }
+}
+
+/// The length of `data` rounded up to whole words.
+fn padded_len(data: &[u8]) -> u32 {
+    data.len().next_multiple_of(4) as u32
+}
+
+/// `set value, rd`, which always takes two instructions.
+fn set(value: u32, rd: u32) -> [u32; 2] {
+    [
+        rd << 25 | 0b100 << 22 | value >> 10,
+        arith_imm(OP3_OR, rd, rd, (value & 0x3FF) as i32),
+    ]
+}
+
+/// An arithmetic instruction with two source registers.
+fn arith(op3: u32, rd: u32, rs1: u32, rs2: u32) -> u32 {
+    2 << 30 | rd << 25 | op3 << 19 | rs1 << 14 | rs2
+}
+
+/// An arithmetic instruction with a source register and a signed 13-bit immediate.
+fn arith_imm(op3: u32, rd: u32, rs1: u32, simm13: i32) -> u32 {
+    2 << 30 | rd << 25 | op3 << 19 | rs1 << 14 | 1 << 13 | (simm13 as u32 & 0x1FFF)
+}
+
+/// A load or store of the word at the address in `rs1`.
+fn memory(op3: u32, rd: u32, rs1: u32) -> u32 {
+    3 << 30 | rd << 25 | op3 << 19 | rs1 << 14 | 1 << 13
+}
+
+/// A `bne` to `displacement` instructions from the branch.
+fn bne(displacement: i32) -> u32 {
+    0b1001 << 25 | 0b010 << 22 | (displacement as u32 & 0x3F_FFFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::architecture::leon3::crc::CRC32_ROUTINE;
+
+    #[test]
+    fn encodings() {
+        // Instructions of the CRC routine.
+        assert_eq!(set(0xEDB8_8320, 12), [CRC32_ROUTINE[0], CRC32_ROUTINE[1]]);
+        assert_eq!(arith(0x03, 10, 10, 11), CRC32_ROUTINE[7]);
+        assert_eq!(arith_imm(OP3_ANDCC, G0, O2, 1), CRC32_ROUTINE[9]);
+        assert_eq!(arith_imm(OP3_SUBCC, O1, O1, 1), CRC32_ROUTINE[16]);
+        assert_eq!(bne(-5), CRC32_ROUTINE[14]);
+
+        assert_eq!(memory(OP3_LD, G3, G1), 0xC600_6000);
+        assert_eq!(memory(OP3_ST, G2, G1), 0xC420_6000);
+        assert_eq!(arith_imm(OP3_WRPSR, G0, G0, BOOT_PSR), 0x8188_2FC0);
+        assert_eq!(arith(OP3_WRTBR, G0, G1, G0), 0x8198_4000);
+        assert_eq!(arith_imm(OP3_JMPL, G0, G1, 0), 0x81C0_6000);
+        assert_eq!(arith(OP3_FLUSH, G0, G0, G0), 0x81D8_0000);
+    }
+
+    #[test]
+    fn sections_follow_the_boot_loader() {
+        let text = [1, 2, 3, 4, 5, 6];
+        let data = [7, 8, 9, 10];
+        let image = BootImage {
+            prom_base: 0,
+            memory_init: vec![],
+            sdram: None,
+            sections: vec![
+                BootSection {
+                    address: 0x4000_0000,
+                    data: &text,
+                },
+                BootSection {
+                    address: 0x4000_1000,
+                    data: &data,
+                },
+            ],
+            entry: 0x4000_0000,
+            stack: 0x4010_0000,
+        };
+        let built = image.build().unwrap();
+        let loader = image.boot_loader(&[0, 0]);
+        let sections = &built[4 * loader.len()..];
+        assert_eq!(sections, [1, 2, 3, 4, 5, 6, 0, 0, 7, 8, 9, 10]);
+
+        // The first copy loop reads from the end of the boot loader.
+        let source = 4 * loader.len() as u32;
+        let code: Vec<_> = built
+            .chunks(4)
+            .map(|word| u32::from_be_bytes(word.try_into().unwrap()))
+            .collect();
+        assert!(code.windows(2).any(|words| words == set(source, O0)));
+        assert!(code.windows(2).any(|words| words == set(source + 8, O0)));
+    }
+
+    #[test]
+    fn reject_unaligned_sections() {
+        let image = BootImage {
+            prom_base: 0,
+            memory_init: vec![],
+            sdram: None,
+            sections: vec![BootSection {
+                address: 0x4000_0002,
+                data: &[0],
+            }],
+            entry: 0x4000_0000,
+            stack: 0x4010_0000,
+        };
+        assert!(image.build().is_err());
+    }
+}
@@ -0,0 +1,327 @@
+//! LEON3 cache control.
+
+use crate::memory_mapped_bitfield_register;
+
+/// The ASI through which the cache control register is accessed.
+pub const ASI_CACHE_CONTROL: u8 = 0x02;
+
+memory_mapped_bitfield_register! {
+    /// Cache Control Register (GRLIB IP Core User's Manual 86.9.3)
+    ///
+    /// Controls the operation of the instruction and data caches. The register is
+    /// located at address 0x0 in ASI 2.
+    pub struct Ccr(u32);
+    0x00, "ccr",
+    impl From;
+    /// Data cache snoop enable (DS) - if set, will enable data cache snooping.
+    pub ds, set_ds: 23;
+    /// Flush data cache (FD) - if set, will flush the data cache. Always reads as zero.
+    pub fd, set_fd: 22;
+    /// Flush instruction cache (FI) - if set, will flush the instruction cache. Always reads as zero.
+    pub fi, set_fi: 21;
+    /// Instruction burst fetch (IB) - enables burst fill during instruction fetch.
+    pub ib, set_ib: 16;
+    /// Instruction cache flush pending (IP) - set while an instruction cache flush is in progress.
+    pub ip, _: 15;
+    /// Data cache flush pending (DP) - set while a data cache flush is in progress.
+    pub dp, _: 14;
+    /// Instruction cache tag error counter (ITE) - the number of detected errors in the
+    /// instruction cache tags, saturating at 3. Only implemented on fault-tolerant cores.
+    pub u8, ite, set_ite: 13, 12;
+    /// Instruction cache data error counter (IDE) - the number of detected errors in the
+    /// instruction cache data, saturating at 3. Only implemented on fault-tolerant cores.
+    pub u8, ide, set_ide: 11, 10;
+    /// Data cache tag error counter (DTE) - the number of detected errors in the data cache
+    /// tags, saturating at 3. Only implemented on fault-tolerant cores.
+    pub u8, dte, set_dte: 9, 8;
+    /// Data cache data error counter (DDE) - the number of detected errors in the data cache
+    /// data, saturating at 3. Only implemented on fault-tolerant cores.
+    pub u8, dde, set_dde: 7, 6;
+    /// Data cache freeze on interrupt (DF) - if set, the data cache will automatically be frozen
+    /// when an asynchronous interrupt is taken.
+    pub df, set_df: 5;
+    /// Instruction cache freeze on interrupt (IF) - if set, the instruction cache will automatically
+    /// be frozen when an asynchronous interrupt is taken.
+    pub if_, set_if: 4;
+    /// Data cache state (DCS) - 00 = disabled, 01 = frozen, 10 = disabled, 11 = enabled.
+    pub u8, dcs, set_dcs: 3, 2;
+    /// Instruction cache state (ICS) - 00 = disabled, 01 = frozen, 10 = disabled, 11 = enabled.
+    pub u8, ics, set_ics: 1, 0;
+}
+
+impl Ccr {
+    /// The state of the instruction or data cache.
+    pub fn cache_state(&self, kind: CacheKind) -> CacheState {
+        CacheState::from_bits(match kind {
+            CacheKind::Instruction => self.ics(),
+            CacheKind::Data => self.dcs(),
+        })
+    }
+
+    /// Set the state of the instruction or data cache.
+    pub fn set_cache_state(&mut self, kind: CacheKind, state: CacheState) {
+        match kind {
+            CacheKind::Instruction => self.set_ics(state.bits()),
+            CacheKind::Data => self.set_dcs(state.bits()),
+        }
+    }
+
+    /// Returns whether the instruction or data cache is frozen when an interrupt is taken.
+    pub fn freeze_on_interrupt(&self, kind: CacheKind) -> bool {
+        match kind {
+            CacheKind::Instruction => self.if_(),
+            CacheKind::Data => self.df(),
+        }
+    }
+
+    /// Set whether the instruction or data cache is frozen when an interrupt is taken.
+    pub fn set_freeze_on_interrupt(&mut self, kind: CacheKind, freeze: bool) {
+        match kind {
+            CacheKind::Instruction => self.set_if(freeze),
+            CacheKind::Data => self.set_df(freeze),
+        }
+    }
+}
+
+/// The state of a cache, as set in the cache control register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheState {
+    /// The cache is neither read nor updated.
+    Disabled,
+    /// Hits are served from the cache, but no new lines are allocated.
+    Frozen,
+    /// The cache is fully operational.
+    Enabled,
+}
+
+impl CacheState {
+    fn from_bits(bits: u8) -> Self {
+        match bits {
+            0b01 => CacheState::Frozen,
+            0b11 => CacheState::Enabled,
+            _ => CacheState::Disabled,
+        }
+    }
+
+    fn bits(self) -> u8 {
+        match self {
+            CacheState::Disabled => 0b00,
+            CacheState::Frozen => 0b01,
+            CacheState::Enabled => 0b11,
+        }
+    }
+}
+
+/// The ASI of the instruction cache tags.
+const ASI_ICACHE_TAGS: u8 = 0x0C;
+
+/// The ASI of the instruction cache data.
+const ASI_ICACHE_DATA: u8 = 0x0D;
+
+/// The ASI of the data cache tags.
+const ASI_DCACHE_TAGS: u8 = 0x0E;
+
+/// The ASI of the data cache data.
+const ASI_DCACHE_DATA: u8 = 0x0F;
+
+/// One of the two caches of a LEON3 core.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKind {
+    /// The instruction cache.
+    Instruction,
+    /// The data cache.
+    Data,
+}
+
+impl CacheKind {
+    /// The address of the cache configuration register in ASI 2.
+    pub(crate) fn configuration_address(self) -> u32 {
+        match self {
+            CacheKind::Instruction => 0x08,
+            CacheKind::Data => 0x0C,
+        }
+    }
+
+    pub(crate) fn tag_asi(self) -> u8 {
+        match self {
+            CacheKind::Instruction => ASI_ICACHE_TAGS,
+            CacheKind::Data => ASI_DCACHE_TAGS,
+        }
+    }
+
+    pub(crate) fn data_asi(self) -> u8 {
+        match self {
+            CacheKind::Instruction => ASI_ICACHE_DATA,
+            CacheKind::Data => ASI_DCACHE_DATA,
+        }
+    }
+}
+
+/// The line replacement policy of a multi-way cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplacementPolicy {
+    /// The cache is direct-mapped.
+    DirectMapped,
+    /// Least recently used.
+    LeastRecentlyUsed,
+    /// Least recently replaced.
+    LeastRecentlyReplaced,
+    /// Pseudo-random.
+    Random,
+}
+
+/// The configuration of a cache, as read from its cache configuration register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfiguration {
+    /// The number of ways (sets) of the cache.
+    pub ways: u32,
+    /// The size of a single way in bytes.
+    pub way_size: u32,
+    /// The size of a cache line in bytes.
+    pub line_size: u32,
+    /// How lines are replaced.
+    pub replacement: ReplacementPolicy,
+    /// Whether lines can be locked.
+    pub locking: bool,
+    /// Whether the cache snoops the bus for writes of other masters.
+    pub snooping: bool,
+    /// Whether an MMU is present.
+    pub mmu: bool,
+}
+
+impl CacheConfiguration {
+    /// The total size of the cache in bytes.
+    pub fn size(&self) -> u32 {
+        self.ways * self.way_size
+    }
+
+    /// The number of lines in each way.
+    pub fn lines_per_way(&self) -> u32 {
+        self.way_size / self.line_size
+    }
+}
+
+impl From<u32> for CacheConfiguration {
+    fn from(word: u32) -> Self {
+        let replacement = match (word >> 28) & 0b11 {
+            0 => ReplacementPolicy::DirectMapped,
+            1 => ReplacementPolicy::LeastRecentlyUsed,
+            2 => ReplacementPolicy::LeastRecentlyReplaced,
+            _ => ReplacementPolicy::Random,
+        };
+        Self {
+            ways: ((word >> 24) & 0b111) + 1,
+            way_size: 1024 << ((word >> 20) & 0b1111),
+            line_size: 4 << ((word >> 16) & 0b111),
+            replacement,
+            locking: word & (1 << 31) != 0,
+            snooping: word & (1 << 27) != 0,
+            mmu: word & (1 << 3) != 0,
+        }
+    }
+}
+
+/// A cache line, as read through the cache diagnostic ASIs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheLine {
+    /// The way the line belongs to.
+    pub way: u32,
+    /// The index of the line within its way.
+    pub index: u32,
+    /// The memory address the line caches.
+    pub address: u32,
+    /// The valid bits, bit `n` for word `n` of the line.
+    pub valid: u8,
+    /// The line is locked and will not be replaced.
+    pub locked: bool,
+    /// The least recently replaced bit, for caches with the LRR replacement policy. The state of
+    /// the LRU replacement policy is not accessible.
+    pub lrr: bool,
+    /// The words of the line.
+    pub data: Vec<u32>,
+}
+
+impl CacheLine {
+    /// Decode a line from its raw tag and data.
+    pub(crate) fn from_raw(
+        configuration: &CacheConfiguration,
+        way: u32,
+        index: u32,
+        tag: u32,
+        data: Vec<u32>,
+    ) -> Self {
+        let line_words = configuration.line_size / 4;
+        Self {
+            way,
+            index,
+            address: (tag & !(configuration.way_size - 1)) | (index * configuration.line_size),
+            valid: (tag & ((1 << line_words) - 1)) as u8,
+            locked: tag & (1 << 8) != 0,
+            lrr: tag & (1 << 9) != 0,
+            data,
+        }
+    }
+
+    /// Returns whether any word of the line is valid.
+    pub fn is_valid(&self) -> bool {
+        self.valid != 0
+    }
+}
+
+/// The contents of a cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheSnapshot {
+    /// The configuration of the cache.
+    pub configuration: CacheConfiguration,
+    /// All lines of all ways, ordered by way and index.
+    pub lines: Vec<CacheLine>,
+}
+
+impl CacheSnapshot {
+    /// The valid lines caching `address`.
+    pub fn lines_for(&self, address: u32) -> impl Iterator<Item = &CacheLine> {
+        let line_size = self.configuration.line_size;
+        self.lines
+            .iter()
+            .filter(move |line| line.is_valid() && line.address == address & !(line_size - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_cache_line() {
+        // 2 ways of 4 KiB, 32-byte lines, LRU
+        let configuration = CacheConfiguration::from(0x1123_0000 | (1 << 27));
+        assert_eq!(configuration.ways, 2);
+        assert_eq!(configuration.way_size, 4096);
+        assert_eq!(configuration.line_size, 32);
+        assert_eq!(
+            configuration.replacement,
+            ReplacementPolicy::LeastRecentlyUsed
+        );
+        assert!(configuration.snooping);
+
+        let line = CacheLine::from_raw(&configuration, 1, 3, 0x4000_1000 | 0x0F, vec![0; 8]);
+        assert_eq!(line.address, 0x4000_1060);
+        assert_eq!(line.valid, 0x0F);
+        assert!(!line.locked);
+    }
+
+    #[test]
+    fn cache_states() {
+        let mut ccr = Ccr(0b1110);
+        assert_eq!(ccr.cache_state(CacheKind::Data), CacheState::Enabled);
+        assert_eq!(
+            ccr.cache_state(CacheKind::Instruction),
+            CacheState::Disabled
+        );
+
+        ccr.set_cache_state(CacheKind::Data, CacheState::Frozen);
+        ccr.set_cache_state(CacheKind::Instruction, CacheState::Enabled);
+        ccr.set_freeze_on_interrupt(CacheKind::Instruction, true);
+        assert_eq!(ccr.0, 0b1_0111);
+    }
+}
@@ -1,59 +1,280 @@
-use std::time::{Duration, Instant};
+//! Debug communication with LEON3 cores through the DSU3.
+
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
 
 use crate::{
     CoreInformation, Error as ProbeRsError, MemoryInterface, MemoryMappedRegister, RegisterId,
     architecture::leon3::{
+        ahbuart::AhbUartError,
+        cache::{ASI_CACHE_CONTROL, CacheConfiguration, CacheKind, CacheLine, CacheSnapshot, Ccr},
         dsu3::{Dsu3, Dsu3State, DsuCtrl, Psr},
-        plugnplay::{Device, GaislerDevice, PlugnPlayState},
-        registers::Leon3RegisterId,
+        edcl::EdclError,
+        pci::PciError,
+        plugnplay::{DEFAULT_PLUGNPLAY_BASE, Device, GaislerDevice, PlugnPlayState},
+        registers::{FpuReg, IuCoreReg, IuSpecialReg, Leon3RegisterId, RegisterFile},
+        trace::{
+            AHB_TRACE_BUFFER_OFFSET, AHB_WATCHPOINT_OFFSET, AhbTraceControl, AhbTraceEntry,
+            AhbTraceIndex, INSTRUCTION_TRACE_BUFFER_OFFSET, InstructionTraceControl,
+            InstructionTraceEntry, TRACE_ENTRY_WORDS, split_entries,
+        },
+        usbdcl::UsbDclError,
     },
-    probe::DebugProbeError,
+    probe::{DebugProbeError, Probe},
     session::BusAccess,
 };
 
+/// How long to wait for a cache flush to complete.
+const CACHE_FLUSH_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The generation of the debug support unit.
+///
+/// The LEON4 DSU4 keeps the register layout of the LEON3 DSU3, so LEON4 processors are
+/// debugged through the same interface. The LEON5 debug support unit has a different register
+/// layout with per-core debug interfaces, which is not implemented. It is recognized in the
+/// plug&play area, but attaching to it fails with [`Leon3Error::UnsupportedByDebugUnit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugUnit {
+    /// The LEON3 debug support unit (DSU3).
+    Dsu3,
+    /// The LEON4 debug support unit (DSU4).
+    Dsu4,
+    /// The LEON5 debug support unit.
+    Leon5,
+}
+
+impl DebugUnit {
+    /// The debug support units looked for in the plug&play area, in order, with their device.
+    const PLUGNPLAY_DEVICES: [(DebugUnit, GaislerDevice); 3] = [
+        (DebugUnit::Dsu3, GaislerDevice::LEON3DSU),
+        (DebugUnit::Dsu4, GaislerDevice::LEON4DSU),
+        (DebugUnit::Leon5, GaislerDevice::LEON5DSU),
+    ];
+}
+
+impl std::fmt::Display for DebugUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DebugUnit::Dsu3 => write!(f, "LEON3 DSU3"),
+            DebugUnit::Dsu4 => write!(f, "LEON4 DSU4"),
+            DebugUnit::Leon5 => write!(f, "LEON5"),
+        }
+    }
+}
+
 /// Some error occurred when working with the Leon3 core.
 #[derive(thiserror::Error, Debug)]
 pub enum Leon3Error {
     /// A timeout occurred during AHB access.
     #[error("Timeout during AHB access.")]
     Timeout,
+    /// An AHB transfer had not completed yet when its data was shifted out of DDATA.
+    #[error("AHB transfer still pending")]
+    TransferPending,
+    /// An AHB transfer did not complete, because the slave responded with an error.
+    #[error("AHB bus error at address {address:#010X}")]
+    BusError {
+        /// The address of the failed transfer.
+        address: u32,
+    },
     /// An error with operating the debug probe occurred.
     #[error("Debug Probe Error")]
     DebugProbe(#[from] DebugProbeError),
+    /// An error with the AHBUART debug link occurred.
+    #[error("AHBUART debug link error")]
+    AhbUart(#[from] AhbUartError),
+    /// An error with the EDCL debug link occurred.
+    #[error("EDCL debug link error")]
+    Edcl(#[from] EdclError),
+    /// An error with the PCI debug link occurred.
+    #[error("PCI debug link error")]
+    Pci(#[from] PciError),
+    /// An error with the USB DCL debug link occurred.
+    #[error("USB DCL debug link error")]
+    UsbDcl(#[from] UsbDclError),
+    /// The MMU does not map the virtual address.
+    #[error("Virtual address {0:#010X} is not mapped by the MMU")]
+    UnmappedAddress(u32),
+    /// The MMU maps a virtual address beyond the 32-bit AHB address space.
+    #[error("Physical address {0:#X} is outside of the AHB address space")]
+    PhysicalAddressOutOfRange(u64),
+    /// The address is outside of the part of an ASI reachable through the DSU.
+    #[error("Address {address:#010X} of ASI {asi:#04X} is not reachable through the DSU")]
+    AsiAddressOutOfRange {
+        /// The alternate address space.
+        asi: u8,
+        /// The first address of the access.
+        address: u32,
+    },
     /// A region outside of the AHB address space was accessed.
     #[error("Out of bounds memory access")]
     OutOfBounds,
+    /// An address range ends before it starts.
+    #[error("The address range {start:#010X}..{end:#010X} ends before it starts")]
+    InvertedRange {
+        /// The first address of the range.
+        start: u64,
+        /// The end of the range.
+        end: u64,
+    },
     /// Failed to scan plugnplay region.
     #[error("Failed to scan plug&play region")]
     PlugnPlayFailure {
+        /// The underlying error.
         source: Box<dyn std::error::Error + 'static + Send + Sync>,
     },
-    /// DSU3 not found.
-    #[error("DSU3 plug&play record not found")]
-    Dsu3NotFound,
+    /// The symbol table of an ELF file cannot be read.
+    #[error("Cannot read the symbol table of the ELF file")]
+    InvalidElf(#[from] object::read::Error),
+    /// A device needed for the operation was not found in the plug&play records.
+    #[error("{0} plug&play record not found")]
+    DeviceNotFound(&'static str),
+    /// The SDRAM configuration of the target description cannot be programmed.
+    #[error("Invalid SDRAM configuration: {0}")]
+    InvalidSdramConfiguration(&'static str),
+    /// No boot image can be built for the image.
+    #[error("Cannot build a PROM boot image: {0}")]
+    InvalidBootImage(String),
+    /// A routine run on the core stopped before its end.
+    #[error("The routine run on the core stopped at {pc:#010X} instead of its end")]
+    RoutineFailed {
+        /// The address the core stopped at.
+        pc: u32,
+    },
+    /// No CFI flash responded in the PROM area.
+    #[error("No CFI flash found in the PROM area")]
+    NoCfiFlash,
+    /// No SPI flash responded to the JEDEC ID command.
+    #[error("No SPI flash found behind the SPI memory controller")]
+    NoSpiFlash,
+    /// The flash in the PROM area uses a command set that is not supported.
+    #[error("Unsupported CFI command set {0:#06X}")]
+    UnsupportedCfiCommandSet(u16),
+    /// The PROM bus width configured in the memory controller is not supported.
+    #[error("PROM bus width of {0} bits is not supported")]
+    UnsupportedPromWidth(u32),
+    /// The PROM EDAC is enabled, which needs check bits that are not programmed.
+    #[error("Programming the PROM with EDAC enabled is not supported")]
+    PromEdacEnabled,
+    /// The flash reported an error for an erase or program operation.
+    #[error("PROM {operation} failed at address {address:#010X}")]
+    PromOperationFailed {
+        /// The failed operation.
+        operation: &'static str,
+        /// The address of the failed operation.
+        address: u64,
+    },
+    /// None of the debug support units looked for has a plug&play record.
+    #[error(
+        "No debug support unit found in the plug&play area, looked for: {}",
+        .0.iter().map(|unit| unit.to_string()).collect::<Vec<_>>().join(", ")
+    )]
+    DebugUnitNotFound(Vec<DebugUnit>),
+    /// The feature is not supported by the debug support unit of the target.
+    #[error("{feature} is not supported by the {unit} debug support unit")]
+    UnsupportedByDebugUnit {
+        /// The unsupported feature.
+        feature: &'static str,
+        /// The debug support unit of the target.
+        unit: DebugUnit,
+    },
     /// Core out of range.
     #[error("Core index {core_index} out of range (max 15)")]
-    CoreOutOfRange { core_index: usize },
+    CoreOutOfRange {
+        /// The requested core index.
+        core_index: usize,
+    },
+    /// The debug support unit of a core does not exist.
+    #[error(
+        "Core {core_index} is assigned to debug support unit {dsu}, but only {num_dsus} were found"
+    )]
+    DsuOutOfRange {
+        /// The index of the core.
+        core_index: usize,
+        /// The index of the debug support unit.
+        dsu: usize,
+        /// The number of debug support units found.
+        num_dsus: usize,
+    },
     /// Invalid register ID.
     #[error("Invalid Register ID: {0:?}")]
     InvalidRegisterId(RegisterId),
     /// Reset halt request not supported by this chip.
     #[error("Reset halt request not supported")]
     ResetHaltRequestNotSupported,
+    /// Resetting through the probe needs the AHBJTAG debug link.
+    #[error("Resetting through the probe needs the AHBJTAG debug link")]
+    ProbeResetUnavailable,
+    /// The operation requires the core to be halted.
+    #[error("The core is not halted")]
+    CoreNotHalted,
+    /// The core is in error mode and has to be reset or recovered first.
+    #[error("The core is in error mode")]
+    ErrorMode,
+    /// The requested hardware breakpoint unit does not exist.
+    #[error("Breakpoint unit {0} is not implemented")]
+    InvalidBreakpointUnit(usize),
+    /// The connected target is not a LEON3 device.
+    #[error("Connected target is not a LEON3 device.")]
+    NoLeon3Target,
+    /// A software breakpoint could not be written, e.g. because the memory is read-only.
+    #[error("Unable to set a software breakpoint at address {0:#010X}")]
+    SoftwareBreakpointFailed(u32),
+    /// The watchpoint unit is already used for a different purpose.
+    #[error("Watchpoint unit {0} is already in use")]
+    BreakpointUnitInUse(usize),
+    /// The requested watchpoint range cannot be expressed with an address mask.
+    #[error("Unable to watch {size} bytes at address {address:#X}")]
+    UnsupportedWatchpoint {
+        /// The start address of the watched range.
+        address: u64,
+        /// The size of the watched range in bytes.
+        size: u64,
+    },
+    /// Hardware breakpoints can only be placed at 32-bit addresses.
+    #[error("Unable to create a breakpoint at address {0:#X}")]
+    UnsupportedBreakpointAddress(u64),
+    /// The core is in power-down mode and could not be woken up.
+    #[error("The core is powered down and could not be woken up")]
+    PowerDown,
+    /// An APBUART without FIFOs cannot be put into FIFO debug mode.
+    #[error("The APBUART at {0:#010X} has no FIFOs and cannot be used as a console")]
+    UartWithoutFifo(u64),
+    /// PC sampling and instruction tracing need an instruction trace buffer.
+    #[error("The core has no instruction trace buffer")]
+    NoInstructionTrace,
+    /// A counter of a statistics unit does not exist.
+    #[error("Counter {counter} does not exist, the statistics unit has {counters} counters")]
+    NoSuchCounter {
+        /// The requested counter.
+        counter: usize,
+        /// The number of counters of the unit.
+        counters: usize,
+    },
+    /// A register did not hold the written value when reading it back.
+    #[error("Write to {register} was not applied: wrote {written:#010X}, read back {read:#010X}")]
+    WriteVerifyFailed {
+        /// The name of the register.
+        register: String,
+        /// The value written to the register.
+        written: u32,
+        /// The value read back from the register.
+        read: u32,
+    },
 }
 
 impl From<Leon3Error> for ProbeRsError {
     fn from(err: Leon3Error) -> Self {
-        match err {
-            other => ProbeRsError::Leon3(other),
-        }
+        ProbeRsError::Leon3(err)
     }
 }
 
 /// An interface that implements controls for Leon3 cores.
 #[derive(Debug)]
 pub struct Leon3CommunicationInterface<'state> {
-    /// Which core we are controlling.
+    /// Which core we are controlling, as index within its debug support unit.
     ///
     /// Everything else in this struct specifically for the communication interface
     /// and doesn't change for different cores, but this temporary struct is constructed
@@ -65,16 +286,17 @@ pub struct Leon3CommunicationInterface<'state> {
 }
 
 impl<'state> Leon3CommunicationInterface<'state> {
-    pub fn try_attach(
+    /// Creates the communication interface for the core with the given index.
+    pub(crate) fn try_attach(
         core_index: usize,
         probe: &'state mut BusAccess,
         state: &'state mut Leon3DebugInterfaceState,
     ) -> Result<Self, crate::Error> {
+        let (dsu_index, core_index) = state.locate_core(core_index);
         let Leon3DebugInterfaceState {
-            plugnplay,
-            dsu: dsu_state,
+            plugnplay, dsus, ..
         } = state;
-        let dsu = Dsu3::new(dsu_state);
+        let dsu = Dsu3::new(&mut dsus[dsu_index]);
 
         Ok(Self {
             core_index,
@@ -84,24 +306,44 @@ impl<'state> Leon3CommunicationInterface<'state> {
         })
     }
 
+    /// Returns the index of the core this interface controls.
+    pub fn core_index(&self) -> usize {
+        self.core_index
+    }
+
+    /// Returns the generation of the debug support unit.
+    pub fn debug_unit(&self) -> DebugUnit {
+        self.dsu.debug_unit()
+    }
+
+    /// Returns the devices found in the plug&play area.
+    pub(crate) fn plugnplay(&self) -> &PlugnPlayState {
+        self.plugnplay
+    }
+
+    /// Returns the memory interface used to access the system bus.
     pub fn as_memory_interface(&self) -> &dyn MemoryInterface {
         self.probe
     }
 
+    /// Returns the mutable memory interface used to access the system bus.
     pub fn as_memory_interface_mut(&mut self) -> &mut dyn MemoryInterface {
         self.probe
     }
 
+    /// Returns the debug probe, if the system bus is accessed through it with AHBJTAG.
+    pub fn as_probe(&mut self) -> Option<&mut Probe> {
+        self.probe.as_probe()
+    }
+
     pub(crate) fn on_first_attach(&mut self) -> Result<(), crate::Error> {
         // From DSU3 section in GRLIB IP Core User's Manual:
         //   For the break-now BN bit to have effect the Break-on-IU-watchpoint
         //   (BW) bit must be set in the DSU control register.  This bit should
         //   be set by debug monitor software when initializing the DSU.
-        Ok(self
-            .dsu
-            .modify_reg::<DsuCtrl, _>(self.probe, self.core_index, |ctrl| {
-                ctrl.set_bw(true);
-            })?)
+        self.modify_dsu_ctrl(|ctrl| {
+            ctrl.set_bw(true);
+        })
     }
 
     pub(crate) fn core_halted(&mut self) -> Result<bool, crate::Error> {
@@ -114,55 +356,316 @@ impl<'state> Leon3CommunicationInterface<'state> {
         Ok(ctrl.dm())
     }
 
+    /// Enable or disable verification of register writes.
+    ///
+    /// When enabled, every write through [`Self::write_core_reg`] and [`Self::write_dsu_reg`]
+    /// is read back, and a [`Leon3Error::WriteVerifyFailed`] is returned if the register does
+    /// not hold the written value. Read-only and self-clearing bits are not compared. This
+    /// costs an extra AHB read per write and is disabled by default.
+    ///
+    /// The setting applies to all cores of the target.
+    pub fn set_write_verify(&mut self, enabled: bool) {
+        self.dsu.set_verify_writes(enabled);
+    }
+
+    /// Whether register writes are verified, see [`Self::set_write_verify`].
+    pub fn write_verify(&self) -> bool {
+        self.dsu.verify_writes()
+    }
+
     pub(crate) fn read_dsu_reg<R: MemoryMappedRegister<u32>>(&mut self) -> Result<R, crate::Error> {
         self.dsu.read_reg(self.probe, self.core_index)
     }
 
-    pub(crate) fn write_dsu_reg<R: MemoryMappedRegister<u32>>(
+    /// Write a DSU register of this core.
+    pub fn write_dsu_reg<R: MemoryMappedRegister<u32>>(
         &mut self,
         value: R,
     ) -> Result<(), crate::Error> {
-        self.dsu.write_reg(value, self.probe, self.core_index)
+        let written: u32 = value.into();
+        self.dsu
+            .write_reg(R::from(written), self.probe, self.core_index)?;
+
+        if self.write_verify() {
+            // Only the configuration bits of the control register read back as written.
+            let mask = if R::ADDRESS_OFFSET == DsuCtrl::ADDRESS_OFFSET {
+                0x3F
+            } else {
+                !0
+            };
+            let read: u32 = self.read_dsu_reg::<R>()?.into();
+            check_written(R::NAME, written, read, mask)?;
+        }
+
+        Ok(())
     }
 
+    /// Read-modify-write a DSU register of this core.
     pub fn modify_dsu_reg<R: MemoryMappedRegister<u32>, T>(
         &mut self,
         f: impl Fn(&mut R) -> T,
     ) -> Result<T, crate::Error> {
-        self.dsu.modify_reg(self.probe, self.core_index, f)
+        let mut value = self.read_dsu_reg::<R>()?;
+        let result = f(&mut value);
+        self.write_dsu_reg(value)?;
+        Ok(result)
+    }
+
+    /// Read-modify-write the DSU control register of this core.
+    ///
+    /// Unlike [`Self::modify_dsu_reg`], this does not write back the error mode (PE) bit, which
+    /// would take the core out of error mode. Set it explicitly in `f` to do so.
+    pub fn modify_dsu_ctrl(&mut self, f: impl Fn(&mut DsuCtrl)) -> Result<(), crate::Error> {
+        self.modify_dsu_reg(|ctrl: &mut DsuCtrl| {
+            ctrl.set_pe(false);
+            f(ctrl);
+        })
+    }
+
+    /// The number of register windows implemented by this core.
+    pub fn num_windows(&mut self) -> Result<u32, crate::Error> {
+        self.dsu.num_windows(self.probe, self.core_index)
+    }
+
+    /// Read a general purpose register of the given window, regardless of the current
+    /// window pointer.
+    pub(crate) fn read_window_reg(
+        &mut self,
+        reg: IuCoreReg,
+        cwp: u32,
+    ) -> Result<u32, crate::Error> {
+        self.dsu
+            .read_core_reg(reg, self.probe, self.core_index, cwp)
+    }
+
+    /// Read the whole IU register file in one access.
+    pub fn read_register_file(&mut self) -> Result<RegisterFile, crate::Error> {
+        let words = self.dsu.read_register_file(self.probe, self.core_index)?;
+        let psr: Psr = self.read_dsu_reg()?;
+        let wim = self
+            .dsu
+            .read_special_reg(IuSpecialReg::WIM, self.probe, self.core_index)?;
+        Ok(RegisterFile::from_raw(&words, psr.cwp(), wim))
+    }
+
+    /// Write a general purpose register of the given window, regardless of the current
+    /// window pointer.
+    pub(crate) fn write_window_reg(
+        &mut self,
+        reg: IuCoreReg,
+        cwp: u32,
+        value: u32,
+    ) -> Result<(), crate::Error> {
+        self.dsu
+            .write_core_reg(reg, value, self.probe, self.core_index, cwp)
+    }
+
+    /// The number of entries of the instruction trace buffer, or 0 if there is none.
+    pub fn instruction_trace_entries(&mut self) -> Result<usize, crate::Error> {
+        self.dsu
+            .instruction_trace_entries(self.probe, self.core_index)
+    }
+
+    /// AHB address of the DSU register at `offset` of this core.
+    pub(crate) fn dsu_register_address(&self, offset: u64) -> Result<u64, crate::Error> {
+        Ok(self.dsu.register_address(offset, self.core_index)?)
+    }
+
+    /// Read the instruction trace buffer, oldest entry first.
+    pub fn read_instruction_trace(&mut self) -> Result<Vec<InstructionTraceEntry>, crate::Error> {
+        let entries = self.instruction_trace_entries()?;
+        let mut words = vec![0; entries * TRACE_ENTRY_WORDS];
+        self.dsu.read_block(
+            INSTRUCTION_TRACE_BUFFER_OFFSET,
+            &mut words,
+            self.probe,
+            self.core_index,
+        )?;
+        let control: InstructionTraceControl = self.read_dsu_reg()?;
+        Ok(split_entries(
+            &words,
+            usize::from(control.it_pointer()),
+            InstructionTraceEntry::from_words,
+        ))
+    }
+
+    /// Read a DSU register that is shared by all cores.
+    pub(crate) fn read_shared_dsu_reg<R: MemoryMappedRegister<u32>>(
+        &mut self,
+    ) -> Result<R, crate::Error> {
+        self.dsu.read_reg(self.probe, 0)
+    }
+
+    /// Read-modify-write a DSU register that is shared by all cores.
+    pub(crate) fn modify_shared_dsu_reg<R: MemoryMappedRegister<u32>, T>(
+        &mut self,
+        f: impl Fn(&mut R) -> T,
+    ) -> Result<T, crate::Error> {
+        let mut value: R = self.read_shared_dsu_reg()?;
+        let result = f(&mut value);
+        self.dsu.write_reg(value, self.probe, 0)?;
+        Ok(result)
     }
 
+    /// Program the address and mask register of an AHB watchpoint.
+    pub(crate) fn write_ahb_watchpoint(
+        &mut self,
+        unit_index: usize,
+        address: u32,
+        mask: u32,
+    ) -> Result<(), crate::Error> {
+        let offset = AHB_WATCHPOINT_OFFSET + 8 * unit_index as u64;
+        self.dsu.write_word(offset, address, self.probe, 0)?;
+        self.dsu.write_word(offset + 4, mask, self.probe, 0)
+    }
+
+    /// The number of entries of the AHB trace buffer, or 0 if there is none.
+    pub fn ahb_trace_entries(&mut self) -> Result<usize, crate::Error> {
+        let control: AhbTraceControl = self.read_shared_dsu_reg()?;
+        self.modify_shared_dsu_reg(|control: &mut AhbTraceControl| control.set_en(false))?;
+        let entries = self.dsu.ahb_trace_entries(self.probe);
+        self.dsu.write_reg(control, self.probe, 0)?;
+        entries
+    }
+
+    /// Read the AHB trace buffer, oldest transfer first.
+    ///
+    /// Tracing is paused while the buffer is read.
+    pub fn read_ahb_trace(&mut self) -> Result<Vec<AhbTraceEntry>, crate::Error> {
+        let control: AhbTraceControl = self.read_shared_dsu_reg()?;
+        self.modify_shared_dsu_reg(|control: &mut AhbTraceControl| control.set_en(false))?;
+
+        let result = self.read_ahb_trace_buffer();
+        self.dsu.write_reg(control, self.probe, 0)?;
+        result
+    }
+
+    fn read_ahb_trace_buffer(&mut self) -> Result<Vec<AhbTraceEntry>, crate::Error> {
+        let entries = self.dsu.ahb_trace_entries(self.probe)?;
+        let mut words = vec![0; entries * TRACE_ENTRY_WORDS];
+        self.dsu
+            .read_block(AHB_TRACE_BUFFER_OFFSET, &mut words, self.probe, 0)?;
+        let index: AhbTraceIndex = self.read_shared_dsu_reg()?;
+        Ok(split_entries(
+            &words,
+            usize::from(index.index()),
+            AhbTraceEntry::from_words,
+        ))
+    }
+
+    /// Make sure `window` is implemented by this core.
+    fn check_window(&mut self, reg: Leon3RegisterId, window: u8) -> Result<u32, crate::Error> {
+        let window = u32::from(window);
+        if window >= self.num_windows()? {
+            return Err(Leon3Error::InvalidRegisterId(reg.into()).into());
+        }
+        Ok(window)
+    }
+
+    /// Read a core register.
     pub fn read_core_reg(&mut self, reg: Leon3RegisterId) -> Result<u32, crate::Error> {
         match reg {
             Leon3RegisterId::IuCore(iu_core_reg) => {
-                // TODO(darsor): cache this
+                // `Leon3` caches the window pointer, this is only used by sequences.
                 let psr: Psr = self.read_dsu_reg()?;
                 let cwp = psr.cwp();
                 self.dsu
                     .read_core_reg(iu_core_reg, self.probe, self.core_index, cwp)
             }
+            Leon3RegisterId::IuWindow {
+                window,
+                reg: iu_core_reg,
+            } => {
+                let window = self.check_window(reg, window)?;
+                self.read_window_reg(iu_core_reg, window)
+            }
             Leon3RegisterId::IuSpecial(iu_special_reg) => {
                 self.dsu
                     .read_special_reg(iu_special_reg, self.probe, self.core_index)
             }
-            Leon3RegisterId::Fpu(_fpu_reg) => todo!(),
+            Leon3RegisterId::Fpu(FpuReg::D(_)) => {
+                Err(Leon3Error::InvalidRegisterId(reg.into()).into())
+            }
+            Leon3RegisterId::Fpu(fpu_reg) => {
+                self.dsu.read_fpu_reg(fpu_reg, self.probe, self.core_index)
+            }
         }
     }
 
+    /// Write a core register.
+    ///
+    /// If write verification is enabled, the register is read back afterwards, see
+    /// [`Self::set_write_verify`].
     pub fn write_core_reg(&mut self, reg: Leon3RegisterId, value: u32) -> Result<(), crate::Error> {
+        self.write_core_reg_unverified(reg, value)?;
+
+        if self.write_verify() {
+            let mask = self.verify_mask(reg)?;
+            if mask != 0 {
+                let read = self.read_core_reg(reg)?;
+                check_written(&format!("{reg:?}"), value, read, mask)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The bits of `reg` that read back as written.
+    fn verify_mask(&mut self, reg: Leon3RegisterId) -> Result<u32, crate::Error> {
+        Ok(match reg {
+            Leon3RegisterId::IuCore(IuCoreReg::G(0))
+            | Leon3RegisterId::IuWindow {
+                reg: IuCoreReg::G(0),
+                ..
+            } => 0,
+            Leon3RegisterId::IuCore(_) | Leon3RegisterId::IuWindow { .. } => !0,
+            Leon3RegisterId::IuSpecial(special) => match special {
+                IuSpecialReg::Y => !0,
+                // Without impl, ver and the FPU/coprocessor enable bits, which are hardwired to
+                // zero if the unit is not present.
+                IuSpecialReg::PSR => 0x00F0_0FFF,
+                IuSpecialReg::WIM => (1 << self.num_windows()?) - 1,
+                // The trap type field is read-only.
+                IuSpecialReg::TBR => 0xFFFF_F000,
+                IuSpecialReg::PC | IuSpecialReg::NPC => !0b11,
+                // These contain implementation-defined read-only fields.
+                IuSpecialReg::FSR | IuSpecialReg::CPSR | IuSpecialReg::ASR(_) => 0,
+            },
+            Leon3RegisterId::Fpu(_) => !0,
+        })
+    }
+
+    fn write_core_reg_unverified(
+        &mut self,
+        reg: Leon3RegisterId,
+        value: u32,
+    ) -> Result<(), crate::Error> {
         match reg {
             Leon3RegisterId::IuCore(iu_core_reg) => {
-                // TODO(darsor): cache this
+                // `Leon3` caches the window pointer, this is only used by sequences.
                 let psr: Psr = self.read_dsu_reg()?;
                 let cwp = psr.cwp();
                 self.dsu
                     .write_core_reg(iu_core_reg, value, self.probe, self.core_index, cwp)
             }
+            Leon3RegisterId::IuWindow {
+                window,
+                reg: iu_core_reg,
+            } => {
+                let window = self.check_window(reg, window)?;
+                self.write_window_reg(iu_core_reg, window, value)
+            }
             Leon3RegisterId::IuSpecial(iu_special_reg) => {
                 self.dsu
                     .write_special_reg(iu_special_reg, value, self.probe, self.core_index)
             }
-            Leon3RegisterId::Fpu(_fpu_reg) => todo!(),
+            Leon3RegisterId::Fpu(FpuReg::D(_)) => {
+                Err(Leon3Error::InvalidRegisterId(reg.into()).into())
+            }
+            Leon3RegisterId::Fpu(fpu_reg) => {
+                self.dsu
+                    .write_fpu_reg(fpu_reg, value, self.probe, self.core_index)
+            }
         }
     }
 
@@ -181,6 +684,154 @@ impl<'state> Leon3CommunicationInterface<'state> {
         Ok(())
     }
 
+    /// Flush the instruction and data caches of this core.
+    ///
+    /// The caches stay enabled, but all their lines are invalidated.
+    pub fn flush_caches(&mut self) -> Result<(), crate::Error> {
+        self.flush(true, true)
+    }
+
+    /// Flush the instruction cache of this core.
+    pub fn flush_instruction_cache(&mut self) -> Result<(), crate::Error> {
+        self.flush(true, false)
+    }
+
+    /// Invalidate the instruction cache lines holding any address in `ranges`.
+    ///
+    /// If more lines are affected than a cache way holds, the whole instruction cache is
+    /// flushed instead. Addresses are compared to the tags of the lines, so this only works
+    /// for physically addressed code.
+    pub fn invalidate_instruction_lines(
+        &mut self,
+        ranges: &[Range<u64>],
+    ) -> Result<(), crate::Error> {
+        let configuration = self.cache_configuration(CacheKind::Instruction)?;
+        let line_size = u64::from(configuration.line_size);
+        let num_lines: u64 = ranges
+            .iter()
+            .map(|range| range.end.div_ceil(line_size) - range.start / line_size)
+            .sum();
+        if num_lines > u64::from(configuration.lines_per_way()) {
+            return self.flush_instruction_cache();
+        }
+
+        let way_size = configuration.way_size;
+        let valid_mask = (1 << (configuration.line_size / 4)) - 1;
+        let tag_asi = CacheKind::Instruction.tag_asi();
+        for range in ranges {
+            let mut line = range.start & !(line_size - 1);
+            while line < range.end {
+                let Ok(address) = u32::try_from(line) else {
+                    break;
+                };
+                for way in 0..configuration.ways {
+                    let tag_address = way * way_size + address % way_size;
+                    let tag = self.read_asi(tag_asi, tag_address)?;
+                    if tag & valid_mask != 0 && (tag ^ address) & !(way_size - 1) == 0 {
+                        self.write_asi(tag_asi, tag_address, tag & !valid_mask)?;
+                    }
+                }
+                line += line_size;
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self, instruction: bool, data: bool) -> Result<(), crate::Error> {
+        let address = Ccr::ADDRESS_OFFSET as u32;
+        let mut ccr = Ccr(self.read_asi(ASI_CACHE_CONTROL, address)?);
+        ccr.set_fi(instruction);
+        ccr.set_fd(data);
+        self.write_asi(ASI_CACHE_CONTROL, address, ccr.into())?;
+
+        let start = Instant::now();
+        loop {
+            let ccr = Ccr(self.read_asi(ASI_CACHE_CONTROL, address)?);
+            if !ccr.ip() && !ccr.dp() {
+                return Ok(());
+            }
+            if start.elapsed() >= CACHE_FLUSH_TIMEOUT {
+                return Err(Leon3Error::Timeout.into());
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Read the configuration of the instruction or data cache of this core.
+    pub fn cache_configuration(
+        &mut self,
+        kind: CacheKind,
+    ) -> Result<CacheConfiguration, crate::Error> {
+        let word = self.read_asi(ASI_CACHE_CONTROL, kind.configuration_address())?;
+        Ok(CacheConfiguration::from(word))
+    }
+
+    /// Read the tags and data of all lines of the instruction or data cache of this core.
+    ///
+    /// The core should be halted, otherwise the cache contents may change while they are read.
+    pub fn read_cache(&mut self, kind: CacheKind) -> Result<CacheSnapshot, crate::Error> {
+        let configuration = self.cache_configuration(kind)?;
+        let line_words = (configuration.line_size / 4) as usize;
+        let mut lines = Vec::new();
+        for way in 0..configuration.ways {
+            let way_base = way * configuration.way_size;
+            // Every word of a line reads as the tag of the line in the tag ASI.
+            let mut tags = vec![0; (configuration.way_size / 4) as usize];
+            self.read_asi_block(kind.tag_asi(), way_base, &mut tags)?;
+
+            let mut data = vec![0; (configuration.way_size / 4) as usize];
+            self.read_asi_block(kind.data_asi(), way_base, &mut data)?;
+
+            let line_tags = tags.chunks(line_words).map(|tags| tags[0]);
+            for (index, (tag, data)) in line_tags.zip(data.chunks(line_words)).enumerate() {
+                lines.push(CacheLine::from_raw(
+                    &configuration,
+                    way,
+                    index as u32,
+                    tag,
+                    data.to_vec(),
+                ));
+            }
+        }
+        Ok(CacheSnapshot {
+            configuration,
+            lines,
+        })
+    }
+
+    /// Read a word from an alternate address space of this core.
+    pub fn read_asi(&mut self, asi: u8, address: u32) -> Result<u32, crate::Error> {
+        self.dsu.read_asi(asi, address, self.probe, self.core_index)
+    }
+
+    /// Read consecutive words from an alternate address space of this core.
+    pub fn read_asi_block(
+        &mut self,
+        asi: u8,
+        address: u32,
+        data: &mut [u32],
+    ) -> Result<(), crate::Error> {
+        self.dsu
+            .read_asi_block(asi, address, data, self.probe, self.core_index)
+    }
+
+    /// Write consecutive words to an alternate address space of this core.
+    pub fn write_asi_block(
+        &mut self,
+        asi: u8,
+        address: u32,
+        data: &[u32],
+    ) -> Result<(), crate::Error> {
+        self.dsu
+            .write_asi_block(asi, address, data, self.probe, self.core_index)
+    }
+
+    /// Write a word to an alternate address space of this core.
+    pub fn write_asi(&mut self, asi: u8, address: u32, value: u32) -> Result<(), crate::Error> {
+        self.dsu
+            .write_asi(asi, address, value, self.probe, self.core_index)
+    }
+
     pub(crate) fn core_info(&mut self) -> Result<CoreInformation, crate::Error> {
         let pc: u32 = self.read_core_reg(super::registers::PC.id().try_into()?)?;
 
@@ -188,31 +839,236 @@ impl<'state> Leon3CommunicationInterface<'state> {
     }
 }
 
-/// The combined state of a LEON3's DSU3 debug module and its transport interface.
+/// Compare the `mask`ed bits of a value read back from a register with the written value.
+fn check_written(register: &str, written: u32, read: u32, mask: u32) -> Result<(), Leon3Error> {
+    if (written ^ read) & mask != 0 {
+        return Err(Leon3Error::WriteVerifyFailed {
+            register: register.to_string(),
+            written,
+            read,
+        });
+    }
+    Ok(())
+}
+
+/// The processors controlled by one debug support unit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DsuCoreGroup {
+    /// The generation of the debug support unit.
+    pub unit: DebugUnit,
+    /// The base address of the debug support unit.
+    pub base_address: u64,
+    /// The session core indices of the processors, ordered by their index within the debug
+    /// support unit.
+    pub cores: Vec<usize>,
+}
+
+/// The combined state of a LEON3's DSU3 debug modules and their transport interface.
 #[derive(Debug)]
 pub(crate) struct Leon3DebugInterfaceState {
     plugnplay: PlugnPlayState,
-    dsu: Dsu3State,
+    dsus: Vec<Dsu3State>,
+    /// The debug support unit of each core, and the index of the core within it.
+    core_map: Vec<(usize, usize)>,
 }
 
 impl Leon3DebugInterfaceState {
-    pub fn try_attach<'probe>(
-        probe: &'probe mut dyn MemoryInterface,
+    /// Scan the plug&play area and find the debug support units.
+    ///
+    /// `debug_unit` overrides the debug support units found in the plug&play area, see
+    /// [`Leon3DebugSequence::debug_unit`](super::sequences::Leon3DebugSequence::debug_unit).
+    /// `dsu_base` and `plugnplay_base` are the overrides of the target description, see
+    /// [`Leon3CoreAccessOptions`](probe_rs_target::Leon3CoreAccessOptions).
+    ///
+    /// The scan is done once, and its results are reused for all cores of the session. Until
+    /// [`Self::map_cores`] is called, all cores belong to the first debug support unit.
+    pub fn try_attach(
+        probe: &mut dyn MemoryInterface,
+        debug_unit: Option<(DebugUnit, u64)>,
+        dsu_base: Option<u64>,
+        plugnplay_base: Option<u64>,
     ) -> Result<Self, crate::Error> {
-        let plugnplay = PlugnPlayState::scan_plugnplay(probe)?;
-        let dsu3_record = plugnplay
-            .find_device(Device::Gaisler(GaislerDevice::LEON3DSU))
-            .ok_or(Leon3Error::Dsu3NotFound)?;
-        let dsu3_base_address = dsu3_record
-            .address_spaces
-            .first()
-            .ok_or(Leon3Error::Dsu3NotFound)?
-            .addresses
-            .start;
+        let plugnplay_base = plugnplay_base.unwrap_or(DEFAULT_PLUGNPLAY_BASE);
+        let plugnplay = match PlugnPlayState::scan_plugnplay(probe, plugnplay_base) {
+            Ok(plugnplay) => plugnplay,
+            // A broken plug&play area is not fatal if the debug support unit is known.
+            Err(error) if dsu_base.is_some() || debug_unit.is_some() => {
+                tracing::warn!("Ignoring failed plug&play scan: {error}");
+                PlugnPlayState::default()
+            }
+            Err(error) => return Err(error),
+        };
+
+        let found = match debug_unit {
+            Some(debug_unit) => vec![debug_unit],
+            None => Self::find_debug_units(&plugnplay),
+        };
+        let debug_units = match (found.first(), dsu_base) {
+            (Some(&(unit, _)), Some(base)) => vec![(unit, base)],
+            (None, Some(base)) => vec![(DebugUnit::Dsu3, base)],
+            (Some(_), None) => found,
+            (None, None) => {
+                let looked_for = DebugUnit::PLUGNPLAY_DEVICES.map(|(unit, _)| unit);
+                return Err(Leon3Error::DebugUnitNotFound(looked_for.to_vec()).into());
+            }
+        };
+        if let Some(&(unit @ DebugUnit::Leon5, _)) = debug_units.first() {
+            return Err(Leon3Error::UnsupportedByDebugUnit {
+                feature: "Debugging",
+                unit,
+            }
+            .into());
+        }
+        for (unit, base) in &debug_units {
+            tracing::debug!("Found {unit:?} debug support unit at {base:#010x}");
+        }
 
         Ok(Self {
-            plugnplay: plugnplay,
-            dsu: Dsu3State::new(dsu3_base_address),
+            plugnplay,
+            dsus: debug_units
+                .into_iter()
+                .map(|(unit, base)| Dsu3State::new(base, unit))
+                .collect(),
+            core_map: vec![],
         })
     }
+
+    /// Returns the devices found in the plug&play area.
+    pub fn plugnplay(&self) -> &PlugnPlayState {
+        &self.plugnplay
+    }
+
+    /// Assign the cores of the session to the debug support units. `dsu_of_core` holds the
+    /// index of the debug support unit of each core, in plug&play order. The cores of a debug
+    /// support unit are numbered in the order they appear.
+    pub fn map_cores(&mut self, dsu_of_core: &[usize]) -> Result<(), Leon3Error> {
+        let mut next_index = vec![0; self.dsus.len()];
+        let mut core_map = Vec::with_capacity(dsu_of_core.len());
+        for (core, &dsu) in dsu_of_core.iter().enumerate() {
+            let Some(index) = next_index.get_mut(dsu) else {
+                return Err(Leon3Error::DsuOutOfRange {
+                    core_index: core,
+                    dsu,
+                    num_dsus: self.dsus.len(),
+                });
+            };
+            core_map.push((dsu, *index));
+            *index += 1;
+        }
+        self.core_map = core_map;
+        Ok(())
+    }
+
+    /// The debug support unit of the core, and the index of the core within it.
+    fn locate_core(&self, core_index: usize) -> (usize, usize) {
+        self.core_map
+            .get(core_index)
+            .copied()
+            .unwrap_or((0, core_index))
+    }
+
+    /// The processors of each debug support unit.
+    pub fn core_groups(&self) -> Vec<DsuCoreGroup> {
+        let mut groups: Vec<DsuCoreGroup> = self
+            .dsus
+            .iter()
+            .map(|dsu| DsuCoreGroup {
+                unit: dsu.unit(),
+                base_address: dsu.base_address(),
+                cores: vec![],
+            })
+            .collect();
+        for (core, &(dsu, _)) in self.core_map.iter().enumerate() {
+            groups[dsu].cores.push(core);
+        }
+        groups
+    }
+
+    /// Find the debug support units in the plug&play area. LEON3 debug support units are
+    /// preferred over the later generations, which some designs include as well.
+    fn find_debug_units(plugnplay: &PlugnPlayState) -> Vec<(DebugUnit, u64)> {
+        DebugUnit::PLUGNPLAY_DEVICES
+            .into_iter()
+            .map(|(unit, device)| {
+                plugnplay
+                    .find_devices(Device::Gaisler(device))
+                    .filter_map(|record| record.base_address())
+                    .map(|base| (unit, base))
+                    .collect::<Vec<_>>()
+            })
+            .find(|units| !units.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// The number of LEON3, LEON4 and LEON5 processors in the plug&play area.
+    ///
+    /// Every processor is an AHB master with its own plug&play record.
+    pub fn num_cores(&self) -> usize {
+        [
+            GaislerDevice::LEON3,
+            GaislerDevice::LEON3FT,
+            GaislerDevice::LEON4,
+            GaislerDevice::LEON5,
+        ]
+        .into_iter()
+        .map(|device| self.plugnplay.count_devices(Device::Gaisler(device)))
+        .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::architecture::leon3::mock_ahbjtag::MockAhbJtag;
+
+    #[test]
+    fn map_cores_to_debug_units() {
+        let mut state = Leon3DebugInterfaceState {
+            plugnplay: PlugnPlayState::default(),
+            dsus: vec![
+                Dsu3State::new(0x9000_0000, DebugUnit::Dsu3),
+                Dsu3State::new(0xA000_0000, DebugUnit::Dsu3),
+            ],
+            core_map: vec![],
+        };
+
+        state.map_cores(&[0, 1, 0, 1, 1]).unwrap();
+        assert_eq!(state.locate_core(3), (1, 1));
+        assert_eq!(state.locate_core(4), (1, 2));
+
+        let groups = state.core_groups();
+        assert_eq!(groups[0].base_address, 0x9000_0000);
+        assert_eq!(groups[0].cores, [0, 2]);
+        assert_eq!(groups[1].cores, [1, 3, 4]);
+
+        assert!(matches!(
+            state.map_cores(&[0, 2]),
+            Err(Leon3Error::DsuOutOfRange { dsu: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn attach_names_the_debug_units() {
+        let link = MockAhbJtag::new();
+        let mut bus = link.bus_access();
+
+        let error = Leon3DebugInterfaceState::try_attach(&mut bus, None, None, None).unwrap_err();
+        let ProbeRsError::Leon3(error) = error else {
+            panic!("unexpected error {error:?}");
+        };
+        assert_eq!(
+            error.to_string(),
+            "No debug support unit found in the plug&play area, looked for: LEON3 DSU3, LEON4 DSU4, LEON5"
+        );
+
+        let leon5 = Some((DebugUnit::Leon5, 0x9000_0000));
+        let error = Leon3DebugInterfaceState::try_attach(&mut bus, leon5, None, None).unwrap_err();
+        assert!(matches!(
+            error,
+            ProbeRsError::Leon3(Leon3Error::UnsupportedByDebugUnit {
+                unit: DebugUnit::Leon5,
+                ..
+            })
+        ));
+    }
 }
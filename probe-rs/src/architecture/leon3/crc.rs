@@ -0,0 +1,79 @@
+//! CRC-32 computation on the target, which is much faster than reading the memory back over
+//! a slow debug link.
+//!
+//! A small routine is downloaded to RAM and run by the halted core. It computes the CRC-32 of
+//! the IEEE 802.3 standard, bit by bit, so it needs no table in memory.
+
+use crate::architecture::leon3::instructions::TA_1;
+
+/// The reflected CRC-32 polynomial.
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// The CRC-32 routine. It expects the address in `%o0` and the length in `%o1`, and returns
+/// the inverted CRC in `%o2` when it hits the final `ta 1`. It is a leaf routine, which only
+/// uses `%o0` to `%o5` and the condition codes.
+pub(crate) const CRC32_ROUTINE: [u32; 20] = [
+    0x193B_6E20, //             sethi %hi(0xEDB88320), %o4
+    0x9813_2320, //             or    %o4, %lo(0xEDB88320), %o4
+    0x9410_3FFF, //             mov   -1, %o2
+    0x80A2_6000, //             cmp   %o1, 0
+    0x0280_000F, //             be    done
+    0x0100_0000, //              nop
+    0xD60A_2000, // byte_loop:  ldub  [%o0], %o3
+    0x941A_800B, //             xor   %o2, %o3, %o2
+    0x9A10_2008, //             mov   8, %o5
+    0x808A_A001, // bit_loop:   andcc %o2, 1, %g0
+    0x0280_0003, //             be    1f
+    0x9532_A001, //              srl  %o2, 1, %o2
+    0x941A_800C, //             xor   %o2, %o4, %o2
+    0x9AA3_6001, // 1:          subcc %o5, 1, %o5
+    0x12BF_FFFB, //             bne   bit_loop
+    0x0100_0000, //              nop
+    0x92A2_6001, //             subcc %o1, 1, %o1
+    0x12BF_FFF5, //             bne   byte_loop
+    0x9002_2001, //              add  %o0, 1, %o0
+    TA_1,        // done:       ta    1
+];
+
+/// The offset of the final `ta 1` of [`CRC32_ROUTINE`].
+pub(crate) const CRC32_ROUTINE_END: u32 = 4 * (CRC32_ROUTINE.len() as u32 - 1);
+
+/// Compute the CRC-32 of `data` on the host, like [`CRC32_ROUTINE`] does on the target.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::architecture::leon3::instructions::Instruction;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(&[]), 0);
+    }
+
+    #[test]
+    fn routine_branch_targets() {
+        let target = |index: usize| match Instruction::decode(CRC32_ROUTINE[index]) {
+            Instruction::Branch { displacement, .. } => (index as i32 * 4 + displacement) / 4,
+            _ => panic!("no branch at {index}"),
+        };
+        assert_eq!(target(4) as u32 * 4, CRC32_ROUTINE_END);
+        assert_eq!(target(10), 13);
+        assert_eq!(target(14), 9);
+        assert_eq!(target(17), 6);
+    }
+}
@@ -0,0 +1,168 @@
+//! Diagnostic dump of the DSU3 state.
+
+use std::fmt;
+
+use crate::architecture::leon3::{
+    dsu3::{DsuCtrl, DsuDtr},
+    trace::AhbTraceControl,
+};
+
+/// The state of an IU watchpoint, as configured in its %asr24-%asr31 register pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchpointState {
+    /// The raw watchpoint address register (WADDR).
+    pub address: u32,
+    /// The raw watchpoint mask register (WMASK).
+    pub mask: u32,
+}
+
+/// A snapshot of the DSU registers relevant to a core, for bug reports.
+///
+/// The [`fmt::Display`] implementation decodes the registers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DsuReport {
+    /// The index of the core the report is for.
+    pub core_index: usize,
+    /// The DSU control register of the core.
+    pub ctrl: u32,
+    /// The DSU break and single step register, shared by all cores.
+    pub brss: u32,
+    /// The DSU debug mode mask register, shared by all cores.
+    pub dbgm: u32,
+    /// The DSU trap register of the core.
+    pub dtr: u32,
+    /// The DSU time tag counter.
+    pub time_tag: u32,
+    /// The AHB trace buffer control register.
+    pub ahb_trace_control: u32,
+    /// The IU watchpoints of the core. Only available while the core is halted.
+    pub watchpoints: Option<Vec<WatchpointState>>,
+}
+
+/// Format the indices of the set bits of the lower 16 bits of `value`.
+fn cores(value: u32) -> String {
+    let cores: Vec<String> = (0..16)
+        .filter(|core| value & (1 << core) != 0)
+        .map(|core| core.to_string())
+        .collect();
+    format!("[{}]", cores.join(", "))
+}
+
+/// Format the names of the set flags.
+fn flags(flags: &[(&str, bool)]) -> String {
+    let set: Vec<&str> = flags
+        .iter()
+        .filter(|(_, set)| *set)
+        .map(|(name, _)| *name)
+        .collect();
+    if set.is_empty() {
+        "-".to_string()
+    } else {
+        set.join(" ")
+    }
+}
+
+impl fmt::Display for DsuReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ctrl = DsuCtrl::from(self.ctrl);
+        let dtr = DsuDtr::from(self.dtr);
+        let ahb_trace = AhbTraceControl::from(self.ahb_trace_control);
+
+        writeln!(f, "DSU state of core {}", self.core_index)?;
+        writeln!(
+            f,
+            "  Control:         {:#010x} {}",
+            self.ctrl,
+            flags(&[
+                ("PW", ctrl.pw()),
+                ("HL", ctrl.hl()),
+                ("PE", ctrl.pe()),
+                ("EB", ctrl.eb()),
+                ("EE", ctrl.ee()),
+                ("DM", ctrl.dm()),
+                ("BZ", ctrl.bz()),
+                ("BX", ctrl.bx()),
+                ("BS", ctrl.bs()),
+                ("BW", ctrl.bw()),
+                ("BE", ctrl.be()),
+                ("TE", ctrl.te()),
+            ])
+        )?;
+        writeln!(
+            f,
+            "  Break/step:      {:#010x} break now {}, single step {}",
+            self.brss,
+            cores(self.brss),
+            cores(self.brss >> 16)
+        )?;
+        writeln!(
+            f,
+            "  Debug mode mask: {:#010x} halt with others {}, do not halt others {}",
+            self.dbgm,
+            cores(self.dbgm),
+            cores(self.dbgm >> 16)
+        )?;
+        writeln!(
+            f,
+            "  Trap:            {:#010x} trap type {:#04x}{}",
+            self.dtr,
+            dtr.traptype(),
+            if dtr.em() { ", error mode" } else { "" }
+        )?;
+        writeln!(f, "  Time tag:        {:#010x}", self.time_tag)?;
+        writeln!(
+            f,
+            "  AHB trace:       {:#010x} {}, delay {}",
+            self.ahb_trace_control,
+            flags(&[
+                ("EN", ahb_trace.en()),
+                ("DM", ahb_trace.dm()),
+                ("BR", ahb_trace.br())
+            ]),
+            ahb_trace.dcnt()
+        )?;
+
+        match &self.watchpoints {
+            None => writeln!(f, "  Watchpoints:     unavailable while running")?,
+            Some(watchpoints) => {
+                for (unit, watchpoint) in watchpoints.iter().enumerate() {
+                    writeln!(
+                        f,
+                        "  Watchpoint {unit}:    address {:#010x} mask {:#010x} {}",
+                        watchpoint.address & !0b11,
+                        watchpoint.mask & !0b11,
+                        flags(&[
+                            ("IF", watchpoint.address & 0b1 != 0),
+                            ("DL", watchpoint.mask & 0b10 != 0),
+                            ("DS", watchpoint.mask & 0b01 != 0),
+                        ])
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_dsu_report() {
+        let report = DsuReport {
+            core_index: 1,
+            ctrl: 0x0000_004C,
+            brss: 0x0000_0002,
+            dbgm: 0x0001_0003,
+            dtr: 0x0000_00B0,
+            time_tag: 0x1234,
+            ahb_trace_control: 0,
+            watchpoints: Some(vec![WatchpointState {
+                address: 0x4000_0001,
+                mask: 0xFFFF_FFFC,
+            }]),
+        };
+        insta::assert_snapshot!(report.to_string());
+    }
+}
@@ -1,8 +1,9 @@
 use crate::{
     MemoryInterface, MemoryMappedRegister,
     architecture::leon3::{
-        communication_interface::Leon3Error,
-        registers::{IuCoreReg, IuSpecialReg},
+        communication_interface::{DebugUnit, Leon3Error},
+        registers::{FpuReg, IuCoreReg, IuSpecialReg},
+        trace::{AHB_TRACE_BUFFER_OFFSET, AhbTraceIndex, InstructionTraceControl},
     },
     memory_mapped_bitfield_register,
 };
@@ -19,14 +20,43 @@ impl<'state> Dsu3<'state> {
     }
 
     /// Base address of the registers for controlling a single core.
-    ///
-    /// NOTE: Some registers are only implemented for core 0 and have bits
-    /// for each available core.
     fn base_address(&self, core_index: usize) -> Result<u64, Leon3Error> {
         if core_index >= 16 {
             return Err(Leon3Error::CoreOutOfRange { core_index });
         }
-        Ok(self.state.base_addr + (core_index as u64) << 24)
+        Ok(self.state.base_addr + ((core_index as u64) << 24))
+    }
+
+    /// AHB address of the DSU register at `offset` for the given core.
+    ///
+    /// Registers that are shared by all cores, like the break and single step register or the
+    /// AHB trace buffer, are only implemented in the address space of core 0. All other
+    /// registers have a copy per core.
+    pub(crate) fn register_address(
+        &self,
+        offset: u64,
+        core_index: usize,
+    ) -> Result<u64, Leon3Error> {
+        let base = self.base_address(core_index)?;
+        if is_shared_register(offset) {
+            Ok(self.state.base_addr + offset)
+        } else {
+            Ok(base + offset)
+        }
+    }
+
+    /// The generation of the debug support unit.
+    pub fn debug_unit(&self) -> DebugUnit {
+        self.state.unit
+    }
+
+    /// Whether register writes are read back and verified.
+    pub fn verify_writes(&self) -> bool {
+        self.state.verify_writes
+    }
+
+    pub fn set_verify_writes(&mut self, verify: bool) {
+        self.state.verify_writes = verify;
     }
 
     pub fn read_reg<R: MemoryMappedRegister<u32>>(
@@ -34,7 +64,7 @@ impl<'state> Dsu3<'state> {
         ahb: &mut dyn MemoryInterface,
         core_index: usize,
     ) -> Result<R, crate::Error> {
-        let addr = R::get_mmio_address_from_base(self.base_address(core_index)?)?;
+        let addr = self.register_address(R::ADDRESS_OFFSET, core_index)?;
         Ok(R::from(ahb.read_word_32(addr)?))
     }
 
@@ -44,56 +74,142 @@ impl<'state> Dsu3<'state> {
         ahb: &mut dyn MemoryInterface,
         core_index: usize,
     ) -> Result<(), crate::Error> {
-        let addr = R::get_mmio_address_from_base(self.base_address(core_index)?)?;
+        let addr = self.register_address(R::ADDRESS_OFFSET, core_index)?;
         ahb.write_word_32(addr, value.into())
     }
 
-    pub fn modify_reg<R: MemoryMappedRegister<u32>, T>(
+    /// The number of register windows implemented by the given core.
+    ///
+    /// This is read from %asr17 on first use, so the core must be in debug mode.
+    pub fn num_windows(
+        &mut self,
+        ahb: &mut dyn MemoryInterface,
+        core_index: usize,
+    ) -> Result<u32, crate::Error> {
+        self.base_address(core_index)?;
+        if let Some(num_windows) = self.state.num_windows[core_index] {
+            return Ok(num_windows);
+        }
+        let asr17: Asr17 = self.read_reg(ahb, core_index)?;
+        let num_windows = u32::from(asr17.nwin()) + 1;
+        self.state.num_windows[core_index] = Some(num_windows);
+        Ok(num_windows)
+    }
+
+    /// The number of entries of the instruction trace buffer of the given core, or 0 if the
+    /// core has no trace buffer.
+    ///
+    /// The size is determined on first use by writing all ones to the trace pointer and reading
+    /// back which bits stuck, so the core must be in debug mode.
+    pub fn instruction_trace_entries(
+        &mut self,
+        ahb: &mut dyn MemoryInterface,
+        core_index: usize,
+    ) -> Result<usize, crate::Error> {
+        self.base_address(core_index)?;
+        if let Some(pointer_mask) = self.state.trace_pointer_masks[core_index] {
+            return Ok(trace_entries(pointer_mask));
+        }
+        let original: InstructionTraceControl = self.read_reg(ahb, core_index)?;
+        let mut probe = original;
+        probe.set_it_pointer(0xFFFF);
+        self.write_reg(probe, ahb, core_index)?;
+        let probe: InstructionTraceControl = self.read_reg(ahb, core_index)?;
+        self.write_reg(original, ahb, core_index)?;
+
+        self.state.trace_pointer_masks[core_index] = Some(probe.it_pointer());
+        Ok(trace_entries(probe.it_pointer()))
+    }
+
+    /// The number of entries of the AHB trace buffer, or 0 if the DSU has no AHB trace buffer.
+    ///
+    /// The size is determined on first use by writing all ones to the trace index and reading
+    /// back which bits stuck, so tracing must be disabled.
+    pub fn ahb_trace_entries(
+        &mut self,
+        ahb: &mut dyn MemoryInterface,
+    ) -> Result<usize, crate::Error> {
+        if let Some(index_mask) = self.state.ahb_trace_index_mask {
+            return Ok(trace_entries(index_mask));
+        }
+        let original: AhbTraceIndex = self.read_reg(ahb, 0)?;
+        let mut probe = original;
+        probe.set_index(0xFFF);
+        self.write_reg(probe, ahb, 0)?;
+        let probe: AhbTraceIndex = self.read_reg(ahb, 0)?;
+        self.write_reg(original, ahb, 0)?;
+
+        self.state.ahb_trace_index_mask = Some(probe.index());
+        Ok(trace_entries(probe.index()))
+    }
+
+    /// Write a word in the DSU address space of the given core.
+    pub fn write_word(
         &self,
+        offset: u64,
+        value: u32,
         ahb: &mut dyn MemoryInterface,
         core_index: usize,
-        f: impl Fn(&mut R) -> T,
-    ) -> Result<T, crate::Error> {
-        let mut value = self.read_reg::<R>(ahb, core_index)?;
-        let result = f(&mut value);
-        self.write_reg(value, ahb, core_index)?;
-        Ok(result)
+    ) -> Result<(), crate::Error> {
+        ahb.write_word_32(self.register_address(offset, core_index)?, value)
     }
 
-    pub fn read_core_reg(
+    /// Read a block of words from the DSU address space of the given core.
+    pub fn read_block(
         &self,
+        offset: u64,
+        data: &mut [u32],
+        ahb: &mut dyn MemoryInterface,
+        core_index: usize,
+    ) -> Result<(), crate::Error> {
+        ahb.read_32(self.register_address(offset, core_index)?, data)
+    }
+
+    pub fn read_core_reg(
+        &mut self,
         reg: IuCoreReg,
         ahb: &mut dyn MemoryInterface,
         core_index: usize,
         cwp: u32,
     ) -> Result<u32, crate::Error> {
-        // TODO(darsor): may not always be 8, read from ASR17
-        let num_windows = 8;
-        let addr = self.base_address(core_index)? + reg.dsu3_addr(num_windows, cwp);
+        let num_windows = self.num_windows(ahb, core_index)?;
+        let addr = self.register_address(reg.dsu3_addr(num_windows, cwp), core_index)?;
         ahb.read_word_32(addr)
     }
 
     pub fn write_core_reg(
-        &self,
+        &mut self,
         reg: IuCoreReg,
         value: u32,
         ahb: &mut dyn MemoryInterface,
         core_index: usize,
         cwp: u32,
     ) -> Result<(), crate::Error> {
-        // TODO(darsor): may not always be 8, read from ASR17
-        let num_windows = 8;
-        let addr = self.base_address(core_index)? + reg.dsu3_addr(num_windows, cwp);
+        let num_windows = self.num_windows(ahb, core_index)?;
+        let addr = self.register_address(reg.dsu3_addr(num_windows, cwp), core_index)?;
         ahb.write_word_32(addr, value)
     }
 
+    /// Read the whole IU register file: the windowed registers of all windows, followed
+    /// by the globals.
+    pub fn read_register_file(
+        &mut self,
+        ahb: &mut dyn MemoryInterface,
+        core_index: usize,
+    ) -> Result<Vec<u32>, crate::Error> {
+        let num_windows = self.num_windows(ahb, core_index)?;
+        let mut words = vec![0; num_windows as usize * 16 + 8];
+        ahb.read_32(self.register_address(0x30_0000, core_index)?, &mut words)?;
+        Ok(words)
+    }
+
     pub fn read_special_reg(
         &self,
         reg: IuSpecialReg,
         ahb: &mut dyn MemoryInterface,
         core_index: usize,
     ) -> Result<u32, crate::Error> {
-        let addr = self.base_address(core_index)? + reg.dsu3_addr();
+        let addr = self.register_address(reg.dsu3_addr(), core_index)?;
         ahb.read_word_32(addr)
     }
 
@@ -104,9 +220,108 @@ impl<'state> Dsu3<'state> {
         ahb: &mut dyn MemoryInterface,
         core_index: usize,
     ) -> Result<(), crate::Error> {
-        let addr = self.base_address(core_index)? + reg.dsu3_addr();
+        let addr = self.register_address(reg.dsu3_addr(), core_index)?;
+        ahb.write_word_32(addr, value)
+    }
+
+    pub fn read_fpu_reg(
+        &self,
+        reg: FpuReg,
+        ahb: &mut dyn MemoryInterface,
+        core_index: usize,
+    ) -> Result<u32, crate::Error> {
+        let addr = self.register_address(reg.dsu3_addr(), core_index)?;
+        ahb.read_word_32(addr)
+    }
+
+    pub fn write_fpu_reg(
+        &self,
+        reg: FpuReg,
+        value: u32,
+        ahb: &mut dyn MemoryInterface,
+        core_index: usize,
+    ) -> Result<(), crate::Error> {
+        let addr = self.register_address(reg.dsu3_addr(), core_index)?;
+        ahb.write_word_32(addr, value)
+    }
+
+    /// Read a word from an alternate address space of the given core.
+    ///
+    /// The access is performed through the DSU ASI diagnostic area, so only the lower
+    /// 20 bits of the address are used.
+    pub fn read_asi(
+        &self,
+        asi: u8,
+        address: u32,
+        ahb: &mut dyn MemoryInterface,
+        core_index: usize,
+    ) -> Result<u32, crate::Error> {
+        let addr = self.select_asi(asi, address, ahb, core_index)?;
+        ahb.read_word_32(addr)
+    }
+
+    /// Write a word to an alternate address space of the given core.
+    ///
+    /// The access is performed through the DSU ASI diagnostic area, so only the lower
+    /// 20 bits of the address are used.
+    pub fn write_asi(
+        &self,
+        asi: u8,
+        address: u32,
+        value: u32,
+        ahb: &mut dyn MemoryInterface,
+        core_index: usize,
+    ) -> Result<(), crate::Error> {
+        let addr = self.select_asi(asi, address, ahb, core_index)?;
         ahb.write_word_32(addr, value)
     }
+
+    /// Read consecutive words from an alternate address space of the given core.
+    ///
+    /// The access is performed through the DSU ASI diagnostic area, so only the lower
+    /// 20 bits of the address are used.
+    pub fn read_asi_block(
+        &self,
+        asi: u8,
+        address: u32,
+        data: &mut [u32],
+        ahb: &mut dyn MemoryInterface,
+        core_index: usize,
+    ) -> Result<(), crate::Error> {
+        let addr = self.select_asi(asi, address, ahb, core_index)?;
+        ahb.read_32(addr, data)
+    }
+
+    /// Write consecutive words to an alternate address space of the given core.
+    ///
+    /// The access is performed through the DSU ASI diagnostic area, so only the lower
+    /// 20 bits of the address are used.
+    pub fn write_asi_block(
+        &self,
+        asi: u8,
+        address: u32,
+        data: &[u32],
+        ahb: &mut dyn MemoryInterface,
+        core_index: usize,
+    ) -> Result<(), crate::Error> {
+        let addr = self.select_asi(asi, address, ahb, core_index)?;
+        ahb.write_32(addr, data)
+    }
+
+    /// Program the DSU ASI register and return the AHB address of `address` in the
+    /// ASI diagnostic area.
+    fn select_asi(
+        &self,
+        asi: u8,
+        address: u32,
+        ahb: &mut dyn MemoryInterface,
+        core_index: usize,
+    ) -> Result<u64, crate::Error> {
+        let mut reg = DsuAsi(0);
+        reg.set_asi(asi);
+        self.write_reg(reg, ahb, core_index)?;
+        Ok(self.register_address(0x70_0000 + u64::from(address & 0xF_FFFC), core_index)?)
+    }
 }
 
 /// State of the DSU3 (not for any specific core).
@@ -114,11 +329,90 @@ impl<'state> Dsu3<'state> {
 pub(crate) struct Dsu3State {
     /// Base address of the DSU3 addresss space
     base_addr: u64,
+
+    /// The number of register windows of each core, once known
+    num_windows: [Option<u32>; 16],
+
+    /// The implemented bits of the instruction trace pointer of each core, once known
+    trace_pointer_masks: [Option<u16>; 16],
+
+    /// The implemented bits of the AHB trace index, once known
+    ahb_trace_index_mask: Option<u16>,
+
+    /// Read back register writes to make sure they took effect
+    verify_writes: bool,
+
+    /// The generation of the debug support unit
+    unit: DebugUnit,
 }
 
 impl Dsu3State {
-    pub(crate) fn new(base_addr: u64) -> Self {
-        Self { base_addr }
+    pub(crate) fn new(base_addr: u64, unit: DebugUnit) -> Self {
+        Self {
+            base_addr,
+            unit,
+            num_windows: [None; 16],
+            trace_pointer_masks: [None; 16],
+            ahb_trace_index_mask: None,
+            verify_writes: false,
+        }
+    }
+
+    /// The base address of the debug support unit.
+    pub(crate) fn base_address(&self) -> u64 {
+        self.base_addr
+    }
+
+    /// The generation of the debug support unit.
+    pub(crate) fn unit(&self) -> DebugUnit {
+        self.unit
+    }
+}
+
+/// Request the first `num_cores` processors of the debug support unit at `base_addr` to enter
+/// debug mode, starting with a write to the break and single step register.
+///
+/// This is used right after the system reset is released, before the plug&play area is scanned,
+/// so that the firmware executes as few instructions as possible. Break-now only takes effect
+/// once the break on IU watchpoint (BW) bit is set in the control register of a processor,
+/// which is set afterwards.
+pub(crate) fn break_now(
+    ahb: &mut dyn MemoryInterface,
+    base_addr: u64,
+    num_cores: usize,
+) -> Result<(), crate::Error> {
+    let num_cores = num_cores.min(16);
+    let mut brss = DsuBrss::from(0);
+    for core_index in 0..num_cores {
+        brss.set_bn(core_index, true);
+    }
+    ahb.write_word_32(base_addr + DsuBrss::ADDRESS_OFFSET, brss.into())?;
+
+    for core_index in 0..num_cores {
+        let address = base_addr + ((core_index as u64) << 24) + DsuCtrl::ADDRESS_OFFSET;
+        let mut ctrl = DsuCtrl::from(ahb.read_word_32(address)?);
+        // Writing back PE would take the processor out of error mode.
+        ctrl.set_pe(false);
+        ctrl.set_bw(true);
+        ahb.write_word_32(address, ctrl.into())?;
+    }
+    Ok(())
+}
+
+/// Whether the DSU register at `offset` is shared by all cores: the time tag counter, the break
+/// and single step register, the debug mode mask register and the AHB trace buffer with its
+/// control and watchpoint registers.
+fn is_shared_register(offset: u64) -> bool {
+    matches!(offset, 0x08 | 0x20 | 0x24 | 0x40..0x60)
+        || (AHB_TRACE_BUFFER_OFFSET..0x30_0000).contains(&offset)
+}
+
+/// The number of trace buffer entries addressed by a trace pointer or index with the given
+/// implemented bits.
+fn trace_entries(pointer_mask: u16) -> usize {
+    match pointer_mask {
+        0 => 0,
+        mask => usize::from(mask) + 1,
     }
 }
 
@@ -138,6 +432,16 @@ impl IuCoreReg {
     }
 }
 
+impl FpuReg {
+    fn dsu3_addr(&self) -> u64 {
+        match self {
+            FpuReg::F(n) => 0x30_1000 + u64::from(*n) * 4,
+            // The most significant word of the pair.
+            FpuReg::D(n) => 0x30_1000 + u64::from(*n) * 8,
+        }
+    }
+}
+
 impl IuSpecialReg {
     fn dsu3_addr(&self) -> u64 {
         match self {
@@ -194,6 +498,20 @@ memory_mapped_bitfield_register! {
     pub te, set_te: 0;
 }
 
+memory_mapped_bitfield_register! {
+    /// DSU Time Tag Counter (GRLIB IP Core User's Manual, DSU3)
+    ///
+    /// Incremented every clock cycle while any processor is running, and stopped while all
+    /// processors are in debug mode. Its value is stored with each trace buffer entry. This
+    /// register is shared by all processors, and is only accessible in the DSU memory map of
+    /// processor 0.
+    pub struct DsuTimeTag(u32);
+    0x08, "dsu_time_tag",
+    impl From;
+    /// Time tag value (TIMETAG).
+    pub u32, value, set_value: 29, 0;
+}
+
 memory_mapped_bitfield_register! {
     /// DSU Break and Single Step Register (GRLIB IP Core User's Manual 32.6.2)
     ///
@@ -221,15 +539,15 @@ memory_mapped_bitfield_register! {
     /// the DSU Debug Mode Mask register determines if the other processors are forced in the debug mode.
     /// This register controls all processors in a multi-processor system, and is only accessible in the DSU
     /// memory map of processor 0.
-    struct DsuDbgm(u32);
+    pub struct DsuDbgm(u32);
     0x24, "dsu_dbgm",
     impl From;
     /// Debug mode mask (DMx) - If set, the corresponding processor will not be able to force running
     /// processors into debug mode even if it enters debug mode.
-    bool, dm, set_dm: 16, 16, 16;
+    pub bool, dm, set_dm: 16, 16, 16;
     /// Enter debug mode (EDx) - Force processor x into debug mode if any of processors in a
     /// multiprocessor system enters the debug mode. If 0, the processor x will not enter the debug mode.
-    bool, ed, set_ed: 0, 0, 16;
+    pub bool, ed, set_ed: 0, 0, 16;
 }
 
 memory_mapped_bitfield_register! {
@@ -238,13 +556,25 @@ memory_mapped_bitfield_register! {
     /// The DSU trap register is a read-only register that indicates which SPARC trap type that caused the
     /// processor to enter debug mode. When debug mode is force by setting the BN bit in the DSU control
     /// register, the trap type will be 0xb (hardware watchpoint trap).
-    struct DsuDtr(u32);
+    pub struct DsuDtr(u32);
     0x40_0020, "dsu_dtr",
     impl From;
     /// Error mode (EM) - Set if the trap would have cause the processor to enter error mode.
-    em, _: 12;
+    pub em, _: 12;
     /// Trap type (TRAPTYPE) - 8-bit SPARC trap type
-    u8, traptype, _: 11, 4;
+    pub u8, traptype, _: 11, 4;
+}
+
+memory_mapped_bitfield_register! {
+    /// DSU ASI Diagnostic Access Register (GRLIB IP Core User's Manual 32.6.5)
+    ///
+    /// Selects the ASI used for accesses to the ASI diagnostic area of the DSU
+    /// memory map.
+    struct DsuAsi(u32);
+    0x40_0024, "dsu_asi",
+    impl From;
+    /// ASI (ASI) - The ASI used for diagnostic accesses.
+    u8, asi, set_asi: 7, 0;
 }
 
 memory_mapped_bitfield_register! {
@@ -322,3 +652,57 @@ memory_mapped_bitfield_register! {
     /// RESTORE and RETT instructions (modulo NWINDOWS).
     pub cwp, _: 4, 0;
 }
+
+memory_mapped_bitfield_register! {
+    /// %asr17 - LEON3 configuration register (GRLIB IP Core User's Manual 86.10.2)
+    ///
+    /// Provides information on how the LEON3 implementation has been configured.
+    pub struct Asr17(u32);
+    0x40_0044, "asr17",
+    impl From;
+    /// Processor index (INDEX) - Identifies the processor in a multi-processor system.
+    pub u8, index, _: 31, 28;
+    /// Floating-point option (FPU) - 00 = no FPU, 01 = GRFPU, 10 = Meiko FPU, 11 = GRFPU-Lite.
+    pub u8, fpu, _: 11, 10;
+    /// Multiply-accumulate (M) - Set if the optional UMAC/SMAC instructions and %asr18 are implemented.
+    pub mac, _: 9;
+    /// Number of watchpoints (NWP) - The number of implemented IU watchpoints (0 - 4).
+    pub u8, nwp, _: 7, 5;
+    /// Number of windows (NWIN) - The number of implemented register windows minus one.
+    pub u8, nwin, _: 4, 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_address_per_core() {
+        let mut state = Dsu3State::new(0x9000_0000, DebugUnit::Dsu3);
+        let dsu = Dsu3::new(&mut state);
+
+        assert_eq!(dsu.base_address(0).unwrap(), 0x9000_0000);
+        assert_eq!(dsu.base_address(1).unwrap(), 0x9100_0000);
+        assert_eq!(dsu.base_address(15).unwrap(), 0x9F00_0000);
+        assert!(dsu.base_address(16).is_err());
+    }
+
+    #[test]
+    fn register_address_per_core() {
+        let mut state = Dsu3State::new(0x9000_0000, DebugUnit::Dsu3);
+        let dsu = Dsu3::new(&mut state);
+
+        // Per-core registers
+        assert_eq!(dsu.register_address(0x00, 2).unwrap(), 0x9200_0000);
+        assert_eq!(dsu.register_address(0x40_0020, 2).unwrap(), 0x9240_0020);
+        assert_eq!(dsu.register_address(0x30_0000, 3).unwrap(), 0x9330_0000);
+        assert_eq!(dsu.register_address(0x11_0000, 1).unwrap(), 0x9111_0000);
+
+        // Shared registers
+        assert_eq!(dsu.register_address(0x20, 2).unwrap(), 0x9000_0020);
+        assert_eq!(dsu.register_address(0x44, 3).unwrap(), 0x9000_0044);
+        assert_eq!(dsu.register_address(0x20_0010, 1).unwrap(), 0x9020_0010);
+
+        assert!(dsu.register_address(0x00, 16).is_err());
+    }
+}
@@ -0,0 +1,239 @@
+//! EDAC status and error injection of fault-tolerant LEON3 systems.
+//!
+//! Memory errors are corrected by the FTMCTRL memory controller and reported through the AHB
+//! status register (AHBSTAT). The caches of fault-tolerant cores count their errors in the
+//! cache control register.
+
+use crate::{
+    MemoryMappedRegister,
+    architecture::leon3::{
+        cache::Ccr,
+        communication_interface::Leon3CommunicationInterface,
+        l2cache::L2CacheError,
+        mctrl::Mcfg3,
+        plugnplay::{Device, GaislerDevice},
+    },
+    memory_mapped_bitfield_register,
+};
+
+memory_mapped_bitfield_register! {
+    /// AHB Status Register (GRLIB IP Core User's Manual, AHBSTAT)
+    ///
+    /// Latches the first AHB error or correctable EDAC error after it was cleared.
+    pub struct AhbStatus(u32);
+    0x00, "ahbstat_status",
+    impl From;
+    /// Correctable error (CE) - set if the latched error is a corrected EDAC error.
+    pub ce, _: 9;
+    /// New error (NE) - set when an error is latched. No further errors are latched until
+    /// the register is cleared.
+    pub ne, _: 8;
+    /// The latched access was a write (HWRITE).
+    pub hwrite, _: 7;
+    /// The AHB master of the latched access (HMASTER).
+    pub u8, hmaster, _: 6, 3;
+    /// The size of the latched access (HSIZE).
+    pub u8, hsize, _: 2, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// AHB Failing Address Register (GRLIB IP Core User's Manual, AHBSTAT)
+    ///
+    /// Holds the address of the latched access.
+    pub struct AhbFailingAddress(u32);
+    0x04, "ahbstat_failing_address",
+    impl From;
+    /// The address of the latched access.
+    pub u32, address, _: 31, 0;
+}
+
+/// The kind of error to inject into memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InjectedError {
+    /// A single bit error, which the EDAC corrects.
+    Correctable,
+    /// A double bit error, which the EDAC detects but cannot correct.
+    Uncorrectable,
+}
+
+impl InjectedError {
+    /// The data bits to flip while keeping the check bits of the original data.
+    fn flipped_bits(self) -> u32 {
+        match self {
+            InjectedError::Correctable => 0b01,
+            InjectedError::Uncorrectable => 0b11,
+        }
+    }
+}
+
+/// An error latched by the AHB status register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryError {
+    /// The address of the failing access.
+    pub address: u32,
+    /// The error was corrected by the EDAC. Otherwise, the access got an AHB error response.
+    pub correctable: bool,
+    /// The failing access was a write.
+    pub write: bool,
+    /// The AHB master of the failing access.
+    pub master: u8,
+}
+
+/// The error counters of the caches of a fault-tolerant core, each saturating at 3.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheErrorCounters {
+    /// Errors in the instruction cache tags.
+    pub instruction_tags: u8,
+    /// Errors in the instruction cache data.
+    pub instruction_data: u8,
+    /// Errors in the data cache tags.
+    pub data_tags: u8,
+    /// Errors in the data cache data.
+    pub data_data: u8,
+}
+
+impl CacheErrorCounters {
+    pub(crate) fn from_ccr(ccr: Ccr) -> Self {
+        Self {
+            instruction_tags: ccr.ite(),
+            instruction_data: ccr.ide(),
+            data_tags: ccr.dte(),
+            data_data: ccr.dde(),
+        }
+    }
+}
+
+/// The EDAC status of a LEON3 system, as seen by one core.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EdacStatus {
+    /// The error latched by the AHB status register, if there is one. `None` if no error was
+    /// latched or the system has no AHB status register.
+    pub memory_error: Option<MemoryError>,
+    /// The error counters of the caches of the core.
+    pub cache_errors: CacheErrorCounters,
+    /// The error latched by the L2 cache, if there is one. `None` if no error was latched or
+    /// the system has no L2 cache.
+    pub l2_cache_error: Option<L2CacheError>,
+}
+
+/// The AHB status register, which latches AHB errors and corrected EDAC errors.
+pub(crate) struct AhbStat {
+    base: u64,
+}
+
+impl AhbStat {
+    /// Find the AHB status register in the plug&play records.
+    pub(crate) fn find(interface: &mut Leon3CommunicationInterface) -> Option<Self> {
+        interface
+            .plugnplay()
+            .find_apb_registers(Device::Gaisler(GaislerDevice::AHBSTAT))
+            .map(|base| Self { base })
+    }
+
+    /// Read the latched error, if there is one.
+    pub(crate) fn read_error(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+    ) -> Result<Option<MemoryError>, crate::Error> {
+        let memory = interface.as_memory_interface_mut();
+        let status = AhbStatus(memory.read_word_32(self.base + AhbStatus::ADDRESS_OFFSET)?);
+        if !status.ne() {
+            return Ok(None);
+        }
+        let address =
+            AhbFailingAddress(memory.read_word_32(self.base + AhbFailingAddress::ADDRESS_OFFSET)?);
+        Ok(Some(MemoryError {
+            address: address.address(),
+            correctable: status.ce(),
+            write: status.hwrite(),
+            master: status.hmaster(),
+        }))
+    }
+
+    /// Clear the latched error, so the next error is latched.
+    pub(crate) fn clear(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+    ) -> Result<(), crate::Error> {
+        interface
+            .as_memory_interface_mut()
+            .write_word_32(self.base + AhbStatus::ADDRESS_OFFSET, 0)
+    }
+}
+
+/// The FTMCTRL memory controller with EDAC.
+pub(crate) struct Ftmctrl {
+    base: u64,
+}
+
+impl Ftmctrl {
+    /// Find the memory controller in the plug&play records.
+    pub(crate) fn find(interface: &mut Leon3CommunicationInterface) -> Option<Self> {
+        interface
+            .plugnplay()
+            .find_apb_registers(Device::Gaisler(GaislerDevice::FTMCTRL))
+            .map(|base| Self { base })
+    }
+
+    /// Inject an error into the word at `address` by writing it back with some data bits
+    /// flipped, but with the check bits of the original data.
+    ///
+    /// The error is detected the next time the word is read.
+    pub(crate) fn inject_error(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        address: u64,
+        error: InjectedError,
+    ) -> Result<(), crate::Error> {
+        let memory = interface.as_memory_interface_mut();
+        let mcfg3_address = self.base + Mcfg3::ADDRESS_OFFSET;
+        let original = Mcfg3(memory.read_word_32(mcfg3_address)?);
+
+        // Capture the check bits of the original data with a bypassed read...
+        let mut mcfg3 = original;
+        mcfg3.set_rb(true);
+        mcfg3.set_wb(false);
+        memory.write_word_32(mcfg3_address, mcfg3.into())?;
+        let data = memory.read_word_32(address)?;
+
+        // ...and write the corrupted data with those check bits.
+        let mut mcfg3 = Mcfg3(memory.read_word_32(mcfg3_address)?);
+        mcfg3.set_rb(false);
+        mcfg3.set_wb(true);
+        memory.write_word_32(mcfg3_address, mcfg3.into())?;
+        let written = memory.write_word_32(address, data ^ error.flipped_bits());
+
+        memory.write_word_32(mcfg3_address, original.into())?;
+        written
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_error_counters() {
+        let counters = CacheErrorCounters::from_ccr(Ccr(0b11_10_01_00 << 6 | 0b1111));
+        assert_eq!(
+            counters,
+            CacheErrorCounters {
+                instruction_tags: 3,
+                instruction_data: 2,
+                data_tags: 1,
+                data_data: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn decode_ahb_status() {
+        // A corrected read error of AHB master 2.
+        let status = AhbStatus(0x0000_0312);
+        assert!(status.ne());
+        assert!(status.ce());
+        assert!(!status.hwrite());
+        assert_eq!(status.hmaster(), 2);
+        assert_eq!(status.hsize(), 2);
+    }
+}
@@ -0,0 +1,410 @@
+//! EDCL debug link, giving access to the AHB bus through the Ethernet debug communication
+//! link of a GRETH Ethernet MAC.
+//!
+//! EDCL requests are UDP packets sent to the IP address of the EDCL. Each packet carries a
+//! control word with a 14-bit sequence number, the transfer direction and length, followed
+//! by the AHB address and, for writes, the data. The EDCL answers each request with the same
+//! header, and the data for reads. A request with an unexpected sequence number is answered
+//! with a NAK carrying the expected sequence number.
+
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::Duration,
+};
+
+use crate::{
+    MemoryInterface,
+    architecture::leon3::{
+        ahbjtag::check_out_of_bounds,
+        communication_interface::Leon3Error,
+        word_access::{read_unaligned, write_unaligned},
+    },
+    memory::{InvalidDataLengthError, valid_32bit_address},
+};
+
+/// The UDP port requests are sent to, if none is given. The EDCL accepts any port.
+pub const DEFAULT_EDCL_PORT: u16 = 8000;
+
+/// The default time to wait for the response to a single request.
+const DEFAULT_EDCL_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How often a request is sent before giving up.
+const MAX_ATTEMPTS: usize = 5;
+
+/// The maximum number of data bytes per request. The length field is 10 bits wide, but the
+/// smallest EDCL buffers only hold about 1 kB per packet.
+const MAX_PAYLOAD: usize = 512;
+
+/// Length of the offset, control and address fields.
+const HEADER_LEN: usize = 10;
+
+const SEQUENCE_MASK: u16 = 0x3FFF;
+
+/// Errors of the EDCL debug link.
+#[derive(thiserror::Error, Debug)]
+pub enum EdclError {
+    /// The address of the EDCL could not be resolved.
+    #[error("Invalid EDCL address {0:?}")]
+    InvalidAddress(String),
+    /// Sending or receiving a packet failed.
+    #[error("EDCL socket error")]
+    Io(#[from] io::Error),
+    /// The EDCL did not answer a request.
+    #[error("No response from the EDCL at {0}")]
+    NoResponse(SocketAddr),
+    /// The EDCL answered with a malformed packet.
+    #[error("Invalid EDCL response")]
+    InvalidResponse,
+}
+
+/// The direction of an EDCL request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Direction {
+    Read,
+    Write,
+}
+
+/// The decoded header of an EDCL response.
+#[derive(Debug, PartialEq, Eq)]
+struct ResponseHeader {
+    sequence: u16,
+    nak: bool,
+}
+
+/// EDCL driver used to access the AHB bus over Ethernet.
+#[derive(Debug)]
+pub struct Edcl {
+    socket: UdpSocket,
+    remote: SocketAddr,
+    sequence: u16,
+}
+
+impl Edcl {
+    /// Connects to the EDCL at `address`, an IP address or host name with an optional port.
+    pub fn connect(address: &str) -> Result<Self, EdclError> {
+        let invalid = || EdclError::InvalidAddress(address.to_string());
+        let remote = match address.to_socket_addrs() {
+            Ok(mut addrs) => addrs.next(),
+            Err(_) => (address, DEFAULT_EDCL_PORT)
+                .to_socket_addrs()
+                .map_err(|_| invalid())?
+                .next(),
+        }
+        .ok_or_else(invalid)?;
+
+        let local: SocketAddr = if remote.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(remote)?;
+        socket.set_read_timeout(Some(DEFAULT_EDCL_TIMEOUT))?;
+        tracing::info!("Using EDCL at {remote}");
+
+        Ok(Self {
+            socket,
+            remote,
+            sequence: 0,
+        })
+    }
+
+    /// Returns how long to wait for the response to a single request.
+    pub fn timeout(&self) -> Duration {
+        self.socket
+            .read_timeout()
+            .ok()
+            .flatten()
+            .unwrap_or(DEFAULT_EDCL_TIMEOUT)
+    }
+
+    /// Sets how long to wait for the response to a single request, before it is sent again.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        if let Err(error) = self.socket.set_read_timeout(Some(timeout)) {
+            tracing::warn!("Failed to set the EDCL timeout: {error}");
+        }
+    }
+
+    /// Performs a single request of at most [`MAX_PAYLOAD`] bytes.
+    ///
+    /// Requests that are not answered are sent again. A NAK means the EDCL expects a
+    /// different sequence number, so the request is sent again with that number.
+    fn transfer(
+        &mut self,
+        direction: Direction,
+        address: u32,
+        write_data: &[u8],
+        read_data: &mut [u8],
+    ) -> Result<(), EdclError> {
+        let length = match direction {
+            Direction::Read => read_data.len(),
+            Direction::Write => write_data.len(),
+        };
+        let mut response = [0u8; HEADER_LEN + MAX_PAYLOAD];
+
+        for _ in 0..MAX_ATTEMPTS {
+            let request = encode_request(self.sequence, direction, address, length, write_data);
+            self.socket.send(&request)?;
+
+            let received = match self.socket.recv(&mut response) {
+                Ok(received) => received,
+                Err(error)
+                    if matches!(
+                        error.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    tracing::debug!("EDCL request {} timed out", self.sequence);
+                    continue;
+                }
+                Err(error) => return Err(error.into()),
+            };
+            let header = decode_response(&response[..received])?;
+
+            let next_sequence = (self.sequence + 1) & SEQUENCE_MASK;
+            if header.nak && direction == Direction::Write && header.sequence == next_sequence {
+                // The response to an earlier attempt got lost, but the write was performed.
+                self.sequence = next_sequence;
+                return Ok(());
+            }
+            if header.nak {
+                tracing::debug!(
+                    "EDCL expects sequence number {}, not {}",
+                    header.sequence,
+                    self.sequence
+                );
+                self.sequence = header.sequence;
+                continue;
+            }
+            if header.sequence != self.sequence {
+                // A late response to a request that was already sent again.
+                continue;
+            }
+
+            if direction == Direction::Read {
+                let data = &response[HEADER_LEN..received];
+                if data.len() < length {
+                    return Err(EdclError::InvalidResponse);
+                }
+                read_data.copy_from_slice(&data[..length]);
+            }
+            self.sequence = next_sequence;
+            return Ok(());
+        }
+
+        Err(EdclError::NoResponse(self.remote))
+    }
+
+    fn read_bytes(&mut self, address: u32, data: &mut [u8]) -> Result<(), Leon3Error> {
+        check_out_of_bounds(address, data.len())?;
+        for (chunk_idx, chunk) in data.chunks_mut(MAX_PAYLOAD).enumerate() {
+            let chunk_address = address + (chunk_idx * MAX_PAYLOAD) as u32;
+            self.transfer(Direction::Read, chunk_address, &[], chunk)?;
+        }
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, address: u32, data: &[u8]) -> Result<(), Leon3Error> {
+        check_out_of_bounds(address, data.len())?;
+        for (chunk_idx, chunk) in data.chunks(MAX_PAYLOAD).enumerate() {
+            let chunk_address = address + (chunk_idx * MAX_PAYLOAD) as u32;
+            self.transfer(Direction::Write, chunk_address, chunk, &mut [])?;
+        }
+        Ok(())
+    }
+}
+
+fn encode_request(
+    sequence: u16,
+    direction: Direction,
+    address: u32,
+    length: usize,
+    data: &[u8],
+) -> Vec<u8> {
+    let write = match direction {
+        Direction::Read => 0,
+        Direction::Write => 1,
+    };
+    let control =
+        (u32::from(sequence & SEQUENCE_MASK) << 18) | (write << 17) | ((length as u32) << 7);
+
+    let mut request = Vec::with_capacity(HEADER_LEN + data.len());
+    // The offset field aligns the control word to 32 bits in the receive buffer.
+    request.extend_from_slice(&[0, 0]);
+    request.extend_from_slice(&control.to_be_bytes());
+    request.extend_from_slice(&address.to_be_bytes());
+    request.extend_from_slice(data);
+    request
+}
+
+fn decode_response(response: &[u8]) -> Result<ResponseHeader, EdclError> {
+    if response.len() < HEADER_LEN {
+        return Err(EdclError::InvalidResponse);
+    }
+    let control = u32::from_be_bytes([response[2], response[3], response[4], response[5]]);
+
+    Ok(ResponseHeader {
+        sequence: (control >> 18) as u16 & SEQUENCE_MASK,
+        nak: control & (1 << 17) != 0,
+    })
+}
+
+impl MemoryInterface for Edcl {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        false
+    }
+
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(8) {
+            return read_unaligned(self, address, data);
+        }
+        let mut bytes = vec![0u8; data.len() * 8];
+        self.read_mem_64bit(address, &mut bytes)?;
+        for (word, bytes) in data.iter_mut().zip(bytes.chunks_exact(8)) {
+            *word = u64::from_be_bytes(bytes.try_into().unwrap());
+        }
+        Ok(())
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(4) {
+            return read_unaligned(self, address, data);
+        }
+        let mut bytes = vec![0u8; data.len() * 4];
+        self.read_mem_32bit(address, &mut bytes)?;
+        for (word, bytes) in data.iter_mut().zip(bytes.chunks_exact(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        Ok(())
+    }
+
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), crate::Error> {
+        let mut bytes = vec![0u8; data.len() * 2];
+        self.read_8(address, &mut bytes)?;
+        for (word, bytes) in data.iter_mut().zip(bytes.chunks_exact(2)) {
+            *word = u16::from_be_bytes([bytes[0], bytes[1]]);
+        }
+        Ok(())
+    }
+
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        let address = valid_32bit_address(address)?;
+        self.read_bytes(address, data)?;
+        Ok(())
+    }
+
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(8) {
+            return write_unaligned(self, address, data);
+        }
+        let bytes = data
+            .iter()
+            .flat_map(|word| word.to_be_bytes())
+            .collect::<Vec<_>>();
+        self.write_mem_64bit(address, &bytes)
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(4) {
+            return write_unaligned(self, address, data);
+        }
+        let bytes = data
+            .iter()
+            .flat_map(|word| word.to_be_bytes())
+            .collect::<Vec<_>>();
+        self.write_mem_32bit(address, &bytes)
+    }
+
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), crate::Error> {
+        let bytes = data
+            .iter()
+            .flat_map(|word| word.to_be_bytes())
+            .collect::<Vec<_>>();
+        self.write_8(address, &bytes)
+    }
+
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        let address = valid_32bit_address(address)?;
+        self.write_bytes(address, data)?;
+        Ok(())
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, crate::Error> {
+        Ok(true)
+    }
+
+    fn flush(&mut self) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn read_mem_64bit(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(8) {
+            return Err(InvalidDataLengthError::new("read_mem_64bit", 8).into());
+        }
+        self.read_8(address, data)
+    }
+
+    fn read_mem_32bit(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(4) {
+            return Err(InvalidDataLengthError::new("read_mem_32bit", 4).into());
+        }
+        self.read_8(address, data)
+    }
+
+    fn write_mem_64bit(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(8) {
+            return Err(InvalidDataLengthError::new("write_mem_64bit", 8).into());
+        }
+        self.write_8(address, data)
+    }
+
+    fn write_mem_32bit(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(4) {
+            return Err(InvalidDataLengthError::new("write_mem_32bit", 4).into());
+        }
+        self.write_8(address, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_read_request() {
+        let request = encode_request(0x1234, Direction::Read, 0x4000_0000, 512, &[]);
+        let control: u32 = (0x1234 << 18) | (512 << 7);
+        let mut expected = vec![0, 0];
+        expected.extend_from_slice(&control.to_be_bytes());
+        expected.extend_from_slice(&[0x40, 0x00, 0x00, 0x00]);
+        assert_eq!(request, expected);
+    }
+
+    #[test]
+    fn encode_write_request() {
+        let request = encode_request(1, Direction::Write, 0x4000_0010, 4, &[1, 2, 3, 4]);
+        assert_eq!(
+            request,
+            [
+                0, 0, 0x00, 0x06, 0x02, 0x00, 0x40, 0x00, 0x00, 0x10, 1, 2, 3, 4
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_nak_response() {
+        let control: u32 = (0x0042 << 18) | (1 << 17);
+        let mut response = vec![0, 0];
+        response.extend_from_slice(&control.to_be_bytes());
+        response.extend_from_slice(&[0; 4]);
+        assert_eq!(
+            decode_response(&response).unwrap(),
+            ResponseHeader {
+                sequence: 0x42,
+                nak: true,
+            }
+        );
+        assert!(decode_response(&response[..6]).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! LEON3 floating-point unit state.
+
+use crate::memory_mapped_bitfield_register;
+
+memory_mapped_bitfield_register! {
+    /// Floating-point State Register (SPARC V8 4.4)
+    ///
+    /// Holds the FPU mode and status information, including the cause of the last
+    /// floating-point exception.
+    pub struct Fsr(u32);
+    0x40_0018, "fsr",
+    impl From;
+    /// Rounding Direction (RD) - the rounding mode of floating-point results.
+    pub u8, rd, _: 31, 30;
+    /// Trap Enable Mask (TEM) - enables traps for the IEEE exceptions in the same bit order as cexc.
+    pub u8, tem, _: 27, 23;
+    /// Nonstandard floating-point (NS) - implementation-defined non-IEEE results are produced.
+    pub ns, _: 22;
+    /// FPU version (ver) - identifies the FPU implementation.
+    pub u8, ver, _: 19, 17;
+    /// Floating-point Trap Type (ftt) - the type of the floating-point exception that caused the
+    /// last fp_exception trap.
+    pub u8, ftt, _: 16, 14;
+    /// Queue Not Empty (qne) - the deferred-trap queue holds the instruction that caused the
+    /// pending floating-point exception.
+    pub qne, _: 13;
+    /// Floating-point Condition Codes (fcc) - result of the last FCMP instruction.
+    pub u8, fcc, _: 11, 10;
+    /// Accrued Exceptions (aexc) - IEEE exceptions accumulated while their traps were disabled.
+    pub u8, aexc, _: 9, 5;
+    /// Current Exceptions (cexc) - IEEE exceptions raised by the last FPop.
+    pub u8, cexc, _: 4, 0;
+}
+
+/// The type of a floating-point exception, as reported in the `ftt` field of the FSR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FpTrapType {
+    /// No floating-point exception occurred.
+    None,
+    /// An IEEE 754 exception whose trap was enabled, see [`FpuException::current`].
+    Ieee754Exception,
+    /// The FPU was unable to complete the operation, it has to be emulated in software.
+    UnfinishedFpop,
+    /// The instruction is not implemented by the FPU.
+    UnimplementedFpop,
+    /// An FPU instruction was executed in an invalid state, e.g. STDFQ with an empty queue.
+    SequenceError,
+    /// The FPU detected a hardware error.
+    HardwareError,
+    /// An instruction used a misaligned double or quad register.
+    InvalidFpRegister,
+    /// A reserved trap type.
+    Reserved(u8),
+}
+
+impl From<u8> for FpTrapType {
+    fn from(ftt: u8) -> Self {
+        match ftt {
+            0 => FpTrapType::None,
+            1 => FpTrapType::Ieee754Exception,
+            2 => FpTrapType::UnfinishedFpop,
+            3 => FpTrapType::UnimplementedFpop,
+            4 => FpTrapType::SequenceError,
+            5 => FpTrapType::HardwareError,
+            6 => FpTrapType::InvalidFpRegister,
+            ftt => FpTrapType::Reserved(ftt),
+        }
+    }
+}
+
+/// A set of IEEE 754 exceptions, as stored in the `cexc` and `aexc` fields of the FSR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IeeeExceptions {
+    /// Invalid operation (nv).
+    pub invalid: bool,
+    /// Overflow (of).
+    pub overflow: bool,
+    /// Underflow (uf).
+    pub underflow: bool,
+    /// Division by zero (dz).
+    pub division_by_zero: bool,
+    /// Inexact result (nx).
+    pub inexact: bool,
+}
+
+impl From<u8> for IeeeExceptions {
+    fn from(bits: u8) -> Self {
+        Self {
+            invalid: bits & (1 << 4) != 0,
+            overflow: bits & (1 << 3) != 0,
+            underflow: bits & (1 << 2) != 0,
+            division_by_zero: bits & (1 << 1) != 0,
+            inexact: bits & (1 << 0) != 0,
+        }
+    }
+}
+
+/// A floating-point exception pending in or raised by the FPU.
+///
+/// LEON3 FPUs use the SPARC deferred-trap model: the fp_exception trap is taken at the next
+/// floating-point instruction after the one that failed, which is kept in the FPU queue.
+///
+/// The address and instruction of the failed FPop are not part of the exception. The FPU
+/// register window of the DSU (0x301000 - 0x30107C) only holds %f0 - %f31 and the FSR is a
+/// special register, there is no DSU address for the queue. The queue can only be read by
+/// executing STDFQ on the core, which removes the entry the trap handler relies on and
+/// cannot be undone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FpuException {
+    /// The type of the exception.
+    pub trap_type: FpTrapType,
+    /// The IEEE exceptions raised by the failed instruction.
+    pub current: IeeeExceptions,
+    /// The IEEE exceptions accumulated while their traps were disabled.
+    pub accrued: IeeeExceptions,
+    /// The failed instruction is still held in the FPU queue, i.e. the trap has not been
+    /// handled yet.
+    pub queue_not_empty: bool,
+}
+
+impl FpuException {
+    /// Decode the exception state in `fsr`. Returns `None` if no exception is pending.
+    pub(crate) fn from_fsr(fsr: Fsr) -> Option<Self> {
+        let trap_type = FpTrapType::from(fsr.ftt());
+        if trap_type == FpTrapType::None && !fsr.qne() {
+            return None;
+        }
+        Some(Self {
+            trap_type,
+            current: fsr.cexc().into(),
+            accrued: fsr.aexc().into(),
+            queue_not_empty: fsr.qne(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_fsr_exception() {
+        // ftt = IEEE 754 exception, qne, aexc = nx, cexc = dz
+        let fsr = Fsr::from((1 << 14) | (1 << 13) | (1 << 5) | (1 << 1));
+        let exception = FpuException::from_fsr(fsr).unwrap();
+        assert_eq!(exception.trap_type, FpTrapType::Ieee754Exception);
+        assert!(exception.queue_not_empty);
+        assert_eq!(
+            exception.current,
+            IeeeExceptions {
+                division_by_zero: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            exception.accrued,
+            IeeeExceptions {
+                inexact: true,
+                ..Default::default()
+            }
+        );
+
+        assert_eq!(FpuException::from_fsr(Fsr::from(0x1F << 5)), None);
+    }
+}
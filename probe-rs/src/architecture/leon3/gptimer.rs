@@ -0,0 +1,64 @@
+//! Access to the GPTIMER and GRTIMER timer units, whose last timer is often used as the
+//! system watchdog.
+
+use crate::architecture::leon3::{
+    communication_interface::Leon3CommunicationInterface,
+    plugnplay::{Device, GaislerDevice, Record},
+};
+
+/// Offset of the configuration register.
+const CONFIGURATION: u64 = 0x08;
+
+/// Disable timer freeze (DF) bit of the configuration register. If set, the timers keep
+/// running while the processor is in debug mode.
+const CONFIGURATION_DF: u32 = 1 << 9;
+
+/// Offset of the scaler reload value register.
+const SCALER_RELOAD: u64 = 0x04;
+
+/// The base addresses of all GPTIMER and GRTIMER units, in plug&play order.
+fn timer_units(interface: &mut Leon3CommunicationInterface) -> Vec<u64> {
+    let plugnplay = interface.plugnplay();
+    [GaislerDevice::GPTIMER, GaislerDevice::GRTIMER]
+        .into_iter()
+        .flat_map(|device| plugnplay.find_devices(Device::Gaisler(device)))
+        .filter_map(Record::base_address)
+        .collect()
+}
+
+/// Make all GPTIMER and GRTIMER units halt their timers while a processor is in debug mode,
+/// so the watchdog does not expire while the core is halted.
+pub(crate) fn freeze_in_debug_mode(
+    interface: &mut Leon3CommunicationInterface,
+) -> Result<(), crate::Error> {
+    let bases = timer_units(interface);
+
+    let memory = interface.as_memory_interface_mut();
+    for base in bases {
+        let configuration = memory.read_word_32(base + CONFIGURATION)?;
+        if configuration & CONFIGURATION_DF != 0 {
+            tracing::debug!("Freezing the timer unit at {base:#010x} in debug mode");
+            memory.write_word_32(base + CONFIGURATION, configuration & !CONFIGURATION_DF)?;
+        }
+    }
+    Ok(())
+}
+
+/// The frequency of the system clock in Hz, derived from the prescaler of the first timer
+/// unit, or `None` if there is no timer unit.
+///
+/// The prescaler divides the system clock by its reload value plus one to generate the timer
+/// ticks. Boot loaders and operating systems set it up so the timers tick at `tick_rate` Hz,
+/// usually 1 MHz.
+pub(crate) fn system_clock_frequency(
+    interface: &mut Leon3CommunicationInterface,
+    tick_rate: u64,
+) -> Result<Option<u64>, crate::Error> {
+    let Some(&base) = timer_units(interface).first() else {
+        return Ok(None);
+    };
+    let reload = interface
+        .as_memory_interface_mut()
+        .read_word_32(base + SCALER_RELOAD)?;
+    Ok(Some((u64::from(reload) + 1) * tick_rate))
+}
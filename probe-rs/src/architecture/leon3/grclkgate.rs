@@ -0,0 +1,68 @@
+//! Access to the GRCLKGATE clock gating unit, which gates the clocks of cores and peripherals
+//! and holds them in reset while they are gated.
+
+use crate::architecture::leon3::{
+    communication_interface::Leon3CommunicationInterface,
+    plugnplay::{Device, GaislerDevice, Record},
+};
+
+/// Offsets of the unlock, clock enable and core reset registers. Each bit of them belongs to
+/// one gated unit.
+const UNLOCK: u64 = 0x00;
+const CLOCK_ENABLE: u64 = 0x04;
+const CORE_RESET: u64 = 0x08;
+
+/// A GRCLKGATE clock gating unit.
+pub(crate) struct ClockGate {
+    base: u64,
+}
+
+impl ClockGate {
+    /// Find all clock gating units in the plug&play records.
+    pub(crate) fn find_all(interface: &mut Leon3CommunicationInterface) -> Vec<Self> {
+        interface
+            .plugnplay()
+            .find_devices(Device::Gaisler(GaislerDevice::CLKGATE))
+            .filter_map(Record::base_address)
+            .map(|base| Self { base })
+            .collect()
+    }
+
+    /// The units whose clocks are gated, as a bit mask.
+    pub(crate) fn gated(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+    ) -> Result<u32, crate::Error> {
+        let enabled = interface
+            .as_memory_interface_mut()
+            .read_word_32(self.base + CLOCK_ENABLE)?;
+        Ok(!enabled)
+    }
+
+    /// Enable the clocks of the units in `mask` and release them from reset, with the
+    /// sequence of the GRLIB IP Core User's Manual.
+    pub(crate) fn ungate(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        mask: u32,
+    ) -> Result<(), crate::Error> {
+        if mask == 0 {
+            return Ok(());
+        }
+        tracing::debug!(
+            "Ungating units {mask:#010x} of the clock gating unit at {:#010x}",
+            self.base
+        );
+        let memory = interface.as_memory_interface_mut();
+        let set = |memory: &mut dyn crate::MemoryInterface, offset, enabled: bool| {
+            let value = memory.read_word_32(self.base + offset)?;
+            let value = if enabled { value | mask } else { value & !mask };
+            memory.write_word_32(self.base + offset, value)
+        };
+        set(memory, UNLOCK, true)?;
+        set(memory, CORE_RESET, true)?;
+        set(memory, CLOCK_ENABLE, true)?;
+        set(memory, CORE_RESET, false)?;
+        set(memory, UNLOCK, false)
+    }
+}
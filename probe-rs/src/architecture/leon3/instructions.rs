@@ -0,0 +1,741 @@
+//! Helpers to decode SPARC V8 instructions for debugger use.
+
+use crate::architecture::leon3::dsu3::Psr;
+
+/// The `ta 1` instruction, which is used as software breakpoint.
+pub(crate) const TA_1: u32 = 0x91D0_2001;
+
+/// The subset of SPARC V8 instructions the debugger needs to understand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Instruction {
+    /// A conditional branch on integer (Bicc), floating-point (FBfcc) or
+    /// coprocessor (CBccc) condition codes.
+    Branch {
+        /// Which condition codes the branch tests.
+        kind: BranchKind,
+        /// The 4-bit condition field.
+        cond: u8,
+        /// The annul bit.
+        annul: bool,
+        /// Byte displacement relative to the address of the branch.
+        displacement: i32,
+    },
+    /// A trap on integer condition codes (Ticc).
+    Trap {
+        /// The 4-bit condition field.
+        cond: u8,
+        /// The software trap number, if it is an immediate with `%g0` as base (`ta n`).
+        number: Option<u8>,
+    },
+    /// Any other instruction.
+    Other,
+}
+
+/// The condition codes tested by a branch instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BranchKind {
+    /// Bicc, tests the integer condition codes in the PSR.
+    Integer,
+    /// FBfcc, tests the floating-point condition codes in the FSR.
+    Float,
+    /// CBccc, tests the coprocessor condition codes.
+    Coprocessor,
+}
+
+impl Instruction {
+    /// Decode a raw instruction word.
+    pub(crate) fn decode(word: u32) -> Self {
+        let op = word >> 30;
+        let op2 = (word >> 22) & 0b111;
+        if op == 0 {
+            let kind = match op2 {
+                0b010 => Some(BranchKind::Integer),
+                0b110 => Some(BranchKind::Float),
+                0b111 => Some(BranchKind::Coprocessor),
+                _ => None,
+            };
+            if let Some(kind) = kind {
+                return Instruction::Branch {
+                    kind,
+                    cond: ((word >> 25) & 0xF) as u8,
+                    annul: (word >> 29) & 1 == 1,
+                    displacement: sign_extend(word & 0x3F_FFFF, 22) << 2,
+                };
+            }
+        }
+        if op == 2 && (word >> 19) & 0x3F == 0x3A {
+            let rs1 = (word >> 14) & 0x1F;
+            let immediate = (word >> 13) & 1 == 1;
+            return Instruction::Trap {
+                cond: ((word >> 25) & 0xF) as u8,
+                number: (immediate && rs1 == 0).then_some((word & 0x7F) as u8),
+            };
+        }
+        Instruction::Other
+    }
+
+    /// Returns whether the raw instruction word is a delayed control transfer, whose next
+    /// instruction is executed in its delay slot: a branch, `call`, `jmpl` or `rett`.
+    pub(crate) fn has_delay_slot(word: u32) -> bool {
+        match word >> 30 {
+            0 => matches!(Instruction::decode(word), Instruction::Branch { .. }),
+            1 => true,
+            2 => matches!((word >> 19) & 0x3F, 0x38 | 0x39),
+            _ => false,
+        }
+    }
+
+    /// The addresses execution can continue at after this instruction executed at `pc`,
+    /// with `npc` being the address of the next instruction, without taking a trap.
+    ///
+    /// Branches that annul their delay slot continue after it or at the branch target, all
+    /// other instructions continue at `npc`. Taken traps are not included, as they depend on
+    /// the trap base register.
+    pub(crate) fn successors(self, pc: u32, npc: u32) -> Vec<u32> {
+        match self {
+            Instruction::Branch {
+                cond,
+                annul: true,
+                displacement,
+                ..
+            } => {
+                let target = pc.wrapping_add_signed(displacement);
+                match cond {
+                    // Branch never, the delay slot is always annulled.
+                    0 => vec![npc.wrapping_add(4)],
+                    // Branch always, the delay slot is always annulled.
+                    8 => vec![target],
+                    // The delay slot is annulled unless the branch is taken.
+                    _ => vec![npc, npc.wrapping_add(4)],
+                }
+            }
+            _ => vec![npc],
+        }
+    }
+}
+
+/// The memory accessed by a load or store instruction (`op` 3).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct MemoryAccess {
+    /// The register `%rN` holding the base address.
+    pub(crate) rs1: u8,
+    /// What is added to the base address.
+    pub(crate) offset: AddressOffset,
+    /// The number of words accessed, 2 for doubleword accesses.
+    pub(crate) words: u32,
+    /// Whether the instruction reads memory.
+    pub(crate) load: bool,
+    /// Whether the instruction writes memory.
+    pub(crate) store: bool,
+}
+
+/// The second operand of the address of a load or store instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AddressOffset {
+    /// The register `%rN`.
+    Register(u8),
+    /// A sign-extended immediate.
+    Immediate(i32),
+}
+
+impl MemoryAccess {
+    /// Decode a raw instruction word, or return `None` if it is not a load or store.
+    pub(crate) fn decode(word: u32) -> Option<Self> {
+        let op3 = (word >> 19) & 0x3F;
+        if word >> 30 != 3 || MEMORY[op3 as usize].is_none() {
+            return None;
+        }
+        let offset = if op3 == 0x3C {
+            // casa uses rs2 as the compare value.
+            AddressOffset::Immediate(0)
+        } else if (word >> 13) & 1 == 1 {
+            AddressOffset::Immediate(sign_extend(word & 0x1FFF, 13))
+        } else {
+            AddressOffset::Register((word & 0x1F) as u8)
+        };
+        let store = matches!(op3 & 0xF, 0x4..=0x7);
+        Some(MemoryAccess {
+            rs1: ((word >> 14) & 0x1F) as u8,
+            offset,
+            words: if matches!(op3 & 0xF, 0x3 | 0x7) || op3 == 0x26 {
+                2
+            } else {
+                1
+            },
+            load: !store,
+            // ldstub, swap and casa read and write memory atomically.
+            store: store || matches!(op3, 0x0D | 0x0F | 0x1D | 0x1F | 0x3C),
+        })
+    }
+}
+
+/// Sign-extend the lowest `bits` bits of `value`.
+fn sign_extend(value: u32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    ((value << shift) as i32) >> shift
+}
+
+/// Evaluate a Bicc condition against the integer condition codes of the PSR.
+pub(crate) fn integer_condition_holds(cond: u8, psr: Psr) -> bool {
+    let (n, z, v, c) = (psr.n(), psr.z(), psr.v(), psr.c());
+    let result = match cond & 0x7 {
+        0 => false,
+        1 => z,
+        2 => z || (n ^ v),
+        3 => n ^ v,
+        4 => c || z,
+        5 => c,
+        6 => n,
+        _ => v,
+    };
+    // The upper half of the condition encodings are the negations of the lower half.
+    if cond & 0x8 == 0 { result } else { !result }
+}
+
+/// Evaluate an FBfcc condition against the floating-point condition codes of the FSR.
+pub(crate) fn float_condition_holds(cond: u8, fsr: u32) -> bool {
+    // fcc: 0 = equal, 1 = less, 2 = greater, 3 = unordered
+    let fcc = (fsr >> 10) & 0b11;
+    let (e, l, g, u) = (fcc == 0, fcc == 1, fcc == 2, fcc == 3);
+    match cond {
+        0 => false,
+        1 => l || g || u,
+        2 => l || g,
+        3 => l || u,
+        4 => l,
+        5 => g || u,
+        6 => g,
+        7 => u,
+        8 => true,
+        9 => e,
+        10 => e || u,
+        11 => e || g,
+        12 => e || g || u,
+        13 => e || l,
+        14 => e || l || u,
+        _ => e || l || g,
+    }
+}
+
+/// Names of the integer condition codes, as used by Bicc and Ticc.
+const INTEGER_CONDITIONS: [&str; 16] = [
+    "n", "e", "le", "l", "leu", "cs", "neg", "vs", "a", "ne", "g", "ge", "gu", "cc", "pos", "vc",
+];
+
+/// Names of the floating-point condition codes of FBfcc.
+const FLOAT_CONDITIONS: [&str; 16] = [
+    "n", "ne", "lg", "ul", "l", "ug", "g", "u", "a", "e", "ue", "ge", "uge", "le", "ule", "o",
+];
+
+/// Names of the coprocessor condition codes of CBccc.
+const COPROCESSOR_CONDITIONS: [&str; 16] = [
+    "n", "123", "12", "13", "1", "23", "2", "3", "a", "0", "03", "02", "023", "01", "013", "012",
+];
+
+/// Mnemonics of the arithmetic and logic instructions (`op` 2), indexed by `op3`.
+const ARITHMETIC: [Option<&str>; 64] = {
+    let mut table = [None; 64];
+    let names = [
+        (0x00, "add"),
+        (0x01, "and"),
+        (0x02, "or"),
+        (0x03, "xor"),
+        (0x04, "sub"),
+        (0x05, "andn"),
+        (0x06, "orn"),
+        (0x07, "xnor"),
+        (0x08, "addx"),
+        (0x0A, "umul"),
+        (0x0B, "smul"),
+        (0x0C, "subx"),
+        (0x0E, "udiv"),
+        (0x0F, "sdiv"),
+        (0x10, "addcc"),
+        (0x11, "andcc"),
+        (0x12, "orcc"),
+        (0x13, "xorcc"),
+        (0x14, "subcc"),
+        (0x15, "andncc"),
+        (0x16, "orncc"),
+        (0x17, "xnorcc"),
+        (0x18, "addxcc"),
+        (0x1A, "umulcc"),
+        (0x1B, "smulcc"),
+        (0x1C, "subxcc"),
+        (0x1E, "udivcc"),
+        (0x1F, "sdivcc"),
+        (0x20, "taddcc"),
+        (0x21, "tsubcc"),
+        (0x22, "taddcctv"),
+        (0x23, "tsubcctv"),
+        (0x24, "mulscc"),
+        (0x25, "sll"),
+        (0x26, "srl"),
+        (0x27, "sra"),
+        (0x3C, "save"),
+        (0x3D, "restore"),
+        (0x3E, "umac"),
+        (0x3F, "smac"),
+    ];
+    let mut i = 0;
+    while i < names.len() {
+        table[names[i].0] = Some(names[i].1);
+        i += 1;
+    }
+    table
+};
+
+/// Mnemonics of the load and store instructions (`op` 3), indexed by `op3`.
+const MEMORY: [Option<&str>; 64] = {
+    let mut table = [None; 64];
+    let names = [
+        (0x00, "ld"),
+        (0x01, "ldub"),
+        (0x02, "lduh"),
+        (0x03, "ldd"),
+        (0x04, "st"),
+        (0x05, "stb"),
+        (0x06, "sth"),
+        (0x07, "std"),
+        (0x09, "ldsb"),
+        (0x0A, "ldsh"),
+        (0x0D, "ldstub"),
+        (0x0F, "swap"),
+        (0x10, "lda"),
+        (0x11, "lduba"),
+        (0x12, "lduha"),
+        (0x13, "ldda"),
+        (0x14, "sta"),
+        (0x15, "stba"),
+        (0x16, "stha"),
+        (0x17, "stda"),
+        (0x19, "ldsba"),
+        (0x1A, "ldsha"),
+        (0x1D, "ldstuba"),
+        (0x1F, "swapa"),
+        (0x20, "ld"),
+        (0x21, "ld"),
+        (0x23, "ldd"),
+        (0x24, "st"),
+        (0x25, "st"),
+        (0x26, "std"),
+        (0x27, "std"),
+        (0x30, "ld"),
+        (0x31, "ld"),
+        (0x33, "ldd"),
+        (0x34, "st"),
+        (0x35, "st"),
+        (0x36, "std"),
+        (0x37, "std"),
+        (0x3C, "casa"),
+    ];
+    let mut i = 0;
+    while i < names.len() {
+        table[names[i].0] = Some(names[i].1);
+        i += 1;
+    }
+    table
+};
+
+/// The name of integer register `%rN` in the windowed view.
+fn register(n: u32) -> String {
+    match n {
+        14 => "%sp".to_string(),
+        30 => "%fp".to_string(),
+        0..8 => format!("%g{n}"),
+        8..16 => format!("%o{}", n - 8),
+        16..24 => format!("%l{}", n - 16),
+        _ => format!("%i{}", n - 24),
+    }
+}
+
+/// Disassemble a SPARC V8 instruction word at `address` into GNU assembler syntax, with the
+/// common synthetic instructions like `nop`, `mov`, `cmp` and `ret`.
+///
+/// Words that are not valid instructions are shown as `.word`.
+pub(crate) fn disassemble(word: u32, address: u32) -> String {
+    let rd = (word >> 25) & 0x1F;
+    let op3 = ((word >> 19) & 0x3F) as usize;
+    let rs1 = (word >> 14) & 0x1F;
+    let immediate = (word >> 13) & 1 == 1;
+    let simm13 = sign_extend(word & 0x1FFF, 13);
+    let rs2 = word & 0x1F;
+
+    let operand = || {
+        if immediate {
+            simm13.to_string()
+        } else {
+            register(rs2)
+        }
+    };
+    let address_operand = || {
+        let offset = match (immediate, simm13, rs2) {
+            (true, 0, _) | (false, _, 0) => String::new(),
+            (true, simm13, _) if simm13 < 0 => format!(" - {}", -simm13),
+            (true, simm13, _) => format!(" + {simm13}"),
+            (false, _, rs2) => format!(" + {}", register(rs2)),
+        };
+        if rs1 == 0 && !offset.is_empty() {
+            // `[%g0 + x]` is an absolute address.
+            return format!("[{}]", offset.trim_start_matches(" + "));
+        }
+        format!("[{}{offset}]", register(rs1))
+    };
+    let target = |displacement: i32| format!("{:#010x}", address.wrapping_add_signed(displacement));
+    let invalid = || format!(".word {word:#010x}");
+
+    match word >> 30 {
+        0 => match (word >> 22) & 0b111 {
+            0b000 => format!("unimp {:#x}", word & 0x3F_FFFF),
+            0b100 if rd == 0 && word & 0x3F_FFFF == 0 => "nop".to_string(),
+            0b100 => format!(
+                "sethi %hi({:#010x}), {}",
+                (word & 0x3F_FFFF) << 10,
+                register(rd)
+            ),
+            _ => match Instruction::decode(word) {
+                Instruction::Branch {
+                    kind,
+                    cond,
+                    annul,
+                    displacement,
+                } => {
+                    let (prefix, conditions) = match kind {
+                        BranchKind::Integer => ("b", &INTEGER_CONDITIONS),
+                        BranchKind::Float => ("fb", &FLOAT_CONDITIONS),
+                        BranchKind::Coprocessor => ("cb", &COPROCESSOR_CONDITIONS),
+                    };
+                    let annul = if annul { ",a" } else { "" };
+                    format!(
+                        "{prefix}{}{annul} {}",
+                        conditions[cond as usize],
+                        target(displacement)
+                    )
+                }
+                _ => invalid(),
+            },
+        },
+        1 => format!("call {}", target((word << 2) as i32)),
+        2 => match op3 {
+            0x02 if rs1 == 0 => format!("mov {}, {}", operand(), register(rd)),
+            0x14 if rd == 0 => format!("cmp {}, {}", register(rs1), operand()),
+            0x3C | 0x3D if rd == 0 && rs1 == 0 && !immediate && rs2 == 0 => {
+                ARITHMETIC[op3].unwrap().to_string()
+            }
+            0x28 if rs1 == 15 && rd == 0 => "stbar".to_string(),
+            0x28 if rs1 == 0 => format!("rd %y, {}", register(rd)),
+            0x28 => format!("rd %asr{rs1}, {}", register(rd)),
+            0x29 => format!("rd %psr, {}", register(rd)),
+            0x2A => format!("rd %wim, {}", register(rd)),
+            0x2B => format!("rd %tbr, {}", register(rd)),
+            0x30..=0x33 => {
+                let special = match op3 {
+                    0x30 if rd == 0 => "%y".to_string(),
+                    0x30 => format!("%asr{rd}"),
+                    0x31 => "%psr".to_string(),
+                    0x32 => "%wim".to_string(),
+                    _ => "%tbr".to_string(),
+                };
+                format!("wr {}, {}, {special}", register(rs1), operand())
+            }
+            0x34 | 0x35 => float_operation(word, op3 == 0x35).unwrap_or_else(invalid),
+            0x36 | 0x37 => format!("cpop{} {:#x}", op3 - 0x35, (word >> 5) & 0x1FF),
+            0x38 => {
+                let destination = address_operand();
+                let destination = destination.trim_start_matches('[').trim_end_matches(']');
+                match (rd, rs1, immediate, simm13) {
+                    (0, 31, true, 8) => "ret".to_string(),
+                    (0, 15, true, 8) => "retl".to_string(),
+                    (0, ..) => format!("jmp {destination}"),
+                    (15, ..) => format!("call {destination}"),
+                    _ => format!("jmpl {destination}, {}", register(rd)),
+                }
+            }
+            0x39 => {
+                let destination = address_operand();
+                format!(
+                    "rett {}",
+                    destination.trim_start_matches('[').trim_end_matches(']')
+                )
+            }
+            0x3A => {
+                let number = if immediate {
+                    (word & 0x7F).to_string()
+                } else {
+                    register(rs2)
+                };
+                let trap = match rs1 {
+                    0 => number,
+                    rs1 => format!("{} + {number}", register(rs1)),
+                };
+                format!("t{} {trap}", INTEGER_CONDITIONS[(rd & 0xF) as usize])
+            }
+            0x3B => format!("flush {}", address_operand()),
+            op3 => match ARITHMETIC[op3] {
+                Some(name) => format!("{name} {}, {}, {}", register(rs1), operand(), register(rd)),
+                None => invalid(),
+            },
+        },
+        _ => {
+            let Some(name) = MEMORY[op3] else {
+                return invalid();
+            };
+            let alternate = (0x10..0x20).contains(&op3) || op3 == 0x3C;
+            if alternate && immediate {
+                return invalid();
+            }
+            let data = match op3 {
+                0x20 | 0x23 | 0x24 | 0x27 => format!("%f{rd}"),
+                0x21 | 0x25 => "%fsr".to_string(),
+                0x26 => "%fq".to_string(),
+                0x30 | 0x33 | 0x34 | 0x37 => format!("%c{rd}"),
+                0x31 | 0x35 => "%csr".to_string(),
+                0x36 => "%cq".to_string(),
+                _ => register(rd),
+            };
+            let asi = (word >> 5) & 0xFF;
+            if op3 == 0x3C {
+                return format!(
+                    "casa [{}] {asi:#x}, {}, {data}",
+                    register(rs1),
+                    register(rs2)
+                );
+            }
+            let mut address = address_operand();
+            if alternate {
+                address = format!("{address} {asi:#x}");
+            }
+            let is_store = matches!(op3 & 0xF, 0x4..=0x7);
+            if is_store {
+                format!("{name} {data}, {address}")
+            } else {
+                format!("{name} {address}, {data}")
+            }
+        }
+    }
+}
+
+/// Disassemble a floating-point operate instruction, FPop1 or FPop2 (the comparisons).
+fn float_operation(word: u32, compare: bool) -> Option<String> {
+    let rd = (word >> 25) & 0x1F;
+    let rs1 = (word >> 14) & 0x1F;
+    let rs2 = word & 0x1F;
+    let opf = (word >> 5) & 0x1FF;
+    if compare {
+        let name = match opf {
+            0x51 => "fcmps",
+            0x52 => "fcmpd",
+            0x53 => "fcmpq",
+            0x55 => "fcmpes",
+            0x56 => "fcmped",
+            0x57 => "fcmpeq",
+            _ => return None,
+        };
+        return Some(format!("{name} %f{rs1}, %f{rs2}"));
+    }
+    let unary = match opf {
+        0x01 => Some("fmovs"),
+        0x05 => Some("fnegs"),
+        0x09 => Some("fabss"),
+        0x29 => Some("fsqrts"),
+        0x2A => Some("fsqrtd"),
+        0x2B => Some("fsqrtq"),
+        0xC4 => Some("fitos"),
+        0xC6 => Some("fdtos"),
+        0xC7 => Some("fqtos"),
+        0xC8 => Some("fitod"),
+        0xC9 => Some("fstod"),
+        0xCB => Some("fqtod"),
+        0xCC => Some("fitoq"),
+        0xCD => Some("fstoq"),
+        0xCE => Some("fdtoq"),
+        0xD1 => Some("fstoi"),
+        0xD2 => Some("fdtoi"),
+        0xD3 => Some("fqtoi"),
+        _ => None,
+    };
+    if let Some(name) = unary {
+        return Some(format!("{name} %f{rs2}, %f{rd}"));
+    }
+    let name = match opf {
+        0x41 => "fadds",
+        0x42 => "faddd",
+        0x43 => "faddq",
+        0x45 => "fsubs",
+        0x46 => "fsubd",
+        0x47 => "fsubq",
+        0x49 => "fmuls",
+        0x4A => "fmuld",
+        0x4B => "fmulq",
+        0x4D => "fdivs",
+        0x4E => "fdivd",
+        0x4F => "fdivq",
+        0x69 => "fsmuld",
+        0x6E => "fdmulq",
+        _ => return None,
+    };
+    Some(format!("{name} %f{rs1}, %f{rs2}, %f{rd}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_memory_access() {
+        // ld [%o0 + 8], %o1
+        assert_eq!(
+            MemoryAccess::decode(0xD202_2008),
+            Some(MemoryAccess {
+                rs1: 8,
+                offset: AddressOffset::Immediate(8),
+                words: 1,
+                load: true,
+                store: false,
+            })
+        );
+        // std %g2, [%fp + -8]
+        assert_eq!(
+            MemoryAccess::decode(0xC43F_BFF8),
+            Some(MemoryAccess {
+                rs1: 30,
+                offset: AddressOffset::Immediate(-8),
+                words: 2,
+                load: false,
+                store: true,
+            })
+        );
+        // swap [%o0 + %o1], %o2
+        assert_eq!(
+            MemoryAccess::decode(0xD47A_0009),
+            Some(MemoryAccess {
+                rs1: 8,
+                offset: AddressOffset::Register(9),
+                words: 1,
+                load: true,
+                store: true,
+            })
+        );
+        // nop
+        assert_eq!(MemoryAccess::decode(0x0100_0000), None);
+    }
+
+    #[test]
+    fn decode_branch_always_annul() {
+        // ba,a . + 0x10
+        let instr = Instruction::decode(0x3080_0004);
+        assert_eq!(
+            instr,
+            Instruction::Branch {
+                kind: BranchKind::Integer,
+                cond: 8,
+                annul: true,
+                displacement: 0x10,
+            }
+        );
+    }
+
+    #[test]
+    fn decode_backwards_branch() {
+        // bne . - 8
+        let instr = Instruction::decode(0x12BF_FFFE);
+        assert_eq!(
+            instr,
+            Instruction::Branch {
+                kind: BranchKind::Integer,
+                cond: 9,
+                annul: false,
+                displacement: -8,
+            }
+        );
+    }
+
+    #[test]
+    fn integer_conditions() {
+        // Z set
+        let psr = Psr::from(1 << 22);
+        assert!(integer_condition_holds(1, psr)); // be
+        assert!(!integer_condition_holds(9, psr)); // bne
+        assert!(integer_condition_holds(8, psr)); // ba
+        assert!(!integer_condition_holds(0, psr)); // bn
+    }
+
+    #[test]
+    fn disassemble_instructions() {
+        let address = 0x4000_0100;
+        for (word, text) in [
+            (0x0100_0000, "nop"),
+            (TA_1, "ta 1"),
+            (0x9DE3_BFA0, "save %sp, -96, %sp"),
+            (0x81C7_E008, "ret"),
+            (0x81E8_0000, "restore"),
+            (0x4000_0004, "call 0x40000110"),
+            (0x12BF_FFFE, "bne 0x400000f8"),
+            (0x3080_0004, "ba,a 0x40000110"),
+            (0x0310_0000, "sethi %hi(0x40000000), %g1"),
+            (0xD007_BFFC, "ld [%fp - 4], %o0"),
+            (0xD027_BFFC, "st %o0, [%fp - 4]"),
+            (0x9010_2005, "mov 5, %o0"),
+            (0x80A2_0009, "cmp %o0, %o1"),
+            (0x8602_4009, "add %o1, %o1, %g3"),
+            (0x8148_0000, "rd %psr, %g0"),
+            (0x0000_0000, "unimp 0x0"),
+            (0xFFFF_FFFF, ".word 0xffffffff"),
+        ] {
+            assert_eq!(disassemble(word, address), text, "{word:#010x}");
+        }
+    }
+
+    #[test]
+    fn decode_software_trap() {
+        assert_eq!(
+            Instruction::decode(TA_1),
+            Instruction::Trap {
+                cond: 8,
+                number: Some(1),
+            }
+        );
+        // ta %g1 + 1
+        assert_eq!(
+            Instruction::decode(0x91D0_6001),
+            Instruction::Trap {
+                cond: 8,
+                number: None,
+            }
+        );
+    }
+
+    #[test]
+    fn successors_of_annulling_branches() {
+        let (pc, npc) = (0x4000_0100, 0x4000_0104);
+        // ba,a . + 0x10
+        assert_eq!(
+            Instruction::decode(0x3080_0004).successors(pc, npc),
+            [0x4000_0110]
+        );
+        // bne,a . - 8
+        assert_eq!(
+            Instruction::decode(0x32BF_FFFE).successors(pc, npc),
+            [npc, npc + 4]
+        );
+        // bne . - 8
+        assert_eq!(Instruction::decode(0x12BF_FFFE).successors(pc, npc), [npc]);
+        // A branch in the delay slot of another branch continues relative to its own address.
+        assert_eq!(
+            Instruction::decode(0x3080_0004).successors(pc, 0x4000_2000),
+            [0x4000_0110]
+        );
+    }
+
+    #[test]
+    fn delayed_control_transfers() {
+        // call, ret, bne, rett %l2 + 4
+        for word in [0x4000_0004, 0x81C7_E008, 0x12BF_FFFE, 0x81CC_A004] {
+            assert!(Instruction::has_delay_slot(word), "{word:#010x}");
+        }
+        // nop, ta 1, save
+        for word in [0x0100_0000, TA_1, 0x9DE3_BFA0] {
+            assert!(!Instruction::has_delay_slot(word), "{word:#010x}");
+        }
+    }
+}
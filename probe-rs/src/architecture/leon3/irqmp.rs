@@ -0,0 +1,109 @@
+//! Access to the IRQMP multiprocessor interrupt controller.
+
+use crate::architecture::leon3::{
+    communication_interface::Leon3CommunicationInterface,
+    plugnplay::{Device, GaislerDevice, Record},
+};
+
+/// Offset of the force register of a single-processor interrupt controller.
+const FORCE: u64 = 0x08;
+
+/// Offset of the multiprocessor status register.
+const MP_STATUS: u64 = 0x10;
+
+/// Offset of the interrupt mask register of processor 0.
+const PROCESSOR_MASK: u64 = 0x40;
+
+/// Offset of the interrupt force register of processor 0.
+const PROCESSOR_FORCE: u64 = 0x80;
+
+/// The non-maskable interrupt level.
+const NMI_LEVEL: u32 = 15;
+
+/// The IRQMP interrupt controller, as seen by one processor.
+pub(crate) struct Irqmp {
+    base: u64,
+    core_index: usize,
+    /// Whether the controller has per-processor force registers.
+    multiprocessor: bool,
+}
+
+impl Irqmp {
+    /// Find the interrupt controller in the plug&play records.
+    pub(crate) fn find(
+        interface: &mut Leon3CommunicationInterface,
+    ) -> Result<Option<Self>, crate::Error> {
+        let Some(base) = interface
+            .plugnplay()
+            .find_device(Device::Gaisler(GaislerDevice::IRQMP))
+            .and_then(Record::base_address)
+        else {
+            return Ok(None);
+        };
+        let core_index = interface.core_index();
+
+        // The NCPU field holds the number of processors minus one.
+        let status = interface
+            .as_memory_interface_mut()
+            .read_word_32(base + MP_STATUS)?;
+        Ok(Some(Self {
+            base,
+            core_index,
+            multiprocessor: status >> 28 != 0,
+        }))
+    }
+
+    /// Start the processor if it is in power-down, as done by the boot code of processor 0 to
+    /// start the other processors of a multiprocessor system.
+    pub(crate) fn start_processor(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+    ) -> Result<(), crate::Error> {
+        // Writing a one to bit n of the status register starts processor n.
+        interface
+            .as_memory_interface_mut()
+            .write_word_32(self.base + MP_STATUS, 1 << self.core_index)
+    }
+
+    fn mask_address(&self) -> u64 {
+        self.base + PROCESSOR_MASK + 4 * self.core_index as u64
+    }
+
+    /// Force the non-maskable interrupt on the processor, unmasking it if necessary.
+    ///
+    /// Returns the original interrupt mask, to be restored with [`Irqmp::release_nmi`].
+    pub(crate) fn force_nmi(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+    ) -> Result<u32, crate::Error> {
+        let memory = interface.as_memory_interface_mut();
+        let mask = memory.read_word_32(self.mask_address())?;
+        memory.write_word_32(self.mask_address(), mask | (1 << NMI_LEVEL))?;
+        if self.multiprocessor {
+            let force = self.base + PROCESSOR_FORCE + 4 * self.core_index as u64;
+            memory.write_word_32(force, 1 << NMI_LEVEL)?;
+        } else {
+            let force = memory.read_word_32(self.base + FORCE)?;
+            memory.write_word_32(self.base + FORCE, force | (1 << NMI_LEVEL))?;
+        }
+        Ok(mask)
+    }
+
+    /// Withdraw the interrupt forced by [`Irqmp::force_nmi`] and restore the interrupt mask.
+    pub(crate) fn release_nmi(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        mask: u32,
+    ) -> Result<(), crate::Error> {
+        let memory = interface.as_memory_interface_mut();
+        if self.multiprocessor {
+            // The upper half of the processor force registers clears the forced interrupts.
+            let force = self.base + PROCESSOR_FORCE + 4 * self.core_index as u64;
+            memory.write_word_32(force, 1 << (NMI_LEVEL + 16))?;
+        } else {
+            let force = memory.read_word_32(self.base + FORCE)?;
+            memory.write_word_32(self.base + FORCE, force & !(1 << NMI_LEVEL))?;
+        }
+        memory.write_word_32(self.mask_address(), mask)
+    }
+}
@@ -0,0 +1,331 @@
+//! Control of the GRLIB L2 cache (L2C) of GR740-class designs.
+//!
+//! The L2 cache sits between the processor bus and the memory bus. Writes through the debug
+//! link are cached like those of the processors, but memory written behind the L2 cache,
+//! for example by an I/O master on the memory bus, is only seen after the lines are
+//! invalidated.
+
+use std::ops::Range;
+
+use crate::{
+    MemoryMappedRegister,
+    architecture::leon3::{
+        communication_interface::Leon3CommunicationInterface,
+        plugnplay::{AddressSpaceKind, Device, GaislerDevice},
+    },
+    memory_mapped_bitfield_register,
+};
+
+/// The smallest L2 cache line size. Flushing a range line by line with this step covers
+/// every line of the range, whatever the configured line size.
+const MIN_LINE_SIZE: u64 = 32;
+
+/// Ranges with more lines than this are flushed by flushing the whole cache.
+const MAX_LINE_FLUSHES: u64 = 1024;
+
+memory_mapped_bitfield_register! {
+    /// L2C Control Register (GRLIB IP Core User's Manual, L2C)
+    ///
+    /// Enables the cache and its EDAC.
+    pub struct L2cControl(u32);
+    0x00, "l2c_control",
+    impl From;
+    /// Cache enable (EN) - if set, the cache is enabled.
+    pub en, set_en: 31;
+    /// EDAC enable (EDAC) - if set, errors in the cache are detected and corrected.
+    pub edac, set_edac: 30;
+    /// Replacement policy (REPL).
+    pub u8, repl, set_repl: 29, 28;
+    /// Locked ways (LOCK) - the number of locked ways.
+    pub u8, lock, set_lock: 11, 8;
+    /// Write policy (WP) - if set, the cache is write-through, otherwise copy-back.
+    pub wp, set_wp: 1;
+}
+
+memory_mapped_bitfield_register! {
+    /// L2C Flush Memory Address Register (GRLIB IP Core User's Manual, L2C)
+    ///
+    /// Writing the register flushes the line holding the address, or the whole cache.
+    pub struct L2cFlushMemory(u32);
+    0x08, "l2c_flush_memory",
+    impl From;
+    /// The address of the line to flush, in bits 31:5.
+    pub u32, addr, set_addr: 31, 0;
+    /// Disable (DI) - if set, the cache is disabled once the flush is complete.
+    pub di, set_di: 3;
+    /// Flush mode (FMODE) - bit 2 selects all lines instead of a single one, bit 1 writes
+    /// the lines back and bit 0 invalidates them.
+    pub u8, fmode, set_fmode: 2, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// L2C Error Status/Control Register (GRLIB IP Core User's Manual, L2C)
+    ///
+    /// Latches the first EDAC error detected by the cache.
+    pub struct L2cErrorStatus(u32);
+    0x20, "l2c_error_status",
+    impl From;
+    /// The AHB master of the access that caused the error (AHB MASTER INDEX).
+    pub u8, master, _: 31, 28;
+    /// The kind of access that caused the error (TYPE) - cache read, cache write, memory
+    /// fetch or memory write.
+    pub u8, error_type, _: 25, 24;
+    /// The error is in a tag, not in data (TAG/DATA).
+    pub tag, _: 23;
+    /// The error was uncorrectable (COR/UNCOR).
+    pub uncorrectable, _: 22;
+    /// More than one error occurred since the status was reset (MULTI).
+    pub multi, _: 21;
+    /// An error is latched (VALID).
+    pub valid, _: 20;
+    /// The number of corrected errors, saturating (CORRECTABLE ERROR COUNTER).
+    pub u8, correctable_count, _: 18, 16;
+    /// Reset the error status (RST).
+    pub _, set_rst: 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// L2C Error Address Register (GRLIB IP Core User's Manual, L2C)
+    ///
+    /// Holds the address of the latched error.
+    pub struct L2cErrorAddress(u32);
+    0x24, "l2c_error_address",
+    impl From;
+    /// The address of the latched error.
+    pub u32, address, _: 31, 0;
+}
+
+/// The kind of access that caused an L2 cache error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum L2CacheAccess {
+    /// A read hitting the cache.
+    CacheRead,
+    /// A write hitting the cache.
+    CacheWrite,
+    /// A line fetch from memory.
+    MemoryFetch,
+    /// A line write-back to memory.
+    MemoryWrite,
+}
+
+impl L2CacheAccess {
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0b00 => L2CacheAccess::CacheRead,
+            0b01 => L2CacheAccess::CacheWrite,
+            0b10 => L2CacheAccess::MemoryFetch,
+            _ => L2CacheAccess::MemoryWrite,
+        }
+    }
+}
+
+/// An EDAC error latched by the L2 cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct L2CacheError {
+    /// The address of the access that caused the error.
+    pub address: u32,
+    /// The kind of access that caused the error.
+    pub access: L2CacheAccess,
+    /// The error is in a tag, not in data.
+    pub tag: bool,
+    /// The error could not be corrected.
+    pub uncorrectable: bool,
+    /// More errors occurred after this one.
+    pub multiple: bool,
+    /// The AHB master of the access that caused the error.
+    pub master: u8,
+    /// The number of corrected errors, saturating at 7.
+    pub correctable_count: u8,
+}
+
+impl L2CacheError {
+    fn from_registers(status: L2cErrorStatus, address: L2cErrorAddress) -> Self {
+        Self {
+            address: address.address(),
+            access: L2CacheAccess::from_bits(status.error_type()),
+            tag: status.tag(),
+            uncorrectable: status.uncorrectable(),
+            multiple: status.multi(),
+            master: status.master(),
+            correctable_count: status.correctable_count(),
+        }
+    }
+}
+
+/// How the lines of the L2 cache are flushed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlushMode {
+    /// Drop the lines, losing data that was not written back.
+    Invalidate = 0b001,
+    /// Write dirty lines back to memory and drop them.
+    WriteBackInvalidate = 0b011,
+}
+
+/// The L2 cache controller.
+pub(crate) struct L2Cache {
+    base: u64,
+}
+
+impl L2Cache {
+    /// The L2 cache controller with its registers at `base`.
+    pub(crate) fn new(base: u64) -> Self {
+        Self { base }
+    }
+
+    /// Find the L2 cache controller in the plug&play records. Its registers are in its AHB
+    /// I/O area.
+    pub(crate) fn find(interface: &mut Leon3CommunicationInterface) -> Option<Self> {
+        interface
+            .plugnplay()
+            .find_device(Device::Gaisler(GaislerDevice::L2CACHE))?
+            .address_spaces
+            .iter()
+            .find(|space| space.kind == AddressSpaceKind::AhbIO)
+            .map(|space| Self::new(space.addresses.start))
+    }
+
+    /// Read the control register.
+    pub(crate) fn read_control(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+    ) -> Result<L2cControl, crate::Error> {
+        let control = interface
+            .as_memory_interface_mut()
+            .read_word_32(self.base + L2cControl::ADDRESS_OFFSET)?;
+        Ok(L2cControl(control))
+    }
+
+    /// Enable or disable the cache.
+    ///
+    /// The cache is invalidated before it is enabled, as its contents are stale, and written
+    /// back before it is disabled.
+    pub(crate) fn set_enabled(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        enabled: bool,
+    ) -> Result<(), crate::Error> {
+        let mut control = self.read_control(interface)?;
+        if control.en() == enabled {
+            return Ok(());
+        }
+        if !enabled {
+            return self.flush_all(interface, FlushMode::WriteBackInvalidate, true);
+        }
+        self.flush_all(interface, FlushMode::Invalidate, false)?;
+        control.set_en(true);
+        interface
+            .as_memory_interface_mut()
+            .write_word_32(self.base + L2cControl::ADDRESS_OFFSET, control.into())
+    }
+
+    /// Write back and invalidate the lines holding `range`, or the whole cache if `range`
+    /// is `None` or spans many lines.
+    pub(crate) fn flush(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        range: Option<Range<u64>>,
+    ) -> Result<(), crate::Error> {
+        self.flush_lines(interface, range, FlushMode::WriteBackInvalidate)
+    }
+
+    /// Invalidate the lines holding `range` without writing them back, or the whole cache if
+    /// `range` is `None` or spans many lines.
+    pub(crate) fn invalidate(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        range: Option<Range<u64>>,
+    ) -> Result<(), crate::Error> {
+        self.flush_lines(interface, range, FlushMode::Invalidate)
+    }
+
+    fn flush_lines(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        range: Option<Range<u64>>,
+        mode: FlushMode,
+    ) -> Result<(), crate::Error> {
+        let Some(range) = range else {
+            return self.flush_all(interface, mode, false);
+        };
+        let start = range.start & !(MIN_LINE_SIZE - 1);
+        if range.end.saturating_sub(start).div_ceil(MIN_LINE_SIZE) > MAX_LINE_FLUSHES {
+            return self.flush_all(interface, mode, false);
+        }
+
+        let memory = interface.as_memory_interface_mut();
+        for line in (start..range.end).step_by(MIN_LINE_SIZE as usize) {
+            let mut flush = L2cFlushMemory(0);
+            flush.set_addr(line as u32);
+            flush.set_fmode(mode as u8);
+            memory.write_word_32(self.base + L2cFlushMemory::ADDRESS_OFFSET, flush.into())?;
+        }
+        memory.flush()
+    }
+
+    fn flush_all(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        mode: FlushMode,
+        disable: bool,
+    ) -> Result<(), crate::Error> {
+        let mut flush = L2cFlushMemory(0);
+        flush.set_fmode(mode as u8 | 0b100);
+        flush.set_di(disable);
+        interface
+            .as_memory_interface_mut()
+            .write_word_32(self.base + L2cFlushMemory::ADDRESS_OFFSET, flush.into())
+    }
+
+    /// Read the latched EDAC error, if there is one.
+    pub(crate) fn read_error(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+    ) -> Result<Option<L2CacheError>, crate::Error> {
+        let memory = interface.as_memory_interface_mut();
+        let status =
+            L2cErrorStatus(memory.read_word_32(self.base + L2cErrorStatus::ADDRESS_OFFSET)?);
+        if !status.valid() {
+            return Ok(None);
+        }
+        let address =
+            L2cErrorAddress(memory.read_word_32(self.base + L2cErrorAddress::ADDRESS_OFFSET)?);
+        Ok(Some(L2CacheError::from_registers(status, address)))
+    }
+
+    /// Reset the error status, so the next error is latched.
+    pub(crate) fn clear_error(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+    ) -> Result<(), crate::Error> {
+        let memory = interface.as_memory_interface_mut();
+        let address = self.base + L2cErrorStatus::ADDRESS_OFFSET;
+        let mut status = L2cErrorStatus(memory.read_word_32(address)?);
+        status.set_rst(true);
+        memory.write_word_32(address, status.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_error() {
+        // An uncorrectable data error on a memory fetch of AHB master 3, with two corrected
+        // errors before it.
+        let status = L2cErrorStatus(0x3000_0000 | 0b10 << 24 | 1 << 22 | 1 << 20 | 2 << 16);
+        let error = L2CacheError::from_registers(status, L2cErrorAddress(0x4000_1000));
+        assert_eq!(
+            error,
+            L2CacheError {
+                address: 0x4000_1000,
+                access: L2CacheAccess::MemoryFetch,
+                tag: false,
+                uncorrectable: true,
+                multiple: false,
+                master: 3,
+                correctable_count: 2,
+            }
+        );
+    }
+}
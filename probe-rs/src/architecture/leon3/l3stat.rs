@@ -0,0 +1,392 @@
+//! The L3STAT and L4STAT statistics units, which count processor and AHB events.
+//!
+//! Each counter of a statistics unit counts one event of one processor, or of one AHB
+//! master, while it is enabled. The counters are read over the debug link while the
+//! processors run, like the `l3stat` command of GRMON.
+
+use std::{fmt, str::FromStr};
+
+use crate::{
+    MemoryInterface, MemoryMappedRegister,
+    architecture::leon3::{
+        communication_interface::{Leon3CommunicationInterface, Leon3Error},
+        plugnplay::{Device, GaislerDevice, Record},
+    },
+    memory_mapped_bitfield_register,
+};
+
+/// Offset of the counter value registers.
+const COUNTER_VALUE: u64 = 0x000;
+
+memory_mapped_bitfield_register! {
+    /// Counter Control Register (GRLIB IP Core User's Manual, L4STAT)
+    ///
+    /// There is one for each counter, the first at this offset.
+    pub struct CounterControl(u32);
+    0x080, "counter_control",
+    impl From;
+    /// Number of processors (NCPU) - the number of processors that can be monitored, minus
+    /// one.
+    pub u8, ncpu, _: 31, 28;
+    /// Number of counters (NCNT) - the number of counters of the unit, minus one.
+    pub u8, ncnt, _: 27, 23;
+    /// Supervisor/user filter (SU) - 0b01 counts only in user mode, 0b10 only in supervisor
+    /// mode, and other values in both.
+    pub u8, su, set_su: 15, 14;
+    /// Clear on read (CL) - if set, the counter is cleared when it is read.
+    pub cl, set_cl: 13;
+    /// Enable (EN) - if set, the counter counts its event.
+    pub en, set_en: 12;
+    /// Event ID (EVENT ID) - the event counted, see [`StatisticsEvent`].
+    pub u8, event_id, set_event_id: 11, 4;
+    /// Processor or AHB master (CPU/AHBM) - the processor whose events are counted, or the
+    /// AHB master for the per-master AHB events.
+    pub u8, source, set_source: 3, 0;
+}
+
+/// The processor mode in which a counter counts its event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ModeFilter {
+    /// Count in user and supervisor mode.
+    #[default]
+    All,
+    /// Count in user mode only.
+    User,
+    /// Count in supervisor mode only.
+    Supervisor,
+}
+
+impl ModeFilter {
+    fn bits(self) -> u8 {
+        match self {
+            ModeFilter::All => 0b00,
+            ModeFilter::User => 0b01,
+            ModeFilter::Supervisor => 0b10,
+        }
+    }
+}
+
+/// Events a counter of a statistics unit can count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatisticsEvent {
+    /// Instruction cache misses.
+    InstructionCacheMiss,
+    /// Instruction MMU TLB misses.
+    InstructionTlbMiss,
+    /// Cycles the pipeline is held by the instruction cache.
+    InstructionCacheHold,
+    /// Cycles the pipeline is held by the instruction MMU.
+    InstructionTlbHold,
+    /// Data cache read misses.
+    DataCacheMiss,
+    /// Data MMU TLB misses.
+    DataTlbMiss,
+    /// Cycles the pipeline is held by the data cache.
+    DataCacheHold,
+    /// Cycles the pipeline is held by the data MMU.
+    DataTlbHold,
+    /// Cycles the pipeline is held by the write buffer.
+    WriteBufferHold,
+    /// Executed instructions.
+    Instructions,
+    /// Executed integer instructions.
+    IntegerInstructions,
+    /// Executed floating-point instructions.
+    FloatingPointInstructions,
+    /// Branch prediction misses.
+    BranchPredictionMiss,
+    /// Cycles outside of debug mode.
+    ExecutionTime,
+    /// Cycles the AHB bus is used by the selected AHB master.
+    AhbUtilization,
+    /// Cycles the AHB bus is used by any master.
+    AhbTotalUtilization,
+    /// Executed integer branches.
+    Branches,
+    /// Executed CALL instructions.
+    Calls,
+    /// Executed regular type 2 instructions.
+    Type2Instructions,
+    /// Executed loads and stores.
+    LoadsAndStores,
+    /// Executed loads.
+    Loads,
+    /// Executed stores.
+    Stores,
+    /// Any other event, by its event ID.
+    Other(u8),
+}
+
+/// The events with a name, their event IDs and the names the `l3stat` command of GRMON uses.
+const NAMED_EVENTS: &[(StatisticsEvent, u8, &str)] = &[
+    (StatisticsEvent::InstructionCacheMiss, 0x00, "icmiss"),
+    (StatisticsEvent::InstructionTlbMiss, 0x01, "itmiss"),
+    (StatisticsEvent::InstructionCacheHold, 0x02, "ichold"),
+    (StatisticsEvent::InstructionTlbHold, 0x03, "ithold"),
+    (StatisticsEvent::DataCacheMiss, 0x08, "dcmiss"),
+    (StatisticsEvent::DataTlbMiss, 0x09, "dtmiss"),
+    (StatisticsEvent::DataCacheHold, 0x0A, "dchold"),
+    (StatisticsEvent::DataTlbHold, 0x0B, "dthold"),
+    (StatisticsEvent::WriteBufferHold, 0x10, "wbhold"),
+    (StatisticsEvent::Instructions, 0x11, "icnt"),
+    (StatisticsEvent::IntegerInstructions, 0x12, "iicnt"),
+    (StatisticsEvent::FloatingPointInstructions, 0x13, "ficnt"),
+    (StatisticsEvent::BranchPredictionMiss, 0x14, "bpmiss"),
+    (StatisticsEvent::ExecutionTime, 0x15, "etime"),
+    (StatisticsEvent::AhbUtilization, 0x17, "ahbutil"),
+    (StatisticsEvent::AhbTotalUtilization, 0x18, "ahbtutil"),
+    (StatisticsEvent::Branches, 0x22, "branch"),
+    (StatisticsEvent::Calls, 0x28, "call"),
+    (StatisticsEvent::Type2Instructions, 0x30, "type2"),
+    (StatisticsEvent::LoadsAndStores, 0x38, "ldst"),
+    (StatisticsEvent::Loads, 0x39, "load"),
+    (StatisticsEvent::Stores, 0x3A, "store"),
+];
+
+impl StatisticsEvent {
+    /// The event ID the counter is configured with.
+    pub fn id(self) -> u8 {
+        match self {
+            StatisticsEvent::Other(id) => id,
+            event => NAMED_EVENTS
+                .iter()
+                .find(|(named, _, _)| *named == event)
+                .map(|(_, id, _)| *id)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Whether the event is counted for an AHB master instead of a processor.
+    pub fn is_per_ahb_master(self) -> bool {
+        self == StatisticsEvent::AhbUtilization
+    }
+}
+
+impl From<u8> for StatisticsEvent {
+    fn from(id: u8) -> Self {
+        NAMED_EVENTS
+            .iter()
+            .find(|(_, named_id, _)| *named_id == id)
+            .map(|(event, _, _)| *event)
+            .unwrap_or(StatisticsEvent::Other(id))
+    }
+}
+
+impl fmt::Display for StatisticsEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match NAMED_EVENTS.iter().find(|(event, _, _)| event == self) {
+            Some((_, _, name)) => f.write_str(name),
+            None => write!(f, "event {:#04x}", self.id()),
+        }
+    }
+}
+
+impl FromStr for StatisticsEvent {
+    type Err = String;
+
+    /// Parse the GRMON name of an event, or an event ID.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((event, _, _)) = NAMED_EVENTS.iter().find(|(_, _, name)| *name == s) {
+            return Ok(*event);
+        }
+        let id = match s.strip_prefix("0x") {
+            Some(hex) => u8::from_str_radix(hex, 16),
+            None => s.parse(),
+        };
+        id.map(Self::from).map_err(|_| {
+            let names: Vec<_> = NAMED_EVENTS.iter().map(|(_, _, name)| *name).collect();
+            format!(
+                "Unknown event {s:?}, expected an event ID or one of {}",
+                names.join(", ")
+            )
+        })
+    }
+}
+
+/// The configuration of a counter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CounterConfig {
+    /// The event to count.
+    pub event: StatisticsEvent,
+    /// The processor whose events are counted, or the AHB master for
+    /// [`StatisticsEvent::AhbUtilization`].
+    pub source: u8,
+    /// The processor mode in which the event is counted.
+    pub mode: ModeFilter,
+}
+
+/// An L3STAT or L4STAT statistics unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatisticsUnit {
+    base: u64,
+    counters: usize,
+    processors: usize,
+}
+
+impl StatisticsUnit {
+    /// All statistics units of the system, in plug&play order.
+    pub(crate) fn find_all(
+        interface: &mut Leon3CommunicationInterface,
+    ) -> Result<Vec<Self>, crate::Error> {
+        let plugnplay = interface.plugnplay();
+        let bases = [GaislerDevice::L3STAT, GaislerDevice::L4STAT]
+            .into_iter()
+            .flat_map(|device| plugnplay.find_devices(Device::Gaisler(device)))
+            .filter_map(Record::base_address)
+            .collect::<Vec<_>>();
+
+        let memory = interface.as_memory_interface_mut();
+        bases
+            .into_iter()
+            .map(|base| {
+                let control =
+                    CounterControl(memory.read_word_32(base + CounterControl::ADDRESS_OFFSET)?);
+                Ok(Self {
+                    base,
+                    counters: usize::from(control.ncnt()) + 1,
+                    processors: usize::from(control.ncpu()) + 1,
+                })
+            })
+            .collect()
+    }
+
+    /// The base address of the unit registers.
+    pub fn base_address(&self) -> u64 {
+        self.base
+    }
+
+    /// The number of counters of the unit.
+    pub fn counters(&self) -> usize {
+        self.counters
+    }
+
+    /// The number of processors whose events the unit can count.
+    pub fn processors(&self) -> usize {
+        self.processors
+    }
+
+    fn control_address(&self, counter: usize) -> Result<u64, crate::Error> {
+        self.check_counter(counter)?;
+        Ok(self.base + CounterControl::ADDRESS_OFFSET + 4 * counter as u64)
+    }
+
+    fn value_address(&self, counter: usize) -> Result<u64, crate::Error> {
+        self.check_counter(counter)?;
+        Ok(self.base + COUNTER_VALUE + 4 * counter as u64)
+    }
+
+    fn check_counter(&self, counter: usize) -> Result<(), crate::Error> {
+        if counter < self.counters {
+            Ok(())
+        } else {
+            Err(Leon3Error::NoSuchCounter {
+                counter,
+                counters: self.counters,
+            }
+            .into())
+        }
+    }
+
+    /// Clear a counter and let it count the configured event.
+    pub fn configure(
+        &self,
+        memory: &mut (impl MemoryInterface + ?Sized),
+        counter: usize,
+        config: CounterConfig,
+    ) -> Result<(), crate::Error> {
+        let mut control = CounterControl(0);
+        control.set_event_id(config.event.id());
+        control.set_source(config.source);
+        control.set_su(config.mode.bits());
+        memory.write_word_32(self.control_address(counter)?, control.into())?;
+        memory.write_word_32(self.value_address(counter)?, 0)?;
+        control.set_en(true);
+        memory.write_word_32(self.control_address(counter)?, control.into())
+    }
+
+    /// The configuration of a counter, or `None` if it is disabled.
+    pub fn configuration(
+        &self,
+        memory: &mut (impl MemoryInterface + ?Sized),
+        counter: usize,
+    ) -> Result<Option<CounterConfig>, crate::Error> {
+        let control = CounterControl(memory.read_word_32(self.control_address(counter)?)?);
+        if !control.en() {
+            return Ok(None);
+        }
+        let mode = match control.su() {
+            0b01 => ModeFilter::User,
+            0b10 => ModeFilter::Supervisor,
+            _ => ModeFilter::All,
+        };
+        Ok(Some(CounterConfig {
+            event: StatisticsEvent::from(control.event_id()),
+            source: control.source(),
+            mode,
+        }))
+    }
+
+    /// Stop a counter. Its value is kept.
+    pub fn disable(
+        &self,
+        memory: &mut (impl MemoryInterface + ?Sized),
+        counter: usize,
+    ) -> Result<(), crate::Error> {
+        let address = self.control_address(counter)?;
+        let mut control = CounterControl(memory.read_word_32(address)?);
+        control.set_en(false);
+        memory.write_word_32(address, control.into())
+    }
+
+    /// Read the value of a counter.
+    pub fn read(
+        &self,
+        memory: &mut (impl MemoryInterface + ?Sized),
+        counter: usize,
+    ) -> Result<u32, crate::Error> {
+        memory.read_word_32(self.value_address(counter)?)
+    }
+
+    /// Read the values of all counters at once, as close together in time as the debug link
+    /// allows.
+    pub fn sample(
+        &self,
+        memory: &mut (impl MemoryInterface + ?Sized),
+    ) -> Result<Vec<u32>, crate::Error> {
+        let mut values = vec![0; self.counters];
+        memory.read_32(self.base + COUNTER_VALUE, &mut values)?;
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events() {
+        assert_eq!("dcmiss".parse(), Ok(StatisticsEvent::DataCacheMiss));
+        assert_eq!("0x17".parse(), Ok(StatisticsEvent::AhbUtilization));
+        assert_eq!("0x60".parse(), Ok(StatisticsEvent::Other(0x60)));
+        assert!("nothing".parse::<StatisticsEvent>().is_err());
+
+        assert_eq!(StatisticsEvent::Instructions.id(), 0x11);
+        assert_eq!(StatisticsEvent::Other(0x60).id(), 0x60);
+        assert_eq!(StatisticsEvent::Loads.to_string(), "load");
+        assert_eq!(StatisticsEvent::Other(0x60).to_string(), "event 0x60");
+    }
+
+    #[test]
+    fn counter_control() {
+        // Counter counting icnt of processor 1 in supervisor mode, on a unit with 4
+        // processors and 8 counters.
+        let mut control = CounterControl(0x3 << 28 | 0x7 << 23);
+        control.set_event_id(StatisticsEvent::Instructions.id());
+        control.set_source(1);
+        control.set_su(ModeFilter::Supervisor.bits());
+        control.set_en(true);
+        assert_eq!(u32::from(control), 0x3380_9111);
+        assert_eq!(control.ncpu(), 3);
+        assert_eq!(control.ncnt(), 7);
+    }
+}
@@ -0,0 +1,438 @@
+//! Configuration of the MCTRL and FTMCTRL PROM/SRAM/SDRAM memory controllers.
+
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use probe_rs_target::{Leon3RegisterWrite, Leon3SdramConfig};
+
+use crate::{
+    MemoryMappedRegister,
+    architecture::leon3::{
+        communication_interface::{Leon3CommunicationInterface, Leon3Error},
+        plugnplay::{AddressSpaceKind, Device, EsaDevice, GaislerDevice},
+    },
+    memory_mapped_bitfield_register,
+};
+
+/// How long to wait for an SDRAM command to complete.
+const SDRAM_COMMAND_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The memory controllers that share the MCFG register layout.
+const MEMORY_CONTROLLERS: [Device; 2] = [
+    Device::Esa(EsaDevice::MCTRL),
+    Device::Gaisler(GaislerDevice::FTMCTRL),
+];
+
+memory_mapped_bitfield_register! {
+    /// Memory Configuration Register 1 (GRLIB IP Core User's Manual, MCTRL/FTMCTRL)
+    ///
+    /// Configures the PROM and I/O areas.
+    pub struct Mcfg1(u32);
+    0x00, "mcfg1",
+    impl From;
+    /// PROM write enable (PWEN) - if set, writes to the PROM area are performed.
+    pub pwen, set_pwen: 11;
+    /// PROM width (PROM WIDTH) - the data width of the PROM, 8 bits shifted left by the
+    /// field value.
+    pub u8, prom_width, set_prom_width: 9, 8;
+    /// PROM write wait states (PROM WRITE WS).
+    pub u8, prom_write_ws, set_prom_write_ws: 7, 4;
+    /// PROM read wait states (PROM READ WS).
+    pub u8, prom_read_ws, set_prom_read_ws: 3, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// Memory Configuration Register 2 (GRLIB IP Core User's Manual, MCTRL/FTMCTRL)
+    ///
+    /// Configures the SRAM and SDRAM banks.
+    pub struct Mcfg2(u32);
+    0x04, "mcfg2",
+    impl From;
+    /// SDRAM refresh (SDRF) - if set, the SDRAM is refreshed periodically.
+    pub sdrf, set_sdrf: 31;
+    /// SDRAM CAS delay (TCAS) - if set, the CAS latency is 3 clock cycles, otherwise 2.
+    pub tcas, set_tcas: 26;
+    /// SDRAM bank size (BANKSZ) - the size of each SDRAM bank, 4 MiB shifted left by the
+    /// field value.
+    pub u8, sdram_banksz, set_sdram_banksz: 25, 23;
+    /// SDRAM column size (COLSZ) - 256 columns shifted left by the field value, with the
+    /// largest value meaning 4096 columns for 512 MiB banks.
+    pub u8, sdram_colsz, set_sdram_colsz: 22, 21;
+    /// SDRAM command (SDCMD) - writing a non-zero value issues a command to the SDRAM. Reads
+    /// as zero when the command is complete.
+    pub u8, sdcmd, set_sdcmd: 20, 19;
+    /// SDRAM enable (SE) - if set, the SDRAM controller is enabled.
+    pub se, set_se: 14;
+    /// SRAM disable (SI) - if set, the SRAM banks are disabled.
+    pub si, set_si: 13;
+}
+
+memory_mapped_bitfield_register! {
+    /// Memory Configuration Register 3 (GRLIB IP Core User's Manual, MCTRL/FTMCTRL)
+    ///
+    /// Holds the SDRAM refresh period and, on the FTMCTRL, controls the EDAC.
+    pub struct Mcfg3(u32);
+    0x08, "mcfg3",
+    impl From;
+    /// SDRAM refresh reload value (RLDVAL) - the number of clock cycles between refreshes,
+    /// minus one.
+    pub u16, rldval, set_rldval: 26, 12;
+    /// EDAC diagnostic write bypass (WB) - if set, the check bits in TCB are written to
+    /// memory instead of the computed ones. FTMCTRL only.
+    pub wb, set_wb: 11;
+    /// EDAC diagnostic read bypass (RB) - if set, the check bits of each read are stored
+    /// in TCB. FTMCTRL only.
+    pub rb, set_rb: 10;
+    /// RAM EDAC enable (RE) - if set, errors in RAM are detected and corrected. FTMCTRL only.
+    pub re, set_re: 9;
+    /// PROM EDAC enable (PE) - if set, errors in PROM are detected and corrected. FTMCTRL
+    /// only.
+    pub pe, set_pe: 8;
+    /// Test check bits (TCB) - the check bits used by the diagnostic bypasses. FTMCTRL only.
+    pub u8, tcb, set_tcb: 7, 0;
+}
+
+/// An SDRAM command, issued through the SDCMD field of MCFG2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SdramCommand {
+    Precharge = 0b01,
+    AutoRefresh = 0b10,
+    LoadModeRegister = 0b11,
+}
+
+/// The JEDEC power-up sequence of an SDRAM, which also loads the CAS latency into the mode
+/// register.
+const SDRAM_POWER_UP: [SdramCommand; 4] = [
+    SdramCommand::Precharge,
+    SdramCommand::AutoRefresh,
+    SdramCommand::AutoRefresh,
+    SdramCommand::LoadModeRegister,
+];
+
+/// How a boot loader enables the SDRAM: the register writes to make, followed by the
+/// commands to issue through MCFG2.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SdramBootSetup {
+    /// The masked writes to MCFG3 and MCFG2 that configure and enable the SDRAM.
+    pub(crate) writes: [Leon3RegisterWrite; 2],
+    /// The address of MCFG2.
+    pub(crate) mcfg2: u64,
+    /// The SDCMD field of MCFG2.
+    pub(crate) sdcmd_mask: u32,
+    /// The values of the SDCMD field that issue the power-up commands, in order.
+    pub(crate) commands: Vec<u32>,
+}
+
+/// The fields of MCFG2 and MCFG3 set from an SDRAM configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SdramFields {
+    tcas: bool,
+    colsz: u8,
+    banksz: Option<u8>,
+    rldval: u16,
+}
+
+impl SdramFields {
+    fn new(config: &Leon3SdramConfig) -> Result<Self, Leon3Error> {
+        let tcas = match config.cas_latency {
+            2 => false,
+            3 => true,
+            _ => {
+                return Err(Leon3Error::InvalidSdramConfiguration(
+                    "the CAS latency must be 2 or 3",
+                ));
+            }
+        };
+        let banksz = config
+            .bank_size
+            .map(|size| match size {
+                4 | 8 | 16 | 32 | 64 | 128 | 256 | 512 => Ok((size / 4_u32).trailing_zeros() as u8),
+                _ => Err(Leon3Error::InvalidSdramConfiguration(
+                    "the bank size must be a power of two from 4 to 512 MiB",
+                )),
+            })
+            .transpose()?;
+        let colsz = match config.column_size {
+            256 => 0,
+            512 => 1,
+            1024 => 2,
+            2048 => 3,
+            4096 if config.bank_size == Some(512) => 3,
+            _ => {
+                return Err(Leon3Error::InvalidSdramConfiguration(
+                    "the column size must be 256, 512, 1024, 2048, or 4096 with 512 MiB banks",
+                ));
+            }
+        };
+        let rldval = u16::try_from(config.refresh_reload)
+            .ok()
+            .filter(|rldval| *rldval < 1 << 15)
+            .ok_or(Leon3Error::InvalidSdramConfiguration(
+                "the refresh reload value must fit in 15 bits",
+            ))?;
+        Ok(Self {
+            tcas,
+            colsz,
+            banksz,
+            rldval,
+        })
+    }
+}
+
+/// A memory controller with the MCTRL register layout.
+pub(crate) struct MemoryController {
+    base: u64,
+    /// The address window of the PROM area.
+    prom: Option<Range<u64>>,
+    /// The controller has an EDAC.
+    fault_tolerant: bool,
+}
+
+impl MemoryController {
+    /// Find the first MCTRL or FTMCTRL in the plug&play records.
+    pub(crate) fn find(interface: &mut Leon3CommunicationInterface) -> Option<Self> {
+        let plugnplay = interface.plugnplay();
+        MEMORY_CONTROLLERS.into_iter().find_map(|device| {
+            let base = plugnplay.find_apb_registers(device)?;
+            // The PROM area is the first bank of the AHB record.
+            let prom = plugnplay
+                .find_devices(device)
+                .flat_map(|record| &record.address_spaces)
+                .find(|space| space.bar == 0 && space.kind == AddressSpaceKind::AhbMemory)
+                .map(|space| space.addresses.clone());
+            Some(Self {
+                base,
+                prom,
+                fault_tolerant: device == Device::Gaisler(GaislerDevice::FTMCTRL),
+            })
+        })
+    }
+
+    /// The address window of the PROM area, if the controller has one.
+    pub(crate) fn prom(&self) -> Option<Range<u64>> {
+        self.prom.clone()
+    }
+
+    /// Read the PROM configuration.
+    pub(crate) fn read_mcfg1(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+    ) -> Result<Mcfg1, crate::Error> {
+        let mcfg1 = interface
+            .as_memory_interface_mut()
+            .read_word_32(self.base + Mcfg1::ADDRESS_OFFSET)?;
+        Ok(Mcfg1(mcfg1))
+    }
+
+    /// Write the PROM configuration.
+    pub(crate) fn write_mcfg1(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        mcfg1: Mcfg1,
+    ) -> Result<(), crate::Error> {
+        interface
+            .as_memory_interface_mut()
+            .write_word_32(self.base + Mcfg1::ADDRESS_OFFSET, mcfg1.into())
+    }
+
+    /// Returns whether the EDAC of the PROM area is enabled, in which case the check bits
+    /// are stored in memory next to the data.
+    pub(crate) fn prom_edac_enabled(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+    ) -> Result<bool, crate::Error> {
+        if !self.fault_tolerant {
+            return Ok(false);
+        }
+        let mcfg3 = interface
+            .as_memory_interface_mut()
+            .read_word_32(self.base + Mcfg3::ADDRESS_OFFSET)?;
+        Ok(Mcfg3(mcfg3).pe())
+    }
+
+    /// Enable the SDRAM with the given configuration and initialize it.
+    pub(crate) fn configure_sdram(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        config: &Leon3SdramConfig,
+    ) -> Result<(), crate::Error> {
+        let fields = SdramFields::new(config)?;
+        let memory = interface.as_memory_interface_mut();
+
+        let mcfg3_address = self.base + Mcfg3::ADDRESS_OFFSET;
+        let mut mcfg3 = Mcfg3(memory.read_word_32(mcfg3_address)?);
+        mcfg3.set_rldval(fields.rldval);
+        memory.write_word_32(mcfg3_address, mcfg3.into())?;
+
+        let mut mcfg2 = Mcfg2(memory.read_word_32(self.base + Mcfg2::ADDRESS_OFFSET)?);
+        mcfg2.set_sdrf(true);
+        mcfg2.set_se(true);
+        mcfg2.set_tcas(fields.tcas);
+        mcfg2.set_sdram_colsz(fields.colsz);
+        if let Some(banksz) = fields.banksz {
+            mcfg2.set_sdram_banksz(banksz);
+        }
+        mcfg2.set_sdcmd(0);
+        memory.write_word_32(self.base + Mcfg2::ADDRESS_OFFSET, mcfg2.into())?;
+
+        for command in SDRAM_POWER_UP {
+            self.sdram_command(interface, mcfg2, command)?;
+        }
+        Ok(())
+    }
+
+    /// The register writes and commands with which a boot loader enables the SDRAM like
+    /// [`MemoryController::configure_sdram`] does.
+    pub(crate) fn sdram_boot_setup(
+        &self,
+        config: &Leon3SdramConfig,
+    ) -> Result<SdramBootSetup, Leon3Error> {
+        let fields = SdramFields::new(config)?;
+
+        let mut mcfg3_mask = Mcfg3(0);
+        mcfg3_mask.set_rldval(0x7FFF);
+        let mut mcfg3 = Mcfg3(0);
+        mcfg3.set_rldval(fields.rldval);
+
+        let mut mcfg2_mask = Mcfg2(0);
+        mcfg2_mask.set_sdrf(true);
+        mcfg2_mask.set_se(true);
+        mcfg2_mask.set_tcas(true);
+        mcfg2_mask.set_sdram_colsz(0b11);
+        mcfg2_mask.set_sdcmd(0b11);
+        let mut mcfg2 = Mcfg2(0);
+        mcfg2.set_sdrf(true);
+        mcfg2.set_se(true);
+        mcfg2.set_tcas(fields.tcas);
+        mcfg2.set_sdram_colsz(fields.colsz);
+        if let Some(banksz) = fields.banksz {
+            mcfg2_mask.set_sdram_banksz(0b111);
+            mcfg2.set_sdram_banksz(banksz);
+        }
+
+        let mut sdcmd_mask = Mcfg2(0);
+        sdcmd_mask.set_sdcmd(0b11);
+        let command = |command: SdramCommand| {
+            let mut mcfg2 = Mcfg2(0);
+            mcfg2.set_sdcmd(command as u8);
+            u32::from(mcfg2)
+        };
+
+        Ok(SdramBootSetup {
+            writes: [
+                Leon3RegisterWrite {
+                    address: self.base + Mcfg3::ADDRESS_OFFSET,
+                    value: mcfg3.into(),
+                    mask: Some(mcfg3_mask.into()),
+                },
+                Leon3RegisterWrite {
+                    address: self.base + Mcfg2::ADDRESS_OFFSET,
+                    value: mcfg2.into(),
+                    mask: Some(mcfg2_mask.into()),
+                },
+            ],
+            mcfg2: self.base + Mcfg2::ADDRESS_OFFSET,
+            sdcmd_mask: sdcmd_mask.into(),
+            commands: SDRAM_POWER_UP.into_iter().map(command).collect(),
+        })
+    }
+
+    /// Issue an SDRAM command and wait for it to complete.
+    fn sdram_command(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        mut mcfg2: Mcfg2,
+        command: SdramCommand,
+    ) -> Result<(), crate::Error> {
+        let memory = interface.as_memory_interface_mut();
+        let address = self.base + Mcfg2::ADDRESS_OFFSET;
+        mcfg2.set_sdcmd(command as u8);
+        memory.write_word_32(address, mcfg2.into())?;
+
+        let start = Instant::now();
+        while Mcfg2(memory.read_word_32(address)?).sdcmd() != 0 {
+            if start.elapsed() > SDRAM_COMMAND_TIMEOUT {
+                return Err(Leon3Error::Timeout.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(cas_latency: u8, column_size: u32, bank_size: Option<u32>) -> Leon3SdramConfig {
+        Leon3SdramConfig {
+            cas_latency,
+            column_size,
+            refresh_reload: 780,
+            bank_size,
+        }
+    }
+
+    #[test]
+    fn sdram_fields() {
+        assert_eq!(
+            SdramFields::new(&config(3, 1024, Some(64))).unwrap(),
+            SdramFields {
+                tcas: true,
+                colsz: 2,
+                banksz: Some(4),
+                rldval: 780,
+            }
+        );
+        assert_eq!(
+            SdramFields::new(&config(2, 4096, Some(512))).unwrap(),
+            SdramFields {
+                tcas: false,
+                colsz: 3,
+                banksz: Some(7),
+                rldval: 780,
+            }
+        );
+    }
+
+    #[test]
+    fn sdram_boot_setup() {
+        let controller = MemoryController {
+            base: 0x8000_0000,
+            prom: None,
+            fault_tolerant: false,
+        };
+        let setup = controller
+            .sdram_boot_setup(&config(3, 1024, Some(64)))
+            .unwrap();
+        assert_eq!(
+            setup.writes,
+            [
+                Leon3RegisterWrite {
+                    address: 0x8000_0008,
+                    value: 0x0030_C000,
+                    mask: Some(0x07FF_F000),
+                },
+                Leon3RegisterWrite {
+                    address: 0x8000_0004,
+                    value: 0x8640_4000,
+                    mask: Some(0x87F8_4000),
+                },
+            ]
+        );
+        assert_eq!(setup.mcfg2, 0x8000_0004);
+        assert_eq!(setup.sdcmd_mask, 0x0018_0000);
+        assert_eq!(
+            setup.commands,
+            [0x0008_0000, 0x0010_0000, 0x0010_0000, 0x0018_0000]
+        );
+    }
+
+    #[test]
+    fn invalid_sdram_fields() {
+        assert!(SdramFields::new(&config(4, 1024, None)).is_err());
+        assert!(SdramFields::new(&config(2, 4096, Some(256))).is_err());
+        assert!(SdramFields::new(&config(2, 1024, Some(48))).is_err());
+    }
+}
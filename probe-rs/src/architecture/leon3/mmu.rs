@@ -0,0 +1,353 @@
+//! LEON3 SPARC reference MMU (SRMMU).
+
+use crate::{
+    architecture::leon3::communication_interface::Leon3Error, memory_mapped_bitfield_register,
+};
+
+/// The ASI through which the MMU registers are accessed.
+pub const ASI_MMU_REGISTERS: u8 = 0x19;
+
+memory_mapped_bitfield_register! {
+    /// MMU Control Register (GRLIB IP Core User's Manual 86.10.2)
+    ///
+    /// Controls address translation. The register is located at address 0x000 in ASI 0x19 and
+    /// is only implemented if the core has an MMU.
+    pub struct MmuCtrl(u32);
+    0x000, "mmu_ctrl",
+    impl From;
+    /// Implementation (IMPL) - the MMU implementation ID.
+    pub u8, implementation, _: 31, 28;
+    /// Version (VER) - the MMU implementation version.
+    pub u8, version, _: 27, 24;
+    /// Number of instruction TLB entries (ITLB), as log2 of the number of entries.
+    pub u8, itlb, _: 23, 21;
+    /// Number of data TLB entries (DTLB), as log2 of the number of entries.
+    pub u8, dtlb, _: 20, 18;
+    /// Page size (PSZ) - 0 = 4 KiB, 1 = 8 KiB, 2 = 16 KiB, 3 = 32 KiB.
+    pub u8, psz, _: 17, 16;
+    /// TLB disable (TD) - if set, the TLB is disabled and every access is a table walk.
+    pub td, _: 15;
+    /// Separate TLB (ST) - set if separate instruction and data TLBs are implemented.
+    pub st, _: 14;
+    /// No fault (NF) - if set, MMU faults are not reported to the processor.
+    pub nf, _: 1;
+    /// Enable (E) - if set, virtual addresses are translated by the MMU.
+    pub e, _: 0;
+}
+
+impl MmuCtrl {
+    /// The page size of the MMU in bytes.
+    pub fn page_size(&self) -> u32 {
+        4096 << self.psz()
+    }
+}
+
+memory_mapped_bitfield_register! {
+    /// MMU Context Pointer Register (SPARC V8 Appendix H.3.2)
+    ///
+    /// Points to the context table in physical memory.
+    pub struct MmuCtxPtr(u32);
+    0x100, "mmu_ctx_ptr",
+    impl From;
+    /// Context Table Pointer (CTP) - bits 35:6 of the physical address of the context table.
+    pub u32, ctp, _: 31, 2;
+}
+
+memory_mapped_bitfield_register! {
+    /// MMU Context Register (SPARC V8 Appendix H.3.3)
+    ///
+    /// Selects the entry of the context table used for translation.
+    pub struct MmuCtx(u32);
+    0x200, "mmu_ctx",
+    impl From;
+    /// Context number (CONTEXT) - the index into the context table.
+    pub u32, context, _: 7, 0;
+}
+
+/// Number of bits of the virtual address indexing the level 1, 2 and 3 page tables.
+const INDEX_BITS: [u32; 3] = [8, 6, 6];
+
+/// Entry types of page table entries (ET field). Other types are invalid.
+const ET_PTD: u32 = 1;
+const ET_PTE: u32 = 2;
+
+/// The page tables used to translate virtual addresses, read from the MMU registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MmuContext {
+    /// Physical address of the context table.
+    pub table: u64,
+    /// The current context number.
+    pub context: u32,
+}
+
+/// Where a virtual address is mapped to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Translation {
+    /// The physical address the virtual address maps to.
+    pub physical_address: u32,
+    /// Size in bytes of the naturally aligned region mapped by the same page table entry.
+    pub region_size: u64,
+}
+
+/// A region of virtual memory mapped by a single page table entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageMapping {
+    /// The first virtual address of the region.
+    pub virtual_address: u32,
+    /// The physical address the region is mapped to, 36 bits wide.
+    pub physical_address: u64,
+    /// The size of the region in bytes: 4 GiB, 16 MiB, 256 KiB or 4 KiB.
+    pub size: u64,
+    /// Access permissions (ACC) of the page table entry, see the SPARC V8 manual, table H-1.
+    pub access: u8,
+    /// Whether accesses to the region are cacheable (C).
+    pub cacheable: bool,
+    /// Whether the region was written (M).
+    pub modified: bool,
+    /// Whether the region was accessed (R).
+    pub referenced: bool,
+}
+
+impl PageMapping {
+    fn new(virtual_address: u32, entry: u32, region_bits: u32) -> Self {
+        let size = 1u64 << region_bits;
+        Self {
+            virtual_address,
+            physical_address: (u64::from(entry >> 8) << 12) & !(size - 1),
+            size,
+            access: ((entry >> 2) & 0b111) as u8,
+            cacheable: entry & (1 << 7) != 0,
+            modified: entry & (1 << 6) != 0,
+            referenced: entry & (1 << 5) != 0,
+        }
+    }
+}
+
+impl MmuContext {
+    /// Reads the context from the MMU registers.
+    pub fn new(ctx_ptr: MmuCtxPtr, ctx: MmuCtx) -> Self {
+        Self {
+            table: u64::from(ctx_ptr.ctp()) << 6,
+            context: ctx.context(),
+        }
+    }
+
+    /// Translates `virtual_address` by walking the page tables, reading each table entry
+    /// from physical memory with `read_physical`.
+    pub fn translate(
+        &self,
+        virtual_address: u32,
+        mut read_physical: impl FnMut(u32) -> Result<u32, crate::Error>,
+    ) -> Result<Translation, crate::Error> {
+        let mut entry_address = self.table + u64::from(self.context) * 4;
+        // Region mapped by a PTE in the context table, then in the level 1, 2 and 3 tables.
+        let mut region_bits = 32;
+        let mut levels = INDEX_BITS.iter();
+        loop {
+            let entry = read_physical(physical_address(entry_address)?)?;
+            match entry & 0b11 {
+                ET_PTD => {
+                    let Some(index_bits) = levels.next() else {
+                        // A level 3 table cannot point to another table.
+                        return Err(Leon3Error::UnmappedAddress(virtual_address).into());
+                    };
+                    region_bits -= index_bits;
+                    let index = u64::from(virtual_address >> region_bits) & ((1 << index_bits) - 1);
+                    entry_address = (u64::from(entry & !0b11) << 4) + index * 4;
+                }
+                ET_PTE => {
+                    let region_size = 1u64 << region_bits;
+                    let offset = u64::from(virtual_address) & (region_size - 1);
+                    let page = (u64::from(entry >> 8) << 12) & !(region_size - 1);
+                    return Ok(Translation {
+                        physical_address: physical_address(page + offset)?,
+                        region_size,
+                    });
+                }
+                _ => {
+                    return Err(Leon3Error::UnmappedAddress(virtual_address).into());
+                }
+            }
+        }
+    }
+
+    /// Lists all mappings of the context by walking its page tables, reading whole tables
+    /// from physical memory with `read_physical`.
+    ///
+    /// Invalid entries are skipped, so the mappings are ordered by virtual address.
+    pub fn mappings(
+        &self,
+        mut read_physical: impl FnMut(u32, &mut [u32]) -> Result<(), crate::Error>,
+    ) -> Result<Vec<PageMapping>, crate::Error> {
+        let mut entry = [0];
+        read_physical(
+            physical_address(self.table + u64::from(self.context) * 4)?,
+            &mut entry,
+        )?;
+
+        let mut mappings = Vec::new();
+        match entry[0] & 0b11 {
+            ET_PTD => walk_table(entry[0], 0, 0, &mut read_physical, &mut mappings)?,
+            ET_PTE => mappings.push(PageMapping::new(0, entry[0], 32)),
+            _ => {}
+        }
+        Ok(mappings)
+    }
+}
+
+/// Collects the mappings of the level `level + 1` table that `ptd` points to, which maps
+/// the virtual addresses starting at `base`.
+fn walk_table(
+    ptd: u32,
+    level: usize,
+    base: u32,
+    read_physical: &mut impl FnMut(u32, &mut [u32]) -> Result<(), crate::Error>,
+    mappings: &mut Vec<PageMapping>,
+) -> Result<(), crate::Error> {
+    let index_bits = INDEX_BITS[level];
+    let region_bits = 32 - INDEX_BITS[..=level].iter().sum::<u32>();
+    let mut entries = vec![0; 1 << index_bits];
+    read_physical(physical_address(u64::from(ptd & !0b11) << 4)?, &mut entries)?;
+
+    for (index, entry) in entries.into_iter().enumerate() {
+        let virtual_address = base | ((index as u32) << region_bits);
+        match entry & 0b11 {
+            ET_PTD if level + 1 < INDEX_BITS.len() => {
+                walk_table(entry, level + 1, virtual_address, read_physical, mappings)?;
+            }
+            ET_PTE => mappings.push(PageMapping::new(virtual_address, entry, region_bits)),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Physical addresses are 36 bits wide, but only the lower 4 GiB can be reached over AHB.
+fn physical_address(address: u64) -> Result<u32, Leon3Error> {
+    u32::try_from(address).map_err(|_| Leon3Error::PhysicalAddressOutOfRange(address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_mmu_control() {
+        // LEON3 SRMMU with 8 instruction and data TLB entries, 4 KiB pages, enabled
+        let ctrl = MmuCtrl(0x0000_0001 | (3 << 21) | (3 << 18));
+        assert!(ctrl.e());
+        assert!(!ctrl.nf());
+        assert_eq!(ctrl.itlb(), 3);
+        assert_eq!(ctrl.dtlb(), 3);
+        assert_eq!(ctrl.page_size(), 4096);
+    }
+
+    /// Translate with context 1 of the context table at 0x40000000, looking up the page
+    /// table entries by their physical address in `tables`.
+    fn walk(tables: &[(u32, u32)], virtual_address: u32) -> Result<Translation, crate::Error> {
+        let context = MmuContext::new(MmuCtxPtr(0x4000_0000 >> 4), MmuCtx(1));
+        context.translate(virtual_address, |address| {
+            tables
+                .iter()
+                .find(|(entry_address, _)| *entry_address == address)
+                .map(|(_, entry)| *entry)
+                .ok_or_else(|| Leon3Error::BusError { address }.into())
+        })
+    }
+
+    /// Page table descriptor pointing to the table at `address`.
+    fn ptd(address: u32) -> u32 {
+        (address >> 4) | ET_PTD
+    }
+
+    /// Page table entry mapping the physical `address`.
+    fn pte(address: u32) -> u32 {
+        ((address >> 12) << 8) | ET_PTE
+    }
+
+    #[test]
+    fn translate_through_all_levels() {
+        let tables = [
+            // Context 1
+            (0x4000_0004, ptd(0x4000_1000)),
+            // Level 1 index 0xF0
+            (0x4000_13C0, ptd(0x4000_2000)),
+            // Level 2 index 0x01
+            (0x4000_2004, ptd(0x4000_3000)),
+            // Level 3 index 0x02
+            (0x4000_3008, pte(0x4012_3000)),
+        ];
+        let translation = walk(&tables, 0xF004_2ABC).unwrap();
+        assert_eq!(translation.physical_address, 0x4012_3ABC);
+        assert_eq!(translation.region_size, 4096);
+    }
+
+    #[test]
+    fn translate_large_pages() {
+        // A level 1 PTE maps 16 MiB
+        let tables = [
+            (0x4000_0004, ptd(0x4000_1000)),
+            (0x4000_1000, pte(0x4000_0000)),
+        ];
+        let translation = walk(&tables, 0x0012_3456).unwrap();
+        assert_eq!(translation.physical_address, 0x4012_3456);
+        assert_eq!(translation.region_size, 16 << 20);
+    }
+
+    #[test]
+    fn list_mappings() {
+        let mut memory = std::collections::BTreeMap::from([
+            (0x4000_0004, ptd(0x4000_1000)),
+            // 16 MiB at virtual 0x00000000
+            (0x4000_1000, pte(0x4000_0000) | (0b011 << 2) | (1 << 7)),
+            // Level 2 table for virtual 0xF0000000
+            (0x4000_13C0, ptd(0x4000_2000)),
+            // 256 KiB at virtual 0xF0040000
+            (0x4000_2004, pte(0x8000_0000) | (1 << 5)),
+        ]);
+        let context = MmuContext::new(MmuCtxPtr(0x4000_0000 >> 4), MmuCtx(1));
+        let mappings = context
+            .mappings(|address, data| {
+                for (offset, word) in data.iter_mut().enumerate() {
+                    *word = *memory.entry(address + offset as u32 * 4).or_default();
+                }
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(
+            mappings,
+            [
+                PageMapping {
+                    virtual_address: 0,
+                    physical_address: 0x4000_0000,
+                    size: 16 << 20,
+                    access: 3,
+                    cacheable: true,
+                    modified: false,
+                    referenced: false,
+                },
+                PageMapping {
+                    virtual_address: 0xF004_0000,
+                    physical_address: 0x8000_0000,
+                    size: 256 << 10,
+                    access: 0,
+                    cacheable: false,
+                    modified: false,
+                    referenced: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_entries_are_unmapped() {
+        let tables = [(0x4000_0004, ptd(0x4000_1000)), (0x4000_1000, 0)];
+        assert!(matches!(
+            walk(&tables, 0x0000_1000),
+            Err(crate::Error::Leon3(Leon3Error::UnmappedAddress(
+                0x0000_1000
+            )))
+        ));
+    }
+}
@@ -0,0 +1,257 @@
+//! A simulated AHBJTAG link for tests, backed by a word-addressed memory model.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use bitvec::{field::BitField as _, order::Lsb0, vec::BitVec, view::BitView as _};
+use probe_rs_target::ScanChainElement;
+
+use crate::{
+    architecture::leon3::ahbjtag::AhbJtag,
+    probe::{
+        BatchExecutionError, CommandQueue, DebugProbe, DebugProbeError, DeferredResultSet,
+        JtagAccess, JtagCommand, JtagSequence, Probe, WireProtocol,
+    },
+    session::BusAccess,
+};
+
+const ADATA_ADDR: u32 = 2;
+const DDATA_ADDR: u32 = 3;
+
+/// Something the simulated link observed, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LinkEvent {
+    ResetAssert,
+    ResetDeassert,
+    /// A word read from the AHB bus.
+    Read(u32),
+    /// A word written to the AHB bus, with the word after the write.
+    Write(u32, u32),
+}
+
+#[derive(Debug, Default)]
+struct LinkState {
+    memory: BTreeMap<u32, u32>,
+    events: Vec<LinkEvent>,
+    /// The address and size (0 = byte, 1 = half-word, 2 = word) of the current transfer
+    transfer: Option<(u32, bool, u8)>,
+    /// The error returned for batched transfers, if any
+    batch_error: Option<String>,
+}
+
+/// A JTAG probe that implements the AHBJTAG ADATA and DDATA registers. Clones share the
+/// simulated memory and event log.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MockAhbJtag {
+    state: Arc<Mutex<LinkState>>,
+}
+
+impl MockAhbJtag {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, LinkState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Set the word at `address` without logging an event.
+    pub(crate) fn set_word(&self, address: u32, value: u32) {
+        self.state().memory.insert(address, value);
+    }
+
+    /// The events observed so far.
+    pub(crate) fn events(&self) -> Vec<LinkEvent> {
+        self.state().events.clone()
+    }
+
+    /// Fail all batched transfers with an error that is not a probe error.
+    pub(crate) fn fail_batches(&self, message: &str) {
+        self.state().batch_error = Some(message.to_string());
+    }
+
+    /// A probe wrapping a clone of this link.
+    pub(crate) fn probe(&self) -> Probe {
+        Probe::new(self.clone())
+    }
+
+    /// An AHBJTAG bus access through a clone of this link.
+    pub(crate) fn bus_access(&self) -> BusAccess {
+        BusAccess::AhbJtag(AhbJtag::new(self.probe(), Self::config()))
+    }
+
+    /// The AHBJTAG register configuration the link responds to.
+    pub(crate) fn config() -> probe_rs_target::AhbJtag {
+        probe_rs_target::AhbJtag {
+            adata_addr: ADATA_ADDR,
+            ddata_addr: DDATA_ADDR,
+        }
+    }
+}
+
+impl LinkState {
+    fn shift_ddata(&mut self, data: u32, seq: bool) -> u32 {
+        let (address, write, size) = self
+            .transfer
+            .expect("DDATA shifted before ADATA was written");
+        let aligned = address & !3;
+        let mut word = self.memory.get(&aligned).copied().unwrap_or(0);
+        if write {
+            // Byte lanes are big-endian within the word.
+            let (shift, mask) = match size {
+                0 => (8 * (3 - address % 4), 0xFF),
+                1 => (8 * (2 - address % 4), 0xFFFF),
+                _ => (0, 0xFFFF_FFFF),
+            };
+            word = (word & !(mask << shift)) | ((data & mask) << shift);
+            self.memory.insert(aligned, word);
+            self.events.push(LinkEvent::Write(aligned, word));
+        } else {
+            self.events.push(LinkEvent::Read(aligned));
+        }
+        self.transfer = seq.then(|| (address.wrapping_add(4), write, size));
+        word
+    }
+}
+
+impl DebugProbe for MockAhbJtag {
+    fn get_name(&self) -> &str {
+        "Mock AHBJTAG"
+    }
+
+    fn speed_khz(&self) -> u32 {
+        1000
+    }
+
+    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        Ok(speed_khz)
+    }
+
+    fn attach(&mut self) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+
+    fn detach(&mut self) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn target_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.target_reset_assert()?;
+        self.target_reset_deassert()
+    }
+
+    fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.state().events.push(LinkEvent::ResetAssert);
+        Ok(())
+    }
+
+    fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
+        self.state().events.push(LinkEvent::ResetDeassert);
+        Ok(())
+    }
+
+    fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
+        match protocol {
+            WireProtocol::Jtag => Ok(()),
+            protocol => Err(DebugProbeError::UnsupportedProtocol(protocol)),
+        }
+    }
+
+    fn active_protocol(&self) -> Option<WireProtocol> {
+        Some(WireProtocol::Jtag)
+    }
+
+    fn try_as_jtag_probe(&mut self) -> Option<&mut dyn JtagAccess> {
+        Some(self)
+    }
+
+    fn into_probe(self: Box<Self>) -> Box<dyn DebugProbe> {
+        self
+    }
+}
+
+impl JtagAccess for MockAhbJtag {
+    fn set_scan_chain(&mut self, _: &[ScanChainElement]) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+
+    fn scan_chain(&mut self) -> Result<&[ScanChainElement], DebugProbeError> {
+        Ok(&[])
+    }
+
+    fn shift_raw_sequence(&mut self, _: JtagSequence) -> Result<BitVec, DebugProbeError> {
+        unimplemented!()
+    }
+
+    fn tap_reset(&mut self) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+
+    fn set_idle_cycles(&mut self, _: u8) -> Result<(), DebugProbeError> {
+        Ok(())
+    }
+
+    fn idle_cycles(&self) -> u8 {
+        0
+    }
+
+    fn write_register(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        len: u32,
+    ) -> Result<BitVec, DebugProbeError> {
+        let bits = data.view_bits::<Lsb0>();
+        let mut response = BitVec::repeat(false, len as usize);
+        match address {
+            ADATA_ADDR => {
+                let ahb_address = bits[0..32].load_le::<u32>();
+                let write = bits[34];
+                let size = bits[32..34].load_le::<u8>();
+                self.state().transfer = Some((ahb_address, write, size));
+            }
+            DDATA_ADDR => {
+                let word = self.state().shift_ddata(bits[0..32].load_le(), bits[32]);
+                response[0..32].store_le(word);
+                response.set(32, true);
+            }
+            _ => panic!("Unexpected JTAG register {address}"),
+        }
+        Ok(response)
+    }
+
+    fn write_dr(&mut self, _: &[u8], _: u32) -> Result<BitVec, DebugProbeError> {
+        unimplemented!()
+    }
+
+    fn write_register_batch(
+        &mut self,
+        writes: &CommandQueue<JtagCommand>,
+    ) -> Result<DeferredResultSet<crate::probe::CommandResult>, BatchExecutionError> {
+        if let Some(message) = self.state().batch_error.clone() {
+            return Err(BatchExecutionError::new(
+                crate::Error::Other(message),
+                DeferredResultSet::new(),
+            ));
+        }
+
+        let mut results = DeferredResultSet::new();
+        for (index, command) in writes.iter() {
+            let JtagCommand::WriteRegister(write) = command else {
+                unimplemented!()
+            };
+            let response = self
+                .write_register(write.address, &write.data, write.len)
+                .map_err(|error| {
+                    BatchExecutionError::new(error.into(), DeferredResultSet::new())
+                })?;
+            match (write.transform)(write, &response) {
+                Ok(result) => results.push(index, result),
+                Err(error) => return Err(BatchExecutionError::new(error, results)),
+            }
+        }
+        Ok(results)
+    }
+}
@@ -1,58 +1,2411 @@
+//! All the interface bits for LEON3.
+
 // TODO(darsor): pub use stuff that other architectures pub use
 // TODO(darsor): rename things from LEON3 to SPARC or SPARCV8 as appropriate
 
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use probe_rs_target::{
+    Leon3CoreAccessOptions, Leon3PromMemory, Leon3RegisterWrite, Leon3ResetStrategy,
+    Leon3SdramConfig,
+};
+
+use crate::{
+    BreakpointCause, CoreInformation, CoreInterface, CoreStatus, HaltReason, MemoryInterface,
+    MemoryMappedRegister, RegisterId, RegisterValue, VectorCatchCondition,
+    architecture::leon3::{
+        apbuart::ApbUart,
+        boot_image::{BootImage, BootSection},
+        cache::{ASI_CACHE_CONTROL, CacheConfiguration, CacheKind, CacheSnapshot, CacheState, Ccr},
+        communication_interface::{DebugUnit, Leon3CommunicationInterface, Leon3Error},
+        crc::{CRC32_ROUTINE, CRC32_ROUTINE_END},
+        diagnostics::{DsuReport, WatchpointState},
+        dsu3::{Asr17, DsuBrss, DsuCtrl, DsuDbgm, DsuDtr, DsuTimeTag, Psr},
+        edac::{AhbStat, CacheErrorCounters, EdacStatus, Ftmctrl, InjectedError},
+        fpu::{FpuException, Fsr},
+        instructions::{
+            AddressOffset, BranchKind, Instruction, MemoryAccess, TA_1, disassemble,
+            float_condition_holds, integer_condition_holds,
+        },
+        irqmp::Irqmp,
+        l2cache::L2Cache,
+        l3stat::StatisticsUnit,
+        mctrl::MemoryController,
+        mmu::{
+            ASI_MMU_REGISTERS, MmuContext, MmuCtrl, MmuCtx, MmuCtxPtr, PageMapping, Translation,
+        },
+        plugnplay::{Device, GaislerDevice},
+        prom::{
+            CfiInfo, DirectProm, PROGRESS_CHUNK, Prom, PromProgress, PromWriteOptions, SectorFlash,
+            erase_sectors, progress_chunks, write_sectors,
+        },
+        registers::{IuCoreReg, IuSpecialReg, Leon3RegisterId, RegisterFile},
+        sequences::{Leon3DebugSequence, emulate_processor_reset},
+        spimctrl::SpiFlash,
+        trace::{
+            AHB_WATCHPOINTS, AhbTraceControl, AhbTraceEntry, AhbTraceFilter, AhbTraceFilterControl,
+            AhbTraceFilterMask, INSTRUCTION_TRACE_BUFFER_OFFSET, InstructionTraceControl,
+            InstructionTraceEntry, PcSampler, TraceClock,
+        },
+        traps::{
+            TRAP_ENTRY_WORDS, TRAP_TABLE_ENTRIES, TrapTable, TrapTableEntry, entry_address,
+            psr_after_return, trap_name,
+        },
+        word_access::{read_unaligned, write_unaligned},
+    },
+    memory::{InvalidDataLengthError, MemoryNotAlignedError, valid_32bit_address},
+    semihosting::{SemihostingCommand, decode_semihosting_syscall},
+};
+
+pub mod ahbjtag;
+pub mod ahbuart;
+pub mod apbuart;
+mod boot_image;
+pub mod cache;
+pub mod communication_interface;
+pub mod crc;
+pub mod diagnostics;
+pub(crate) mod dsu3;
+pub mod edac;
+pub mod edcl;
+pub mod fpu;
+pub(crate) mod gptimer;
+pub(crate) mod grclkgate;
+mod instructions;
+pub(crate) mod irqmp;
+pub mod l2cache;
+pub mod l3stat;
+pub mod mctrl;
+pub mod mmu;
+#[cfg(test)]
+pub(crate) mod mock_ahbjtag;
+pub mod pci;
+pub mod plugnplay;
+pub mod prom;
+pub mod registers;
+pub mod sequences;
+pub(crate) mod spimctrl;
+pub mod trace;
+pub mod traps;
+pub mod usbdcl;
+mod word_access;
+
+/// How long to wait for the core to re-enter debug mode after a single step.
+const STEP_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How long a powered-down core gets to react to a break-now request, before it is woken up
+/// with an interrupt.
+const POWER_DOWN_WAKE_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// The ASIs of user and supervisor instruction and data accesses, which address memory.
+const MEMORY_ASIS: std::ops::RangeInclusive<u8> = 0x08..=0x0B;
+
+/// The ASI of physical memory accesses that bypass the MMU.
+const ASI_MMU_BYPASS: u8 = 0x1C;
+
+/// The part of an ASI that is reachable through the DSU ASI diagnostic area.
+const ASI_DIAGNOSTIC_SIZE: u64 = 0x10_0000;
+
+/// The size of the smallest SPARC stack frame, which holds the register window save area.
+const MIN_STACK_FRAME: u32 = 96;
+
+/// The maximum number of IU watchpoints a LEON3 can implement.
+const MAX_HW_BREAKPOINTS: usize = 4;
+
+/// Instruction fetch (IF) bit of the watchpoint address register. If set, the watchpoint
+/// triggers on instruction fetches matching the address.
+const WADDR_IF: u32 = 1 << 0;
+
+/// Mask of the address bits in the watchpoint address and mask registers.
+const WATCHPOINT_ADDRESS_MASK: u32 = !0b11;
+
+/// Data load (DL) bit of the watchpoint mask register. If set, the watchpoint triggers on
+/// loads from matching addresses.
+const WMASK_DL: u32 = 1 << 1;
+
+/// Data store (DS) bit of the watchpoint mask register. If set, the watchpoint triggers on
+/// stores to matching addresses.
+const WMASK_DS: u32 = 1 << 0;
+
+/// The trap type the DSU reports when entering debug mode because of an IU watchpoint
+/// or a break-now request.
+const TT_WATCHPOINT_DETECTED: u8 = 0x0B;
+
+/// The trap type of a floating-point exception.
+const TT_FP_EXCEPTION: u8 = 0x08;
+
+/// The trap type of the `ta 1` software breakpoint instruction.
+const TT_SOFTWARE_BREAKPOINT: u8 = 0x81;
+
+/// The accesses a data watchpoint triggers on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchpointKind {
+    /// Trigger on loads.
+    Read,
+    /// Trigger on stores.
+    Write,
+    /// Trigger on loads and stores.
+    ReadWrite,
+}
+
+/// The data watchpoint a core halted on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchpointHit {
+    /// The IU watchpoint unit that matched the access.
+    pub unit_index: usize,
+    /// The address of the access that matched. For doubleword accesses, the address of the
+    /// word that matched.
+    pub address: u32,
+}
+
+/// Traps that make the core enter debug mode instead of jumping to the trap handler.
+///
+/// The DSU cannot select individual trap types. IU watchpoint traps (BW) are always caught,
+/// since halt requests and hardware breakpoints rely on them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrapCatch {
+    /// All traps (BX).
+    AllTraps,
+    /// Error traps (BZ): all traps except privileged_instruction, fp_disabled, window_overflow,
+    /// window_underflow, interrupts and Ticc.
+    ErrorTraps,
+    /// Traps while traps are disabled, which would otherwise put the core in error mode (BE).
+    ErrorMode,
+    /// Software breakpoint traps (BS), including `ta 1` instructions not set by the debugger.
+    SoftwareBreakpoints,
+}
+
+/// How the core executes a single step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepMode {
+    /// The single-step function of the DSU.
+    Hardware,
+    /// Temporary software breakpoints at the addresses the instruction can continue at, for
+    /// designs where the single-step function misbehaves.
+    Software,
+}
+
+/// What an IU watchpoint is currently used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WatchpointUnit {
+    Free,
+    Breakpoint,
+    Data,
+    ResetCatch,
+}
+
+/// The IU watchpoint reserved to halt the core when it comes out of reset.
+#[derive(Debug, Clone, Copy)]
+struct ResetCatch {
+    unit_index: usize,
+    /// The break on IU watchpoint (BW) bit of the DSU control register before the catch was
+    /// armed
+    break_on_watchpoint: bool,
+}
+
+/// An interface to operate a LEON3 core.
+pub struct Leon3<'state> {
+    core_index: usize,
+    interface: Leon3CommunicationInterface<'state>,
+    state: &'state mut Leon3CoreState,
+    sequence: Arc<dyn Leon3DebugSequence>,
+}
+
+impl<'state> Leon3<'state> {
+    /// Create a new LEON3 interface for a particular core.
+    pub fn new(
+        // The index of the core within its debug support unit
+        core_index: usize,
+        interface: Leon3CommunicationInterface<'state>,
+        state: &'state mut Leon3CoreState,
+        sequence: Arc<dyn Leon3DebugSequence>,
+    ) -> Result<Self, crate::Error> {
+        let mut this = Self {
+            core_index,
+            interface,
+            state,
+            sequence,
+        };
+
+        if !this.state.initialized {
+            this.sequence
+                .ungate_clocks(&mut this.interface, &this.state.ungate_clocks)?;
+            this.interface.on_first_attach()?;
+            this.sequence.on_connect(&mut this.interface)?;
+            this.configure_memory_controller()?;
+            this.state.initialized = true;
+        }
+
+        // The configuration registers can only be read in debug mode.
+        if this.core_halted()? {
+            this.read_configuration()?;
+        }
+
+        Ok(this)
+    }
+
+    /// Configure the memory controller through the debug sequence.
+    fn configure_memory_controller(&mut self) -> Result<(), crate::Error> {
+        self.sequence
+            .configure_memory_controller(&mut self.interface, &self.state.memory_controller_init)
+    }
+
+    /// Prepare the RAM for downloading an image through the debug sequence, for example by
+    /// initializing the SDRAM. The core must be halted.
+    pub fn setup_ram(&mut self) -> Result<(), crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        self.sequence
+            .setup_ram(&mut self.interface, self.state.sdram.as_ref())
+    }
+
+    /// Read the implementation details of the core from %asr17, if not done yet.
+    fn read_configuration(&mut self) -> Result<(), crate::Error> {
+        if self.state.configuration_read {
+            return Ok(());
+        }
+        let asr17: Asr17 = self.interface.read_dsu_reg()?;
+        tracing::debug!("LEON3 configuration: {asr17:?}");
+        self.state.fpu_present = asr17.fpu() != 0;
+        self.state.mmu_present = self.interface.cache_configuration(CacheKind::Data)?.mmu;
+        self.state.asrs = self.probe_asrs(asr17)?;
+        self.state.configuration_read = true;
+        Ok(())
+    }
+
+    /// Determine which ancillary state registers the core implements.
+    ///
+    /// Bit `n` of the result is set if `%asr(16 + n)` is implemented.
+    fn probe_asrs(&mut self, asr17: Asr17) -> Result<u16, crate::Error> {
+        let asr = |n: u8| 1u16 << (n - 16);
+
+        // %asr17 is always implemented.
+        let mut asrs = asr(17);
+
+        // The register file protection control register only exists on fault-tolerant cores.
+        if self
+            .interface
+            .plugnplay()
+            .find_device(Device::Gaisler(GaislerDevice::LEON3FT))
+            .is_some()
+        {
+            asrs |= asr(16);
+        }
+
+        if asr17.mac() {
+            asrs |= asr(18);
+        }
+
+        // The optional up-counter in %asr22/%asr23 keeps counting in debug mode, so it is
+        // present if its value changes between two reads.
+        let first = self.read_special_reg(IuSpecialReg::ASR(23))?;
+        let second = self.read_special_reg(IuSpecialReg::ASR(23))?;
+        if first != second {
+            asrs |= asr(22) | asr(23);
+        }
+
+        for unit_index in 0..self.available_breakpoint_units()? as usize {
+            let (waddr, wmask) = Self::watchpoint_registers(unit_index);
+            for register in [waddr, wmask] {
+                if let IuSpecialReg::ASR(n) = register {
+                    asrs |= asr(n);
+                }
+            }
+        }
+
+        Ok(asrs)
+    }
+
+    /// The current window pointer, cached while the core is halted.
+    fn cwp(&mut self) -> Result<u32, crate::Error> {
+        if let Some(cwp) = self.state.cwp {
+            return Ok(cwp);
+        }
+        let cwp = Psr::from(self.read_special_reg(IuSpecialReg::PSR)?).cwp();
+        if self.core_halted()? {
+            self.state.cwp = Some(cwp);
+        }
+        Ok(cwp)
+    }
+
+    fn read_special_reg(&mut self, reg: IuSpecialReg) -> Result<u32, crate::Error> {
+        self.interface
+            .read_core_reg(Leon3RegisterId::IuSpecial(reg))
+    }
+
+    fn write_special_reg(&mut self, reg: IuSpecialReg, value: u32) -> Result<(), crate::Error> {
+        self.interface
+            .write_core_reg(Leon3RegisterId::IuSpecial(reg), value)
+    }
+
+    /// The ASRs holding the watchpoint address and mask registers of an IU watchpoint.
+    ///
+    /// Watchpoint `n` uses %asr(24 + 2n) as address register and %asr(25 + 2n) as mask register.
+    fn watchpoint_registers(unit_index: usize) -> (IuSpecialReg, IuSpecialReg) {
+        let waddr = 24 + 2 * unit_index as u8;
+        (IuSpecialReg::ASR(waddr), IuSpecialReg::ASR(waddr + 1))
+    }
+
+    fn check_breakpoint_unit(&mut self, unit_index: usize) -> Result<(), crate::Error> {
+        if unit_index >= self.available_breakpoint_units()? as usize {
+            return Err(Leon3Error::InvalidBreakpointUnit(unit_index).into());
+        }
+        Ok(())
+    }
+
+    /// Set or clear the instruction fetch bit of all configured watchpoints.
+    fn arm_breakpoints(&mut self, armed: bool) -> Result<(), crate::Error> {
+        for unit_index in 0..MAX_HW_BREAKPOINTS {
+            if self.state.watchpoint_units[unit_index] != WatchpointUnit::Breakpoint {
+                continue;
+            }
+            let (waddr, _) = Self::watchpoint_registers(unit_index);
+            let address = self.read_special_reg(waddr)? & WATCHPOINT_ADDRESS_MASK;
+            let value = if armed { address | WADDR_IF } else { address };
+            self.write_special_reg(waddr, value)?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether an enabled hardware breakpoint is set at `address`.
+    fn hw_breakpoint_at(&mut self, address: u32) -> Result<bool, crate::Error> {
+        if !self.state.hw_breakpoints_enabled {
+            return Ok(false);
+        }
+        Ok(self.hw_breakpoints()?.contains(&Some(address.into())))
+    }
+
+    /// Configure an IU watchpoint to halt the core on data accesses to `size` bytes starting
+    /// at `address`.
+    ///
+    /// The watchpoint units are shared with hardware breakpoints. Since the address is matched
+    /// using a mask, `size` must be a power of two of at least 4 and `address` must be aligned
+    /// to it.
+    pub fn set_watchpoint(
+        &mut self,
+        unit_index: usize,
+        address: u64,
+        size: u64,
+        kind: WatchpointKind,
+    ) -> Result<(), crate::Error> {
+        self.check_breakpoint_unit(unit_index)?;
+        if matches!(
+            self.state.watchpoint_units[unit_index],
+            WatchpointUnit::Breakpoint | WatchpointUnit::ResetCatch
+        ) {
+            return Err(Leon3Error::BreakpointUnitInUse(unit_index).into());
+        }
+        let invalid = || Leon3Error::UnsupportedWatchpoint { address, size };
+        let address = u32::try_from(address).map_err(|_| invalid())?;
+        let size = u32::try_from(size).map_err(|_| invalid())?;
+        if !size.is_power_of_two() || size < 4 || address % size != 0 {
+            return Err(invalid().into());
+        }
+
+        let access = match kind {
+            WatchpointKind::Read => WMASK_DL,
+            WatchpointKind::Write => WMASK_DS,
+            WatchpointKind::ReadWrite => WMASK_DL | WMASK_DS,
+        };
+        let (waddr, wmask) = Self::watchpoint_registers(unit_index);
+        self.write_special_reg(waddr, address)?;
+        self.write_special_reg(wmask, (!(size - 1) & WATCHPOINT_ADDRESS_MASK) | access)?;
+
+        self.state.watchpoint_units[unit_index] = WatchpointUnit::Data;
+        Ok(())
+    }
+
+    /// Remove the data watchpoint of the given unit.
+    pub fn clear_watchpoint(&mut self, unit_index: usize) -> Result<(), crate::Error> {
+        self.check_breakpoint_unit(unit_index)?;
+        if self.state.watchpoint_units[unit_index] != WatchpointUnit::Data {
+            return Ok(());
+        }
+        self.clear_hw_breakpoint(unit_index)
+    }
+
+    /// The data watchpoint the core halted on, or `None` if it halted for another reason.
+    ///
+    /// The DSU does not record which watchpoint triggered, so the load or store at the PC is
+    /// decoded and its address matched against the data watchpoints. The core must be halted.
+    pub fn watchpoint_hit(&mut self) -> Result<Option<WatchpointHit>, crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        let dtr: DsuDtr = self.interface.read_dsu_reg()?;
+        if dtr.traptype() != TT_WATCHPOINT_DETECTED {
+            return Ok(None);
+        }
+        self.matching_watchpoint()
+    }
+
+    /// Match the memory access of the instruction at the PC against the data watchpoints.
+    fn matching_watchpoint(&mut self) -> Result<Option<WatchpointHit>, crate::Error> {
+        if !self.state.watchpoint_units.contains(&WatchpointUnit::Data) {
+            return Ok(None);
+        }
+        let pc = self.read_special_reg(IuSpecialReg::PC)?;
+        let word = match self.state.sw_breakpoints.get(&pc) {
+            Some(&original) => original,
+            None => self.read_word_32(pc.into())?,
+        };
+        let Some(access) = MemoryAccess::decode(word) else {
+            return Ok(None);
+        };
+        let cwp = self.cwp()?;
+        let base = self
+            .interface
+            .read_window_reg(IuCoreReg::from_index(access.rs1), cwp)?;
+        let address = match access.offset {
+            AddressOffset::Register(rs2) => base.wrapping_add(
+                self.interface
+                    .read_window_reg(IuCoreReg::from_index(rs2), cwp)?,
+            ),
+            AddressOffset::Immediate(offset) => base.wrapping_add_signed(offset),
+        };
+
+        for unit_index in 0..MAX_HW_BREAKPOINTS {
+            if self.state.watchpoint_units[unit_index] != WatchpointUnit::Data {
+                continue;
+            }
+            let (waddr, wmask) = Self::watchpoint_registers(unit_index);
+            let watched = self.read_special_reg(waddr)?;
+            let mask = self.read_special_reg(wmask)?;
+            if !(access.load && mask & WMASK_DL != 0 || access.store && mask & WMASK_DS != 0) {
+                continue;
+            }
+            let mask = mask & WATCHPOINT_ADDRESS_MASK;
+            for word in 0..access.words {
+                let address = address.wrapping_add(4 * word);
+                if address & mask == watched & mask {
+                    return Ok(Some(WatchpointHit {
+                        unit_index,
+                        address,
+                    }));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read the registers of all register windows and the globals in one operation.
+    ///
+    /// The core must be halted.
+    pub fn read_register_file(&mut self) -> Result<RegisterFile, crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        self.interface.read_register_file()
+    }
+
+    /// Start the core at `entry` with the stack pointer set to `stack_pointer`, for example to
+    /// run a separate application on a secondary core of an asymmetric multiprocessing system.
+    ///
+    /// The core is halted first if it is running or powered down, and taken out of error mode.
+    /// The other cores are not affected. `entry` must be word aligned, and `stack_pointer` must
+    /// leave room for the register window save area below the initial stack.
+    pub fn start_at(&mut self, entry: u32, stack_pointer: u32) -> Result<(), crate::Error> {
+        if !self.core_halted()? {
+            self.halt(Duration::from_millis(100))?;
+        }
+        if self.in_error_mode()? {
+            self.clear_error_mode()?;
+        }
+
+        self.write_special_reg(IuSpecialReg::PC, entry)?;
+        self.write_special_reg(IuSpecialReg::NPC, entry.wrapping_add(4))?;
+        let window = self.cwp()? as u8;
+        self.interface.write_core_reg(
+            Leon3RegisterId::IuWindow {
+                window,
+                reg: IuCoreReg::O(6),
+            },
+            stack_pointer,
+        )?;
+        // The application may have been loaded without the caches noticing.
+        self.invalidate_caches()?;
+
+        self.run()
+    }
+
+    /// Returns the generation of the debug support unit of the target.
+    pub fn debug_unit(&self) -> DebugUnit {
+        self.interface.debug_unit()
+    }
+
+    /// Enable or disable recording of executed instructions in the instruction trace buffer.
+    ///
+    /// Instructions are only recorded while the core is running.
+    pub fn set_instruction_trace(&mut self, enabled: bool) -> Result<(), crate::Error> {
+        self.interface.modify_dsu_ctrl(|ctrl| ctrl.set_te(enabled))
+    }
+
+    /// Only report instructions within `range` from [`Leon3::read_instruction_trace`], or all
+    /// instructions if `None`.
+    ///
+    /// The DSU has no address comparator for the instruction trace, so the buffer keeps
+    /// recording all instructions and the range is applied when it is read. To stop the trace
+    /// at an address, use a hardware breakpoint instead.
+    pub fn set_instruction_trace_range(&mut self, range: Option<Range<u32>>) {
+        self.state.instruction_trace_range = range;
+    }
+
+    /// Read the instruction trace buffer, oldest instruction first.
+    ///
+    /// Returns an empty list if the core has no trace buffer. The core must be halted.
+    pub fn read_instruction_trace(&mut self) -> Result<Vec<InstructionTraceEntry>, crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        let mut entries = self.interface.read_instruction_trace()?;
+        if let Some(range) = &self.state.instruction_trace_range {
+            entries.retain(|entry| range.contains(&entry.pc));
+        }
+        Ok(entries)
+    }
+
+    /// Enable the instruction trace and return a [`PcSampler`] that reads the program counter
+    /// of this core while it runs.
+    ///
+    /// The core must be halted, as the size of the trace buffer is determined on first use.
+    /// The instruction trace stays enabled until [`Leon3::set_instruction_trace`] disables it.
+    pub fn pc_sampler(&mut self) -> Result<PcSampler, crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        let entries = self.interface.instruction_trace_entries()?;
+        if entries == 0 {
+            return Err(Leon3Error::NoInstructionTrace.into());
+        }
+        self.set_instruction_trace(true)?;
+        Ok(PcSampler::new(
+            self.interface
+                .dsu_register_address(InstructionTraceControl::ADDRESS_OFFSET)?,
+            self.interface
+                .dsu_register_address(INSTRUCTION_TRACE_BUFFER_OFFSET)?,
+            entries,
+        ))
+    }
+
+    /// Read words from an alternate address space (ASI) of this core.
+    ///
+    /// The user and supervisor instruction and data spaces (ASI 0x08 to 0x0B) are read like
+    /// normal memory through this core, and the MMU bypass space (ASI 0x1C) is read from
+    /// physical memory. Other ASIs, like the cache and MMU diagnostic spaces, are read
+    /// through the ASI diagnostic area of the DSU, which reaches the first MiB of the ASI.
+    /// The core must be halted for those.
+    pub fn read_with_asi(
+        &mut self,
+        asi: u8,
+        address: u32,
+        data: &mut [u32],
+    ) -> Result<(), crate::Error> {
+        if MEMORY_ASIS.contains(&asi) {
+            return self.read_32(address.into(), data);
+        }
+        if asi == ASI_MMU_BYPASS {
+            return self
+                .interface
+                .as_memory_interface_mut()
+                .read_32(address.into(), data);
+        }
+        self.check_diagnostic_asi(asi, address, data.len())?;
+        self.interface.read_asi_block(asi, address, data)
+    }
+
+    /// Write words to an alternate address space (ASI) of this core.
+    ///
+    /// See [`Leon3::read_with_asi`] for how the ASIs are reached.
+    pub fn write_with_asi(
+        &mut self,
+        asi: u8,
+        address: u32,
+        data: &[u32],
+    ) -> Result<(), crate::Error> {
+        if MEMORY_ASIS.contains(&asi) {
+            return self.write_32(address.into(), data);
+        }
+        if asi == ASI_MMU_BYPASS {
+            return self
+                .interface
+                .as_memory_interface_mut()
+                .write_32(address.into(), data);
+        }
+        self.check_diagnostic_asi(asi, address, data.len())?;
+        self.interface.write_asi_block(asi, address, data)?;
+        // The MMU registers may have changed.
+        self.state.mmu_context = None;
+        Ok(())
+    }
+
+    /// Check that `num_words` words at `address` can be accessed through the ASI diagnostic
+    /// area of the DSU.
+    fn check_diagnostic_asi(
+        &mut self,
+        asi: u8,
+        address: u32,
+        num_words: usize,
+    ) -> Result<(), crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        if !address.is_multiple_of(4) {
+            return Err(MemoryNotAlignedError {
+                address: address.into(),
+                alignment: 4,
+            }
+            .into());
+        }
+        if u64::from(address) + num_words as u64 * 4 > ASI_DIAGNOSTIC_SIZE {
+            return Err(Leon3Error::AsiAddressOutOfRange { asi, address }.into());
+        }
+        Ok(())
+    }
+
+    /// Read the cache control register, which holds the state of both caches.
+    ///
+    /// The core must be halted.
+    pub fn read_cache_control(&mut self) -> Result<Ccr, crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        let ccr = self
+            .interface
+            .read_asi(ASI_CACHE_CONTROL, Ccr::ADDRESS_OFFSET as u32)?;
+        Ok(Ccr(ccr))
+    }
+
+    /// Write the cache control register.
+    ///
+    /// Setting the flush bits flushes the caches, but does not wait for the flush to complete.
+    /// The core must be halted.
+    pub fn write_cache_control(&mut self, ccr: Ccr) -> Result<(), crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        self.interface
+            .write_asi(ASI_CACHE_CONTROL, Ccr::ADDRESS_OFFSET as u32, ccr.into())
+    }
+
+    /// Modify the cache control register. The core must be halted.
+    pub fn modify_cache_control(&mut self, f: impl FnOnce(&mut Ccr)) -> Result<(), crate::Error> {
+        let mut ccr = self.read_cache_control()?;
+        f(&mut ccr);
+        self.write_cache_control(ccr)
+    }
+
+    /// Enable, freeze or disable the instruction or data cache.
+    ///
+    /// Disabling the caches makes the core access memory directly, for example to get
+    /// deterministic timing. The core must be halted.
+    pub fn set_cache_state(
+        &mut self,
+        kind: CacheKind,
+        state: CacheState,
+    ) -> Result<(), crate::Error> {
+        self.modify_cache_control(|ccr| ccr.set_cache_state(kind, state))
+    }
+
+    /// Configure whether the instruction or data cache is frozen when an interrupt is taken, so
+    /// interrupt handlers do not evict the lines of the interrupted code. The core must be
+    /// halted.
+    pub fn set_cache_freeze_on_interrupt(
+        &mut self,
+        kind: CacheKind,
+        freeze: bool,
+    ) -> Result<(), crate::Error> {
+        self.modify_cache_control(|ccr| ccr.set_freeze_on_interrupt(kind, freeze))
+    }
+
+    /// Invalidate all lines of the instruction and data caches and wait for the flush to
+    /// complete. The core must be halted.
+    ///
+    /// Memory written through this core bypasses the instruction cache, so the instruction
+    /// cache lines of code written through this core are also invalidated automatically
+    /// before the core runs or steps.
+    pub fn flush_caches(&mut self) -> Result<(), crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        self.invalidate_caches()
+    }
+
+    /// Read the EDAC status of a fault-tolerant system: the memory error latched by the AHB
+    /// status register and the error counters of the caches of this core. The core must be
+    /// halted.
+    pub fn read_edac_status(&mut self) -> Result<EdacStatus, crate::Error> {
+        let ccr = self.read_cache_control()?;
+        let memory_error = match AhbStat::find(&mut self.interface) {
+            Some(ahbstat) => ahbstat.read_error(&mut self.interface)?,
+            None => None,
+        };
+        let l2_cache_error = match L2Cache::find(&mut self.interface) {
+            Some(l2_cache) => l2_cache.read_error(&mut self.interface)?,
+            None => None,
+        };
+        Ok(EdacStatus {
+            memory_error,
+            cache_errors: CacheErrorCounters::from_ccr(ccr),
+            l2_cache_error,
+        })
+    }
+
+    /// Clear the memory error latched by the AHB status register, the error latched by the
+    /// L2 cache and the error counters of the caches of this core. The core must be halted.
+    pub fn clear_edac_status(&mut self) -> Result<(), crate::Error> {
+        self.modify_cache_control(|ccr| {
+            ccr.set_ite(0);
+            ccr.set_ide(0);
+            ccr.set_dte(0);
+            ccr.set_dde(0);
+        })?;
+        if let Some(ahbstat) = AhbStat::find(&mut self.interface) {
+            ahbstat.clear(&mut self.interface)?;
+        }
+        if let Some(l2_cache) = L2Cache::find(&mut self.interface) {
+            l2_cache.clear_error(&mut self.interface)?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether the system has an L2 cache.
+    pub fn has_l2_cache(&mut self) -> bool {
+        L2Cache::find(&mut self.interface).is_some()
+    }
+
+    fn l2_cache(&mut self) -> Result<L2Cache, crate::Error> {
+        L2Cache::find(&mut self.interface)
+            .ok_or_else(|| Leon3Error::DeviceNotFound("L2CACHE").into())
+    }
+
+    /// Enable or disable the L2 cache, which is shared by all cores.
+    ///
+    /// The cache is invalidated before it is enabled and written back before it is disabled.
+    pub fn set_l2_cache_enabled(&mut self, enabled: bool) -> Result<(), crate::Error> {
+        let l2_cache = self.l2_cache()?;
+        l2_cache.set_enabled(&mut self.interface, enabled)
+    }
+
+    /// Write back and invalidate the L2 cache lines holding `range`, or the whole L2 cache if
+    /// `range` is `None`.
+    pub fn flush_l2_cache(&mut self, range: Option<Range<u64>>) -> Result<(), crate::Error> {
+        let l2_cache = self.l2_cache()?;
+        l2_cache.flush(&mut self.interface, range)
+    }
+
+    /// Invalidate the L2 cache lines holding `range` without writing them back, or the whole
+    /// L2 cache if `range` is `None`. Data that was not written back yet is lost.
+    pub fn invalidate_l2_cache(&mut self, range: Option<Range<u64>>) -> Result<(), crate::Error> {
+        let l2_cache = self.l2_cache()?;
+        l2_cache.invalidate(&mut self.interface, range)
+    }
+
+    /// Inject an error into the word at the physical `address`, using the diagnostic bypass
+    /// of the FTMCTRL memory controller.
+    ///
+    /// The word keeps the check bits of its original value, so the error is detected the next
+    /// time it is read. The core must be halted, so it does not access memory while the
+    /// bypass is enabled.
+    pub fn inject_memory_error(
+        &mut self,
+        address: u64,
+        error: InjectedError,
+    ) -> Result<(), crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        if !address.is_multiple_of(4) {
+            return Err(MemoryNotAlignedError {
+                address,
+                alignment: 4,
+            }
+            .into());
+        }
+        let Some(ftmctrl) = Ftmctrl::find(&mut self.interface) else {
+            return Err(Leon3Error::DeviceNotFound("FTMCTRL").into());
+        };
+        ftmctrl.inject_error(&mut self.interface, address, error)?;
+        self.record_write(address, 4);
+        Ok(())
+    }
+
+    /// Run `f` with PROM writes enabled in the MCTRL or FTMCTRL memory controller, passing
+    /// the memory controller, the start of its PROM area and the PROM bus width in bytes.
+    fn with_prom_writes<R>(
+        &mut self,
+        f: impl FnOnce(
+            &mut Leon3CommunicationInterface,
+            &MemoryController,
+            u64,
+            usize,
+        ) -> Result<R, crate::Error>,
+    ) -> Result<R, crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        let (controller, prom) = MemoryController::find(&mut self.interface)
+            .and_then(|controller| controller.prom().map(|prom| (controller, prom)))
+            .ok_or(Leon3Error::DeviceNotFound("MCTRL or FTMCTRL PROM area"))?;
+
+        let mcfg1 = controller.read_mcfg1(&mut self.interface)?;
+        let bus_width = match mcfg1.prom_width() {
+            width @ 0..=2 => 1 << width,
+            width => return Err(Leon3Error::UnsupportedPromWidth(8 << width).into()),
+        };
+        let mut writable = mcfg1;
+        writable.set_pwen(true);
+        controller.write_mcfg1(&mut self.interface, writable)?;
+
+        let result = f(&mut self.interface, &controller, prom.start, bus_width);
+        controller.write_mcfg1(&mut self.interface, mcfg1)?;
+        result
+    }
+
+    /// Identify the CFI flash in the PROM area of the MCTRL or FTMCTRL memory controller and
+    /// run `f` with PROM writes enabled.
+    fn with_prom<R>(
+        &mut self,
+        f: impl FnOnce(&Prom, &mut Leon3CommunicationInterface) -> Result<R, crate::Error>,
+    ) -> Result<R, crate::Error> {
+        self.with_prom_writes(|interface, controller, base, bus_width| {
+            if controller.prom_edac_enabled(interface)? {
+                return Err(Leon3Error::PromEdacEnabled.into());
+            }
+            let prom = Prom::identify(interface, base, bus_width)?;
+            f(&prom, interface)
+        })
+    }
+
+    /// Identify the SPI flash behind the SPIMCTRL `flash` and run `f` with the controller
+    /// in user mode. The core must be halted.
+    fn with_spi_flash<R>(
+        &mut self,
+        flash: SpiFlash,
+        f: impl FnOnce(&SpiFlash, &mut Leon3CommunicationInterface) -> Result<R, crate::Error>,
+    ) -> Result<R, crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        flash.with_user_mode(&mut self.interface, f)
+    }
+
+    /// Write `data` at `address` into an MRAM or EEPROM in the PROM area, as selected by the
+    /// `prom` core access option. Returns `false` without writing for CFI flash.
+    fn write_direct_prom(
+        &mut self,
+        address: u64,
+        data: &[u8],
+        mut progress: impl FnMut(PromProgress),
+    ) -> Result<bool, crate::Error> {
+        let Some(prom) = DirectProm::new(&self.state.prom_memory) else {
+            return Ok(false);
+        };
+        self.with_prom_writes(|interface, _, _, bus_width| {
+            for chunk in progress_chunks(address, data.len()) {
+                let offset = (chunk.start - address) as usize;
+                let len = (chunk.end - chunk.start) as usize;
+                prom.program(
+                    interface,
+                    bus_width,
+                    chunk.start,
+                    &data[offset..offset + len],
+                )?;
+                progress(PromProgress::Programmed(len as u64));
+            }
+            Ok(())
+        })?;
+        self.invalidate_caches()?;
+        Ok(true)
+    }
+
+    /// Prepare the core to start an image downloaded to RAM at `entry`, with the registers
+    /// at their reset values, the trap table at `entry` and the stack ending at `stack`. The
+    /// core must be halted.
+    pub fn prepare_ram_boot(&mut self, entry: u32, stack: u32) -> Result<(), crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        self.state.cwp = None;
+        self.state.mmu_context = None;
+        emulate_processor_reset(&mut self.interface, entry)?;
+
+        // Leave room for the register window save area of the first stack frame.
+        let frame = stack & !0xF;
+        self.write_core_reg(registers::FP.id(), RegisterValue::U32(frame))?;
+        self.write_core_reg(
+            registers::SP.id(),
+            RegisterValue::U32(frame - MIN_STACK_FRAME),
+        )
+    }
+
+    /// Build a boot image for the PROM, which configures the memory controller like the core
+    /// access options do, copies `sections` from the PROM to RAM and starts the image at
+    /// `entry` with the stack ending at `stack`. Returns the reset address, which must be in
+    /// the PROM area, and the contents of the boot image to write there.
+    pub fn prom_boot_image(
+        &mut self,
+        sections: &[(u32, &[u8])],
+        entry: u32,
+        stack: u32,
+    ) -> Result<(u64, Vec<u8>), crate::Error> {
+        let controller = MemoryController::find(&mut self.interface)
+            .ok_or(Leon3Error::DeviceNotFound("MCTRL or FTMCTRL"))?;
+        let prom = controller
+            .prom()
+            .ok_or(Leon3Error::DeviceNotFound("MCTRL or FTMCTRL PROM area"))?;
+        let reset_address = self.reset_address();
+        if !prom.contains(&u64::from(reset_address)) {
+            return Err(Leon3Error::InvalidBootImage(format!(
+                "the reset address {reset_address:#010x} is not in the PROM area {prom:#x?}"
+            ))
+            .into());
+        }
+        let sdram = self
+            .state
+            .sdram
+            .as_ref()
+            .map(|config| controller.sdram_boot_setup(config))
+            .transpose()?;
+        let image = BootImage {
+            prom_base: reset_address,
+            memory_init: self.state.memory_controller_init.clone(),
+            sdram,
+            sections: sections
+                .iter()
+                .map(|(address, data)| BootSection {
+                    address: *address,
+                    data,
+                })
+                .collect(),
+            entry,
+            stack,
+        };
+        Ok((u64::from(reset_address), image.build()?))
+    }
+
+    /// Compute the CRC-32 of `len` bytes at `address` on the core, with a routine downloaded
+    /// to the RAM at `scratch`. The core must be halted.
+    ///
+    /// The scratch RAM, the registers used by the routine and the software breakpoint trap
+    /// setting are restored afterwards.
+    pub fn crc32(
+        &mut self,
+        scratch: u32,
+        address: u32,
+        len: u32,
+        timeout: Duration,
+    ) -> Result<u32, crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        let (Some(routine_npc), Some(routine_end)) = (
+            scratch.checked_add(4),
+            scratch.checked_add(CRC32_ROUTINE_END),
+        ) else {
+            return Err(Leon3Error::OutOfBounds.into());
+        };
+
+        let mut saved_scratch = [0; CRC32_ROUTINE.len()];
+        self.read_32(scratch.into(), &mut saved_scratch)?;
+        let output = |n| Leon3RegisterId::IuCore(IuCoreReg::O(n));
+        let mut saved_outputs = [0; 6];
+        for (n, value) in saved_outputs.iter_mut().enumerate() {
+            *value = self.interface.read_core_reg(output(n as u8))?;
+        }
+        let saved_psr = self.read_special_reg(IuSpecialReg::PSR)?;
+        let saved_pc = self.read_special_reg(IuSpecialReg::PC)?;
+        let saved_npc = self.read_special_reg(IuSpecialReg::NPC)?;
+        let saved_ctrl: DsuCtrl = self.interface.read_dsu_reg()?;
+        let saved_halt_reason = self.state.debugger_halt_reason;
+
+        // Everything after this point is undone below, even if running the routine fails.
+        let mut run_routine = || -> Result<u32, crate::Error> {
+            self.write_32(scratch.into(), &CRC32_ROUTINE)?;
+            self.invalidate_caches()?;
+            self.interface.write_core_reg(output(0), address)?;
+            self.interface.write_core_reg(output(1), len)?;
+            // Run with traps disabled, so no interrupt handler runs in between.
+            self.write_special_reg(IuSpecialReg::PSR, saved_psr & !(1 << 5))?;
+            self.write_special_reg(IuSpecialReg::PC, scratch)?;
+            self.write_special_reg(IuSpecialReg::NPC, routine_npc)?;
+            self.interface.modify_dsu_ctrl(|ctrl| ctrl.set_bs(true))?;
+
+            self.resume_core()?;
+            match self.wait_for_core_halted(timeout) {
+                Ok(()) if self.in_error_mode()? => {
+                    let pc = self.read_special_reg(IuSpecialReg::PC)?;
+                    self.clear_error_mode()?;
+                    Err(Leon3Error::RoutineFailed { pc }.into())
+                }
+                Ok(()) => match self.read_special_reg(IuSpecialReg::PC)? {
+                    pc if pc == routine_end => Ok(!self.interface.read_core_reg(output(2))?),
+                    pc => Err(Leon3Error::RoutineFailed { pc }.into()),
+                },
+                Err(error) => {
+                    // Stop the routine. The state is restored below even if that fails.
+                    let core_index = self.core_index;
+                    if let Err(halt_error) = self
+                        .set_break_now(&[core_index], true)
+                        .and_then(|()| self.wait_for_core_halted(timeout))
+                    {
+                        tracing::warn!("Failed to halt the CRC-32 routine: {halt_error}");
+                    }
+                    Err(error)
+                }
+            }
+        };
+        let result = run_routine();
+
+        self.state.cwp = None;
+        self.interface
+            .modify_dsu_ctrl(|ctrl| ctrl.set_bs(saved_ctrl.bs()))?;
+        for (n, value) in saved_outputs.into_iter().enumerate() {
+            self.interface.write_core_reg(output(n as u8), value)?;
+        }
+        self.write_special_reg(IuSpecialReg::PSR, saved_psr)?;
+        self.write_special_reg(IuSpecialReg::PC, saved_pc)?;
+        self.write_special_reg(IuSpecialReg::NPC, saved_npc)?;
+        self.write_32(scratch.into(), &saved_scratch)?;
+        self.invalidate_caches()?;
+        self.state.debugger_halt_reason = saved_halt_reason;
+        result
+    }
+
+    /// Identify the CFI flash in the PROM area. The core must be halted.
+    pub fn query_prom(&mut self) -> Result<CfiInfo, crate::Error> {
+        self.with_prom(|prom, _| Ok(prom.info().clone()))
+    }
+
+    /// The erase sectors of the PROM overlapping `range`, up to the end of the CFI flash.
+    /// MRAM and EEPROM have no sectors, `range` is returned as a single one. The core must be
+    /// halted.
+    pub fn prom_sectors(&mut self, range: Range<u64>) -> Result<Vec<Range<u64>>, crate::Error> {
+        if let Some(flash) = SpiFlash::find(&mut self.interface, range.start) {
+            return self.with_spi_flash(flash, |flash, _| flash.sectors(range));
+        }
+        if DirectProm::new(&self.state.prom_memory).is_some() {
+            return Ok(vec![range]);
+        }
+        self.with_prom(|prom, _| {
+            let info = prom.info();
+            let end = range.end.min(prom.base() + info.size);
+            if range.start >= end {
+                return Ok(vec![]);
+            }
+            prom.sectors(range.start..end)
+        })
+    }
+
+    /// Erase the PROM sectors overlapping `range`. MRAM and EEPROM are erased by filling
+    /// `range` with `0xFF`. The core must be halted.
+    pub fn erase_prom(&mut self, range: Range<u64>) -> Result<(), crate::Error> {
+        self.erase_prom_with_progress(range, |_| {})
+    }
+
+    /// Like [`Leon3::erase_prom`], but reports each erased sector, or each filled chunk of
+    /// MRAM and EEPROM, to `progress`.
+    pub fn erase_prom_with_progress(
+        &mut self,
+        range: Range<u64>,
+        mut progress: impl FnMut(PromProgress),
+    ) -> Result<(), crate::Error> {
+        if range.end < range.start {
+            return Err(Leon3Error::InvertedRange {
+                start: range.start,
+                end: range.end,
+            }
+            .into());
+        }
+        if let Some(flash) = SpiFlash::find(&mut self.interface, range.start) {
+            self.with_spi_flash(flash, |flash, interface| {
+                erase_sectors(flash, interface, range, progress)
+            })?;
+            return self.invalidate_caches();
+        }
+        if let Some(prom) = DirectProm::new(&self.state.prom_memory) {
+            // Fill the range chunk by chunk, so that the fill pattern does not need to cover
+            // all of it.
+            let erased = [0xFF; PROGRESS_CHUNK as usize];
+            self.with_prom_writes(|interface, _, _, bus_width| {
+                for chunk in progress_chunks(range.start, (range.end - range.start) as usize) {
+                    let len = (chunk.end - chunk.start) as usize;
+                    prom.program(interface, bus_width, chunk.start, &erased[..len])?;
+                    progress(PromProgress::Erased(len as u64));
+                }
+                Ok(())
+            })?;
+            return self.invalidate_caches();
+        }
+        self.with_prom(|prom, interface| erase_sectors(prom, interface, range, progress))?;
+        self.invalidate_caches()
+    }
+
+    /// Program `data` into the memory in the PROM area, at the physical `address`. The core
+    /// must be halted.
+    ///
+    /// MRAM and EEPROM are written without erasing, so `options` only apply to CFI flash.
+    pub fn write_prom(
+        &mut self,
+        address: u64,
+        data: &[u8],
+        options: PromWriteOptions,
+    ) -> Result<(), crate::Error> {
+        self.write_prom_with_progress(address, data, options, |_| {})
+    }
+
+    /// Like [`Leon3::write_prom`], but reports each erased sector and each programmed chunk
+    /// of `data` to `progress`.
+    pub fn write_prom_with_progress(
+        &mut self,
+        address: u64,
+        data: &[u8],
+        options: PromWriteOptions,
+        mut progress: impl FnMut(PromProgress),
+    ) -> Result<(), crate::Error> {
+        if let Some(flash) = SpiFlash::find(&mut self.interface, address) {
+            self.with_spi_flash(flash, |flash, interface| {
+                write_sectors(flash, interface, address, data, options, progress)
+            })?;
+            return self.invalidate_caches();
+        }
+        if self.write_direct_prom(address, data, &mut progress)? {
+            return Ok(());
+        }
+        self.with_prom(|prom, interface| {
+            write_sectors(prom, interface, address, data, options, progress)
+        })?;
+        // The caches may hold the old contents of the PROM.
+        self.invalidate_caches()
+    }
+
+    /// Flush the caches, which makes earlier writes through this core visible to the
+    /// instruction fetch.
+    fn invalidate_caches(&mut self) -> Result<(), crate::Error> {
+        self.interface.flush_caches()?;
+        self.state.stale_code.clear();
+        Ok(())
+    }
+
+    /// Remember a write through this core if it may have changed code.
+    ///
+    /// Without executable memory regions in the target description, every write counts.
+    fn record_write(&mut self, address: u64, len: usize) {
+        let range = address..address.saturating_add(len as u64);
+        if range.is_empty() {
+            return;
+        }
+        let code = &self.state.code_ranges;
+        if !code.is_empty()
+            && !code
+                .iter()
+                .any(|code| code.start < range.end && range.start < code.end)
+        {
+            return;
+        }
+        match self.state.stale_code.last_mut() {
+            Some(last) if last.end == range.start => last.end = range.end,
+            _ => self.state.stale_code.push(range),
+        }
+    }
+
+    /// Invalidate the instruction cache lines of code written through this core, so the
+    /// core does not execute stale instructions.
+    fn invalidate_stale_code(&mut self) -> Result<(), crate::Error> {
+        if self.state.stale_code.is_empty() {
+            return Ok(());
+        }
+        let ranges = std::mem::take(&mut self.state.stale_code);
+        if self.mmu_context()?.is_some() {
+            // The lines are tagged with virtual addresses of any context.
+            return self.interface.flush_instruction_cache();
+        }
+        self.interface.invalidate_instruction_lines(&ranges)
+    }
+
+    /// Read the configuration of the instruction or data cache.
+    ///
+    /// The core must be halted.
+    pub fn cache_configuration(
+        &mut self,
+        kind: CacheKind,
+    ) -> Result<CacheConfiguration, crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        self.interface.cache_configuration(kind)
+    }
+
+    /// Read the tags and data of all lines of the instruction or data cache.
+    ///
+    /// The core must be halted.
+    pub fn read_cache(&mut self, kind: CacheKind) -> Result<CacheSnapshot, crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        self.interface.read_cache(kind)
+    }
+
+    /// The APBUARTs of the system, in plug&play order, which can be used as virtual consoles
+    /// with [`ApbUart::enable_console`].
+    pub fn apb_uarts(&mut self) -> Vec<ApbUart> {
+        ApbUart::find_all(&mut self.interface)
+    }
+
+    /// The L3STAT and L4STAT statistics units of the system, in plug&play order.
+    ///
+    /// The counters can be read while the core runs, through the memory interface of any core.
+    pub fn statistics_units(&mut self) -> Result<Vec<StatisticsUnit>, crate::Error> {
+        StatisticsUnit::find_all(&mut self.interface)
+    }
+
+    /// Read the DSU time tag counter.
+    ///
+    /// The counter counts clock cycles while any core is running, and is stored with every trace
+    /// buffer entry. Use [`trace::time_tag_elapsed`] to compute the cycles between two time tags,
+    /// for example between two halts.
+    pub fn read_time_tag(&mut self) -> Result<u32, crate::Error> {
+        let time_tag: DsuTimeTag = self.interface.read_shared_dsu_reg()?;
+        Ok(time_tag.value())
+    }
+
+    /// Reset the DSU time tag counter to zero.
+    pub fn reset_time_tag(&mut self) -> Result<(), crate::Error> {
+        self.interface
+            .modify_shared_dsu_reg(|time_tag: &mut DsuTimeTag| time_tag.set_value(0))
+    }
+
+    /// Read the time tag counter and return a [`TraceClock`] that converts the time tags of
+    /// trace entries to wall-clock time.
+    ///
+    /// The frequency of the system clock is taken from `frequency`, or derived from the
+    /// prescaler of the first GPTIMER unit, which firmware sets up for timer ticks at 1 MHz.
+    /// The time tag stops while all cores are in debug mode, so the wall-clock time of the
+    /// reference is only exact while a core runs, or right after the cores halted.
+    pub fn trace_clock(&mut self, frequency: Option<u64>) -> Result<TraceClock, crate::Error> {
+        let frequency = match frequency {
+            Some(frequency) => frequency,
+            None => gptimer::system_clock_frequency(&mut self.interface, 1_000_000)?
+                .ok_or(Leon3Error::DeviceNotFound("GPTIMER"))?,
+        };
+        let time_tag = self.read_time_tag()?;
+        Ok(TraceClock::new(time_tag, SystemTime::now(), frequency))
+    }
+
+    /// Enable or disable recording of AHB transfers in the AHB trace buffer.
+    ///
+    /// The AHB trace buffer is shared by all cores and records the transfers of all bus masters.
+    pub fn set_ahb_trace(&mut self, enabled: bool) -> Result<(), crate::Error> {
+        self.interface
+            .modify_shared_dsu_reg(|control: &mut AhbTraceControl| control.set_en(enabled))
+    }
+
+    /// Configure an AHB watchpoint to trigger on transfers to `size` bytes starting at `address`.
+    ///
+    /// A hit is marked in the AHB trace, and can stop tracing or halt the cores, see
+    /// [`Leon3::set_ahb_trace_delay`] and [`Leon3::set_ahb_trace_break`]. Since the address is
+    /// matched using a mask, `size` must be a power of two of at least 4 and `address` must be
+    /// aligned to it.
+    pub fn set_ahb_watchpoint(
+        &mut self,
+        unit_index: usize,
+        address: u64,
+        size: u64,
+        kind: WatchpointKind,
+    ) -> Result<(), crate::Error> {
+        if unit_index >= AHB_WATCHPOINTS {
+            return Err(Leon3Error::InvalidBreakpointUnit(unit_index).into());
+        }
+        let invalid = || Leon3Error::UnsupportedWatchpoint { address, size };
+        let address = u32::try_from(address).map_err(|_| invalid())?;
+        let size = u32::try_from(size).map_err(|_| invalid())?;
+        if !size.is_power_of_two() || size < 4 || address % size != 0 {
+            return Err(invalid().into());
+        }
+
+        let access = match kind {
+            WatchpointKind::Read => WMASK_DL,
+            WatchpointKind::Write => WMASK_DS,
+            WatchpointKind::ReadWrite => WMASK_DL | WMASK_DS,
+        };
+        self.interface.write_ahb_watchpoint(
+            unit_index,
+            address,
+            (!(size - 1) & WATCHPOINT_ADDRESS_MASK) | access,
+        )
+    }
+
+    /// Disable the AHB watchpoint of the given unit.
+    pub fn clear_ahb_watchpoint(&mut self, unit_index: usize) -> Result<(), crate::Error> {
+        if unit_index >= AHB_WATCHPOINTS {
+            return Err(Leon3Error::InvalidBreakpointUnit(unit_index).into());
+        }
+        self.interface.write_ahb_watchpoint(unit_index, 0, 0)
+    }
+
+    /// Select which AHB transfers are recorded in the AHB trace buffer.
+    ///
+    /// To only trace an address range, configure it with [`Leon3::set_ahb_watchpoint`] for unit
+    /// index 1 and set [`AhbTraceFilter::watchpoint_range_only`].
+    pub fn set_ahb_trace_filter(&mut self, filter: AhbTraceFilter) -> Result<(), crate::Error> {
+        self.interface
+            .modify_shared_dsu_reg(|control: &mut AhbTraceFilterControl| {
+                control.set_fr(!filter.reads);
+                control.set_fw(!filter.writes);
+                control.set_af(filter.watchpoint_range_only);
+            })?;
+        self.interface
+            .modify_shared_dsu_reg(|mask: &mut AhbTraceFilterMask| {
+                mask.set_mmask(filter.ignored_masters);
+                mask.set_smask(filter.ignored_slaves);
+            })
+    }
+
+    /// Halt the cores when an AHB watchpoint hits.
+    pub fn set_ahb_trace_break(&mut self, enabled: bool) -> Result<(), crate::Error> {
+        self.interface
+            .modify_shared_dsu_reg(|control: &mut AhbTraceControl| control.set_br(enabled))
+    }
+
+    /// Stop AHB tracing `transfers` transfers after an AHB watchpoint hit, or keep tracing if
+    /// `None`.
+    ///
+    /// This captures the bus activity around the watched access.
+    pub fn set_ahb_trace_delay(&mut self, transfers: Option<u16>) -> Result<(), crate::Error> {
+        self.interface
+            .modify_shared_dsu_reg(|control: &mut AhbTraceControl| {
+                control.set_dm(transfers.is_some());
+                control.set_dcnt(transfers.unwrap_or(0));
+            })
+    }
+
+    /// Read the AHB trace buffer, oldest transfer first.
+    ///
+    /// Returns an empty list if the DSU has no AHB trace buffer.
+    pub fn read_ahb_trace(&mut self) -> Result<Vec<AhbTraceEntry>, crate::Error> {
+        self.interface.read_ahb_trace()
+    }
+
+    /// Enable or disable read-back verification of register writes.
+    ///
+    /// See [`Leon3CommunicationInterface::set_write_verify`].
+    pub fn set_write_verify(&mut self, enabled: bool) {
+        self.interface.set_write_verify(enabled);
+    }
+
+    /// Read and decode the DSU registers relevant to this core, for example to attach them to
+    /// a bug report.
+    ///
+    /// The watchpoint registers are only included while the core is halted.
+    pub fn dsu_report(&mut self) -> Result<DsuReport, crate::Error> {
+        let ctrl: DsuCtrl = self.interface.read_dsu_reg()?;
+        let dtr: DsuDtr = self.interface.read_dsu_reg()?;
+        let brss: DsuBrss = self.interface.read_shared_dsu_reg()?;
+        let dbgm: DsuDbgm = self.interface.read_shared_dsu_reg()?;
+        let time_tag: DsuTimeTag = self.interface.read_shared_dsu_reg()?;
+        let ahb_trace_control: AhbTraceControl = self.interface.read_shared_dsu_reg()?;
+
+        let watchpoints = if self.core_halted()? {
+            let mut watchpoints = Vec::new();
+            for unit_index in 0..self.available_breakpoint_units()? as usize {
+                let (waddr, wmask) = Self::watchpoint_registers(unit_index);
+                watchpoints.push(WatchpointState {
+                    address: self.read_special_reg(waddr)?,
+                    mask: self.read_special_reg(wmask)?,
+                });
+            }
+            Some(watchpoints)
+        } else {
+            None
+        };
+
+        Ok(DsuReport {
+            core_index: self.core_index,
+            ctrl: ctrl.into(),
+            brss: brss.into(),
+            dbgm: dbgm.into(),
+            dtr: dtr.into(),
+            time_tag: time_tag.into(),
+            ahb_trace_control: ahb_trace_control.into(),
+            watchpoints,
+        })
+    }
+
+    /// Returns whether the core is in error mode.
+    ///
+    /// A LEON3 enters error mode when a trap occurs while traps are disabled, unless break on
+    /// error (BE) is set in the DSU control register. The core can only leave error mode through
+    /// a reset or [`Leon3::clear_error_mode`].
+    pub fn in_error_mode(&mut self) -> Result<bool, crate::Error> {
+        let ctrl: DsuCtrl = self.interface.read_dsu_reg()?;
+        Ok(ctrl.pe())
+    }
+
+    /// Take the core out of error mode and leave it halted in debug mode.
+    ///
+    /// The trap that caused the error mode can be read using [`Leon3::trap_type`] before calling
+    /// this. Registers keep their values, so the core state can be inspected afterwards.
+    pub fn clear_error_mode(&mut self) -> Result<CoreInformation, crate::Error> {
+        if !self.in_error_mode()? {
+            return self.interface.core_info();
+        }
+        self.state.cwp = None;
+        self.state.mmu_context = None;
+
+        let core_index = self.core_index;
+        self.interface.modify_dsu_reg(|reg: &mut DsuBrss| {
+            reg.set_bn(core_index, true);
+        })?;
+        self.interface.modify_dsu_ctrl(|reg| {
+            reg.set_pe(true);
+            reg.set_hl(false);
+        })?;
+        self.wait_for_core_halted(Duration::from_millis(100))?;
+
+        if self.in_error_mode()? {
+            return Err(Leon3Error::ErrorMode.into());
+        }
+        self.state.debugger_halt_reason = Some(HaltReason::Request);
+        self.interface.core_info()
+    }
+
+    /// Returns the SPARC trap type that made the core enter debug mode.
+    pub fn trap_type(&mut self) -> Result<u8, crate::Error> {
+        let dtr: DsuDtr = self.interface.read_dsu_reg()?;
+        Ok(dtr.traptype())
+    }
+
+    /// Reads the trap table the trap base register points to and decodes the handler of
+    /// each entry.
+    ///
+    /// The table also tells the type of the last trap the core took, from the TBR, and of
+    /// the trap that made it enter debug mode. Use [`TrapTable::resolve_symbols`] to name
+    /// the handlers. The core must be halted.
+    pub fn read_trap_table(&mut self) -> Result<TrapTable, crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        let tbr = self.read_special_reg(IuSpecialReg::TBR)?;
+        let base = tbr & 0xFFFF_F000;
+        let mut words = vec![0; TRAP_TABLE_ENTRIES * TRAP_ENTRY_WORDS];
+        self.read_32(u64::from(base), &mut words)?;
+
+        let entries = words
+            .chunks_exact(TRAP_ENTRY_WORDS)
+            .enumerate()
+            .map(|(trap_type, instructions)| {
+                TrapTableEntry::new(
+                    trap_type as u8,
+                    base + (trap_type * TRAP_ENTRY_WORDS * 4) as u32,
+                    instructions.try_into().unwrap(),
+                )
+            })
+            .collect();
+        Ok(TrapTable {
+            base,
+            last_trap_type: ((tbr >> 4) & 0xFF) as u8,
+            debug_trap_type: self.trap_type()?,
+            entries,
+        })
+    }
+
+    /// Returns the floating-point exception pending in the FPU, if any.
+    ///
+    /// When the core halts on an fp_exception trap, this tells which kind of exception the
+    /// failed floating-point instruction raised. Returns `None` if no FPU is present. The core
+    /// must be halted.
+    pub fn fpu_exception(&mut self) -> Result<Option<FpuException>, crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        if !self.state.fpu_present {
+            return Ok(None);
+        }
+        let fsr: Fsr = self.interface.read_dsu_reg()?;
+        Ok(FpuException::from_fsr(fsr))
+    }
+
+    /// Returns whether the core has a SPARC reference MMU.
+    ///
+    /// The core must be halted when this is called for the first time.
+    pub fn has_mmu(&mut self) -> Result<bool, crate::Error> {
+        if !self.state.configuration_read {
+            if !self.core_halted()? {
+                return Err(Leon3Error::CoreNotHalted.into());
+            }
+            self.read_configuration()?;
+        }
+        Ok(self.state.mmu_present)
+    }
+
+    /// Read the MMU control register. Returns `None` if the core has no MMU. The core must be
+    /// halted.
+    pub fn mmu_control(&mut self) -> Result<Option<MmuCtrl>, crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        if !self.has_mmu()? {
+            return Ok(None);
+        }
+        let word = self
+            .interface
+            .read_asi(ASI_MMU_REGISTERS, MmuCtrl::ADDRESS_OFFSET as u32)?;
+        Ok(Some(MmuCtrl(word)))
+    }
+
+    /// Returns whether the core runs with address translation enabled, in which case the
+    /// addresses used by the program are virtual addresses. The core must be halted.
+    pub fn address_translation_enabled(&mut self) -> Result<bool, crate::Error> {
+        Ok(self.mmu_control()?.is_some_and(|ctrl| ctrl.e()))
+    }
+
+    /// Bypass the MMU, so memory accesses through this core use physical addresses even
+    /// while address translation is enabled.
+    ///
+    /// By default, addresses are translated with the page tables of the current MMU context
+    /// while the core is halted.
+    pub fn set_mmu_bypass(&mut self, bypass: bool) {
+        self.state.mmu_bypass = bypass;
+    }
+
+    /// Translate a virtual address with the page tables of the current MMU context.
+    ///
+    /// Returns the address unchanged if address translation is disabled or bypassed, or if
+    /// the core is running.
+    pub fn virtual_to_physical(&mut self, address: u32) -> Result<u32, crate::Error> {
+        match self.mmu_context()? {
+            Some(context) => Ok(self.translate(context, address)?.physical_address),
+            None => Ok(address),
+        }
+    }
+
+    /// The MMU context memory accesses are translated with, or `None` if addresses are
+    /// physical.
+    ///
+    /// The MMU registers can only be read in debug mode, so memory accesses use physical
+    /// addresses while the core is running.
+    fn mmu_context(&mut self) -> Result<Option<MmuContext>, crate::Error> {
+        if self.state.mmu_bypass || (self.state.configuration_read && !self.state.mmu_present) {
+            return Ok(None);
+        }
+        if let Some(context) = self.state.mmu_context {
+            return Ok(context);
+        }
+        if !self.core_halted()? {
+            return Ok(None);
+        }
+
+        let context = if self.address_translation_enabled()? {
+            let context = self.read_mmu_context()?;
+            tracing::debug!("Translating addresses with {context:x?}");
+            context
+        } else {
+            None
+        };
+        self.state.mmu_context = Some(context);
+        Ok(context)
+    }
+
+    /// Read the context table pointer and context registers. Returns `None` if the core has
+    /// no MMU. The core must be halted.
+    ///
+    /// To inspect another context, change the `context` of the result.
+    pub fn read_mmu_context(&mut self) -> Result<Option<MmuContext>, crate::Error> {
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        if !self.has_mmu()? {
+            return Ok(None);
+        }
+        let ctx_ptr = self
+            .interface
+            .read_asi(ASI_MMU_REGISTERS, MmuCtxPtr::ADDRESS_OFFSET as u32)?;
+        let ctx = self
+            .interface
+            .read_asi(ASI_MMU_REGISTERS, MmuCtx::ADDRESS_OFFSET as u32)?;
+        Ok(Some(MmuContext::new(MmuCtxPtr(ctx_ptr), MmuCtx(ctx))))
+    }
+
+    /// Translate a virtual address with the page tables of `context`.
+    ///
+    /// Unlike [`Leon3::virtual_to_physical`], this also works while address translation is
+    /// disabled or for contexts other than the current one.
+    pub fn translate(
+        &mut self,
+        context: MmuContext,
+        address: u32,
+    ) -> Result<Translation, crate::Error> {
+        let memory = self.interface.as_memory_interface_mut();
+        context.translate(address, |physical| memory.read_word_32(physical.into()))
+    }
+
+    /// List all pages mapped by the page tables of `context`, ordered by virtual address.
+    pub fn page_mappings(&mut self, context: MmuContext) -> Result<Vec<PageMapping>, crate::Error> {
+        let memory = self.interface.as_memory_interface_mut();
+        context.mappings(|physical, data| memory.read_32(physical.into(), data))
+    }
+
+    /// Split the `len` bytes at the virtual `address` into the physical ranges they are
+    /// mapped to, as the physical address and the byte range within the access.
+    fn physical_ranges(
+        &mut self,
+        address: u64,
+        len: usize,
+    ) -> Result<Vec<(u64, Range<usize>)>, crate::Error> {
+        let Some(context) = self.mmu_context()? else {
+            return Ok(vec![(address, 0..len)]);
+        };
+
+        let mut ranges = Vec::new();
+        let mut offset = 0;
+        while offset < len {
+            let virtual_address = valid_32bit_address(address + offset as u64)?;
+            let translation = self.translate(context, virtual_address)?;
+            let to_region_end =
+                translation.region_size - u64::from(virtual_address) % translation.region_size;
+            let range_len = usize::try_from(to_region_end).map_or(len - offset, |to_region_end| {
+                to_region_end.min(len - offset)
+            });
+            ranges.push((
+                u64::from(translation.physical_address),
+                offset..offset + range_len,
+            ));
+            offset += range_len;
+        }
+        Ok(ranges)
+    }
+
+    /// Get a powered-down core into debug mode after break-now was requested.
+    ///
+    /// A core in power-down mode only wakes up on an interrupt, so if the break-now request
+    /// has no effect, the non-maskable interrupt is forced through the interrupt controller
+    /// until the core has entered debug mode. The interrupt is withdrawn before it is taken.
+    fn wake_from_power_down(&mut self, timeout: Duration) -> Result<(), crate::Error> {
+        if self
+            .interface
+            .wait_for_core_halted(POWER_DOWN_WAKE_TIMEOUT)
+            .is_ok()
+        {
+            return Ok(());
+        }
+
+        tracing::debug!("Waking core {} from power-down", self.core_index);
+        let Some(irqmp) = Irqmp::find(&mut self.interface)? else {
+            return Err(Leon3Error::PowerDown.into());
+        };
+        let mask = irqmp.force_nmi(&mut self.interface)?;
+        let halted = self.interface.wait_for_core_halted(timeout);
+        irqmp.release_nmi(&mut self.interface, mask)?;
+        halted
+    }
+
+    /// Determine why the core entered debug mode from the DSU trap register.
+    fn decode_halt_reason(&mut self, ctrl: DsuCtrl) -> Result<HaltReason, crate::Error> {
+        if let Some(reason) = self.state.debugger_halt_reason {
+            return Ok(reason);
+        }
+
+        if let Some(command) = self.state.semihosting_command {
+            return Ok(HaltReason::Breakpoint(BreakpointCause::Semihosting(
+                command,
+            )));
+        }
+
+        let dtr: DsuDtr = self.interface.read_dsu_reg()?;
+        let reason = match dtr.traptype() {
+            TT_SOFTWARE_BREAKPOINT => {
+                let pc = self.read_special_reg(IuSpecialReg::PC)?;
+                if Some(pc) == self.state.semihosting_entry {
+                    let command = self.decode_semihosting_call()?;
+                    self.state.semihosting_command = Some(command);
+                    return Ok(HaltReason::Breakpoint(BreakpointCause::Semihosting(
+                        command,
+                    )));
+                }
+                if !self.state.sw_breakpoints.contains_key(&pc) {
+                    let core_index = self.core_index;
+                    tracing::debug!(
+                        "Core {core_index} halted on a breakpoint in the program at {}",
+                        self.describe_pc()
+                    );
+                }
+                HaltReason::Breakpoint(BreakpointCause::Software)
+            }
+            TT_WATCHPOINT_DETECTED => {
+                // Instruction breakpoints, data watchpoints and break-now requests all use
+                // the same trap. A pending break-now request does not mean that it caused the
+                // halt, so the watchpoints are checked first.
+                let pc = self.read_special_reg(IuSpecialReg::PC)?;
+                if self.hw_breakpoint_at(pc)? {
+                    HaltReason::Breakpoint(BreakpointCause::Hardware)
+                } else if let Some(hit) = self.matching_watchpoint()? {
+                    tracing::debug!(
+                        "Core {} halted on watchpoint {} by the access to {:#010x} at {pc:#010x}",
+                        self.core_index,
+                        hit.unit_index,
+                        hit.address
+                    );
+                    HaltReason::Watchpoint
+                } else if self
+                    .interface
+                    .read_dsu_reg::<DsuBrss>()?
+                    .bn(self.core_index)
+                {
+                    HaltReason::Request
+                } else if ctrl.eb() {
+                    HaltReason::External
+                } else if self.state.watchpoint_units.contains(&WatchpointUnit::Data) {
+                    HaltReason::Watchpoint
+                } else if self.halts_with_other_cores()? {
+                    // Most likely forced into debug mode because another core halted.
+                    HaltReason::External
+                } else {
+                    HaltReason::Unknown
+                }
+            }
+            TT_FP_EXCEPTION => {
+                let exception = self.fpu_exception()?;
+                tracing::debug!(
+                    "Core {} halted on floating-point exception: {exception:?}",
+                    self.core_index
+                );
+                HaltReason::Exception
+            }
+            traptype => {
+                let core_index = self.core_index;
+                tracing::debug!(
+                    "Core {core_index} halted on trap {traptype:#04x} ({}) at {}",
+                    trap_name(traptype),
+                    self.describe_pc()
+                );
+                HaltReason::Exception
+            }
+        };
+        if let HaltReason::Breakpoint(_) = reason {
+            let pc = self.read_special_reg(IuSpecialReg::PC)?;
+            let npc = self.read_special_reg(IuSpecialReg::NPC)?;
+            if npc != pc.wrapping_add(4) {
+                tracing::debug!(
+                    "Core {} halted in a delay slot at {pc:#010x}, continuing at {npc:#010x}",
+                    self.core_index
+                );
+            }
+        }
+        Ok(reason)
+    }
+
+    /// Set the software trap number of semihosting calls, or disable semihosting.
+    ///
+    /// The core halts on the trap table entry of `ta n` once it resumes, so that the call can
+    /// be serviced. Overrides the `semihosting_trap` core access option.
+    pub fn set_semihosting_trap(&mut self, trap: Option<u8>) -> Result<(), crate::Error> {
+        self.state.semihosting_trap = trap.map(|trap| trap & 0x7F);
+        if let Some(entry) = self.state.semihosting_entry.take() {
+            self.clear_sw_breakpoint(entry.into())?;
+        }
+        Ok(())
+    }
+
+    /// Set the semihosting breakpoint on the trap table entry of the semihosting trap.
+    ///
+    /// The trap table may have been moved since the core last ran, so the entry is found from
+    /// the current TBR.
+    fn arm_semihosting(&mut self) -> Result<(), crate::Error> {
+        let entry = match self.state.semihosting_trap {
+            Some(trap) => {
+                let tbr = self.read_special_reg(IuSpecialReg::TBR)?;
+                Some(entry_address(tbr, 0x80 | trap))
+            }
+            None => None,
+        };
+        if entry == self.state.semihosting_entry {
+            return Ok(());
+        }
+        if let Some(previous) = self.state.semihosting_entry.take() {
+            self.clear_sw_breakpoint(previous.into())?;
+        }
+        if let Some(entry) = entry {
+            match self.set_sw_breakpoint(entry.into()) {
+                Ok(()) => self.state.semihosting_entry = Some(entry),
+                Err(error) => tracing::warn!(
+                    "Semihosting is unavailable, no breakpoint can be set on the trap table entry at {entry:#010x}: {error}"
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// Decode the semihosting call the core halted on at the trap table entry.
+    ///
+    /// The trap has already been taken, so it is returned from first, like `rett` would: the
+    /// window of the caller is restored, S is restored from PS, traps are enabled again, and
+    /// PC and nPC are moved past the `ta`. The operation and its parameter are then in %o0 and
+    /// %o1 of the caller, which also receives the result in %o0.
+    fn decode_semihosting_call(&mut self) -> Result<SemihostingCommand, crate::Error> {
+        let psr = self.read_special_reg(IuSpecialReg::PSR)?;
+        // %l2 of the trap window holds the nPC of the `ta`.
+        let npc = self.read_core_reg(Leon3RegisterId::IuCore(IuCoreReg::L(2)).into())?;
+        let npc: u32 = npc.try_into()?;
+
+        let num_windows = self.interface.num_windows()?;
+        let psr = psr_after_return(psr, num_windows);
+        self.write_core_reg(
+            Leon3RegisterId::IuSpecial(IuSpecialReg::PSR).into(),
+            RegisterValue::U32(psr),
+        )?;
+        self.write_special_reg(IuSpecialReg::PC, npc)?;
+        self.write_special_reg(IuSpecialReg::NPC, npc.wrapping_add(4))?;
+
+        let command = decode_semihosting_syscall(self)?;
+        tracing::debug!(
+            "Core {} made semihosting call {command:?} at {:#010x}",
+            self.core_index,
+            npc.wrapping_sub(4)
+        );
+        Ok(command)
+    }
+
+    /// The program counter and the disassembled instruction it points to, for diagnostics.
+    fn describe_pc(&mut self) -> String {
+        let Ok(pc) = self.read_special_reg(IuSpecialReg::PC) else {
+            return "an unknown address".to_string();
+        };
+        match self.read_word_32(pc.into()) {
+            Ok(word) => format!("{pc:#010x}: {}", disassemble(word, pc)),
+            Err(_) => format!("{pc:#010x}"),
+        }
+    }
+
+    /// Set a software breakpoint by replacing the instruction at `address` with `ta 1`.
+    ///
+    /// Unlike hardware breakpoints, the number of software breakpoints is unlimited, but they can
+    /// only be placed in writable memory.
+    pub fn set_sw_breakpoint(&mut self, address: u64) -> Result<(), crate::Error> {
+        let address = u32::try_from(address)
+            .map_err(|_| Leon3Error::UnsupportedBreakpointAddress(address))?;
+        if !address.is_multiple_of(4) {
+            return Err(MemoryNotAlignedError {
+                address: address.into(),
+                alignment: 4,
+            }
+            .into());
+        }
+        if self.state.sw_breakpoints.contains_key(&address) {
+            return Ok(());
+        }
+
+        let original = self.read_word_32(address.into())?;
+        self.write_word_32(address.into(), TA_1)?;
+        if self.read_word_32(address.into())? != TA_1 {
+            self.write_word_32(address.into(), original)?;
+            return Err(Leon3Error::SoftwareBreakpointFailed(address).into());
+        }
+        self.state.sw_breakpoints.insert(address, original);
+        self.check_delay_slot_breakpoint(address);
+
+        self.interface.modify_dsu_ctrl(|reg| {
+            reg.set_bs(true);
+        })?;
+        // Make sure the core fetches the patched instruction.
+        self.invalidate_stale_code()
+    }
+
+    /// Report a breakpoint in the delay slot of a control transfer instruction.
+    ///
+    /// When the core halts there, nPC holds the branch target instead of the next address,
+    /// and is kept when resuming or stepping. A delay slot that gets annulled is skipped, so
+    /// its breakpoint does not trigger.
+    fn check_delay_slot_breakpoint(&mut self, address: u32) {
+        let Some(previous) = address.checked_sub(4) else {
+            return;
+        };
+        let word = match self.state.sw_breakpoints.get(&previous) {
+            Some(&original) => original,
+            None => match self.read_word_32(previous.into()) {
+                Ok(word) => word,
+                Err(_) => return,
+            },
+        };
+        if !Instruction::has_delay_slot(word) {
+            return;
+        }
+        let instruction = disassemble(word, previous);
+        if let Instruction::Branch { annul: true, .. } = Instruction::decode(word) {
+            tracing::warn!(
+                "The breakpoint at {address:#010x} is in the delay slot of `{instruction}`, which may annul it, so it may not trigger"
+            );
+        } else {
+            tracing::debug!(
+                "The breakpoint at {address:#010x} is in the delay slot of `{instruction}`"
+            );
+        }
+    }
+
+    /// Remove the software breakpoint at `address` and restore the original instruction.
+    pub fn clear_sw_breakpoint(&mut self, address: u64) -> Result<(), crate::Error> {
+        let Ok(address) = u32::try_from(address) else {
+            return Ok(());
+        };
+        let Some(&original) = self.state.sw_breakpoints.get(&address) else {
+            return Ok(());
+        };
+        // Only forget the breakpoint once the instruction is restored, so that a failed write
+        // leaves it tracked.
+        self.write_word_32(address.into(), original)?;
+        self.state.sw_breakpoints.remove(&address);
+
+        if self.state.sw_breakpoints.is_empty() && !self.state.catch_sw_breakpoint_traps {
+            // Let the application handle its own breakpoint traps again.
+            self.interface.modify_dsu_ctrl(|reg| {
+                reg.set_bs(false);
+            })?;
+        }
+        self.invalidate_stale_code()
+    }
+
+    /// Halt the core on the given traps instead of entering the trap handler.
+    pub fn enable_trap_catch(&mut self, catch: TrapCatch) -> Result<(), crate::Error> {
+        if catch == TrapCatch::SoftwareBreakpoints {
+            self.state.catch_sw_breakpoint_traps = true;
+        }
+        self.set_trap_catch(catch, true)
+    }
+
+    /// Let the trap handler handle the given traps again.
+    ///
+    /// Software breakpoint traps are still caught while software breakpoints are set.
+    pub fn disable_trap_catch(&mut self, catch: TrapCatch) -> Result<(), crate::Error> {
+        if catch == TrapCatch::SoftwareBreakpoints {
+            self.state.catch_sw_breakpoint_traps = false;
+            if !self.state.sw_breakpoints.is_empty() {
+                return Ok(());
+            }
+        }
+        self.set_trap_catch(catch, false)
+    }
+
+    /// Returns whether the given traps are caught.
+    pub fn trap_catch_enabled(&mut self, catch: TrapCatch) -> Result<bool, crate::Error> {
+        let ctrl: DsuCtrl = self.interface.read_dsu_reg()?;
+        Ok(match catch {
+            TrapCatch::AllTraps => ctrl.bx(),
+            TrapCatch::ErrorTraps => ctrl.bz(),
+            TrapCatch::ErrorMode => ctrl.be(),
+            TrapCatch::SoftwareBreakpoints => ctrl.bs(),
+        })
+    }
+
+    fn set_trap_catch(&mut self, catch: TrapCatch, enabled: bool) -> Result<(), crate::Error> {
+        self.interface.modify_dsu_ctrl(|ctrl| match catch {
+            TrapCatch::AllTraps => ctrl.set_bx(enabled),
+            TrapCatch::ErrorTraps => ctrl.set_bz(enabled),
+            TrapCatch::ErrorMode => ctrl.set_be(enabled),
+            TrapCatch::SoftwareBreakpoints => ctrl.set_bs(enabled),
+        })
+    }
+
+    /// Configure whether this core is forced into debug mode when another core enters it.
+    ///
+    /// Together with [`Leon3::set_halt_other_cores`] this makes all cores of a multi-core
+    /// system stop when one of them hits a breakpoint.
+    pub fn set_halt_with_other_cores(&mut self, enabled: bool) -> Result<(), crate::Error> {
+        let core_index = self.core_index;
+        self.interface
+            .modify_shared_dsu_reg(|dbgm: &mut DsuDbgm| dbgm.set_ed(core_index, enabled))
+    }
+
+    /// Returns whether this core is forced into debug mode when another core enters it.
+    pub fn halts_with_other_cores(&mut self) -> Result<bool, crate::Error> {
+        let dbgm: DsuDbgm = self.interface.read_shared_dsu_reg()?;
+        Ok(dbgm.ed(self.core_index))
+    }
+
+    /// Configure whether this core entering debug mode forces the other cores into debug mode.
+    ///
+    /// Only cores configured with [`Leon3::set_halt_with_other_cores`] are halted.
+    pub fn set_halt_other_cores(&mut self, enabled: bool) -> Result<(), crate::Error> {
+        let core_index = self.core_index;
+        self.interface
+            .modify_shared_dsu_reg(|dbgm: &mut DsuDbgm| dbgm.set_dm(core_index, !enabled))
+    }
+
+    /// Returns whether this core entering debug mode forces the other cores into debug mode.
+    pub fn halts_other_cores(&mut self) -> Result<bool, crate::Error> {
+        let dbgm: DsuDbgm = self.interface.read_shared_dsu_reg()?;
+        Ok(!dbgm.dm(self.core_index))
+    }
+
+    /// Remove all software breakpoints.
+    pub fn clear_all_sw_breakpoints(&mut self) -> Result<(), crate::Error> {
+        for address in self.sw_breakpoints() {
+            self.clear_sw_breakpoint(address)?;
+        }
+        self.state.semihosting_entry = None;
+        Ok(())
+    }
+
+    /// Returns the addresses of all software breakpoints.
+    pub fn sw_breakpoints(&self) -> Vec<u64> {
+        self.state
+            .sw_breakpoints
+            .keys()
+            .map(|&address| address.into())
+            .collect()
+    }
+
+    /// Clear the break-now bit of this core, which makes it leave debug mode.
+    fn resume_core(&mut self) -> Result<(), crate::Error> {
+        self.forget_halt_state();
+        let core_index = self.core_index;
+        self.set_break_now(&[core_index], false)
+    }
+
+    /// The address the core starts executing from after reset.
+    ///
+    /// Set by the `reset_address` core access option or [`Leon3::set_reset_address`], and
+    /// defaults to the reset address of the debug sequence.
+    pub fn reset_address(&self) -> u32 {
+        self.state
+            .reset_address
+            .unwrap_or_else(|| self.sequence.reset_address())
+    }
+
+    /// Override the address the core starts executing from after reset, for designs whose
+    /// reset vector is set at runtime.
+    pub fn set_reset_address(&mut self, address: u32) {
+        self.state.reset_address = Some(address);
+    }
+
+    /// Halt the core and put it back at the reset address with an emulated processor reset,
+    /// after attaching under reset.
+    pub(crate) fn halt_at_reset(&mut self) -> Result<(), crate::Error> {
+        self.forget_halt_state();
+        let reset_address = self.reset_address();
+        emulate_processor_reset(&mut self.interface, reset_address)?;
+        self.configure_memory_controller()?;
+        self.state.debugger_halt_reason = Some(HaltReason::Request);
+        self.read_configuration()
+    }
+
+    /// Reset the system with the `reset` core access option.
+    ///
+    /// The debug support unit is reset together with the board, so after a board reset the
+    /// core keeps running unless the external DSUBRE signal holds it in debug mode. If `halt`
+    /// is set, the core is then halted and put back at the reset address with an emulated
+    /// processor reset.
+    fn reset_system(&mut self, halt: bool) -> Result<(), crate::Error> {
+        let strategy = self.state.reset_strategy.clone();
+        let reset_address = self.reset_address();
+        if strategy == Leon3ResetStrategy::Sequence {
+            return self.sequence.reset_core(&mut self.interface, reset_address);
+        }
+
+        tracing::debug!("Resetting the board with {strategy:?}");
+        self.sequence
+            .reset_board(&mut self.interface, &strategy, reset_address)?;
+        self.interface.on_first_attach()?;
+
+        let ctrl: DsuCtrl = self.interface.read_dsu_reg()?;
+        if halt && !ctrl.eb() {
+            emulate_processor_reset(&mut self.interface, reset_address)?;
+        }
+        Ok(())
+    }
+
+    /// Run the `suppress_watchdog` and `on_halt` hooks of the debug sequence, once each time
+    /// the core halts.
+    fn on_halt(&mut self) -> Result<(), crate::Error> {
+        if !self.state.on_halt_done {
+            self.state.on_halt_done = true;
+            self.sequence.suppress_watchdog(&mut self.interface)?;
+            self.sequence.on_halt(&mut self.interface)?;
+        }
+        Ok(())
+    }
+
+    /// Reset the cached state that is only valid while the core is halted.
+    fn forget_halt_state(&mut self) {
+        self.state.on_halt_done = false;
+        self.state.debugger_halt_reason = None;
+        self.state.semihosting_command = None;
+        self.state.cwp = None;
+        self.state.mmu_context = None;
+    }
+
+    /// Set or clear the break-now bits of `cores` in a single write, so that the cores enter or
+    /// leave debug mode at the same time.
+    pub(crate) fn set_break_now(
+        &mut self,
+        cores: &[usize],
+        enabled: bool,
+    ) -> Result<(), crate::Error> {
+        self.interface.modify_shared_dsu_reg(|reg: &mut DsuBrss| {
+            for &core in cores {
+                reg.set_bn(core, enabled);
+            }
+        })
+    }
 
-use crate::{
-    CoreInterface, CoreStatus, HaltReason, RegisterId, RegisterValue,
-    architecture::leon3::{
-        communication_interface::Leon3CommunicationInterface,
-        dsu3::{DsuBrss, DsuCtrl},
-        registers::Leon3RegisterId,
-        sequences::Leon3DebugSequence,
-    },
-    memory::CoreMemoryInterface,
-};
+    /// Record that the debugger requested the halt, before break-now is set.
+    pub(crate) fn prepare_halt(&mut self) -> Result<(), crate::Error> {
+        if !self.core_halted()? {
+            self.state.debugger_halt_reason = Some(HaltReason::Request);
+        }
+        Ok(())
+    }
 
-pub mod ahbjtag;
-pub mod communication_interface;
-mod dsu3;
-mod plugnplay;
-pub mod registers;
-pub mod sequences;
+    /// Wait for the core to enter debug mode after break-now was set.
+    pub(crate) fn finish_halt(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<CoreInformation, crate::Error> {
+        let ctrl: DsuCtrl = self.interface.read_dsu_reg()?;
+        if ctrl.pw() {
+            self.wake_from_power_down(timeout)?;
+        }
+        self.wait_for_core_halted(timeout)?;
+        self.read_configuration()?;
+        self.interface.core_info()
+    }
 
-/// An interface to operate a LEON3 core.
-pub struct Leon3<'state> {
-    core_index: usize,
-    interface: Leon3CommunicationInterface<'state>,
-    state: &'state mut Leon3CoreState,
-    sequence: Arc<dyn Leon3DebugSequence>,
-}
+    /// Get the core ready to leave debug mode, before break-now is cleared.
+    ///
+    /// Returns `false` if the core is already running.
+    pub(crate) fn prepare_run(&mut self) -> Result<bool, crate::Error> {
+        if self.in_error_mode()? {
+            return Err(Leon3Error::ErrorMode.into());
+        }
+        // TODO(darsor): return error if in halt mode
+        if !self.interface.core_in_debug_mode()? {
+            // Already running.
+            return Ok(false);
+        }
 
-impl<'state> Leon3<'state> {
-    pub fn new(
-        // TODO(darsor): is this used?
-        core_index: usize,
-        interface: Leon3CommunicationInterface<'state>,
-        state: &'state mut Leon3CoreState,
-        sequence: Arc<dyn Leon3DebugSequence>,
-    ) -> Result<Self, crate::Error> {
-        let mut this = Self {
-            core_index,
-            interface,
-            state,
-            sequence,
+        // An instruction breakpoint triggers before the instruction is executed, so resuming
+        // at a breakpoint would immediately halt again. Step over it first.
+        let pc = self.read_special_reg(IuSpecialReg::PC)?;
+        if self.hw_breakpoint_at(pc)? || self.state.sw_breakpoints.contains_key(&pc) {
+            self.step()?;
+        }
+        self.arm_semihosting()?;
+
+        if self.sequence.snooping_unreliable() {
+            // Memory may have been written while halted.
+            self.invalidate_caches()?;
+        } else {
+            self.invalidate_stale_code()?;
+        }
+
+        self.forget_halt_state();
+        Ok(true)
+    }
+
+    /// Execute the instruction at `pc` with the single-step function of the DSU.
+    fn hardware_step(&mut self, pc: u32) -> Result<CoreInformation, crate::Error> {
+        // Disable breakpoints so that a breakpoint at the current PC does not trigger again.
+        let breakpoints_enabled = self.hw_breakpoints_enabled();
+        if breakpoints_enabled {
+            self.enable_breakpoints(false)?;
+        }
+
+        // Execute the original instruction instead of a software breakpoint at the current PC.
+        let sw_breakpoint = self.state.sw_breakpoints.get(&pc).copied();
+        let mut result = match sw_breakpoint {
+            Some(original) => self
+                .write_word_32(pc.into(), original)
+                .and_then(|()| self.invalidate_stale_code()),
+            None => Ok(()),
         };
 
-        if !this.state.initialized {
-            this.interface.on_first_attach();
-            this.state.initialized = true;
+        if result.is_ok() {
+            let core_index = self.core_index;
+            result = self
+                .interface
+                .modify_dsu_reg(|reg: &mut DsuBrss| {
+                    reg.set_ss(core_index, true);
+                    reg.set_bn(core_index, false);
+                })
+                .and_then(|()| {
+                    let halted = self.wait_for_core_halted(STEP_TIMEOUT);
+                    // Clear the step request and keep the core in debug mode, even if the step
+                    // failed.
+                    self.interface.modify_dsu_reg(|reg: &mut DsuBrss| {
+                        reg.set_ss(core_index, false);
+                        reg.set_bn(core_index, true);
+                    })?;
+                    halted
+                });
+        }
+
+        // Restore the breakpoints even if the step failed. The first error is reported.
+        if sw_breakpoint.is_some() {
+            let restored = self
+                .write_word_32(pc.into(), TA_1)
+                .and_then(|()| self.invalidate_stale_code());
+            result = result.and(restored);
         }
+        if breakpoints_enabled {
+            result = result.and(self.enable_breakpoints(true));
+        }
+        result?;
 
-        // TODO(darsor)
-        // this.on_attach()?;
+        self.state.debugger_halt_reason = Some(HaltReason::Step);
+        self.interface.core_info()
+    }
 
-        Ok(this)
+    /// Execute the instruction at `pc` by planting temporary software breakpoints at every
+    /// address execution can continue at, and running the core until it reaches one of them.
+    ///
+    /// Traps taken by the instruction, like window overflows, run to completion, unless the
+    /// instruction is a software trap whose trap number is known.
+    fn software_step(&mut self, pc: u32, npc: u32) -> Result<CoreInformation, crate::Error> {
+        let sw_breakpoint = self.state.sw_breakpoints.get(&pc).copied();
+        let word = match sw_breakpoint {
+            Some(original) => original,
+            None => self.read_word_32(pc.into())?,
+        };
+        let instruction = Instruction::decode(word);
+        let mut targets = instruction.successors(pc, npc);
+        if let Instruction::Trap {
+            cond,
+            number: Some(number),
+        } = instruction
+            && cond != 0
+        {
+            let tbr = self.read_special_reg(IuSpecialReg::TBR)?;
+            targets.push(entry_address(tbr, 0x80 | (number & 0x7F)));
+        }
+        // A branch to itself cannot be caught at its own address, and locations that are
+        // breakpoints already need no temporary one.
+        targets.retain(|&target| target != pc && !self.state.sw_breakpoints.contains_key(&target));
+        targets.sort_unstable();
+        targets.dedup();
+        tracing::debug!("Software stepping {pc:#010x}, stopping at {targets:#010x?}");
+
+        let ctrl: DsuCtrl = self.interface.read_dsu_reg()?;
+        let catch_sw_breakpoints = ctrl.bs();
+        let breakpoints_enabled = self.hw_breakpoints_enabled();
+        if breakpoints_enabled {
+            self.enable_breakpoints(false)?;
+        }
+        let mut result = Ok(());
+        if sw_breakpoint.is_some() {
+            result = self.write_word_32(pc.into(), word);
+        }
+        if result.is_ok() && !catch_sw_breakpoints {
+            result = self.set_trap_catch(TrapCatch::SoftwareBreakpoints, true);
+        }
+
+        let mut planted = Vec::with_capacity(targets.len());
+        if result.is_ok() {
+            for &target in &targets {
+                let original = match self.read_word_32(target.into()) {
+                    Ok(original) => original,
+                    Err(error) => {
+                        result = Err(error);
+                        break;
+                    }
+                };
+                planted.push((target, original));
+                let verified = self
+                    .write_word_32(target.into(), TA_1)
+                    .and_then(|()| self.read_word_32(target.into()));
+                if !matches!(verified, Ok(TA_1)) {
+                    result = Err(Leon3Error::SoftwareBreakpointFailed(target).into());
+                    break;
+                }
+            }
+        }
+
+        if result.is_ok() {
+            result = self.invalidate_stale_code().and_then(|()| {
+                let core_index = self.core_index;
+                self.set_break_now(&[core_index], false)?;
+                let halted = self.wait_for_core_halted(STEP_TIMEOUT);
+                // Keep the core in debug mode, even if it did not reach a breakpoint.
+                self.set_break_now(&[core_index], true)?;
+                self.wait_for_core_halted(STEP_TIMEOUT)?;
+                halted
+            });
+        }
+
+        // Try every restore, even if the step or an earlier restore failed. The first error
+        // is reported.
+        for (target, original) in planted.into_iter().rev() {
+            result = result.and(self.write_word_32(target.into(), original));
+        }
+        if sw_breakpoint.is_some() {
+            result = result.and(self.write_word_32(pc.into(), TA_1));
+        }
+        if !catch_sw_breakpoints {
+            result = result.and(self.set_trap_catch(TrapCatch::SoftwareBreakpoints, false));
+        }
+        if breakpoints_enabled {
+            result = result.and(self.enable_breakpoints(true));
+        }
+        result = result.and(self.invalidate_stale_code());
+        result?;
+
+        self.state.debugger_halt_reason = Some(HaltReason::Step);
+        self.interface.core_info()
+    }
+
+    /// Select how single steps are executed.
+    ///
+    /// Hardware stepping is switched to software stepping automatically if a step does not
+    /// halt the core.
+    pub fn set_step_mode(&mut self, mode: StepMode) {
+        self.state.step_mode = mode;
+    }
+
+    /// Returns the address execution continues at if the branch at `pc` annuls its delay slot,
+    /// or `None` if the delay slot will be executed.
+    ///
+    /// When single stepping a branch whose delay slot gets annulled, the DSU executes the
+    /// branch, the annulled delay instruction and the instruction thereafter before returning
+    /// to debug mode. Since a branch only changes PC and nPC, such a branch is instead emulated
+    /// here so that a step never executes more than one instruction.
+    fn annulled_branch_destination(
+        &mut self,
+        instruction: Instruction,
+        pc: u32,
+    ) -> Result<Option<u32>, crate::Error> {
+        let Instruction::Branch {
+            kind,
+            cond,
+            annul: true,
+            displacement,
+        } = instruction
+        else {
+            return Ok(None);
+        };
+
+        let taken = match kind {
+            BranchKind::Integer => {
+                let psr = Psr::from(self.read_special_reg(IuSpecialReg::PSR)?);
+                integer_condition_holds(cond, psr)
+            }
+            BranchKind::Float => {
+                let fsr = self.read_special_reg(IuSpecialReg::FSR)?;
+                float_condition_holds(cond, fsr)
+            }
+            // Coprocessor condition codes are not accessible, let the hardware do the work.
+            BranchKind::Coprocessor => return Ok(None),
+        };
+
+        // "Branch always" with the annul bit set never executes its delay slot, while conditional
+        // branches only annul the delay slot if the branch is not taken.
+        let always = cond == 8;
+        match (always, taken) {
+            (true, _) => Ok(Some(pc.wrapping_add_signed(displacement))),
+            (false, false) => Ok(Some(pc.wrapping_add(8))),
+            (false, true) => Ok(None),
+        }
     }
 }
 
@@ -61,18 +2414,148 @@ impl<'state> Leon3<'state> {
 pub struct Leon3CoreState {
     /// Whether the first-attach initialization has been performed
     initialized: bool,
+
+    /// Whether hardware breakpoints are enabled
+    hw_breakpoints_enabled: bool,
+
+    /// Whether the configuration has been read from %asr17
+    configuration_read: bool,
+
+    /// Whether the core has an FPU
+    fpu_present: bool,
+
+    /// Whether the core has an SRMMU
+    mmu_present: bool,
+
+    /// Whether memory accesses bypass the MMU
+    mmu_bypass: bool,
+
+    /// The executable memory of this core, from the target description
+    code_ranges: Vec<Range<u64>>,
+
+    /// Code written through this core since the instruction cache was last invalidated
+    stale_code: Vec<Range<u64>>,
+
+    /// The MMU context addresses are translated with, `None` if not read since the core halted
+    mmu_context: Option<Option<MmuContext>>,
+
+    /// The implemented ancillary state registers, bit `n` is set for `%asr(16 + n)`
+    asrs: u16,
+
+    /// What each IU watchpoint is used for
+    watchpoint_units: [WatchpointUnit; MAX_HW_BREAKPOINTS],
+
+    /// The watchpoint armed to halt the core when it comes out of reset
+    reset_catch: Option<ResetCatch>,
+
+    /// The original instructions replaced by software breakpoints, by address
+    sw_breakpoints: BTreeMap<u32, u32>,
+
+    /// Why the core was halted, if it was halted by the debugger
+    debugger_halt_reason: Option<HaltReason>,
+
+    /// Whether the `on_halt` hook of the debug sequence ran since the core halted
+    on_halt_done: bool,
+
+    /// The current window pointer, while the core is halted
+    cwp: Option<u32>,
+
+    /// The register writes that configure the memory controller
+    memory_controller_init: Vec<Leon3RegisterWrite>,
+
+    /// The SDRAM configuration programmed before downloading to RAM
+    sdram: Option<Leon3SdramConfig>,
+
+    /// The kind of memory in the PROM area
+    prom_memory: Leon3PromMemory,
+
+    /// How the system is reset
+    reset_strategy: Leon3ResetStrategy,
+
+    /// The reset address, if it differs from the one of the debug sequence
+    reset_address: Option<u32>,
+
+    /// The units to ungate of each clock gating unit when connecting
+    ungate_clocks: Vec<u32>,
+
+    /// The address range the instruction trace is limited to
+    instruction_trace_range: Option<Range<u32>>,
+
+    /// Whether software breakpoint traps should be caught even without software breakpoints
+    catch_sw_breakpoint_traps: bool,
+
+    /// How single steps are executed
+    step_mode: StepMode,
+
+    /// The software trap number of semihosting calls, if semihosting is enabled
+    semihosting_trap: Option<u8>,
+
+    /// The trap table entry the semihosting breakpoint is set at
+    semihosting_entry: Option<u32>,
+
+    /// The semihosting call the core halted on, decoded once per halt
+    semihosting_command: Option<SemihostingCommand>,
 }
 
 impl Leon3CoreState {
+    /// Set the executable memory regions of the core, writes to which invalidate the
+    /// instruction cache.
+    pub(crate) fn set_code_ranges(&mut self, ranges: Vec<Range<u64>>) {
+        self.code_ranges = ranges;
+    }
+
+    /// Take over the options of the core that are applied when connecting and resetting: the
+    /// memory controller, SDRAM and PROM configuration, the reset strategy and address, the
+    /// clock gating units to ungate and the semihosting trap number.
+    pub(crate) fn apply_options(&mut self, options: &Leon3CoreAccessOptions) {
+        self.memory_controller_init = options.memory_controller_init.clone();
+        self.sdram = options.sdram.clone();
+        self.prom_memory = options.prom.clone();
+        self.reset_strategy = options.reset.clone();
+        self.reset_address = options.reset_address;
+        self.ungate_clocks = options.ungate_clocks.clone();
+        self.semihosting_trap = options.semihosting_trap;
+    }
+
     /// Creates a new [`Leon3CoreState`].
     pub(crate) fn new() -> Self {
-        Self { initialized: false }
+        Self {
+            initialized: false,
+            hw_breakpoints_enabled: false,
+            configuration_read: false,
+            fpu_present: false,
+            mmu_present: false,
+            mmu_bypass: false,
+            code_ranges: Vec::new(),
+            stale_code: Vec::new(),
+            mmu_context: None,
+            asrs: 0,
+            watchpoint_units: [WatchpointUnit::Free; MAX_HW_BREAKPOINTS],
+            reset_catch: None,
+            sw_breakpoints: BTreeMap::new(),
+            debugger_halt_reason: None,
+            on_halt_done: false,
+            cwp: None,
+            memory_controller_init: Vec::new(),
+            sdram: None,
+            prom_memory: Leon3PromMemory::CfiFlash,
+            reset_strategy: Leon3ResetStrategy::Sequence,
+            reset_address: None,
+            ungate_clocks: Vec::new(),
+            instruction_trace_range: None,
+            catch_sw_breakpoint_traps: false,
+            step_mode: StepMode::Hardware,
+            semihosting_trap: None,
+            semihosting_entry: None,
+            semihosting_command: None,
+        }
     }
 }
 
 impl<'state> CoreInterface for Leon3<'state> {
     fn wait_for_core_halted(&mut self, timeout: Duration) -> Result<(), crate::Error> {
-        self.interface.wait_for_core_halted(timeout)
+        self.interface.wait_for_core_halted(timeout)?;
+        self.on_halt()
     }
 
     fn core_halted(&mut self) -> Result<bool, crate::Error> {
@@ -80,83 +2563,135 @@ impl<'state> CoreInterface for Leon3<'state> {
     }
 
     fn status(&mut self) -> Result<CoreStatus, crate::Error> {
-        // TODO(darsor): check on hardware if BN is always set when debug mode is entered
         let ctrl: DsuCtrl = self.interface.read_dsu_reg()?;
         if ctrl.pw() {
             return Ok(CoreStatus::Sleeping);
         }
+        if ctrl.pe() {
+            return Ok(CoreStatus::LockedUp);
+        }
         if self.core_halted()? {
             // TODO(darsor): ensure debug mode
-            let brss: DsuBrss = self.interface.read_dsu_reg()?;
-            if ctrl.pe() {
-                return Ok(CoreStatus::Halted(HaltReason::Exception));
-            }
-            if brss.ss(self.core_index) {
-                // TODO(darsor): when to clear this bit?
-                return Ok(CoreStatus::Halted(HaltReason::Step));
-            } else {
-                Ok(CoreStatus::Halted(HaltReason::Unknown))
-            }
-            // TODO(darsor): check PC and see if executed bp instruction?
-            // let pc = self.read_core_reg(registers::PC.id())?;
-
-            // TODO(darsor): check LEON3 registers for hardware watchpoint?
-
-            // TODO(darsor): check PC and see if executed bp instruction?
-
-            // TODO(darsor): otherwise if BN is set then it was probably a request
-            // else {
-            //     return Ok(CoreStatus::Halted(HaltReason::Request));
-            // }
+            self.on_halt()?;
+            Ok(CoreStatus::Halted(self.decode_halt_reason(ctrl)?))
         } else {
-            return Ok(CoreStatus::Running);
+            Ok(CoreStatus::Running)
         }
     }
 
     fn halt(&mut self, timeout: Duration) -> Result<crate::CoreInformation, crate::Error> {
-        self.interface.modify_dsu_reg(|reg: &mut DsuBrss| {
-            reg.set_bn(self.core_index, true);
-        })?;
-        self.wait_for_core_halted(timeout)?;
-        self.interface.core_info()
+        self.prepare_halt()?;
+        let core_index = self.core_index;
+        self.set_break_now(&[core_index], true)?;
+        self.finish_halt(timeout)
     }
 
     fn run(&mut self) -> Result<(), crate::Error> {
-        // TODO(darsor): return error if in halted/error state, only run if in debug mode
-        // TODO(darsor): clear BN bit
-        todo!()
+        if self.prepare_run()? {
+            self.resume_core()?;
+        }
+        Ok(())
     }
 
     fn reset(&mut self) -> Result<(), crate::Error> {
-        // Register Reset values
-        // Trap Base Register       Trap Base Address field reset (value given by rstaddr VHDL generic)
-        // PC                       0x0 (rstaddr VHDL generic)
-        // nPC                      0x4 (rstaddr VHDL genericc + 4)
-        // PSR                      ET=0, S=1
-        // By default, the execution will start from address 0. This can be overridden by setting the rstaddr
-        // VHDL generic in the model to a non-zero value. The reset address is always aligned on a 4 KiB
-        // boundary. If rstaddr is set to 16#FFFFF#, then the reset address is taken from the signal IRQI.RST-
-        // VEC. This allows the reset address to be changed dynamically
-        // TODO(darsor): clear caches
-        todo!()
+        self.reset_system(false)?;
+        // After a board reset, the boot code configures the memory controller.
+        if self.state.reset_strategy == Leon3ResetStrategy::Sequence {
+            self.configure_memory_controller()?;
+        }
+        self.resume_core()
     }
 
     fn reset_and_halt(
         &mut self,
         timeout: Duration,
     ) -> Result<crate::CoreInformation, crate::Error> {
-        todo!()
+        // The DSU registers are not affected by a processor reset, so a pending break-now
+        // request makes the core enter debug mode before executing the first instruction
+        // at the reset address.
+        self.forget_halt_state();
+        let core_index = self.core_index;
+        self.interface.modify_dsu_reg(|reg: &mut DsuBrss| {
+            reg.set_ss(core_index, false);
+            reg.set_bn(core_index, true);
+        })?;
+
+        self.reset_system(true)?;
+        self.wait_for_core_halted(timeout)?;
+        self.configure_memory_controller()?;
+        self.state.debugger_halt_reason = Some(HaltReason::Request);
+        self.read_configuration()?;
+
+        let info = self.interface.core_info()?;
+        tracing::debug!("Core halted after reset at {:#010x}", info.pc);
+        Ok(info)
     }
 
     fn step(&mut self) -> Result<crate::CoreInformation, crate::Error> {
-        todo!()
+        if !self.core_halted()? {
+            return Err(Leon3Error::CoreNotHalted.into());
+        }
+        self.state.cwp = None;
+        self.state.mmu_context = None;
+        self.invalidate_stale_code()?;
+
+        let pc = self.read_special_reg(IuSpecialReg::PC)?;
+        let npc = self.read_special_reg(IuSpecialReg::NPC)?;
+
+        // Only emulate branches that are not themselves in a delay slot.
+        if npc == pc.wrapping_add(4) {
+            let word = match self.state.sw_breakpoints.get(&pc) {
+                Some(&original) => original,
+                None => self.read_word_32(pc.into())?,
+            };
+            let instruction = Instruction::decode(word);
+            if let Some(destination) = self.annulled_branch_destination(instruction, pc)? {
+                tracing::debug!(
+                    "Emulating annulling branch at {pc:#010x}, continuing at {destination:#010x}"
+                );
+                self.write_special_reg(IuSpecialReg::PC, destination)?;
+                self.write_special_reg(IuSpecialReg::NPC, destination.wrapping_add(4))?;
+                self.state.debugger_halt_reason = Some(HaltReason::Step);
+                return Ok(CoreInformation {
+                    pc: destination.into(),
+                });
+            }
+        }
+
+        if self.state.step_mode == StepMode::Software {
+            return self.software_step(pc, npc);
+        }
+        match self.hardware_step(pc) {
+            Err(crate::Error::Leon3(Leon3Error::Timeout)) => {
+                tracing::warn!(
+                    "Single step of core {} at {pc:#010x} did not halt, using software stepping from now on",
+                    self.core_index
+                );
+                self.state.step_mode = StepMode::Software;
+                self.software_step(pc, npc)
+            }
+            result => result,
+        }
     }
 
     fn read_core_reg(&mut self, address: RegisterId) -> Result<RegisterValue, crate::Error> {
-        let leon3_address = Leon3RegisterId::try_from(address)?;
-        self.interface
-            .read_core_reg(leon3_address)
-            .map(RegisterValue::U32)
+        let register = Leon3RegisterId::try_from(address)?;
+        if let Leon3RegisterId::Fpu(reg) = register
+            && let Some((high, low)) = reg.words()
+        {
+            let high = self.interface.read_core_reg(Leon3RegisterId::Fpu(high))?;
+            let low = self.interface.read_core_reg(Leon3RegisterId::Fpu(low))?;
+            return Ok(RegisterValue::U64(u64::from(high) << 32 | u64::from(low)));
+        }
+
+        let value = match register {
+            Leon3RegisterId::IuCore(reg) => {
+                let cwp = self.cwp()?;
+                self.interface.read_window_reg(reg, cwp)?
+            }
+            reg => self.interface.read_core_reg(reg)?,
+        };
+        Ok(RegisterValue::U32(value))
     }
 
     fn write_core_reg(
@@ -164,33 +2699,116 @@ impl<'state> CoreInterface for Leon3<'state> {
         address: RegisterId,
         value: RegisterValue,
     ) -> Result<(), crate::Error> {
-        let leon3_address = Leon3RegisterId::try_from(address)?;
+        let register = Leon3RegisterId::try_from(address)?;
+        if let Leon3RegisterId::Fpu(reg) = register
+            && let Some((high, low)) = reg.words()
+        {
+            let value: u64 = value.try_into()?;
+            self.interface
+                .write_core_reg(Leon3RegisterId::Fpu(high), (value >> 32) as u32)?;
+            return self
+                .interface
+                .write_core_reg(Leon3RegisterId::Fpu(low), value as u32);
+        }
+
         let value: u32 = value.try_into()?;
-        self.interface.write_core_reg(leon3_address, value)
+        match register {
+            Leon3RegisterId::IuCore(reg) => {
+                let window = self.cwp()? as u8;
+                self.interface
+                    .write_core_reg(Leon3RegisterId::IuWindow { window, reg }, value)
+            }
+            Leon3RegisterId::IuSpecial(IuSpecialReg::PC) => {
+                // Execution continues at nPC after the instruction at PC, so moving the PC
+                // elsewhere moves nPC along. Writing back the current PC keeps nPC, which
+                // points to the branch target when the core halted in a delay slot.
+                let pc = self.read_special_reg(IuSpecialReg::PC)?;
+                self.write_special_reg(IuSpecialReg::PC, value)?;
+                if value != pc {
+                    self.write_special_reg(IuSpecialReg::NPC, value.wrapping_add(4))?;
+                }
+                Ok(())
+            }
+            reg => {
+                if let Leon3RegisterId::IuSpecial(IuSpecialReg::PSR) = reg {
+                    self.state.cwp = None;
+                }
+                self.interface.write_core_reg(reg, value)
+            }
+        }
     }
 
     fn available_breakpoint_units(&mut self) -> Result<u32, crate::Error> {
-        todo!()
+        let asr17: Asr17 = self.interface.read_dsu_reg()?;
+        Ok(u32::from(asr17.nwp()).min(MAX_HW_BREAKPOINTS as u32))
     }
 
     fn hw_breakpoints(&mut self) -> Result<Vec<Option<u64>>, crate::Error> {
-        todo!()
+        let units = self.available_breakpoint_units()? as usize;
+        let mut breakpoints = Vec::with_capacity(units);
+        for unit_index in 0..units {
+            // Units used as data watchpoints are reported as well, so that they are not
+            // considered free for breakpoints.
+            let breakpoint = if self.state.watchpoint_units[unit_index] != WatchpointUnit::Free {
+                let (waddr, _) = Self::watchpoint_registers(unit_index);
+                let address = self.read_special_reg(waddr)? & WATCHPOINT_ADDRESS_MASK;
+                Some(u64::from(address))
+            } else {
+                None
+            };
+            breakpoints.push(breakpoint);
+        }
+        Ok(breakpoints)
     }
 
     fn enable_breakpoints(&mut self, state: bool) -> Result<(), crate::Error> {
-        todo!()
+        self.arm_breakpoints(state)?;
+        self.state.hw_breakpoints_enabled = state;
+        Ok(())
     }
 
     fn set_hw_breakpoint(&mut self, unit_index: usize, addr: u64) -> Result<(), crate::Error> {
-        todo!()
+        self.check_breakpoint_unit(unit_index)?;
+        if matches!(
+            self.state.watchpoint_units[unit_index],
+            WatchpointUnit::Data | WatchpointUnit::ResetCatch
+        ) {
+            return Err(Leon3Error::BreakpointUnitInUse(unit_index).into());
+        }
+        let address =
+            u32::try_from(addr).map_err(|_| Leon3Error::UnsupportedBreakpointAddress(addr))?;
+
+        let (waddr, wmask) = Self::watchpoint_registers(unit_index);
+        // Compare all address bits and don't break on data accesses.
+        self.write_special_reg(wmask, WATCHPOINT_ADDRESS_MASK)?;
+        let mut value = address & WATCHPOINT_ADDRESS_MASK;
+        if self.state.hw_breakpoints_enabled {
+            value |= WADDR_IF;
+        }
+        self.write_special_reg(waddr, value)?;
+
+        self.state.watchpoint_units[unit_index] = WatchpointUnit::Breakpoint;
+        self.check_delay_slot_breakpoint(address);
+        Ok(())
     }
 
     fn clear_hw_breakpoint(&mut self, unit_index: usize) -> Result<(), crate::Error> {
-        todo!()
+        self.check_breakpoint_unit(unit_index)?;
+        // The reset catch is reported as a used unit, and freed by `reset_catch_clear`.
+        if self.state.watchpoint_units[unit_index] == WatchpointUnit::ResetCatch {
+            return Ok(());
+        }
+
+        let (waddr, wmask) = Self::watchpoint_registers(unit_index);
+        self.write_special_reg(waddr, 0)?;
+        self.write_special_reg(wmask, 0)?;
+
+        self.state.watchpoint_units[unit_index] = WatchpointUnit::Free;
+        Ok(())
     }
 
     fn registers(&self) -> &'static crate::CoreRegisters {
-        &registers::LEON3_CORE_REGISTERS
+        registers::leon3_registers(self.state.fpu_present, self.state.asrs)
     }
 
     fn program_counter(&self) -> &'static crate::CoreRegister {
@@ -210,7 +2828,7 @@ impl<'state> CoreInterface for Leon3<'state> {
     }
 
     fn hw_breakpoints_enabled(&self) -> bool {
-        todo!()
+        self.state.hw_breakpoints_enabled
     }
 
     fn architecture(&self) -> probe_rs_target::Architecture {
@@ -226,40 +2844,323 @@ impl<'state> CoreInterface for Leon3<'state> {
     }
 
     fn fpu_support(&mut self) -> Result<bool, crate::Error> {
-        todo!()
+        Ok(self.state.fpu_present)
     }
 
     fn floating_point_register_count(&mut self) -> Result<usize, crate::Error> {
-        todo!()
+        Ok(self
+            .registers()
+            .all_registers()
+            .filter(|r| r.register_has_role(crate::RegisterRole::FloatingPoint))
+            .count())
     }
 
     fn reset_catch_set(&mut self) -> Result<(), crate::Error> {
-        Ok(self.sequence.reset_catch_set(&mut self.interface)?)
+        if self.state.reset_catch.is_some() {
+            return Ok(());
+        }
+        // Take the last free watchpoint, the first ones are handed out for breakpoints.
+        let units = self.available_breakpoint_units()? as usize;
+        let unit_index = (0..units)
+            .rev()
+            .find(|&unit_index| self.state.watchpoint_units[unit_index] == WatchpointUnit::Free)
+            .ok_or(Leon3Error::ResetHaltRequestNotSupported)?;
+        let ctrl: DsuCtrl = self.interface.read_dsu_reg()?;
+
+        let reset_address = self.reset_address();
+        self.state.watchpoint_units[unit_index] = WatchpointUnit::ResetCatch;
+        self.state.reset_catch = Some(ResetCatch {
+            unit_index,
+            break_on_watchpoint: ctrl.bw(),
+        });
+        self.sequence
+            .reset_catch_set(&mut self.interface, reset_address, unit_index)
     }
 
     fn reset_catch_clear(&mut self) -> Result<(), crate::Error> {
-        Ok(self.sequence.reset_catch_clear(&mut self.interface)?)
+        let Some(catch) = self.state.reset_catch.take() else {
+            return Ok(());
+        };
+        self.state.watchpoint_units[catch.unit_index] = WatchpointUnit::Free;
+
+        let reset_address = self.reset_address();
+        let result =
+            self.sequence
+                .reset_catch_clear(&mut self.interface, reset_address, catch.unit_index);
+        // Restore the BW bit even if the watchpoint could not be cleared.
+        result.and(
+            self.interface
+                .modify_dsu_ctrl(|ctrl| ctrl.set_bw(catch.break_on_watchpoint)),
+        )
     }
 
     fn debug_core_stop(&mut self) -> Result<(), crate::Error> {
-        todo!()
+        // Software breakpoints would make the core trap once the debugger is gone.
+        self.clear_all_sw_breakpoints()
+    }
+
+    fn enable_vector_catch(&mut self, condition: VectorCatchCondition) -> Result<(), crate::Error> {
+        match condition {
+            VectorCatchCondition::HardFault => {
+                self.enable_trap_catch(TrapCatch::ErrorTraps)?;
+                self.enable_trap_catch(TrapCatch::ErrorMode)
+            }
+            VectorCatchCondition::All => {
+                self.enable_trap_catch(TrapCatch::AllTraps)?;
+                self.enable_trap_catch(TrapCatch::ErrorMode)
+            }
+            VectorCatchCondition::CoreReset => Err(Leon3Error::ResetHaltRequestNotSupported.into()),
+            VectorCatchCondition::SecureFault => Err(crate::Error::NotImplemented("secure fault")),
+        }
+    }
+
+    fn disable_vector_catch(
+        &mut self,
+        condition: VectorCatchCondition,
+    ) -> Result<(), crate::Error> {
+        match condition {
+            VectorCatchCondition::HardFault => {
+                self.disable_trap_catch(TrapCatch::ErrorTraps)?;
+                self.disable_trap_catch(TrapCatch::ErrorMode)
+            }
+            VectorCatchCondition::All => {
+                self.disable_trap_catch(TrapCatch::AllTraps)?;
+                self.disable_trap_catch(TrapCatch::ErrorMode)
+            }
+            VectorCatchCondition::CoreReset => Err(Leon3Error::ResetHaltRequestNotSupported.into()),
+            VectorCatchCondition::SecureFault => Err(crate::Error::NotImplemented("secure fault")),
+        }
+    }
+
+    fn is_big_endian(&self) -> bool {
+        true
     }
 
     fn spill_registers(&mut self) -> Result<(), crate::Error> {
-        // For most architectures, this is not necessary. Use cases include processors
-        // that have a windowed register file, where the whole register file is not visible at once.
-        todo!()
+        // Do what the window overflow trap handler would do: store the locals and ins of every
+        // valid window to the register save area at the stack pointer of that window, so that
+        // the stack can be unwound from memory.
+        let num_windows = self.interface.num_windows()?;
+        let psr = Psr::from(self.read_special_reg(IuSpecialReg::PSR)?);
+        let wim = self.read_special_reg(IuSpecialReg::WIM)?;
+        let cwp = psr.cwp();
+
+        for i in 0..num_windows - 1 {
+            // Callers use the windows with higher window pointers.
+            let window = (cwp + i) % num_windows;
+            if i > 0 && wim & (1 << window) != 0 {
+                break;
+            }
+
+            let sp = self.interface.read_window_reg(IuCoreReg::O(6), window)?;
+            if sp == 0 || sp % 8 != 0 {
+                tracing::debug!("Window {window} has no valid stack pointer, not spilling further");
+                break;
+            }
+
+            let mut save_area = [0; 16];
+            for n in 0..8 {
+                save_area[n as usize] = self.interface.read_window_reg(IuCoreReg::L(n), window)?;
+                save_area[8 + n as usize] =
+                    self.interface.read_window_reg(IuCoreReg::I(n), window)?;
+            }
+            self.write_32(sp.into(), &save_area)?;
+        }
+
+        // The stores bypass the data cache, which must not keep the old stack contents.
+        self.invalidate_caches()
     }
 }
 
-impl<'state> CoreMemoryInterface for Leon3<'state> {
-    type ErrorType = crate::Error;
+impl MemoryInterface for Leon3<'_> {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        self.interface
+            .as_memory_interface_mut()
+            .supports_native_64bit_access()
+    }
+
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(8) {
+            return read_unaligned(self, address, data);
+        }
+        for (physical, bytes) in self.physical_ranges(address, data.len() * 8)? {
+            self.interface
+                .as_memory_interface_mut()
+                .read_64(physical, &mut data[bytes.start / 8..bytes.end / 8])?;
+        }
+        Ok(())
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(4) {
+            return read_unaligned(self, address, data);
+        }
+        for (physical, bytes) in self.physical_ranges(address, data.len() * 4)? {
+            self.interface
+                .as_memory_interface_mut()
+                .read_32(physical, &mut data[bytes.start / 4..bytes.end / 4])?;
+        }
+        Ok(())
+    }
+
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(2) {
+            return read_unaligned(self, address, data);
+        }
+        for (physical, bytes) in self.physical_ranges(address, data.len() * 2)? {
+            self.interface
+                .as_memory_interface_mut()
+                .read_16(physical, &mut data[bytes.start / 2..bytes.end / 2])?;
+        }
+        Ok(())
+    }
+
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        for (physical, bytes) in self.physical_ranges(address, data.len())? {
+            self.interface
+                .as_memory_interface_mut()
+                .read_8(physical, &mut data[bytes])?;
+        }
+        Ok(())
+    }
+
+    fn read_mem_64bit(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(8) {
+            return Err(InvalidDataLengthError::new("read_mem_64bit", 8).into());
+        }
+        if !address.is_multiple_of(8) {
+            return self.read_8(address, data);
+        }
+        for (physical, bytes) in self.physical_ranges(address, data.len())? {
+            self.interface
+                .as_memory_interface_mut()
+                .read_mem_64bit(physical, &mut data[bytes])?;
+        }
+        Ok(())
+    }
+
+    fn read_mem_32bit(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(4) {
+            return Err(InvalidDataLengthError::new("read_mem_32bit", 4).into());
+        }
+        if !address.is_multiple_of(4) {
+            return self.read_8(address, data);
+        }
+        for (physical, bytes) in self.physical_ranges(address, data.len())? {
+            self.interface
+                .as_memory_interface_mut()
+                .read_mem_32bit(physical, &mut data[bytes])?;
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        for (physical, bytes) in self.physical_ranges(address, data.len())? {
+            self.interface
+                .as_memory_interface_mut()
+                .read(physical, &mut data[bytes])?;
+        }
+        Ok(())
+    }
+
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), crate::Error> {
+        self.record_write(address, data.len() * 8);
+        if !address.is_multiple_of(8) {
+            return write_unaligned(self, address, data);
+        }
+        for (physical, bytes) in self.physical_ranges(address, data.len() * 8)? {
+            self.interface
+                .as_memory_interface_mut()
+                .write_64(physical, &data[bytes.start / 8..bytes.end / 8])?;
+        }
+        Ok(())
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), crate::Error> {
+        self.record_write(address, data.len() * 4);
+        if !address.is_multiple_of(4) {
+            return write_unaligned(self, address, data);
+        }
+        for (physical, bytes) in self.physical_ranges(address, data.len() * 4)? {
+            self.interface
+                .as_memory_interface_mut()
+                .write_32(physical, &data[bytes.start / 4..bytes.end / 4])?;
+        }
+        Ok(())
+    }
+
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), crate::Error> {
+        self.record_write(address, data.len() * 2);
+        if !address.is_multiple_of(2) {
+            return write_unaligned(self, address, data);
+        }
+        for (physical, bytes) in self.physical_ranges(address, data.len() * 2)? {
+            self.interface
+                .as_memory_interface_mut()
+                .write_16(physical, &data[bytes.start / 2..bytes.end / 2])?;
+        }
+        Ok(())
+    }
+
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        self.record_write(address, data.len());
+        for (physical, bytes) in self.physical_ranges(address, data.len())? {
+            self.interface
+                .as_memory_interface_mut()
+                .write_8(physical, &data[bytes])?;
+        }
+        Ok(())
+    }
+
+    fn write_mem_64bit(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        self.record_write(address, data.len());
+        if !data.len().is_multiple_of(8) {
+            return Err(InvalidDataLengthError::new("write_mem_64bit", 8).into());
+        }
+        if !address.is_multiple_of(8) {
+            return self.write_8(address, data);
+        }
+        for (physical, bytes) in self.physical_ranges(address, data.len())? {
+            self.interface
+                .as_memory_interface_mut()
+                .write_mem_64bit(physical, &data[bytes])?;
+        }
+        Ok(())
+    }
+
+    fn write_mem_32bit(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        self.record_write(address, data.len());
+        if !data.len().is_multiple_of(4) {
+            return Err(InvalidDataLengthError::new("write_mem_32bit", 4).into());
+        }
+        if !address.is_multiple_of(4) {
+            return self.write_8(address, data);
+        }
+        for (physical, bytes) in self.physical_ranges(address, data.len())? {
+            self.interface
+                .as_memory_interface_mut()
+                .write_mem_32bit(physical, &data[bytes])?;
+        }
+        Ok(())
+    }
 
-    fn memory(&self) -> &dyn crate::MemoryInterface<Self::ErrorType> {
-        self.interface.as_memory_interface()
+    fn write(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        self.record_write(address, data.len());
+        for (physical, bytes) in self.physical_ranges(address, data.len())? {
+            self.interface
+                .as_memory_interface_mut()
+                .write(physical, &data[bytes])?;
+        }
+        Ok(())
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, crate::Error> {
+        self.interface
+            .as_memory_interface()
+            .supports_8bit_transfers()
     }
 
-    fn memory_mut(&mut self) -> &mut dyn crate::MemoryInterface<Self::ErrorType> {
-        self.interface.as_memory_interface_mut()
+    fn flush(&mut self) -> Result<(), crate::Error> {
+        self.interface.as_memory_interface_mut().flush()
     }
 }
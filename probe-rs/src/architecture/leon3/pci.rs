@@ -0,0 +1,367 @@
+//! PCI debug link, giving access to the AHB bus through the BARs of a PCI target like the
+//! GRPCI2.
+//!
+//! Each BAR of the PCI target maps a window of the AHB address space, as configured in its
+//! BAR-to-AHB mapping registers. The BARs are memory mapped on the host, so the AHB bus is
+//! accessed with plain loads and stores. The PCI target converts the byte order, so 32-bit
+//! accesses return the AHB words. Byte and half-word accesses are done on whole words.
+
+use probe_rs_target::Leon3PciAccess;
+
+use crate::{
+    MemoryInterface,
+    architecture::leon3::{
+        ahbjtag::check_out_of_bounds,
+        communication_interface::Leon3Error,
+        word_access::{WordAccess, read_unaligned, write_unaligned},
+    },
+    memory::{InvalidDataLengthError, valid_32bit_address},
+};
+
+/// Errors of the PCI debug link.
+#[derive(thiserror::Error, Debug)]
+pub enum PciError {
+    /// A BAR of the device could not be mapped.
+    #[error("Unable to map BAR {bar} of PCI device {device}")]
+    Map {
+        /// The PCI address of the device.
+        device: String,
+        /// The index of the BAR.
+        bar: u8,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+    /// The AHB base address of a window is not a word aligned 32-bit address.
+    #[error("The AHB base address {ahb_base:#X} of BAR {bar} is not a word aligned 32-bit address")]
+    InvalidAhbBase {
+        /// The index of the BAR.
+        bar: u8,
+        /// The configured AHB base address.
+        ahb_base: u64,
+    },
+    /// The address is not mapped by any BAR.
+    #[error("Address {0:#010X} is outside of the PCI windows")]
+    Unmapped(u32),
+    /// PCI access is not supported on this platform.
+    #[error("PCI access is only supported on Linux")]
+    Unsupported,
+}
+
+/// A BAR mapping a window of the AHB bus.
+#[derive(Debug)]
+struct Window {
+    ahb_base: u32,
+    bar: BarMapping,
+}
+
+impl Window {
+    /// Whether the whole word at `address` lies in the window.
+    fn contains(&self, address: u32) -> bool {
+        address
+            .checked_sub(self.ahb_base)
+            .and_then(|offset| (offset as usize).checked_add(4))
+            .is_some_and(|end| end <= self.bar.len())
+    }
+}
+
+/// PCI driver used to access the AHB bus through memory mapped BARs.
+#[derive(Debug)]
+pub struct PciBus {
+    windows: Vec<Window>,
+}
+
+impl PciBus {
+    /// Maps the BARs of the PCI device given in the target description.
+    pub fn open(config: &Leon3PciAccess) -> Result<Self, PciError> {
+        let windows = config
+            .windows
+            .iter()
+            .map(|window| {
+                let ahb_base = u32::try_from(window.ahb_base)
+                    .ok()
+                    .filter(|ahb_base| ahb_base.is_multiple_of(4))
+                    .ok_or(PciError::InvalidAhbBase {
+                        bar: window.bar,
+                        ahb_base: window.ahb_base,
+                    })?;
+                let bar = BarMapping::open(&config.device, window.bar)?;
+                tracing::info!(
+                    "PCI device {} BAR {} maps {:#x} bytes at AHB address {:#010x}",
+                    config.device,
+                    window.bar,
+                    bar.len(),
+                    ahb_base
+                );
+                Ok(Window { ahb_base, bar })
+            })
+            .collect::<Result<_, PciError>>()?;
+
+        Ok(Self { windows })
+    }
+
+    fn locate(&self, address: u32) -> Result<(&BarMapping, usize), PciError> {
+        self.windows
+            .iter()
+            .find(|window| window.contains(address))
+            .map(|window| (&window.bar, (address - window.ahb_base) as usize))
+            .ok_or(PciError::Unmapped(address))
+    }
+}
+
+impl WordAccess for PciBus {
+    fn read_words(&mut self, address: u32, data: &mut [u32]) -> Result<(), Leon3Error> {
+        check_out_of_bounds(address, data.len() * 4)?;
+        for (word_idx, word) in data.iter_mut().enumerate() {
+            let (bar, offset) = self.locate(address + word_idx as u32 * 4)?;
+            *word = bar.read(offset);
+        }
+        Ok(())
+    }
+
+    fn write_words(&mut self, address: u32, data: &[u32]) -> Result<(), Leon3Error> {
+        check_out_of_bounds(address, data.len() * 4)?;
+        for (word_idx, word) in data.iter().enumerate() {
+            let (bar, offset) = self.locate(address + word_idx as u32 * 4)?;
+            bar.write(offset, *word);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+struct BarMapping {
+    map: memmap2::MmapRaw,
+}
+
+#[cfg(target_os = "linux")]
+impl BarMapping {
+    fn open(device: &str, bar: u8) -> Result<Self, PciError> {
+        let path = format!("/sys/bus/pci/devices/{device}/resource{bar}");
+        let map = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .and_then(|file| memmap2::MmapRaw::map_raw(&file))
+            .map_err(|source| PciError::Map {
+                device: device.to_string(),
+                bar,
+                source,
+            })?;
+        Ok(Self { map })
+    }
+
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    fn read(&self, offset: usize) -> u32 {
+        assert!(offset + 4 <= self.len() && offset.is_multiple_of(4));
+        // SAFETY: The offset is aligned and within the mapping, which lives as long as self.
+        unsafe { self.map.as_ptr().add(offset).cast::<u32>().read_volatile() }
+    }
+
+    fn write(&self, offset: usize, value: u32) {
+        assert!(offset + 4 <= self.len() && offset.is_multiple_of(4));
+        // SAFETY: The offset is aligned and within the mapping, which lives as long as self.
+        unsafe {
+            self.map
+                .as_mut_ptr()
+                .add(offset)
+                .cast::<u32>()
+                .write_volatile(value)
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+#[derive(Debug)]
+struct BarMapping(std::convert::Infallible);
+
+#[cfg(not(target_os = "linux"))]
+impl BarMapping {
+    fn open(_device: &str, _bar: u8) -> Result<Self, PciError> {
+        Err(PciError::Unsupported)
+    }
+
+    fn len(&self) -> usize {
+        match self.0 {}
+    }
+
+    fn read(&self, _offset: usize) -> u32 {
+        match self.0 {}
+    }
+
+    fn write(&self, _offset: usize, _value: u32) {
+        match self.0 {}
+    }
+}
+
+impl MemoryInterface for PciBus {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        false
+    }
+
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(8) {
+            return read_unaligned(self, address, data);
+        }
+        let address = valid_32bit_address(address)?;
+        let mut words = vec![0u32; data.len() * 2];
+        self.read_words(address, &mut words)?;
+        for (word64, pair) in data.iter_mut().zip(words.chunks_exact(2)) {
+            // The target is big-endian, the word at the lower address holds the upper half.
+            *word64 = (u64::from(pair[0]) << 32) | u64::from(pair[1]);
+        }
+        Ok(())
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(4) {
+            return read_unaligned(self, address, data);
+        }
+        let address = valid_32bit_address(address)?;
+        self.read_words(address, data)?;
+        Ok(())
+    }
+
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), crate::Error> {
+        let mut bytes = vec![0u8; data.len() * 2];
+        self.read_8(address, &mut bytes)?;
+        for (word16, bytes) in data.iter_mut().zip(bytes.chunks_exact(2)) {
+            *word16 = u16::from_be_bytes([bytes[0], bytes[1]]);
+        }
+        Ok(())
+    }
+
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        let address = valid_32bit_address(address)?;
+        self.read_bytes(address, data)?;
+        Ok(())
+    }
+
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(8) {
+            return write_unaligned(self, address, data);
+        }
+        let address = valid_32bit_address(address)?;
+        let words = data
+            .iter()
+            .flat_map(|word64| [(word64 >> 32) as u32, *word64 as u32])
+            .collect::<Vec<_>>();
+        self.write_words(address, &words)?;
+        Ok(())
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(4) {
+            return write_unaligned(self, address, data);
+        }
+        let address = valid_32bit_address(address)?;
+        self.write_words(address, data)?;
+        Ok(())
+    }
+
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), crate::Error> {
+        let bytes = data
+            .iter()
+            .flat_map(|word16| word16.to_be_bytes())
+            .collect::<Vec<_>>();
+        self.write_8(address, &bytes)
+    }
+
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        let address = valid_32bit_address(address)?;
+        self.write_bytes(address, data)?;
+        Ok(())
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, crate::Error> {
+        Ok(true)
+    }
+
+    fn flush(&mut self) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn read_mem_64bit(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(8) {
+            return Err(InvalidDataLengthError::new("read_mem_64bit", 8).into());
+        }
+        self.read_8(address, data)
+    }
+
+    fn read_mem_32bit(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(4) {
+            return Err(InvalidDataLengthError::new("read_mem_32bit", 4).into());
+        }
+        self.read_8(address, data)
+    }
+
+    fn write_mem_64bit(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(8) {
+            return Err(InvalidDataLengthError::new("write_mem_64bit", 8).into());
+        }
+        self.write_8(address, data)
+    }
+
+    fn write_mem_32bit(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(4) {
+            return Err(InvalidDataLengthError::new("write_mem_32bit", 4).into());
+        }
+        self.write_8(address, data)
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    fn bus(ahb_base: u32, len: usize) -> PciBus {
+        let map = memmap2::MmapMut::map_anon(len).unwrap().into();
+        PciBus {
+            windows: vec![Window {
+                ahb_base,
+                bar: BarMapping { map },
+            }],
+        }
+    }
+
+    #[test]
+    fn access_within_window() {
+        let mut bus = bus(0x4000_0000, 0x1000);
+        bus.write_words(0x4000_0ffc, &[0x1234_5678]).unwrap();
+        let mut word = [0];
+        bus.read_words(0x4000_0ffc, &mut word).unwrap();
+        assert_eq!(word, [0x1234_5678]);
+    }
+
+    #[test]
+    fn access_outside_window() {
+        // Only the first two bytes of the word at 0x4000_1000 are mapped.
+        let mut bus = bus(0x4000_0000, 0x1002);
+        let mut words = [0; 2];
+        assert!(matches!(
+            bus.read_words(0x4000_0ffc, &mut words),
+            Err(Leon3Error::Pci(PciError::Unmapped(0x4000_1000)))
+        ));
+        assert!(matches!(
+            bus.write_words(0x3fff_fffc, &[0]),
+            Err(Leon3Error::Pci(PciError::Unmapped(0x3fff_fffc)))
+        ));
+    }
+
+    #[test]
+    fn invalid_ahb_base() {
+        let config = |ahb_base| Leon3PciAccess {
+            device: "0000:01:00.0".to_string(),
+            windows: vec![probe_rs_target::Leon3PciWindow { bar: 0, ahb_base }],
+        };
+        for ahb_base in [0x1_0000_0000, 0x4000_0002] {
+            assert!(matches!(
+                PciBus::open(&config(ahb_base)),
+                Err(PciError::InvalidAhbBase { bar: 0, ahb_base: base }) if base == ahb_base
+            ));
+        }
+    }
+}
@@ -1,38 +1,91 @@
+//! AMBA plug&play records of GRLIB designs.
+
 use std::{fmt::Display, ops::Range};
 
+use probe_rs_target::{GenericRegion, MemoryAccess, MemoryRegion, RamRegion};
+
 use crate::{MemoryInterface, architecture::leon3::communication_interface::Leon3Error};
 
-const PNP_BASE_ADDRESS: u32 = 0xFFFF_F000;
+/// Address of the AHB plug&play area in the default GRLIB configuration, at the end of the
+/// I/O area 0xFFF0_0000.
+pub const DEFAULT_PLUGNPLAY_BASE: u64 = 0xFFFF_F000;
+/// Offset of the AHB plug&play area from the start of the I/O area of a bus.
+const PNP_AREA_OFFSET: u64 = 0xF_F000;
 // bytes for each AHB record
-const PNP_RECORD_SIZE: u32 = 32;
+const PNP_RECORD_SIZE: u64 = 32;
 // 64 masters and 63 slaves
-const PNP_NUM_RECORDS: u32 = 64 + 63;
+const PNP_NUM_MASTERS: u64 = 64;
+const PNP_NUM_RECORDS: u64 = PNP_NUM_MASTERS + 63;
+
+/// Offset of the APB plug&play area from the base address of an AHB/APB bridge.
+const APB_PNP_OFFSET: u64 = 0xF_F000;
+// bytes for each APB record
+const APB_PNP_RECORD_SIZE: u64 = 8;
+// An AHB/APB bridge has up to 16 slaves
+const APB_PNP_NUM_RECORDS: u64 = 16;
 
-#[derive(Debug)]
+/// The devices found in the AMBA plug&play areas.
+#[derive(Debug, Default)]
 pub struct PlugnPlayState {
     devices: Vec<Record>,
 }
 
+/// The bus a plug&play record was found on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bus {
+    /// An AHB master.
+    AhbMaster,
+    /// An AHB slave.
+    AhbSlave,
+    /// An APB slave behind the AHB/APB bridge at the given address.
+    Apb {
+        /// The base address of the AHB/APB bridge.
+        bridge: u64,
+    },
+}
+
+/// A plug&play record describing a device.
 #[derive(Debug, Clone)]
 pub struct Record {
+    /// The bus the device is connected to.
+    pub bus: Bus,
+    /// The raw vendor ID.
+    pub vendor_id: u8,
+    /// The raw device ID.
+    pub device_id: u16,
+    /// The decoded vendor and device.
     pub device: Device,
+    /// The version of the device.
     pub version: u8,
+    /// The interrupt line of the device.
     pub irq: u8,
+    /// The address ranges of the device, decoded from its bank address registers.
     pub address_spaces: Vec<AddressSpace>,
 }
 
+/// An address range of a device.
 #[derive(Debug, Clone)]
 pub struct AddressSpace {
+    /// The index of the bank address register the range was decoded from.
+    pub bar: usize,
+    /// The addresses of the range.
     pub addresses: Range<u64>,
+    /// The range is prefetchable.
     pub prefetchable: bool,
+    /// The range is cacheable.
     pub cacheable: bool,
+    /// The kind of the range.
     pub kind: AddressSpaceKind,
 }
 
-#[derive(Debug, Clone, Copy)]
-enum AddressSpaceKind {
+/// The kind of an address range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressSpaceKind {
+    /// APB I/O space.
     ApbIO,
+    /// AHB memory space.
     AhbMemory,
+    /// AHB I/O space.
     AhbIO,
 }
 
@@ -48,7 +101,9 @@ impl AddressSpaceKind {
 }
 
 impl Record {
-    fn from_data(data: &[u32; 8]) -> Option<Self> {
+    /// Decode an AHB record, or an APB record which consists of the identification and the
+    /// bank address register only.
+    fn from_data(bus: Bus, data: &[u32]) -> Option<Self> {
         let vendor_id = u8::try_from((data[0] & 0xFF00_0000) >> 24).unwrap();
         let device_id = u16::try_from((data[0] & 0x00FF_F000) >> 12).unwrap();
         let device = Device::from_ids(vendor_id, device_id);
@@ -58,12 +113,27 @@ impl Record {
         }
         let version = u8::try_from((data[0] & 0x0000_03E0) >> 5).unwrap();
         let irq = u8::try_from(((data[0] & 0x0000_0C00) >> 5) | (data[0] & 0x0000_001F)).unwrap();
-        let address_space = data[4..]
+        let bars = match bus {
+            Bus::Apb { .. } => &data[1..],
+            Bus::AhbMaster | Bus::AhbSlave => &data[4..],
+        };
+        let address_space = bars
             .iter()
-            .filter_map(|bar| AddressSpace::from_bar(*bar))
+            .enumerate()
+            .filter_map(|(index, bar)| AddressSpace::from_bar(index, *bar))
+            .map(|mut space| {
+                // APB addresses are relative to the bridge.
+                if let Bus::Apb { bridge } = bus {
+                    space.addresses = space.addresses.start + bridge..space.addresses.end + bridge;
+                }
+                space
+            })
             .collect();
 
         Some(Self {
+            bus,
+            vendor_id,
+            device_id,
             device,
             version,
             irq,
@@ -72,9 +142,82 @@ impl Record {
     }
 }
 
+impl Record {
+    /// The start address of the first address range of the device, which holds the registers
+    /// of most peripherals.
+    pub fn base_address(&self) -> Option<u64> {
+        self.address_spaces
+            .first()
+            .map(|space| space.addresses.start)
+    }
+
+    /// The I/O area of the bus behind an AHB/AHB bridge, which the bridge stores in its
+    /// second user-defined register. Only set for bridges which have a version above 0.
+    fn secondary_bus(&self, data: &[u32]) -> Option<u64> {
+        let is_bridge = matches!(
+            self.device,
+            Device::Gaisler(
+                GaislerDevice::AHB2AHB | GaislerDevice::L2CACHE | GaislerDevice::GRIOMMU
+            )
+        );
+        if !is_bridge || self.version == 0 || data[2] == 0 {
+            return None;
+        }
+        Some(u64::from(data[2] & 0xFFF0_0000))
+    }
+}
+
+/// The kind of memory mapped by a bank address register of a memory controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MemoryKind {
+    Rom,
+    Ram,
+    Io,
+}
+
+impl Record {
+    /// The name of a memory controller or on-chip memory, and the kind of memory mapped by
+    /// each of its bank address registers.
+    fn memory_bars(&self) -> Option<(&'static str, &'static [(usize, MemoryKind)])> {
+        use GaislerDevice::{
+            AHBDPRAM, AHBRAM, AHBROM, DDR2SP, DDRSP, FTAHBRAM, FTMCTRL, FTSDCTRL, FTSDCTRL64,
+            FTSRCTRL, SDCTRL, SDCTRL64, SRCTRL,
+        };
+        use MemoryKind::{Io, Ram, Rom};
+
+        const PROM_IO_RAM: &[(usize, MemoryKind)] = &[(0, Rom), (1, Io), (2, Ram)];
+        const PROM_RAM: &[(usize, MemoryKind)] = &[(0, Rom), (1, Ram)];
+        const RAM: &[(usize, MemoryKind)] = &[(0, Ram)];
+        const ROM: &[(usize, MemoryKind)] = &[(0, Rom)];
+
+        let device = match self.device {
+            Device::Gaisler(device) => device,
+            Device::Esa(EsaDevice::MCTRL) => return Some(("MCTRL", PROM_IO_RAM)),
+            _ => return None,
+        };
+
+        Some(match device {
+            FTMCTRL => ("FTMCTRL", PROM_IO_RAM),
+            SRCTRL => ("SRCTRL", PROM_RAM),
+            FTSRCTRL => ("FTSRCTRL", PROM_RAM),
+            SDCTRL => ("SDCTRL", RAM),
+            SDCTRL64 => ("SDCTRL64", RAM),
+            FTSDCTRL => ("FTSDCTRL", RAM),
+            FTSDCTRL64 => ("FTSDCTRL64", RAM),
+            DDRSP => ("DDRSPA", RAM),
+            DDR2SP => ("DDR2SPA", RAM),
+            AHBRAM => ("AHBRAM", RAM),
+            AHBDPRAM => ("AHBDPRAM", RAM),
+            FTAHBRAM => ("FTAHBRAM", RAM),
+            AHBROM => ("AHBROM", ROM),
+            _ => return None,
+        })
+    }
+}
+
 impl AddressSpace {
     /// New address space decoded from Bank Address Record
-    fn from_bar(bar: u32) -> Option<Self> {
+    fn from_bar(index: usize, bar: u32) -> Option<Self> {
         let mask = u16::try_from((bar & 0x0000_FFF0) >> 4).unwrap();
         if mask == 0 {
             // if MASK = 0, the BAR is disabled rather than occupying the full
@@ -116,6 +259,7 @@ impl AddressSpace {
             }
         };
         Some(Self {
+            bar: index,
             addresses,
             prefetchable,
             cacheable,
@@ -125,48 +269,261 @@ impl AddressSpace {
 }
 
 impl PlugnPlayState {
+    /// Scan the AHB plug&play area at `base`, and the areas of all buses and AHB/APB bridges
+    /// reachable from it.
     // TODO(darsor) return Plugnplay specific error
-    pub(crate) fn scan_plugnplay(mem: &mut dyn MemoryInterface) -> Result<Self, crate::Error> {
-        let devices = (0..PNP_NUM_RECORDS)
-            .map(|record_idx| PNP_BASE_ADDRESS + record_idx * PNP_RECORD_SIZE)
-            .map(|record_address| -> Result<_, crate::Error> {
-                let mut record_data = [0u32; 8];
-                mem.read_32(u64::from(record_address), &mut record_data)
-                    .map_err(|err| Leon3Error::PlugnPlayFailure {
-                        source: Box::new(err),
-                    })?;
-                Ok(record_data)
-            })
-            .filter_map(|record_data| match record_data {
-                Ok(data) => Record::from_data(&data).map(Ok),
-                Err(e) => Some(Err(e)),
-            })
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|err| Leon3Error::PlugnPlayFailure {
-                source: Box::new(err),
-            })?;
+    pub(crate) fn scan_plugnplay(
+        mem: &mut dyn MemoryInterface,
+        base: u64,
+    ) -> Result<Self, crate::Error> {
+        let mut devices = vec![];
+
+        // Follow AHB/AHB bridges to the buses behind them. Bridges are usually
+        // bidirectional, so each plug&play area is only scanned once.
+        let mut pending = vec![base];
+        let mut scanned = vec![];
+        while let Some(area) = pending.pop() {
+            if scanned.contains(&area) {
+                continue;
+            }
+            scanned.push(area);
+
+            let (bus_devices, secondary_buses) =
+                Self::scan_ahb(mem, area).map_err(|err| Leon3Error::PlugnPlayFailure {
+                    source: Box::new(err),
+                })?;
+            devices.extend(bus_devices);
+            pending.extend(
+                secondary_buses
+                    .into_iter()
+                    .map(|io_area| io_area + PNP_AREA_OFFSET),
+            );
+        }
+
+        let bridges: Vec<u64> = devices
+            .iter()
+            .filter(|record| record.device == Device::Gaisler(GaislerDevice::APBMST))
+            .filter_map(Record::base_address)
+            .collect();
+        for bridge in bridges {
+            let apb_devices =
+                Self::scan_apb(mem, bridge).map_err(|err| Leon3Error::PlugnPlayFailure {
+                    source: Box::new(err),
+                })?;
+            devices.extend(apb_devices);
+        }
+
         tracing::info!("Plug&Play scan complete: {devices:#?}");
         Ok(Self { devices })
     }
 
-    pub(crate) fn find_device(&self, device: Device) -> Option<&Record> {
-        self.devices.iter().find(|record| record.device == device)
+    /// Scan the AHB plug&play area at `area`.
+    ///
+    /// Returns the devices of the bus and the I/O areas of the buses behind AHB/AHB bridges.
+    fn scan_ahb(
+        mem: &mut dyn MemoryInterface,
+        area: u64,
+    ) -> Result<(Vec<Record>, Vec<u64>), crate::Error> {
+        let mut devices = vec![];
+        let mut secondary_buses = vec![];
+        for record_idx in 0..PNP_NUM_RECORDS {
+            let record_address = area + record_idx * PNP_RECORD_SIZE;
+            let mut record_data = [0u32; 8];
+            mem.read_32(record_address, &mut record_data)?;
+            let bus = if record_idx < PNP_NUM_MASTERS {
+                Bus::AhbMaster
+            } else {
+                Bus::AhbSlave
+            };
+            let Some(record) = Record::from_data(bus, &record_data) else {
+                continue;
+            };
+            if bus == Bus::AhbSlave
+                && let Some(secondary_bus) = record.secondary_bus(&record_data)
+            {
+                secondary_buses.push(secondary_bus);
+            }
+            devices.push(record);
+        }
+        Ok((devices, secondary_buses))
+    }
+
+    fn scan_apb(mem: &mut dyn MemoryInterface, bridge: u64) -> Result<Vec<Record>, crate::Error> {
+        let mut record_data = [0u32; 2 * APB_PNP_NUM_RECORDS as usize];
+        mem.read_32(bridge + APB_PNP_OFFSET, &mut record_data)?;
+        Ok(record_data
+            .chunks_exact((APB_PNP_RECORD_SIZE / 4) as usize)
+            .filter_map(|data| Record::from_data(Bus::Apb { bridge }, data))
+            .collect())
+    }
+
+    /// All devices found in the plug&play areas.
+    pub fn records(&self) -> &[Record] {
+        &self.devices
+    }
+
+    /// The first record of the given device.
+    pub fn find_device(&self, device: Device) -> Option<&Record> {
+        self.find_devices(device).next()
+    }
+
+    /// All records of the given device, for example all APBUARTs of a system.
+    pub fn find_devices(&self, device: Device) -> impl Iterator<Item = &Record> {
+        self.devices
+            .iter()
+            .filter(move |record| record.device == device)
+    }
+
+    /// The register base address of the given device, from its first APB record. Devices
+    /// like memory controllers also have an AHB record for the memory they map.
+    pub(crate) fn find_apb_registers(&self, device: Device) -> Option<u64> {
+        self.find_devices(device)
+            .find(|record| matches!(record.bus, Bus::Apb { .. }))
+            .and_then(Record::base_address)
+    }
+
+    /// Memory regions for the RAM and ROM areas of the memory controllers and on-chip memories
+    /// found in the plug&play records, accessible by the given cores.
+    ///
+    /// The regions cover the full address window decoded by the controller, which may be
+    /// larger than the memory which is actually fitted.
+    pub fn memory_regions(&self, cores: &[String]) -> Vec<MemoryRegion> {
+        let mut regions = vec![];
+        for record in &self.devices {
+            let Some((name, bars)) = record.memory_bars() else {
+                continue;
+            };
+            for (bar, kind) in bars {
+                let Some(space) = record
+                    .address_spaces
+                    .iter()
+                    .find(|space| space.bar == *bar && space.kind == AddressSpaceKind::AhbMemory)
+                else {
+                    continue;
+                };
+                let range = space.addresses.clone();
+                let region = match kind {
+                    MemoryKind::Ram => MemoryRegion::Ram(RamRegion {
+                        name: Some(format!("{name} RAM")),
+                        range,
+                        cores: cores.to_vec(),
+                        access: None,
+                    }),
+                    MemoryKind::Rom => MemoryRegion::Generic(GenericRegion {
+                        name: Some(format!("{name} PROM")),
+                        range,
+                        cores: cores.to_vec(),
+                        access: Some(MemoryAccess {
+                            write: false,
+                            ..Default::default()
+                        }),
+                    }),
+                    MemoryKind::Io => continue,
+                };
+                regions.push(region);
+            }
+        }
+        regions
+    }
+
+    /// Memory regions for the I/O areas found in the plug&play records, accessible by the
+    /// given cores: the AHB I/O areas, the APB areas of the AHB/APB bridges and the I/O
+    /// areas of the memory controllers.
+    ///
+    /// The regions are not executable, so that a debugger does not place software
+    /// breakpoints in them.
+    pub fn io_regions(&self, cores: &[String]) -> Vec<MemoryRegion> {
+        let mut regions: Vec<MemoryRegion> = vec![];
+        for record in &self.devices {
+            let mut areas: Vec<(String, Range<u64>)> = record
+                .address_spaces
+                .iter()
+                .filter(|space| space.kind == AddressSpaceKind::AhbIO)
+                .map(|space| (record.device.to_string(), space.addresses.clone()))
+                .collect();
+
+            if let Device::Gaisler(device @ (GaislerDevice::APBMST | GaislerDevice::APB3MST)) =
+                record.device
+            {
+                areas.extend(
+                    record
+                        .address_spaces
+                        .iter()
+                        .filter(|space| space.kind == AddressSpaceKind::AhbMemory)
+                        .map(|space| (device.to_string(), space.addresses.clone())),
+                );
+            }
+
+            if let Some((name, bars)) = record.memory_bars() {
+                areas.extend(
+                    bars.iter()
+                        .filter(|(_, kind)| *kind == MemoryKind::Io)
+                        .filter_map(|(bar, _)| {
+                            record.address_spaces.iter().find(|space| {
+                                space.bar == *bar && space.kind == AddressSpaceKind::AhbMemory
+                            })
+                        })
+                        .map(|space| (name.to_string(), space.addresses.clone())),
+                );
+            }
+
+            for (name, range) in areas {
+                if regions.iter().any(|region| region.address_range() == range) {
+                    continue;
+                }
+                regions.push(MemoryRegion::Generic(GenericRegion {
+                    name: Some(format!("{name} I/O")),
+                    range,
+                    cores: cores.to_vec(),
+                    access: Some(MemoryAccess {
+                        execute: false,
+                        ..Default::default()
+                    }),
+                }));
+            }
+        }
+        regions
+    }
+
+    /// The number of records of the given device.
+    pub(crate) fn count_devices(&self, device: Device) -> usize {
+        self.find_devices(device).count()
     }
 }
 
 /// Vendor codes
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Device {
+    /// Vendor ID 0, used for empty records.
     Reserved,
+    /// A Frontgrade Gaisler device.
     Gaisler(GaislerDevice),
-    Unknown { vendor_id: u8, device_id: u16 },
+    /// A European Space Agency device.
+    Esa(EsaDevice),
+    /// A device of another vendor.
+    Unknown {
+        /// The vendor ID.
+        vendor_id: u8,
+        /// The device ID.
+        device_id: u16,
+    },
 }
 
 impl Device {
+    /// The kind of function the device provides.
+    pub fn category(&self) -> DeviceCategory {
+        match self {
+            Device::Gaisler(device) => device.category(),
+            Device::Esa(device) => device.category(),
+            Device::Reserved | Device::Unknown { .. } => DeviceCategory::Other,
+        }
+    }
+
     fn from_ids(vendor_id: u8, device_id: u16) -> Self {
         match vendor_id {
             0x00 => Self::Reserved,
             0x01 => Self::Gaisler(GaislerDevice::from_id(device_id)),
+            0x04 => Self::Esa(EsaDevice::from_id(device_id)),
             _ => Self::Unknown {
                 vendor_id,
                 device_id,
@@ -180,18 +537,24 @@ impl Display for Device {
         match self {
             Device::Reserved => write!(f, "Reserved"),
             Device::Gaisler(device) => write!(f, "Frontgrade Gaisler {device}"),
+            Device::Esa(device) => write!(f, "European Space Agency {device}"),
             Device::Unknown {
                 vendor_id,
                 device_id,
-            } => write!(
-                f,
-                "Unknown (Vendor 0x{vendor_id:02X}, Device 0x{device_id:03X}"
-            ),
+            } => match vendor_name(*vendor_id) {
+                Some(vendor) => write!(f, "{vendor} Unknown Device (ID 0x{device_id:03X})"),
+                None => write!(
+                    f,
+                    "Unknown (Vendor 0x{vendor_id:02X}, Device 0x{device_id:03X})"
+                ),
+            },
         }
     }
 }
 
-#[allow(non_camel_case_types)]
+/// Frontgrade Gaisler device IDs, named after the GRLIB device constants. The
+/// [`Display`] implementation describes them.
+#[allow(non_camel_case_types, clippy::upper_case_acronyms, missing_docs)]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum GaislerDevice {
     LEON2DSU,
@@ -877,3 +1240,493 @@ impl Display for GaislerDevice {
         }
     }
 }
+
+/// The names of the vendors which have been assigned an AMBA plug&play vendor ID, following
+/// the GRLIB vendor constants.
+fn vendor_name(vendor_id: u8) -> Option<&'static str> {
+    Some(match vendor_id {
+        0x01 => "Frontgrade Gaisler",
+        0x02 => "Pender Electronic Design",
+        0x04 => "European Space Agency",
+        0x06 => "Astrium EADS",
+        0x07 => "OpenChip",
+        0x08 => "OpenCores",
+        0x09 => "Various contributions",
+        0x0A => "German Aerospace Center",
+        0x0B => "Eonic BV",
+        0x0C => "Telecom ParisTech",
+        0x0D => "Technical University of Denmark",
+        0x0E => "Barcelona Supercomputing Center",
+        0x0F => "Radionor Communications",
+        0x10 => "Gleichmann Electronics",
+        0x11 => "Menta",
+        0x13 => "Sun Microsystems",
+        0x14 => "Movidia",
+        0x17 => "Orbita",
+        0x21 => "Synopsys",
+        0x22 => "NASA",
+        0x31 => "S3 Group",
+        0xAC => "Actel Corporation",
+        0xAE => "AppleCore",
+        0xC8 => "CBK PAN",
+        0xEA => "Embedd.it",
+        0xFC => "NASA Goddard Space Flight Center",
+        _ => return None,
+    })
+}
+
+/// The kind of function a device provides.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DeviceCategory {
+    /// A processor core.
+    Processor,
+    /// Debug support units, debug links and trace buffers.
+    Debug,
+    /// A controller for external or on-chip memory.
+    MemoryController,
+    /// On-chip RAM or ROM.
+    OnChipMemory,
+    /// A bridge to another bus, including caches and IOMMUs.
+    Bridge,
+    /// An interrupt controller.
+    InterruptController,
+    /// A timer or watchdog.
+    Timer,
+    /// A UART, SPI or I2C controller.
+    Serial,
+    /// General purpose I/O and registers.
+    Gpio,
+    /// Ethernet, SpaceWire, CAN, MIL-STD-1553 and other network interfaces.
+    Network,
+    /// A PCI or PCI Express controller.
+    Pci,
+    /// A USB controller.
+    Usb,
+    /// A DMA controller.
+    Dma,
+    /// Spacecraft telemetry and telecommand.
+    Telemetry,
+    /// Any other device.
+    Other,
+}
+
+impl Display for DeviceCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Processor => write!(f, "Processor"),
+            Self::Debug => write!(f, "Debug"),
+            Self::MemoryController => write!(f, "Memory Controller"),
+            Self::OnChipMemory => write!(f, "On-chip Memory"),
+            Self::Bridge => write!(f, "Bridge"),
+            Self::InterruptController => write!(f, "Interrupt Controller"),
+            Self::Timer => write!(f, "Timer"),
+            Self::Serial => write!(f, "Serial"),
+            Self::Gpio => write!(f, "GPIO"),
+            Self::Network => write!(f, "Network"),
+            Self::Pci => write!(f, "PCI"),
+            Self::Usb => write!(f, "USB"),
+            Self::Dma => write!(f, "DMA"),
+            Self::Telemetry => write!(f, "Telemetry"),
+            Self::Other => write!(f, "Other"),
+        }
+    }
+}
+
+impl GaislerDevice {
+    /// The kind of function the device provides.
+    pub fn category(&self) -> DeviceCategory {
+        match self {
+            Self::LEON3 | Self::LEON3FT | Self::LEON4 | Self::LEON5 | Self::RV64GC => {
+                DeviceCategory::Processor
+            }
+            Self::LEON2DSU
+            | Self::LEON3DSU
+            | Self::LEON4DSU
+            | Self::LEON5DSU
+            | Self::LEON5ADSU
+            | Self::RVDM
+            | Self::AHBJTAG
+            | Self::AHBUART
+            | Self::EDCLMST
+            | Self::AHBTRACE
+            | Self::DSUCTRL
+            | Self::L3STAT
+            | Self::L4STAT
+            | Self::L5STAT
+            | Self::ETRACE
+            | Self::DFITRACE => DeviceCategory::Debug,
+            Self::SRCTRL
+            | Self::SDCTRL
+            | Self::SSRCTRL
+            | Self::DDRMP
+            | Self::DDRSP
+            | Self::DDR2SP
+            | Self::SDCTRL64
+            | Self::FTSRCTRL
+            | Self::FTMCTRL
+            | Self::FTMCTRL2
+            | Self::FTSDCTRL
+            | Self::FTSRCTRL8
+            | Self::FTSDCTRL64
+            | Self::MEMSCRUB
+            | Self::SPIMCTRL
+            | Self::NANDFCTRL
+            | Self::NANDFCTRL2
+            | Self::MIGDDR2
+            | Self::MIG_7SERIES => DeviceCategory::MemoryController,
+            Self::AHBRAM
+            | Self::AHBDPRAM
+            | Self::AHBROM
+            | Self::AHBFROM
+            | Self::FTAHBRAM
+            | Self::LRAM => DeviceCategory::OnChipMemory,
+            Self::APBMST
+            | Self::APB3MST
+            | Self::AHB2AHB
+            | Self::AHB2AHB_STR
+            | Self::L2CACHE
+            | Self::L2CACHE_IO
+            | Self::GRIOMMU
+            | Self::GRIOMMU2
+            | Self::GRIOMMURV
+            | Self::AHB2AXI
+            | Self::AXI2AHB
+            | Self::AHB2AVLA
+            | Self::AHBLM2AHB
+            | Self::AHBS2NOC
+            | Self::SOCBRIDGE
+            | Self::IGLOO2_BRIDGE => DeviceCategory::Bridge,
+            Self::IRQMP
+            | Self::IRQGEN
+            | Self::GRPLIC
+            | Self::GRAPLIC
+            | Self::CLINT
+            | Self::ACLINT
+            | Self::IMSIC => DeviceCategory::InterruptController,
+            Self::GPTIMER | Self::GRTIMER | Self::GRWATCHDOG | Self::GRPPSTIMER => {
+                DeviceCategory::Timer
+            }
+            Self::APBUART
+            | Self::APBUART_16550
+            | Self::U16550
+            | Self::FIFOUART
+            | Self::I2CMST
+            | Self::I2CSLV
+            | Self::I2C2AHB
+            | Self::SPICTRL
+            | Self::SPIMASTER
+            | Self::SPISLAVE
+            | Self::SPI2AHB => DeviceCategory::Serial,
+            Self::GPIO | Self::GPREG | Self::GPREGBANK => DeviceCategory::Gpio,
+            Self::ETHAHB
+            | Self::ETHMAC
+            | Self::SGMII
+            | Self::RGMII
+            | Self::MDIO_CTRL
+            | Self::SPW
+            | Self::SPW2
+            | Self::SPW2_DMA
+            | Self::SPWROUTER
+            | Self::GRSPW2_SIST
+            | Self::CANAHB
+            | Self::GRCAN
+            | Self::GRHCAN
+            | Self::GRCANFD
+            | Self::SATCAN
+            | Self::CANMUX
+            | Self::GR1553B
+            | Self::B1553BC
+            | Self::B1553RT
+            | Self::B1553BRM
+            | Self::GRSRIO => DeviceCategory::Network,
+            Self::PCITRG
+            | Self::PCISBRG
+            | Self::PCIFBRG
+            | Self::PCITRACE
+            | Self::PCIF
+            | Self::PCIEXP
+            | Self::GRPCI2
+            | Self::GRPCI2_DMA => DeviceCategory::Pci,
+            Self::USBDC | Self::USB_DCL | Self::EHCI | Self::UHCI => DeviceCategory::Usb,
+            Self::DMACTRL | Self::AHBDMA | Self::GRDMAC | Self::GRDMAC2 => DeviceCategory::Dma,
+            Self::GRTM
+            | Self::GRTC
+            | Self::GRTMRX
+            | Self::GRTCTX
+            | Self::GRTMDESC
+            | Self::GRTMVC
+            | Self::GRTMPAHB
+            | Self::GRTMDYNVCID => DeviceCategory::Telemetry,
+            _ => DeviceCategory::Other,
+        }
+    }
+}
+
+/// European Space Agency device IDs, named after the GRLIB device constants. The
+/// [`Display`] implementation describes them.
+#[allow(non_camel_case_types, clippy::upper_case_acronyms, missing_docs)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EsaDevice {
+    LEON2,
+    LEON2APB,
+    IRQ,
+    TIMER,
+    UART,
+    CFG,
+    IO,
+    MCTRL,
+    PCIARB,
+    HURRICANE,
+    SPW_RMAP,
+    AHBUART,
+    SPWA,
+    BOSCHCAN,
+    IRQ2,
+    AHBSTAT,
+    WPROT,
+    WPROT2,
+    PDEC3AMBA,
+    PTME3AMBA,
+    Unknown { device_id: u16 },
+}
+
+impl EsaDevice {
+    fn from_id(device_id: u16) -> Self {
+        match device_id {
+            0x002 => Self::LEON2,
+            0x003 => Self::LEON2APB,
+            0x005 => Self::IRQ,
+            0x006 => Self::TIMER,
+            0x007 => Self::UART,
+            0x008 => Self::CFG,
+            0x009 => Self::IO,
+            0x00F => Self::MCTRL,
+            0x010 => Self::PCIARB,
+            0x011 => Self::HURRICANE,
+            0x012 => Self::SPW_RMAP,
+            0x013 => Self::AHBUART,
+            0x014 => Self::SPWA,
+            0x015 => Self::BOSCHCAN,
+            0x016 => Self::IRQ2,
+            0x017 => Self::AHBSTAT,
+            0x018 => Self::WPROT,
+            0x019 => Self::WPROT2,
+            0x020 => Self::PDEC3AMBA,
+            0x021 => Self::PTME3AMBA,
+            _ => Self::Unknown { device_id },
+        }
+    }
+
+    /// The kind of function the device provides.
+    pub fn category(&self) -> DeviceCategory {
+        match self {
+            Self::LEON2 => DeviceCategory::Processor,
+            Self::LEON2APB => DeviceCategory::Bridge,
+            Self::IRQ | Self::IRQ2 => DeviceCategory::InterruptController,
+            Self::TIMER => DeviceCategory::Timer,
+            Self::UART => DeviceCategory::Serial,
+            Self::MCTRL => DeviceCategory::MemoryController,
+            Self::AHBUART => DeviceCategory::Debug,
+            Self::SPW_RMAP | Self::SPWA | Self::BOSCHCAN => DeviceCategory::Network,
+            Self::PDEC3AMBA | Self::PTME3AMBA => DeviceCategory::Telemetry,
+            _ => DeviceCategory::Other,
+        }
+    }
+}
+
+impl Display for EsaDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LEON2 => write!(f, "LEON2 SPARC V8 Processor"),
+            Self::LEON2APB => write!(f, "LEON2 AHB/APB Bridge"),
+            Self::IRQ => write!(f, "LEON2 Interrupt Controller"),
+            Self::TIMER => write!(f, "LEON2 Timer"),
+            Self::UART => write!(f, "LEON2 UART"),
+            Self::CFG => write!(f, "LEON2 Configuration Register"),
+            Self::IO => write!(f, "LEON2 Input/Output"),
+            Self::MCTRL => write!(f, "LEON2 Memory Controller"),
+            Self::PCIARB => write!(f, "PCI Arbiter"),
+            Self::HURRICANE => write!(f, "HURRICANE Processor"),
+            Self::SPW_RMAP => write!(f, "UoD/Saab SpaceWire RMAP"),
+            Self::AHBUART => write!(f, "LEON2 AHB Debug UART"),
+            Self::SPWA => write!(f, "ESA SpaceWire Core"),
+            Self::BOSCHCAN => write!(f, "SSC/BOSCH CAN Core"),
+            Self::IRQ2 => write!(f, "LEON2 Secondary Interrupt Controller"),
+            Self::AHBSTAT => write!(f, "LEON2 AHB Status Register"),
+            Self::WPROT => write!(f, "LEON2 RAM Write Protection"),
+            Self::WPROT2 => write!(f, "LEON2 Extended RAM Write Protection"),
+            Self::PDEC3AMBA => write!(f, "ESA CCSDS PDEC3AMBA TC Decoder"),
+            Self::PTME3AMBA => write!(f, "ESA CCSDS PTME3AMBA TM Encoder"),
+            Self::Unknown { device_id } => {
+                write!(f, "Unknown Device (ID 0x{device_id:03X})")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::MockMemory;
+
+    #[test]
+    fn decode_apb_record() {
+        // IRQMP, version 3, at offset 0x200 of the bridge, 256 bytes
+        let data = [0x0100_D060, 0x0020_FFF1];
+        let record = Record::from_data(
+            Bus::Apb {
+                bridge: 0x8000_0000,
+            },
+            &data,
+        )
+        .unwrap();
+        assert_eq!(record.device, Device::Gaisler(GaislerDevice::IRQMP));
+        assert_eq!(record.version, 3);
+        assert_eq!(record.address_spaces.len(), 1);
+        assert_eq!(record.address_spaces[0].addresses, 0x8000_0200..0x8000_0300);
+    }
+
+    #[test]
+    fn scan_finds_apb_devices() {
+        let mut mem = MockMemory::new();
+
+        // AHB/APB bridge at 0x8000_0000 with an APBUART and a GPTIMER behind it.
+        let mut ahb = vec![0u32; 8 * PNP_NUM_RECORDS as usize];
+        let bridge = 8 * PNP_NUM_MASTERS as usize;
+        ahb[bridge] = 0x0100_6000;
+        ahb[bridge + 4] = 0x800F_FFF2;
+        mem.add_word_range(DEFAULT_PLUGNPLAY_BASE, &ahb);
+
+        let mut apb = vec![0u32; 2 * APB_PNP_NUM_RECORDS as usize];
+        apb[0] = 0x0100_C022;
+        apb[1] = 0x0010_FFF1;
+        apb[2] = 0x0101_1068;
+        apb[3] = 0x0030_FFF1;
+        mem.add_word_range(0x8000_0000 + APB_PNP_OFFSET, &apb);
+
+        let state = PlugnPlayState::scan_plugnplay(&mut mem, DEFAULT_PLUGNPLAY_BASE).unwrap();
+        let uart = state
+            .find_device(Device::Gaisler(GaislerDevice::APBUART))
+            .unwrap();
+        assert_eq!(
+            uart.bus,
+            Bus::Apb {
+                bridge: 0x8000_0000
+            }
+        );
+        assert_eq!(uart.base_address(), Some(0x8000_0100));
+        assert_eq!(uart.irq, 2);
+
+        let timer = state
+            .find_device(Device::Gaisler(GaislerDevice::GPTIMER))
+            .unwrap();
+        assert_eq!(timer.base_address(), Some(0x8000_0300));
+        assert_eq!(timer.irq, 8);
+    }
+
+    #[test]
+    fn memory_regions_from_memory_controller() {
+        // FTMCTRL with PROM at 0x0000_0000, I/O at 0x2000_0000 and SRAM at 0x4000_0000.
+        let data = [
+            0x0105_4000,
+            0,
+            0,
+            0,
+            0x0003_E002,
+            0x2002_E002,
+            0x4003_C002,
+            0,
+        ];
+        let record = Record::from_data(Bus::AhbSlave, &data).unwrap();
+        let state = PlugnPlayState {
+            devices: vec![record],
+        };
+
+        let regions = state.memory_regions(&["core0".to_string()]);
+        assert_eq!(regions.len(), 2);
+        assert!(!regions[0].is_ram());
+        assert_eq!(regions[0].address_range(), 0x0000_0000..0x2000_0000);
+        assert!(regions[1].is_ram());
+        assert_eq!(regions[1].address_range(), 0x4000_0000..0x8000_0000);
+
+        let regions = state.io_regions(&["core0".to_string()]);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].address_range(), 0x2000_0000..0x4000_0000);
+    }
+
+    #[test]
+    fn io_regions_from_apb_bridge() {
+        // APBCTRL with its APB area at 0x8000_0000.
+        let data = [0x0100_6000, 0, 0, 0, 0x8000_FFF2, 0, 0, 0];
+        let record = Record::from_data(Bus::AhbSlave, &data).unwrap();
+        let state = PlugnPlayState {
+            devices: vec![record],
+        };
+
+        assert!(state.memory_regions(&["core0".to_string()]).is_empty());
+
+        let regions = state.io_regions(&["core0".to_string()]);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].address_range(), 0x8000_0000..0x8010_0000);
+        let MemoryRegion::Generic(region) = &regions[0] else {
+            panic!("expected a generic region");
+        };
+        assert_eq!(region.name.as_deref(), Some("AHB/APB Bridge I/O"));
+        assert!(!region.access.unwrap().execute);
+    }
+
+    #[test]
+    fn decode_device_ids() {
+        let mctrl = Device::from_ids(0x04, 0x00F);
+        assert_eq!(mctrl, Device::Esa(EsaDevice::MCTRL));
+        assert_eq!(mctrl.category(), DeviceCategory::MemoryController);
+
+        let irqmp = Device::from_ids(0x01, 0x00D);
+        assert_eq!(irqmp.category(), DeviceCategory::InterruptController);
+
+        assert_eq!(
+            Device::from_ids(0x08, 0x123).to_string(),
+            "OpenCores Unknown Device (ID 0x123)"
+        );
+    }
+
+    #[test]
+    fn scan_follows_ahb_bridges() {
+        let mut mem = MockMemory::new();
+
+        // Primary bus: LEON3 master and an AHB2AHB bridge slave, which leads to a
+        // secondary bus with the I/O area 0xEFF0_0000.
+        let mut primary = vec![0u32; 8 * PNP_NUM_RECORDS as usize];
+        primary[0] = 0x0100_3000;
+        let bridge = 8 * PNP_NUM_MASTERS as usize;
+        primary[bridge] = 0x0102_0020;
+        primary[bridge + 2] = 0xEFF0_0000;
+        primary[bridge + 4] = 0x000F_F002;
+        mem.add_word_range(DEFAULT_PLUGNPLAY_BASE, &primary);
+
+        // Secondary bus: the bridge seen from the other side, pointing back to the primary bus,
+        // and an AHB RAM slave.
+        let mut secondary = vec![0u32; 8 * PNP_NUM_RECORDS as usize];
+        secondary[0] = 0x0102_0020;
+        secondary[2] = 0xFFF0_0000;
+        secondary[bridge] = 0x0100_E000;
+        secondary[bridge + 4] = 0xA00F_FFF2;
+        mem.add_word_range(0xEFF0_0000 + PNP_AREA_OFFSET, &secondary);
+
+        let state = PlugnPlayState::scan_plugnplay(&mut mem, DEFAULT_PLUGNPLAY_BASE).unwrap();
+        let devices: Vec<_> = state.records().iter().map(|record| record.device).collect();
+        assert_eq!(
+            devices,
+            [
+                Device::Gaisler(GaislerDevice::LEON3),
+                Device::Gaisler(GaislerDevice::AHB2AHB),
+                Device::Gaisler(GaislerDevice::AHB2AHB),
+                Device::Gaisler(GaislerDevice::AHBRAM),
+            ]
+        );
+        assert_eq!(
+            state.records()[3].address_spaces[0].addresses,
+            0xA000_0000..0xA010_0000
+        );
+    }
+}
@@ -0,0 +1,764 @@
+//! Programming of the PROM area of the MCTRL and FTMCTRL memory controllers.
+//!
+//! The memory is programmed from the host through the debug link. CFI parallel NOR flash is
+//! programmed one bus word at a time. A PROM bus wider than the flash devices is assumed to
+//! be shared by identical devices, like two 16-bit devices on a 32-bit bus, which all receive
+//! the same commands. MRAM and EEPROM are written without commands, like SRAM.
+
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use probe_rs_target::Leon3PromMemory;
+
+use crate::{
+    MemoryInterface,
+    architecture::leon3::communication_interface::{Leon3CommunicationInterface, Leon3Error},
+};
+
+/// The CFI query command.
+const CMD_CFI_QUERY: u8 = 0x98;
+
+/// The offset the CFI query command is written to.
+const CFI_QUERY_OFFSET: u64 = 0x55;
+
+/// The offset of the "QRY" signature in the CFI query table.
+const CFI_SIGNATURE_OFFSET: u64 = 0x10;
+
+/// The commands of the Intel/Sharp command sets.
+const INTEL_READ_ARRAY: u8 = 0xFF;
+const INTEL_CLEAR_STATUS: u8 = 0x50;
+const INTEL_PROGRAM: u8 = 0x40;
+const INTEL_BLOCK_ERASE: u8 = 0x20;
+const INTEL_LOCK_SETUP: u8 = 0x60;
+const INTEL_CONFIRM: u8 = 0xD0;
+
+/// The ready bit of the Intel status register.
+const INTEL_STATUS_READY: u8 = 0x80;
+
+/// The error bits of the Intel status register: erase, program, voltage and lock errors.
+const INTEL_STATUS_ERRORS: u8 = 0x3A;
+
+/// The commands of the AMD/Fujitsu command set.
+const AMD_RESET: u8 = 0xF0;
+const AMD_UNLOCK_1: (u64, u8) = (0x555, 0xAA);
+const AMD_UNLOCK_2: (u64, u8) = (0x2AA, 0x55);
+const AMD_PROGRAM: u8 = 0xA0;
+const AMD_ERASE_SETUP: u8 = 0x80;
+const AMD_SECTOR_ERASE: u8 = 0x30;
+
+/// Timeouts for devices that do not specify them in their CFI query table.
+const DEFAULT_PROGRAM_TIMEOUT: Duration = Duration::from_millis(10);
+const DEFAULT_ERASE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The write cycle timeout for EEPROMs that do not configure it.
+const DEFAULT_EEPROM_WRITE_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// The amount of data programmed between progress reports, at most.
+pub(crate) const PROGRESS_CHUNK: u64 = 4 * 1024;
+
+/// How [`Leon3::write_prom`](super::Leon3::write_prom) prepares the sectors it writes to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PromWriteOptions {
+    /// Erase the sectors before programming them. Otherwise, they must already be erased.
+    pub erase: bool,
+    /// Restore the bytes of the erased sectors that are not written.
+    pub keep_unwritten_bytes: bool,
+}
+
+/// The progress of [`Leon3::write_prom_with_progress`](super::Leon3::write_prom_with_progress),
+/// in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromProgress {
+    /// A sector of the given size was erased.
+    Erased(u64),
+    /// The given number of bytes were programmed.
+    Programmed(u64),
+}
+
+/// The command set of a CFI flash device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CfiCommandSet {
+    /// The Intel/Sharp extended or standard command set.
+    Intel,
+    /// The AMD/Fujitsu standard command set.
+    Amd,
+}
+
+/// A range of equally sized erase sectors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EraseRegion {
+    /// The size of each sector in bytes, across all devices on the bus.
+    pub sector_size: u64,
+    /// The number of sectors.
+    pub sectors: u64,
+}
+
+/// The description of the flash in the PROM area, from its CFI query table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CfiInfo {
+    /// The command set of the flash devices.
+    pub command_set: CfiCommandSet,
+    /// The size of the flash in bytes, across all devices on the bus.
+    pub size: u64,
+    /// The erase sectors, from the start of the flash.
+    pub erase_regions: Vec<EraseRegion>,
+    /// The width of the PROM bus in bytes.
+    pub bus_width: usize,
+    /// The width of each flash device in bytes.
+    pub device_width: usize,
+    /// The maximum time to program a single word.
+    pub program_timeout: Duration,
+    /// The maximum time to erase a single sector.
+    pub erase_timeout: Duration,
+    /// The distance between the addresses of consecutive command offsets.
+    stride: u64,
+}
+
+impl CfiInfo {
+    /// Decode the CFI query table, read with `query` by offset.
+    fn parse(
+        mut query: impl FnMut(u64) -> Result<u8, crate::Error>,
+        bus_width: usize,
+        device_width: usize,
+        stride: u64,
+    ) -> Result<Self, crate::Error> {
+        fn word(
+            query: &mut impl FnMut(u64) -> Result<u8, crate::Error>,
+            offset: u64,
+        ) -> Result<u16, crate::Error> {
+            Ok(u16::from_le_bytes([query(offset)?, query(offset + 1)?]))
+        }
+        let command_set = match word(&mut query, 0x13)? {
+            0x0001 | 0x0003 => CfiCommandSet::Intel,
+            0x0002 => CfiCommandSet::Amd,
+            command_set => return Err(Leon3Error::UnsupportedCfiCommandSet(command_set).into()),
+        };
+
+        // Typical times are given as powers of two, and the maximum as a power of two
+        // multiple of those.
+        let timeout = |typical: u8, max: u8, unit: Duration, default| match typical {
+            0 => default,
+            _ => 1u32
+                .checked_shl(u32::from(typical) + u32::from(max))
+                .map_or(default, |factor| unit * factor),
+        };
+        let program_timeout = timeout(
+            query(0x1F)?,
+            query(0x23)?,
+            Duration::from_micros(1),
+            DEFAULT_PROGRAM_TIMEOUT,
+        );
+        let erase_timeout = timeout(
+            query(0x21)?,
+            query(0x25)?,
+            Duration::from_millis(1),
+            DEFAULT_ERASE_TIMEOUT,
+        );
+
+        let devices = (bus_width / device_width) as u64;
+        let size = 1u64
+            .checked_shl(u32::from(query(0x27)?))
+            .ok_or(Leon3Error::NoCfiFlash)?
+            * devices;
+        let mut erase_regions = vec![];
+        for region in 0..u64::from(query(0x2C)?) {
+            let offset = 0x2D + 4 * region;
+            let sectors = u64::from(word(&mut query, offset)?) + 1;
+            let sector_size = match word(&mut query, offset + 2)? {
+                0 => 128,
+                size => u64::from(size) * 256,
+            };
+            erase_regions.push(EraseRegion {
+                sector_size: sector_size * devices,
+                sectors,
+            });
+        }
+
+        Ok(Self {
+            command_set,
+            size,
+            erase_regions,
+            bus_width,
+            device_width,
+            program_timeout,
+            erase_timeout,
+            stride,
+        })
+    }
+
+    /// The sectors overlapping `range`, as offsets from the start of the flash.
+    pub fn sectors(&self, range: Range<u64>) -> Vec<Range<u64>> {
+        let mut sectors = vec![];
+        let mut start = 0;
+        for region in &self.erase_regions {
+            for _ in 0..region.sectors {
+                let sector = start..start + region.sector_size;
+                if sector.start < range.end && range.start < sector.end {
+                    sectors.push(sector.clone());
+                }
+                start = sector.end;
+            }
+        }
+        sectors
+    }
+}
+
+/// `value` repeated for every device on the bus.
+fn replicate(value: u32, device_width: usize, bus_width: usize) -> u32 {
+    let mut word = 0u64;
+    for _ in 0..bus_width / device_width {
+        word = (word << (device_width * 8)) | u64::from(value);
+    }
+    word as u32
+}
+
+/// Word accesses to the flash devices on the PROM bus.
+struct PromBus<'a> {
+    memory: &'a mut dyn MemoryInterface,
+    base: u64,
+    bus_width: usize,
+    device_width: usize,
+    stride: u64,
+}
+
+impl PromBus<'_> {
+    fn read(&mut self, address: u64) -> Result<u32, crate::Error> {
+        Ok(match self.bus_width {
+            1 => u32::from(self.memory.read_word_8(address)?),
+            2 => u32::from(self.memory.read_word_16(address)?),
+            _ => self.memory.read_word_32(address)?,
+        })
+    }
+
+    fn write(&mut self, address: u64, value: u32) -> Result<(), crate::Error> {
+        match self.bus_width {
+            1 => self.memory.write_word_8(address, value as u8),
+            2 => self.memory.write_word_16(address, value as u16),
+            _ => self.memory.write_word_32(address, value),
+        }
+    }
+
+    /// Write a command to all devices, at a command offset.
+    fn command(&mut self, offset: u64, command: u8) -> Result<(), crate::Error> {
+        self.command_at(self.base + offset * self.stride, command)
+    }
+
+    /// Write a command to all devices, at a bus address.
+    fn command_at(&mut self, address: u64, command: u8) -> Result<(), crate::Error> {
+        let value = replicate(u32::from(command), self.device_width, self.bus_width);
+        self.write(address, value)
+    }
+
+    /// Read a byte of the CFI query table of the first device.
+    fn query(&mut self, offset: u64) -> Result<u8, crate::Error> {
+        Ok(self.read(self.base + offset * self.stride)? as u8)
+    }
+
+    /// Wait for the status register of all Intel devices to report ready.
+    fn intel_wait(
+        &mut self,
+        address: u64,
+        timeout: Duration,
+        operation: &'static str,
+    ) -> Result<(), crate::Error> {
+        let ready = replicate(
+            u32::from(INTEL_STATUS_READY),
+            self.device_width,
+            self.bus_width,
+        );
+        let errors = replicate(
+            u32::from(INTEL_STATUS_ERRORS),
+            self.device_width,
+            self.bus_width,
+        );
+        let start = Instant::now();
+        let status = loop {
+            let status = self.read(address)?;
+            if status & ready == ready {
+                break status;
+            }
+            if start.elapsed() > timeout {
+                return Err(Leon3Error::Timeout.into());
+            }
+        };
+        if status & errors != 0 {
+            self.command_at(address, INTEL_CLEAR_STATUS)?;
+            self.command_at(address, INTEL_READ_ARRAY)?;
+            return Err(Leon3Error::PromOperationFailed { operation, address }.into());
+        }
+        self.command_at(address, INTEL_READ_ARRAY)
+    }
+
+    /// Wait for an AMD device operation to complete, which is the case when the toggle bit
+    /// stops toggling between two reads.
+    fn amd_wait(&mut self, address: u64, timeout: Duration) -> Result<(), crate::Error> {
+        let start = Instant::now();
+        let mut previous = self.read(address)?;
+        loop {
+            let current = self.read(address)?;
+            if current == previous {
+                return Ok(());
+            }
+            if start.elapsed() > timeout {
+                self.command_at(address, AMD_RESET)?;
+                return Err(Leon3Error::Timeout.into());
+            }
+            previous = current;
+        }
+    }
+
+    fn amd_unlock(&mut self) -> Result<(), crate::Error> {
+        self.command(AMD_UNLOCK_1.0, AMD_UNLOCK_1.1)?;
+        self.command(AMD_UNLOCK_2.0, AMD_UNLOCK_2.1)
+    }
+}
+
+/// The flash in the PROM area of a memory controller.
+pub(crate) struct Prom {
+    base: u64,
+    info: CfiInfo,
+}
+
+impl Prom {
+    /// Identify the flash at `base` on a PROM bus of `bus_width` bytes.
+    ///
+    /// PROM writes must be enabled in the memory controller.
+    pub(crate) fn identify(
+        interface: &mut Leon3CommunicationInterface,
+        base: u64,
+        bus_width: usize,
+    ) -> Result<Self, crate::Error> {
+        // Devices with a configurable width, used in their narrowest mode, take byte
+        // addresses, which doubles the command offsets.
+        for stride in [bus_width as u64, 2 * bus_width as u64] {
+            for device_width in [1, 2, 4].into_iter().filter(|width| *width <= bus_width) {
+                let mut bus = PromBus {
+                    memory: interface.as_memory_interface_mut(),
+                    base,
+                    bus_width,
+                    device_width,
+                    stride,
+                };
+                bus.command(CFI_QUERY_OFFSET, CMD_CFI_QUERY)?;
+                let signature = bus.read(base + CFI_SIGNATURE_OFFSET * stride)?;
+                let found = signature == replicate(u32::from(b'Q'), device_width, bus_width)
+                    && bus.query(CFI_SIGNATURE_OFFSET + 1)? == b'R'
+                    && bus.query(CFI_SIGNATURE_OFFSET + 2)? == b'Y';
+                let info = found.then(|| {
+                    CfiInfo::parse(|offset| bus.query(offset), bus_width, device_width, stride)
+                });
+
+                // Both reset commands return the devices of either command set to read mode.
+                bus.command(0, AMD_RESET)?;
+                bus.command(0, INTEL_READ_ARRAY)?;
+                let Some(info) = info.transpose()? else {
+                    continue;
+                };
+                tracing::debug!("PROM flash: {info:?}");
+                return Ok(Self { base, info });
+            }
+        }
+        Err(Leon3Error::NoCfiFlash.into())
+    }
+
+    /// The CFI description of the flash.
+    pub(crate) fn info(&self) -> &CfiInfo {
+        &self.info
+    }
+
+    /// The address of the flash.
+    pub(crate) fn base(&self) -> u64 {
+        self.base
+    }
+
+    fn bus<'a>(&self, interface: &'a mut Leon3CommunicationInterface) -> PromBus<'a> {
+        PromBus {
+            memory: interface.as_memory_interface_mut(),
+            base: self.base,
+            bus_width: self.info.bus_width,
+            device_width: self.info.device_width,
+            stride: self.info.stride,
+        }
+    }
+
+    /// Check that `range` is inside of the flash and return it as offsets from its start.
+    fn offsets(&self, range: Range<u64>) -> Result<Range<u64>, crate::Error> {
+        if range.start < self.base || range.end > self.base + self.info.size {
+            return Err(Leon3Error::OutOfBounds.into());
+        }
+        Ok(range.start - self.base..range.end - self.base)
+    }
+}
+
+/// A flash memory that is erased in sectors and programmed from the host.
+pub(crate) trait SectorFlash {
+    /// The sectors overlapping `range`.
+    fn sectors(&self, range: Range<u64>) -> Result<Vec<Range<u64>>, crate::Error>;
+
+    /// Erase the sectors overlapping `range`.
+    fn erase(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        range: Range<u64>,
+    ) -> Result<(), crate::Error>;
+
+    /// Read the contents of the flash at `address` into `data`.
+    fn read(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        address: u64,
+        data: &mut [u8],
+    ) -> Result<(), crate::Error>;
+
+    /// Program `data` at `address` into erased flash.
+    fn program(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        address: u64,
+        data: &[u8],
+    ) -> Result<(), crate::Error>;
+}
+
+/// Program `data` at `address` into `flash`, preparing the sectors as `options` select, and
+/// report each erased sector and each programmed chunk to `progress`.
+pub(crate) fn write_sectors(
+    flash: &impl SectorFlash,
+    interface: &mut Leon3CommunicationInterface,
+    address: u64,
+    data: &[u8],
+    options: PromWriteOptions,
+    mut progress: impl FnMut(PromProgress),
+) -> Result<(), crate::Error> {
+    let end = address + data.len() as u64;
+    if !options.erase {
+        for chunk in progress_chunks(address, data.len()) {
+            let offset = (chunk.start - address) as usize;
+            let len = (chunk.end - chunk.start) as usize;
+            flash.program(interface, chunk.start, &data[offset..offset + len])?;
+            progress(PromProgress::Programmed(len as u64));
+        }
+        return Ok(());
+    }
+    for sector in flash.sectors(address..end)? {
+        let start = sector.start.max(address);
+        let data = &data[(start - address) as usize..(sector.end.min(end) - address) as usize];
+        if options.keep_unwritten_bytes {
+            let mut contents = vec![0; (sector.end - sector.start) as usize];
+            flash.read(interface, sector.start, &mut contents)?;
+            let offset = (start - sector.start) as usize;
+            contents[offset..offset + data.len()].copy_from_slice(data);
+            flash.erase(interface, sector.clone())?;
+            progress(PromProgress::Erased(sector.end - sector.start));
+            flash.program(interface, sector.start, &contents)?;
+        } else {
+            flash.erase(interface, sector.clone())?;
+            progress(PromProgress::Erased(sector.end - sector.start));
+            flash.program(interface, start, data)?;
+        }
+        progress(PromProgress::Programmed(data.len() as u64));
+    }
+    Ok(())
+}
+
+/// Erase the sectors of `flash` overlapping `range`, and report each of them to `progress`.
+pub(crate) fn erase_sectors(
+    flash: &impl SectorFlash,
+    interface: &mut Leon3CommunicationInterface,
+    range: Range<u64>,
+    mut progress: impl FnMut(PromProgress),
+) -> Result<(), crate::Error> {
+    for sector in flash.sectors(range)? {
+        flash.erase(interface, sector.clone())?;
+        progress(PromProgress::Erased(sector.end - sector.start));
+    }
+    Ok(())
+}
+
+impl SectorFlash for Prom {
+    fn read(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        address: u64,
+        data: &mut [u8],
+    ) -> Result<(), crate::Error> {
+        interface.as_memory_interface_mut().read(address, data)
+    }
+
+    fn sectors(&self, range: Range<u64>) -> Result<Vec<Range<u64>>, crate::Error> {
+        let offsets = self.offsets(range)?;
+        Ok(self
+            .info
+            .sectors(offsets)
+            .into_iter()
+            .map(|sector| self.base + sector.start..self.base + sector.end)
+            .collect())
+    }
+
+    fn erase(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        range: Range<u64>,
+    ) -> Result<(), crate::Error> {
+        let offsets = self.offsets(range)?;
+        for sector in self.info.sectors(offsets) {
+            let address = self.base + sector.start;
+            tracing::debug!("Erasing PROM sector at {address:#010x}");
+            let mut bus = self.bus(interface);
+            match self.info.command_set {
+                CfiCommandSet::Intel => {
+                    // Clear the lock bit of the block, if the device has one.
+                    bus.command_at(address, INTEL_LOCK_SETUP)?;
+                    bus.command_at(address, INTEL_CONFIRM)?;
+                    bus.command_at(address, INTEL_READ_ARRAY)?;
+
+                    bus.command_at(address, INTEL_BLOCK_ERASE)?;
+                    bus.command_at(address, INTEL_CONFIRM)?;
+                    bus.intel_wait(address, self.info.erase_timeout, "erase")?;
+                }
+                CfiCommandSet::Amd => {
+                    bus.amd_unlock()?;
+                    bus.command(AMD_UNLOCK_1.0, AMD_ERASE_SETUP)?;
+                    bus.amd_unlock()?;
+                    bus.command_at(address, AMD_SECTOR_ERASE)?;
+                    bus.amd_wait(address, self.info.erase_timeout)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Bytes which are not part of `data` but share a bus word with it are left unchanged.
+    fn program(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        address: u64,
+        data: &[u8],
+    ) -> Result<(), crate::Error> {
+        self.offsets(address..address + data.len() as u64)?;
+        let width = self.info.bus_width as u64;
+        let start = address - address % width;
+        let end = (address + data.len() as u64).next_multiple_of(width);
+
+        // Programming a bit to one leaves it unchanged, so the padding is all ones.
+        let mut words = vec![0xFF; (end - start) as usize];
+        let offset = (address - start) as usize;
+        words[offset..offset + data.len()].copy_from_slice(data);
+
+        let mut bus = self.bus(interface);
+        for (index, word) in words.chunks(width as usize).enumerate() {
+            if word.iter().all(|byte| *byte == 0xFF) {
+                continue;
+            }
+            let word_address = start + index as u64 * width;
+            let value = word
+                .iter()
+                .fold(0u32, |value, byte| (value << 8) | u32::from(*byte));
+            match self.info.command_set {
+                CfiCommandSet::Intel => {
+                    bus.command_at(word_address, INTEL_PROGRAM)?;
+                    bus.write(word_address, value)?;
+                    bus.intel_wait(word_address, self.info.program_timeout, "program")?;
+                }
+                CfiCommandSet::Amd => {
+                    bus.amd_unlock()?;
+                    bus.command(AMD_UNLOCK_1.0, AMD_PROGRAM)?;
+                    bus.write(word_address, value)?;
+                    bus.amd_wait(word_address, self.info.program_timeout)?;
+                }
+            }
+        }
+        bus.memory.flush()
+    }
+}
+
+/// A memory in the PROM area that is written like SRAM, without erasing, like MRAM or EEPROM.
+pub(crate) struct DirectProm {
+    /// The number of bytes written in one write cycle, or `None` if the memory has no write
+    /// cycles.
+    page_size: Option<u64>,
+    /// How long to poll for the end of each write cycle.
+    write_timeout: Duration,
+}
+
+impl DirectProm {
+    /// The programming profile of `memory`, or `None` for CFI flash.
+    pub(crate) fn new(memory: &Leon3PromMemory) -> Option<Self> {
+        match memory {
+            Leon3PromMemory::CfiFlash => None,
+            Leon3PromMemory::Mram => Some(Self {
+                page_size: None,
+                write_timeout: Duration::ZERO,
+            }),
+            Leon3PromMemory::Eeprom(config) => Some(Self {
+                page_size: Some(config.page_size.map_or(1, u64::from)),
+                write_timeout: config
+                    .write_timeout
+                    .map_or(DEFAULT_EEPROM_WRITE_TIMEOUT, |timeout| {
+                        Duration::from_millis(u64::from(timeout))
+                    }),
+            }),
+        }
+    }
+
+    /// Write `data` at `address` on a PROM bus of `bus_width` bytes.
+    ///
+    /// Bytes which are not part of `data` but share a bus word with it keep their contents.
+    pub(crate) fn program(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        bus_width: usize,
+        address: u64,
+        data: &[u8],
+    ) -> Result<(), crate::Error> {
+        let width = bus_width as u64;
+        let data_end = address + data.len() as u64;
+        let start = address - address % width;
+        let end = data_end.next_multiple_of(width);
+
+        let memory = interface.as_memory_interface_mut();
+        let mut words = vec![0; (end - start) as usize];
+        if start != address {
+            memory.read(start, &mut words[..bus_width])?;
+        }
+        if end != data_end {
+            let last = words.len() - bus_width;
+            memory.read(end - width, &mut words[last..])?;
+        }
+        let offset = (address - start) as usize;
+        words[offset..offset + data.len()].copy_from_slice(data);
+
+        let page_size = self.page_size.map(|size| size.max(width));
+        for page in pages(start..end, page_size) {
+            tracing::debug!("Writing PROM page at {:#010x}", page.start);
+            let bytes = &words[(page.start - start) as usize..(page.end - start) as usize];
+            match bus_width {
+                1 => memory.write_8(page.start, bytes)?,
+                2 => memory.write_16(
+                    page.start,
+                    &bytes
+                        .chunks(2)
+                        .map(|word| u16::from_be_bytes([word[0], word[1]]))
+                        .collect::<Vec<_>>(),
+                )?,
+                _ => memory.write_32(
+                    page.start,
+                    &bytes
+                        .chunks(4)
+                        .map(|word| u32::from_be_bytes([word[0], word[1], word[2], word[3]]))
+                        .collect::<Vec<_>>(),
+                )?,
+            }
+            memory.flush()?;
+
+            if self.page_size.is_some() {
+                // The EEPROM returns the written data once the write cycle is complete.
+                let last = page.end - width;
+                let expected = &bytes[bytes.len() - bus_width..];
+                let mut current = vec![0; bus_width];
+                let start = Instant::now();
+                loop {
+                    memory.read(last, &mut current)?;
+                    if current == expected {
+                        break;
+                    }
+                    if start.elapsed() > self.write_timeout {
+                        return Err(Leon3Error::PromOperationFailed {
+                            operation: "write",
+                            address: last,
+                        }
+                        .into());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Split the `len` bytes at `address` into the chunks programmed between progress reports.
+pub(crate) fn progress_chunks(address: u64, len: usize) -> Vec<Range<u64>> {
+    pages(address..address + len as u64, Some(PROGRESS_CHUNK))
+}
+
+/// Split `range` at multiples of `page_size`, or not at all if `page_size` is `None`.
+fn pages(range: Range<u64>, page_size: Option<u64>) -> Vec<Range<u64>> {
+    let Some(page_size) = page_size else {
+        return vec![range];
+    };
+    let mut pages = vec![];
+    let mut start = range.start;
+    while start < range.end {
+        let end = (start / page_size + 1) * page_size;
+        pages.push(start..end.min(range.end));
+        start = end;
+    }
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The CFI query table of an S29GL256P, a 32 MiB AMD command set device with 128 KiB
+    /// sectors.
+    fn s29gl256p(offset: u64) -> Result<u8, crate::Error> {
+        Ok(match offset {
+            0x10 => b'Q',
+            0x11 => b'R',
+            0x12 => b'Y',
+            0x13 => 0x02,
+            0x1F => 0x06,
+            0x21 => 0x09,
+            0x23 => 0x03,
+            0x25 => 0x03,
+            0x27 => 0x19,
+            0x2C => 0x01,
+            0x2D => 0xFF,
+            0x2E => 0x00,
+            0x2F => 0x00,
+            0x30 => 0x02,
+            _ => 0x00,
+        })
+    }
+
+    #[test]
+    fn parse_cfi_query() {
+        // Two 16-bit devices on a 32-bit bus.
+        let info = CfiInfo::parse(s29gl256p, 4, 2, 4).unwrap();
+        assert_eq!(info.command_set, CfiCommandSet::Amd);
+        assert_eq!(info.size, 64 << 20);
+        assert_eq!(
+            info.erase_regions,
+            vec![EraseRegion {
+                sector_size: 256 << 10,
+                sectors: 256,
+            }]
+        );
+        assert_eq!(info.program_timeout, Duration::from_micros(512));
+        assert_eq!(info.erase_timeout, Duration::from_millis(4096));
+
+        assert_eq!(
+            info.sectors(0x3_FFFF..0x4_0001),
+            vec![0..0x4_0000, 0x4_0000..0x8_0000]
+        );
+    }
+
+    #[test]
+    fn split_pages() {
+        assert_eq!(
+            pages(0x3C..0xC4, Some(0x40)),
+            vec![0x3C..0x40, 0x40..0x80, 0x80..0xC0, 0xC0..0xC4]
+        );
+        assert_eq!(pages(0x3C..0xC4, None), vec![0x3C..0xC4]);
+    }
+
+    #[test]
+    fn replicate_commands() {
+        assert_eq!(replicate(0x98, 1, 4), 0x9898_9898);
+        assert_eq!(replicate(0x98, 2, 4), 0x0098_0098);
+        assert_eq!(replicate(0x98, 4, 4), 0x0000_0098);
+        assert_eq!(replicate(0xAA, 1, 1), 0xAA);
+    }
+}
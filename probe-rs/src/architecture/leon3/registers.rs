@@ -1,6 +1,9 @@
 //! LEON3 register descriptions.
 
-use std::sync::LazyLock;
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
 
 use crate::{
     CoreRegisters,
@@ -8,24 +11,36 @@ use crate::{
     core::{CoreRegister, RegisterDataType, RegisterId, RegisterRole, UnwindRule},
 };
 
-#[derive(Clone, Copy)]
+/// Identifies a LEON3 register.
+#[derive(Debug, Clone, Copy)]
 pub enum Leon3RegisterId {
+    /// An integer unit general purpose register, relative to the current window.
     IuCore(IuCoreReg),
+    /// An integer unit general purpose register of the given window, regardless of the current
+    /// window pointer. The window is numbered like the CWP field of the PSR.
+    IuWindow {
+        /// The window number.
+        window: u8,
+        /// The register within the window.
+        reg: IuCoreReg,
+    },
+    /// An integer unit special purpose register.
     IuSpecial(IuSpecialReg),
+    /// A floating-point unit register.
     Fpu(FpuReg),
 }
 
 impl Leon3RegisterId {
     const fn to_u16(self) -> u16 {
         match self {
-            Leon3RegisterId::IuCore(iu_core_reg) => {
-                0x0000
-                    | match iu_core_reg {
-                        IuCoreReg::G(n) => 0x0000 | (n as u16),
-                        IuCoreReg::O(n) => 0x0100 | (n as u16),
-                        IuCoreReg::L(n) => 0x0200 | (n as u16),
-                        IuCoreReg::I(n) => 0x0300 | (n as u16),
-                    }
+            Leon3RegisterId::IuCore(iu_core_reg) => match iu_core_reg {
+                IuCoreReg::G(n) => n as u16,
+                IuCoreReg::O(n) => 0x0100 | (n as u16),
+                IuCoreReg::L(n) => 0x0200 | (n as u16),
+                IuCoreReg::I(n) => 0x0300 | (n as u16),
+            },
+            Leon3RegisterId::IuWindow { window, reg } => {
+                0x3000 | ((window as u16) << 5) | reg.index() as u16
             }
             Leon3RegisterId::IuSpecial(iu_special_reg) => {
                 0x1000
@@ -45,6 +60,7 @@ impl Leon3RegisterId {
                 0x2000
                     | match fpu_reg {
                         FpuReg::F(n) => n as u16,
+                        FpuReg::D(n) => 0x0100 | (n as u16),
                     }
             }
         }
@@ -95,37 +111,171 @@ impl TryFrom<RegisterId> for Leon3RegisterId {
             2 => {
                 // fpu
                 let n = (value.0 & 0xFF) as u8;
-                Ok(Leon3RegisterId::Fpu(FpuReg::F(n)))
+                Ok(Leon3RegisterId::Fpu(match (value.0 >> 8) & 0xF {
+                    0 if n < 32 => FpuReg::F(n),
+                    1 if n < 16 => FpuReg::D(n),
+                    _ => Err(Leon3Error::InvalidRegisterId(value))?,
+                }))
+            }
+            3 => {
+                // iu core, absolute window
+                if value.0 & 0x0C00 != 0 {
+                    Err(Leon3Error::InvalidRegisterId(value))?
+                }
+                Ok(Leon3RegisterId::IuWindow {
+                    window: ((value.0 >> 5) & 0x1F) as u8,
+                    reg: IuCoreReg::from_index((value.0 & 0x1F) as u8),
+                })
             }
             _ => Err(Leon3Error::InvalidRegisterId(value)),
         }
     }
 }
 
-#[derive(Clone, Copy)]
+/// The integer unit general purpose registers visible in the current window.
+#[derive(Debug, Clone, Copy)]
 pub enum IuCoreReg {
+    /// Global register `%gN`.
     G(u8),
+    /// Out register `%oN`.
     O(u8),
+    /// Local register `%lN`.
     L(u8),
+    /// In register `%iN`.
     I(u8),
 }
 
-#[derive(Clone, Copy)]
+impl IuCoreReg {
+    /// The register number `N` of `%rN`, 0 to 31.
+    pub const fn index(self) -> u8 {
+        match self {
+            IuCoreReg::G(n) => n,
+            IuCoreReg::O(n) => 8 + n,
+            IuCoreReg::L(n) => 16 + n,
+            IuCoreReg::I(n) => 24 + n,
+        }
+    }
+
+    /// The register `%rN` for `index` `N`, 0 to 31.
+    pub const fn from_index(index: u8) -> Self {
+        let n = index % 8;
+        match index / 8 {
+            0 => IuCoreReg::G(n),
+            1 => IuCoreReg::O(n),
+            2 => IuCoreReg::L(n),
+            _ => IuCoreReg::I(n),
+        }
+    }
+}
+
+/// The integer unit special purpose registers.
+#[derive(Debug, Clone, Copy)]
 pub enum IuSpecialReg {
+    /// Multiply/divide register.
     Y,
+    /// Processor state register.
     PSR,
+    /// Window invalid mask.
     WIM,
+    /// Trap base register.
     TBR,
+    /// Program counter.
     PC,
+    /// Next program counter.
     NPC,
+    /// Floating-point state register.
     FSR,
+    /// Coprocessor state register.
     CPSR,
+    /// Ancillary state register `%asrN` (16 to 31).
     ASR(u8),
 }
 
-#[derive(Clone, Copy)]
+/// The floating-point unit registers.
+#[derive(Debug, Clone, Copy)]
 pub enum FpuReg {
+    /// Single-precision register `%fN`.
     F(u8),
+    /// Double-precision register `%dN`, made up of the pair `%f(2N)` and `%f(2N+1)`.
+    D(u8),
+}
+
+impl FpuReg {
+    /// The single-precision registers holding the most and least significant word of a
+    /// double-precision register, or `None` for single-precision registers.
+    ///
+    /// SPARC is big-endian, so the even register of a pair holds the most significant word.
+    pub fn words(self) -> Option<(FpuReg, FpuReg)> {
+        match self {
+            FpuReg::F(_) => None,
+            FpuReg::D(n) => Some((FpuReg::F(2 * n), FpuReg::F(2 * n + 1))),
+        }
+    }
+}
+
+/// The registers of one register window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RegisterWindow {
+    /// The out registers `%o0` - `%o7`, shared with the ins of the next lower window.
+    pub outs: [u32; 8],
+    /// The local registers `%l0` - `%l7`.
+    pub locals: [u32; 8],
+    /// The in registers `%i0` - `%i7`, shared with the outs of the next higher window.
+    pub ins: [u32; 8],
+}
+
+/// The complete integer unit register file of a core.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterFile {
+    /// The current window pointer at the time the register file was read.
+    pub cwp: u32,
+    /// The window invalid mask at the time the register file was read.
+    pub wim: u32,
+    /// The global registers `%g0` - `%g7`.
+    pub globals: [u32; 8],
+    /// All register windows, indexed by window number.
+    pub windows: Vec<RegisterWindow>,
+}
+
+impl RegisterFile {
+    /// Build the register file from the raw DSU register file contents: 16 words per window
+    /// followed by the 8 globals.
+    pub(crate) fn from_raw(words: &[u32], cwp: u32, wim: u32) -> Self {
+        let windowed = &words[..words.len() - 8];
+        let num_windows = windowed.len() / 16;
+        let bank = |window: usize, offset: usize| -> [u32; 8] {
+            let start = (window * 16 + offset) % windowed.len();
+            std::array::from_fn(|n| windowed[(start + n) % windowed.len()])
+        };
+
+        let windows = (0..num_windows)
+            .map(|window| RegisterWindow {
+                outs: bank(window, 8),
+                locals: bank(window, 16),
+                ins: bank(window, 24),
+            })
+            .collect();
+
+        let mut globals = [0; 8];
+        globals.copy_from_slice(&words[words.len() - 8..]);
+
+        Self {
+            cwp,
+            wim,
+            globals,
+            windows,
+        }
+    }
+
+    /// The registers of the current window.
+    pub fn current_window(&self) -> &RegisterWindow {
+        &self.windows[self.cwp as usize % self.windows.len()]
+    }
+
+    /// Returns whether the given window is marked invalid in the WIM.
+    pub fn is_invalid(&self, window: usize) -> bool {
+        self.wim & (1 << window) != 0
+    }
 }
 
 /// The program counter register.
@@ -175,13 +325,23 @@ pub const RA: CoreRegister = CoreRegister {
     unwind_rule: UnwindRule::Clear,
 };
 
-/// The LEON3 core registers.
+/// The LEON3 core registers without FPU.
 pub static LEON3_CORE_REGISTERS: LazyLock<CoreRegisters> =
     LazyLock::new(|| CoreRegisters::new(LEON3_REGISTERS_SET.iter().collect::<Vec<_>>()));
 
-// TODO(darsor): these register IDs assume 8 windows
+/// The LEON3 core registers with FPU.
+pub static LEON3_WITH_FPU_CORE_REGISTERS: LazyLock<CoreRegisters> = LazyLock::new(|| {
+    CoreRegisters::new(
+        LEON3_REGISTERS_SET
+            .iter()
+            .chain(LEON3_FPU_REGISTERS_SET)
+            .collect(),
+    )
+});
+
+// The integer registers come first and in order, so that their index is their DWARF register
+// number.
 static LEON3_REGISTERS_SET: &[CoreRegister] = &[
-    PC,
     CoreRegister {
         roles: &[RegisterRole::Core("r0"), RegisterRole::Core("g0")],
         id: RegisterId(Leon3RegisterId::IuCore(IuCoreReg::G(0)).to_u16()),
@@ -411,7 +571,509 @@ static LEON3_REGISTERS_SET: &[CoreRegister] = &[
     },
     FP,
     RA,
+    PC,
+];
+
+/// Returns the registers of a core with the given FPU and set of ancillary state registers.
+///
+/// Bit `n` of `asrs` is set if `%asr(16 + n)` is implemented.
+pub(crate) fn leon3_registers(fpu: bool, asrs: u16) -> &'static CoreRegisters {
+    static REGISTER_SETS: LazyLock<Mutex<HashMap<(bool, u16), &'static CoreRegisters>>> =
+        LazyLock::new(Default::default);
+
+    match (fpu, asrs) {
+        (false, 0) => &LEON3_CORE_REGISTERS,
+        (true, 0) => &LEON3_WITH_FPU_CORE_REGISTERS,
+        _ => {
+            let mut sets = REGISTER_SETS.lock().unwrap();
+            // There are only a few different configurations in practice, so leaking them is fine.
+            sets.entry((fpu, asrs)).or_insert_with(|| {
+                let fpu_registers = if fpu { LEON3_FPU_REGISTERS_SET } else { &[] };
+                let asr_registers = LEON3_ASR_REGISTERS_SET
+                    .iter()
+                    .enumerate()
+                    .filter(|(n, _)| asrs & (1 << n) != 0)
+                    .map(|(_, register)| register);
+                let registers = LEON3_REGISTERS_SET
+                    .iter()
+                    .chain(fpu_registers)
+                    .chain(asr_registers)
+                    .collect();
+                Box::leak(Box::new(CoreRegisters::new(registers)))
+            })
+        }
+    }
+}
+
+// FPU registers
+static LEON3_FPU_REGISTERS_SET: &[CoreRegister] = &[
+    CoreRegister {
+        roles: &[RegisterRole::Core("fsr"), RegisterRole::FloatingPointStatus],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::FSR).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f0"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(0)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f1"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(1)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f2"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(2)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f3"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(3)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f4"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(4)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f5"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(5)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f6"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(6)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f7"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(7)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f8"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(8)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f9"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(9)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f10"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(10)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f11"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(11)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f12"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(12)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f13"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(13)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f14"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(14)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f15"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(15)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f16"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(16)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f17"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(17)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f18"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(18)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f19"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(19)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f20"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(20)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f21"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(21)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f22"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(22)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f23"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(23)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f24"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(24)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f25"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(25)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f26"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(26)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f27"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(27)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f28"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(28)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f29"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(29)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f30"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(30)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("f31"), RegisterRole::FloatingPoint],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::F(31)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("d0")],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::D(0)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(64),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("d1")],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::D(1)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(64),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("d2")],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::D(2)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(64),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("d3")],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::D(3)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(64),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("d4")],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::D(4)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(64),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("d5")],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::D(5)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(64),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("d6")],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::D(6)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(64),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("d7")],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::D(7)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(64),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("d8")],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::D(8)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(64),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("d9")],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::D(9)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(64),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("d10")],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::D(10)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(64),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("d11")],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::D(11)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(64),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("d12")],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::D(12)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(64),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("d13")],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::D(13)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(64),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("d14")],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::D(14)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(64),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("d15")],
+        id: RegisterId(Leon3RegisterId::Fpu(FpuReg::D(15)).to_u16()),
+        data_type: RegisterDataType::FloatingPoint(64),
+        unwind_rule: UnwindRule::Clear,
+    },
 ];
 
 // TODO(darsor):
 // include  "y", "psr", "wim", "tbr", "pc", "npc", "fsr", and "csr"
+
+// Ancillary state registers %asr16 - %asr31, only some of which are implemented
+static LEON3_ASR_REGISTERS_SET: &[CoreRegister] = &[
+    CoreRegister {
+        roles: &[RegisterRole::Core("asr16")],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(16)).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("asr17")],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(17)).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("asr18")],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(18)).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("asr19")],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(19)).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("asr20")],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(20)).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("asr21")],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(21)).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("asr22")],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(22)).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("asr23")],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(23)).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("asr24")],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(24)).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("asr25")],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(25)).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("asr26")],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(26)).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("asr27")],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(27)).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("asr28")],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(28)).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("asr29")],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(29)).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("asr30")],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(30)).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("asr31")],
+        id: RegisterId(Leon3RegisterId::IuSpecial(IuSpecialReg::ASR(31)).to_u16()),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Clear,
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn register_file_windows_overlap() {
+        // Two windows with 16 registers each, followed by the globals.
+        let words: Vec<u32> = (0..40).collect();
+        let file = RegisterFile::from_raw(&words, 1, 0b01);
+
+        assert_eq!(file.windows.len(), 2);
+        assert_eq!(file.globals, [32, 33, 34, 35, 36, 37, 38, 39]);
+        assert_eq!(file.windows[0].outs, [8, 9, 10, 11, 12, 13, 14, 15]);
+        assert_eq!(file.windows[0].locals, [16, 17, 18, 19, 20, 21, 22, 23]);
+        // The ins of the highest window wrap around to the start of the register file.
+        assert_eq!(file.windows[1].ins, [8, 9, 10, 11, 12, 13, 14, 15]);
+        assert_eq!(file.windows[1].outs, file.windows[0].ins);
+        assert_eq!(file.current_window(), &file.windows[1]);
+        assert!(file.is_invalid(0));
+    }
+
+    #[test]
+    fn window_register_ids_round_trip() {
+        let id = RegisterId::from(Leon3RegisterId::IuWindow {
+            window: 31,
+            reg: IuCoreReg::I(7),
+        });
+        assert_eq!(id, RegisterId(0x33FF));
+        let Ok(Leon3RegisterId::IuWindow { window, reg }) = Leon3RegisterId::try_from(id) else {
+            panic!("not a windowed register");
+        };
+        assert_eq!((window, reg.index()), (31, 31));
+
+        assert!(Leon3RegisterId::try_from(RegisterId(0x3400)).is_err());
+    }
+
+    #[test]
+    fn double_register_ids_round_trip() {
+        let id = RegisterId::from(Leon3RegisterId::Fpu(FpuReg::D(15)));
+        let Ok(Leon3RegisterId::Fpu(reg)) = Leon3RegisterId::try_from(id) else {
+            panic!("not an FPU register");
+        };
+        let Some((FpuReg::F(high), FpuReg::F(low))) = reg.words() else {
+            panic!("not a double-precision register");
+        };
+        assert_eq!((high, low), (30, 31));
+
+        let invalid = RegisterId(id.0 + 1);
+        assert!(Leon3RegisterId::try_from(invalid).is_err());
+    }
+
+    #[test]
+    fn register_set_includes_implemented_asrs() {
+        let asrs = (1 << 1) | (1 << 8) | (1 << 9);
+        let registers = leon3_registers(true, asrs);
+
+        let names: Vec<_> = registers
+            .all_registers()
+            .map(|r| r.name())
+            .filter(|name| name.starts_with("asr"))
+            .collect();
+        assert_eq!(names, ["asr17", "asr24", "asr25"]);
+        assert_eq!(
+            registers
+                .all_registers()
+                .filter(|r| r.register_has_role(RegisterRole::FloatingPoint))
+                .count(),
+            32
+        );
+    }
+}
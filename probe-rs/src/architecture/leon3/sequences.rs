@@ -1,66 +1,382 @@
-use std::{fmt::Debug, sync::Arc};
+//! Debug sequences to operate special requirements LEON3 targets.
+
+use std::{fmt::Debug, sync::Arc, thread, time::Duration};
+
+use probe_rs_target::{
+    CoreAccessOptions, Leon3RegisterWrite, Leon3ResetStrategy, Leon3SdramConfig, MemoryRegion,
+};
 
 use crate::{
-    Session,
-    architecture::leon3::communication_interface::{Leon3CommunicationInterface, Leon3Error},
+    CoreInterface, Session, Target,
+    architecture::leon3::{
+        Leon3, WADDR_IF, WATCHPOINT_ADDRESS_MASK,
+        communication_interface::{DebugUnit, Leon3CommunicationInterface, Leon3Error},
+        dsu3::{DsuBrss, DsuCtrl},
+        gptimer,
+        grclkgate::ClockGate,
+        mctrl::MemoryController,
+        registers::{IuSpecialReg, Leon3RegisterId},
+    },
 };
 
+/// The address the core starts executing from after reset, unless the `rstaddr` VHDL generic
+/// of the core was set to a different value.
+const DEFAULT_RESET_ADDRESS: u32 = 0x0000_0000;
+
+/// How long the reset line of the probe is asserted.
+const RESET_PULSE: Duration = Duration::from_millis(10);
+
+/// How long to wait for the system to come out of a board reset.
+const RESET_SETTLE: Duration = Duration::from_millis(10);
+
 /// A interface to operate debug sequences for Leon3 targets.
 ///
 /// Should be implemented on a custom handle for chips that require special sequence code.
 pub trait Leon3DebugSequence: Send + Sync + Debug {
-    // TODO(darsor): call this when appropriate
-    /// Executed when the probe establishes a connection to the target.
+    /// Executed when the probe establishes a connection to the target, once for each core
+    /// before the memory controller is configured.
     fn on_connect(&self, _interface: &mut Leon3CommunicationInterface) -> Result<(), crate::Error> {
         Ok(())
     }
 
-    // TODO(darsor): call this when appropriate
-    /// Executed when the target is halted.
+    /// Executed when the target is halted, once each time the core is found halted until it
+    /// is resumed by the debugger.
     fn on_halt(&self, _interface: &mut Leon3CommunicationInterface) -> Result<(), crate::Error> {
         Ok(())
     }
 
+    /// Keep the watchdog from resetting the system while the core is halted, executed each
+    /// time the core halts, before [`Leon3DebugSequence::on_halt`].
+    ///
+    /// The default implementation makes the GPTIMER and GRTIMER timer units, whose last timer
+    /// is often the watchdog, freeze while a processor is in debug mode. Chips with a
+    /// separate watchdog, like GRWATCHDOG, should freeze or kick it here.
+    fn suppress_watchdog(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+    ) -> Result<(), crate::Error> {
+        gptimer::freeze_in_debug_mode(interface)
+    }
+
+    /// Enable the clocks of the units that must respond to the debug link, executed when
+    /// connecting, before the debug support unit is accessed for the core.
+    ///
+    /// `ungate` holds the units to ungate of each GRCLKGATE clock gating unit, in plug&play
+    /// order, from the `ungate_clocks` core access option. The default implementation ungates
+    /// them with [`ungate_units`].
+    fn ungate_clocks(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        ungate: &[u32],
+    ) -> Result<(), crate::Error> {
+        ungate_units(interface, ungate)
+    }
+
+    /// The debug support unit and its base address, for chips where it is not listed in the
+    /// plug&play area reachable through the debug link.
+    ///
+    /// If `None`, the debug support unit is looked up in the plug&play area.
+    fn debug_unit(&self) -> Option<(DebugUnit, u64)> {
+        None
+    }
+
+    /// The address the cores start executing from after reset, unless the `reset_address`
+    /// core access option overrides it.
+    fn reset_address(&self) -> u32 {
+        DEFAULT_RESET_ADDRESS
+    }
+
+    /// Whether data cache snooping cannot be relied on to keep the caches of the cores coherent
+    /// with memory written through the debug link, for chips with snooping errata.
+    ///
+    /// If `true`, the caches are flushed every time a core leaves debug mode.
+    fn snooping_unreliable(&self) -> bool {
+        false
+    }
+
+    /// Configure the memory controller, so the SRAM and SDRAM can be accessed.
+    ///
+    /// Executed when connecting to a core and after each reset, with the register writes of
+    /// the `memory_controller_init` core access option. The default implementation applies
+    /// them with [`write_registers`].
+    fn configure_memory_controller(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        init: &[Leon3RegisterWrite],
+    ) -> Result<(), crate::Error> {
+        write_registers(interface, init)
+    }
+
+    /// Prepare the RAM for downloading an image, executed by the flash loader before it
+    /// writes to RAM.
+    ///
+    /// The default implementation programs the SDRAM configuration of the `sdram` core access
+    /// option, if there is one, into the first MCTRL or FTMCTRL memory controller.
+    fn setup_ram(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        sdram: Option<&Leon3SdramConfig>,
+    ) -> Result<(), crate::Error> {
+        let Some(sdram) = sdram else {
+            return Ok(());
+        };
+        let controller = MemoryController::find(interface)
+            .ok_or(Leon3Error::DeviceNotFound("MCTRL or FTMCTRL"))?;
+        controller.configure_sdram(interface, sdram)
+    }
+
+    /// Soft reset of the core, used by [`Leon3ResetStrategy::Sequence`].
+    ///
+    /// This is not a system reset: the DSU cannot assert the reset signal, so the default
+    /// implementation emulates a processor reset with [`emulate_processor_reset`] and leaves
+    /// the core halted at `reset_address`. Peripherals and the other cores keep their state.
+    /// Boards that must reset the whole system select the `probe` or `register` reset strategy
+    /// instead, see [`Leon3DebugSequence::reset_board`].
+    fn reset_core(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        reset_address: u32,
+    ) -> Result<(), crate::Error> {
+        emulate_processor_reset(interface, reset_address)
+    }
+
+    /// Reset the whole board with the `reset` core access option, used instead of
+    /// [`Leon3DebugSequence::reset_core`] if it is not [`Leon3ResetStrategy::Sequence`].
+    ///
+    /// The default implementation pulses the reset line of the probe or writes the reset
+    /// controller register, then waits for the system to come out of reset.
+    fn reset_board(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        strategy: &Leon3ResetStrategy,
+        reset_address: u32,
+    ) -> Result<(), crate::Error> {
+        match strategy {
+            Leon3ResetStrategy::Sequence => return self.reset_core(interface, reset_address),
+            Leon3ResetStrategy::Probe => {
+                let probe = interface
+                    .as_probe()
+                    .ok_or(Leon3Error::ProbeResetUnavailable)?;
+                probe.target_reset_assert()?;
+                thread::sleep(RESET_PULSE);
+                probe.target_reset_deassert()?;
+            }
+            Leon3ResetStrategy::Register(write) => {
+                write_registers(interface, std::slice::from_ref(write))?
+            }
+        }
+        thread::sleep(RESET_SETTLE);
+        Ok(())
+    }
+
     /// Configure the target to stop code execution after a reset. After this, the core will halt when it comes
     /// out of reset.
+    ///
+    /// `unit_index` is the IU watchpoint the core reserved for the reset catch. The default
+    /// implementation arms it on instruction fetches from `reset_address` with
+    /// [`arm_reset_catch`].
     fn reset_catch_set(
         &self,
         interface: &mut Leon3CommunicationInterface,
-    ) -> Result<(), Leon3Error> {
-        return Err(Leon3Error::ResetHaltRequestNotSupported);
+        reset_address: u32,
+        unit_index: usize,
+    ) -> Result<(), crate::Error> {
+        arm_reset_catch(interface, reset_address, unit_index)
     }
 
     /// Free hardware resources allocated by ResetCatchSet.
+    ///
+    /// The default implementation clears the IU watchpoint armed by [`arm_reset_catch`].
     fn reset_catch_clear(
         &self,
         interface: &mut Leon3CommunicationInterface,
-    ) -> Result<(), Leon3Error> {
-        return Err(Leon3Error::ResetHaltRequestNotSupported);
+        _reset_address: u32,
+        unit_index: usize,
+    ) -> Result<(), crate::Error> {
+        disarm_reset_catch(interface, unit_index)
     }
 
     /// This LEON3 sequence is called if an image was flashed to RAM directly.
     /// It will perform the necessary preparation to run that image.
     ///
     /// Core should be already `reset_and_halt`ed right before this call.
+    ///
+    /// The first core is prepared to run from `entry_point`, which is left to the caller.
+    /// The other cores are started at the entry point of their `ram_boot` core access
+    /// option, for AMP configurations, or stay halted so an SMP image can start them.
     fn prepare_running_on_ram(
         &self,
-        vector_table_addr: u64,
+        entry_point: u64,
         session: &mut Session,
     ) -> Result<(), crate::Error> {
         tracing::info!("Performing RAM flash start");
-        const SP_MAIN_OFFSET: usize = 0;
-        const RESET_VECTOR_OFFSET: usize = 1;
 
-        if session.list_cores().len() > 1 {
-            return Err(crate::Error::NotImplemented(
-                "multi-core ram flash start not implemented yet",
-            ));
+        let cores = session.target().cores.clone();
+        for (core_index, core) in cores.iter().enumerate() {
+            let ram_boot = match &core.core_access_options {
+                CoreAccessOptions::Leon3(options) => options.ram_boot.clone(),
+                _ => None,
+            }
+            .unwrap_or_default();
+            let entry = match ram_boot.entry {
+                Some(entry) => entry,
+                None if core_index == 0 => entry_point,
+                None => {
+                    tracing::debug!("Leaving core {} halted", core.name);
+                    continue;
+                }
+            };
+            let stack = match ram_boot.stack {
+                Some(stack) => stack,
+                None => default_stack(session.target(), entry).ok_or_else(|| {
+                    crate::Error::Other(format!(
+                        "No RAM region holds the entry point {entry:#010x} of core {}",
+                        core.name
+                    ))
+                })?,
+            };
+
+            tracing::debug!(
+                "Starting core {} at {entry:#010x} with the stack at {stack:#010x}",
+                core.name
+            );
+            let mut leon3 = session.get_leon3_core(core_index)?;
+            leon3.prepare_ram_boot(entry as u32, stack as u32)?;
+            if core_index != 0 {
+                leon3.run()?;
+            }
         }
+        Ok(())
+    }
+}
+
+/// The default end of the stack of an image started at `entry`: the end of the RAM region
+/// holding the entry point.
+pub(crate) fn default_stack(target: &Target, entry: u64) -> Option<u64> {
+    target
+        .memory_map
+        .iter()
+        .filter_map(MemoryRegion::as_ram_region)
+        .find(|region| region.range.contains(&entry))
+        .map(|region| region.range.end)
+}
 
-        tracing::debug!("RAM flash start for LEON3 single core target");
-        // TODO(darsor): set stack pointer and PC? And TBR?
-        todo!()
+/// Emulate a processor reset of the core, leaving it halted at `reset_address`.
+///
+/// The registers are set to their reset values and the instruction and data caches are flushed
+/// so that no stale code is executed.
+pub fn emulate_processor_reset(
+    interface: &mut Leon3CommunicationInterface,
+    reset_address: u32,
+) -> Result<(), crate::Error> {
+    let core_index = interface.core_index();
+    interface.modify_dsu_reg(|reg: &mut DsuBrss| {
+        reg.set_bn(core_index, true);
+    })?;
+    interface.wait_for_core_halted(Duration::from_millis(100))?;
+
+    // A reset also takes the core out of error mode.
+    let ctrl: DsuCtrl = interface.read_dsu_reg()?;
+    if ctrl.pe() {
+        interface.modify_dsu_ctrl(|ctrl| {
+            ctrl.set_pe(true);
+            ctrl.set_hl(false);
+        })?;
+        interface.wait_for_core_halted(Duration::from_millis(100))?;
     }
+
+    let special = |reg| Leon3RegisterId::IuSpecial(reg);
+
+    // Keep the read-only impl and ver fields, set S and clear everything else (ET=0, CWP=0).
+    let psr = interface.read_core_reg(special(IuSpecialReg::PSR))?;
+    interface.write_core_reg(special(IuSpecialReg::PSR), (psr & 0xFF00_0000) | (1 << 7))?;
+    interface.write_core_reg(special(IuSpecialReg::WIM), 0)?;
+    interface.write_core_reg(special(IuSpecialReg::TBR), reset_address & !0xFFF)?;
+    interface.write_core_reg(special(IuSpecialReg::PC), reset_address)?;
+    interface.write_core_reg(special(IuSpecialReg::NPC), reset_address + 4)?;
+
+    interface.flush_caches()
+}
+
+/// Arm IU watchpoint `unit_index` of the core to halt it when it fetches the instruction at
+/// `reset_address`, so the core halts when it comes out of reset.
+///
+/// The watchpoint registers are not changed by the emulated processor reset, and the core
+/// starts by fetching from the reset address when it is resumed afterwards. Watchpoints only
+/// halt the core while the break on IU watchpoint (BW) bit of the DSU control register is set,
+/// so it is set as well.
+pub fn arm_reset_catch(
+    interface: &mut Leon3CommunicationInterface,
+    reset_address: u32,
+    unit_index: usize,
+) -> Result<(), crate::Error> {
+    tracing::debug!("Catching the reset at {reset_address:#010x} with watchpoint {unit_index}");
+    let (waddr, wmask) = Leon3::watchpoint_registers(unit_index);
+    let special = |reg| Leon3RegisterId::IuSpecial(reg);
+    interface.write_core_reg(special(wmask), WATCHPOINT_ADDRESS_MASK)?;
+    interface.write_core_reg(
+        special(waddr),
+        (reset_address & WATCHPOINT_ADDRESS_MASK) | WADDR_IF,
+    )?;
+    interface.modify_dsu_ctrl(|ctrl| ctrl.set_bw(true))
+}
+
+/// Clear IU watchpoint `unit_index`, armed by [`arm_reset_catch`].
+pub fn disarm_reset_catch(
+    interface: &mut Leon3CommunicationInterface,
+    unit_index: usize,
+) -> Result<(), crate::Error> {
+    let (waddr, wmask) = Leon3::watchpoint_registers(unit_index);
+    let special = |reg| Leon3RegisterId::IuSpecial(reg);
+    interface.write_core_reg(special(waddr), 0)?;
+    interface.write_core_reg(special(wmask), 0)
+}
+
+/// Ungate the units in `ungate` of the GRCLKGATE clock gating units found in the plug&play
+/// area, one bit mask for each unit in plug&play order. Units that already run are left
+/// alone, so they are not reset.
+pub fn ungate_units(
+    interface: &mut Leon3CommunicationInterface,
+    ungate: &[u32],
+) -> Result<(), crate::Error> {
+    if ungate.is_empty() {
+        return Ok(());
+    }
+    let clock_gates = ClockGate::find_all(interface);
+    if clock_gates.len() < ungate.len() {
+        tracing::warn!(
+            "Clocks to ungate are configured for {} clock gating units, but only {} were found",
+            ungate.len(),
+            clock_gates.len()
+        );
+    }
+    for (clock_gate, mask) in clock_gates.iter().zip(ungate) {
+        let gated = clock_gate.gated(interface)?;
+        clock_gate.ungate(interface, gated & mask)?;
+    }
+    Ok(())
+}
+
+/// Apply a list of register writes in order.
+///
+/// Writes with a mask only change the masked bits of the register.
+pub fn write_registers(
+    interface: &mut Leon3CommunicationInterface,
+    writes: &[Leon3RegisterWrite],
+) -> Result<(), crate::Error> {
+    let memory = interface.as_memory_interface_mut();
+    for write in writes {
+        let value = match write.mask {
+            Some(mask) => {
+                let current = memory.read_word_32(write.address)?;
+                (current & !mask) | (write.value & mask)
+            }
+            None => write.value,
+        };
+        tracing::debug!("Writing {value:#010x} to {:#010x}", write.address);
+        memory.write_word_32(write.address, value)?;
+    }
+    memory.flush()
 }
 
 /// The default sequences that is used for Leon3 chips that do not specify a specific sequence.
@@ -75,3 +391,66 @@ impl DefaultLeon3Sequence {
 }
 
 impl Leon3DebugSequence for DefaultLeon3Sequence {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        MemoryMappedRegister,
+        architecture::leon3::{
+            Leon3CoreState, communication_interface::Leon3DebugInterfaceState, dsu3::Asr17,
+            mock_ahbjtag::MockAhbJtag,
+        },
+    };
+
+    const DSU_BASE: u32 = 0x9000_0000;
+
+    #[test]
+    fn reset_catch_restores_watchpoint_and_bw() {
+        let link = MockAhbJtag::new();
+        // Two watchpoints (NWP).
+        link.set_word(DSU_BASE + Asr17::ADDRESS_OFFSET as u32, 2 << 5);
+        let mut bus = link.bus_access();
+        let mut interface_state = Leon3DebugInterfaceState::try_attach(
+            &mut bus,
+            Some((DebugUnit::Dsu3, DSU_BASE.into())),
+            None,
+            None,
+        )
+        .unwrap();
+        let interface =
+            Leon3CommunicationInterface::try_attach(0, &mut bus, &mut interface_state).unwrap();
+        let mut state = Leon3CoreState::new();
+        let mut core =
+            Leon3::new(0, interface, &mut state, DefaultLeon3Sequence::create()).unwrap();
+
+        // Start with BW cleared, which the disarm has to restore.
+        core.interface
+            .modify_dsu_ctrl(|ctrl| ctrl.set_bw(false))
+            .unwrap();
+        let bw = |core: &mut Leon3| core.interface.read_dsu_reg::<DsuCtrl>().unwrap().bw();
+        let special = |reg| Leon3RegisterId::IuSpecial(reg);
+        let (waddr, wmask) = Leon3::watchpoint_registers(1);
+        let reset_address = core.reset_address();
+
+        core.reset_catch_set().unwrap();
+        assert!(bw(&mut core));
+        assert_eq!(
+            core.interface.read_core_reg(special(waddr)).unwrap(),
+            reset_address | WADDR_IF
+        );
+        assert_eq!(
+            core.interface.read_core_reg(special(wmask)).unwrap(),
+            WATCHPOINT_ADDRESS_MASK
+        );
+        // The reserved watchpoint is not handed out for breakpoints.
+        assert!(core.set_hw_breakpoint(1, 0x4000_0000).is_err());
+        assert_eq!(core.hw_breakpoints().unwrap()[0], None);
+
+        core.reset_catch_clear().unwrap();
+        assert!(!bw(&mut core));
+        assert_eq!(core.interface.read_core_reg(special(waddr)).unwrap(), 0);
+        assert_eq!(core.interface.read_core_reg(special(wmask)).unwrap(), 0);
+        assert_eq!(core.hw_breakpoints().unwrap(), [None, None]);
+    }
+}
@@ -0,0 +1,366 @@
+//! Programming of the SPI flash behind the SPIMCTRL SPI memory controller.
+//!
+//! The SPIMCTRL maps the SPI flash into an AHB memory area for reading. To erase and program
+//! it, the controller is switched to user mode, in which each byte written to the transmit
+//! register is shifted out to the flash while a byte is shifted in to the receive register.
+
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    MemoryMappedRegister,
+    architecture::leon3::{
+        communication_interface::{Leon3CommunicationInterface, Leon3Error},
+        plugnplay::{AddressSpaceKind, Device, GaislerDevice},
+        prom::SectorFlash,
+    },
+    memory_mapped_bitfield_register,
+};
+
+/// The SPI flash commands.
+const CMD_WRITE_ENABLE: u8 = 0x06;
+const CMD_READ_STATUS: u8 = 0x05;
+const CMD_PAGE_PROGRAM: u8 = 0x02;
+const CMD_SECTOR_ERASE: u8 = 0xD8;
+const CMD_READ: u8 = 0x03;
+const CMD_READ_ID: u8 = 0x9F;
+
+/// The write-in-progress bit of the flash status register.
+const STATUS_WIP: u8 = 0x01;
+
+/// The size of the sectors erased by [`CMD_SECTOR_ERASE`], which all SPI flash devices
+/// support.
+const SECTOR_SIZE: u64 = 64 * 1024;
+
+/// The size of the pages written by [`CMD_PAGE_PROGRAM`].
+const PAGE_SIZE: u64 = 256;
+
+/// How long to wait for a single byte to be shifted out and in.
+const TRANSFER_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// How long to wait for a page program or a sector erase to complete.
+const PROGRAM_TIMEOUT: Duration = Duration::from_millis(10);
+const ERASE_TIMEOUT: Duration = Duration::from_secs(3);
+
+memory_mapped_bitfield_register! {
+    /// Control Register (GRLIB IP Core User's Manual, SPIMCTRL)
+    pub struct SpimCtrl(u32);
+    0x04, "ctrl",
+    impl From;
+    /// Reset core (RST) - resets the core and the SD card, if there is one.
+    pub rst, set_rst: 4;
+    /// Chip select (CSN) - the level of the chip select line in user mode, active low.
+    pub csn, set_csn: 3;
+    /// Enable extended address (EAS) - use 4-byte addresses for memory mapped reads.
+    pub eas, set_eas: 2;
+    /// Interrupt enable (IEN) - interrupt when a user mode transfer is done.
+    pub ien, set_ien: 1;
+    /// User control (USRC) - if set, the SPI bus is driven through the transmit and receive
+    /// registers, and memory mapped reads are not possible.
+    pub usrc, set_usrc: 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// Status Register (GRLIB IP Core User's Manual, SPIMCTRL)
+    pub struct SpimStat(u32);
+    0x08, "stat",
+    impl From;
+    /// Error (ERR) - an SD card operation failed.
+    pub err, set_err: 3;
+    /// Initialized (INIT) - the core has completed its initialization.
+    pub init, set_init: 2;
+    /// Busy (BUSY) - the core is busy with an operation.
+    pub busy, set_busy: 1;
+    /// Operation done (DONE) - a user mode transfer has completed. Cleared by writing one.
+    pub done, set_done: 0;
+}
+
+/// Offsets of the receive and transmit registers.
+const RECEIVE: u64 = 0x0C;
+const TRANSMIT: u64 = 0x10;
+
+/// An SPI flash behind a SPIMCTRL.
+pub(crate) struct SpiFlash {
+    /// The base address of the controller registers.
+    registers: u64,
+    /// The address window of the flash.
+    memory: Range<u64>,
+}
+
+impl SpiFlash {
+    /// Find the SPIMCTRL whose memory area holds `address`.
+    pub(crate) fn find(interface: &mut Leon3CommunicationInterface, address: u64) -> Option<Self> {
+        interface
+            .plugnplay()
+            .find_devices(Device::Gaisler(GaislerDevice::SPIMCTRL))
+            .find_map(|record| {
+                let space = |kind| {
+                    record
+                        .address_spaces
+                        .iter()
+                        .find(|space| space.kind == kind)
+                        .map(|space| space.addresses.clone())
+                };
+                let registers = space(AddressSpaceKind::AhbIO)?.start;
+                let memory = space(AddressSpaceKind::AhbMemory)?;
+                memory
+                    .contains(&address)
+                    .then_some(Self { registers, memory })
+            })
+    }
+
+    /// Identify the flash with its JEDEC ID and limit the memory area to its size.
+    ///
+    /// The controller is in user mode while `f` runs, so the flash cannot be read through
+    /// the memory area until it returns.
+    pub(crate) fn with_user_mode<R>(
+        mut self,
+        interface: &mut Leon3CommunicationInterface,
+        f: impl FnOnce(&Self, &mut Leon3CommunicationInterface) -> Result<R, crate::Error>,
+    ) -> Result<R, crate::Error> {
+        let ctrl = self.read_ctrl(interface)?;
+        let mut user = ctrl;
+        user.set_usrc(true);
+        user.set_csn(true);
+        self.write_ctrl(interface, user)?;
+
+        let result = self.identify(interface).and_then(|size| {
+            self.memory.end = self.memory.end.min(self.memory.start + size);
+            f(&self, interface)
+        });
+        self.write_ctrl(interface, ctrl)?;
+        result
+    }
+
+    /// Read the JEDEC ID of the flash and return its size.
+    fn identify(&self, interface: &mut Leon3CommunicationInterface) -> Result<u64, crate::Error> {
+        let mut id = [0; 3];
+        self.transfer(interface, &[CMD_READ_ID], &mut id)?;
+        let [manufacturer, device_type, capacity] = id;
+        if matches!(manufacturer, 0x00 | 0xFF) || !(16..32).contains(&capacity) {
+            return Err(Leon3Error::NoSpiFlash.into());
+        }
+        tracing::debug!(
+            "SPI flash: manufacturer {manufacturer:#04x}, type {device_type:#04x}, {} KiB",
+            1 << (capacity - 10)
+        );
+        Ok(1 << capacity)
+    }
+
+    fn read_ctrl(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+    ) -> Result<SpimCtrl, crate::Error> {
+        let ctrl = interface
+            .as_memory_interface_mut()
+            .read_word_32(self.registers + SpimCtrl::ADDRESS_OFFSET)?;
+        Ok(SpimCtrl(ctrl))
+    }
+
+    fn write_ctrl(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        ctrl: SpimCtrl,
+    ) -> Result<(), crate::Error> {
+        interface
+            .as_memory_interface_mut()
+            .write_word_32(self.registers + SpimCtrl::ADDRESS_OFFSET, ctrl.into())
+    }
+
+    /// Shift one byte out to the flash and return the byte shifted in.
+    fn exchange(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        byte: u8,
+    ) -> Result<u8, crate::Error> {
+        let memory = interface.as_memory_interface_mut();
+        memory.write_word_32(self.registers + TRANSMIT, u32::from(byte))?;
+        let start = Instant::now();
+        loop {
+            let stat = SpimStat(memory.read_word_32(self.registers + SpimStat::ADDRESS_OFFSET)?);
+            if stat.done() {
+                let mut clear = SpimStat(0);
+                clear.set_done(true);
+                memory.write_word_32(self.registers + SpimStat::ADDRESS_OFFSET, clear.into())?;
+                break;
+            }
+            if start.elapsed() > TRANSFER_TIMEOUT {
+                return Err(Leon3Error::Timeout.into());
+            }
+        }
+        Ok(memory.read_word_32(self.registers + RECEIVE)? as u8)
+    }
+
+    /// Send `command` to the flash with chip select asserted, then read `response`.
+    fn transfer(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        command: &[u8],
+        response: &mut [u8],
+    ) -> Result<(), crate::Error> {
+        let mut ctrl = self.read_ctrl(interface)?;
+        ctrl.set_csn(false);
+        self.write_ctrl(interface, ctrl)?;
+
+        let result = (|| {
+            for byte in command {
+                self.exchange(interface, *byte)?;
+            }
+            for byte in response.iter_mut() {
+                *byte = self.exchange(interface, 0xFF)?;
+            }
+            Ok(())
+        })();
+
+        ctrl.set_csn(true);
+        self.write_ctrl(interface, ctrl)?;
+        result
+    }
+
+    /// The command with the 3-byte offset of `address` in the flash.
+    fn addressed(&self, command: u8, address: u64) -> Vec<u8> {
+        let offset = (address - self.memory.start) as u32;
+        let [_, a2, a1, a0] = offset.to_be_bytes();
+        vec![command, a2, a1, a0]
+    }
+
+    /// Enable writes and send a command with a 3-byte address, followed by `data`.
+    fn command_at(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        command: u8,
+        address: u64,
+        data: &[u8],
+    ) -> Result<(), crate::Error> {
+        let mut bytes = self.addressed(command, address);
+        bytes.extend_from_slice(data);
+        self.transfer(interface, &[CMD_WRITE_ENABLE], &mut [])?;
+        self.transfer(interface, &bytes, &mut [])
+    }
+
+    /// Wait until the flash has completed a program or erase operation.
+    fn wait_ready(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        timeout: Duration,
+    ) -> Result<(), crate::Error> {
+        let start = Instant::now();
+        loop {
+            let mut status = [0];
+            self.transfer(interface, &[CMD_READ_STATUS], &mut status)?;
+            if status[0] & STATUS_WIP == 0 {
+                return Ok(());
+            }
+            if start.elapsed() > timeout {
+                return Err(Leon3Error::Timeout.into());
+            }
+        }
+    }
+
+    /// Check that `range` is inside of the flash.
+    fn check_bounds(&self, range: &Range<u64>) -> Result<(), crate::Error> {
+        if range.start < self.memory.start || range.end > self.memory.end {
+            return Err(Leon3Error::OutOfBounds.into());
+        }
+        Ok(())
+    }
+}
+
+impl SectorFlash for SpiFlash {
+    fn sectors(&self, range: Range<u64>) -> Result<Vec<Range<u64>>, crate::Error> {
+        let end = range.end.min(self.memory.end);
+        let mut sectors = vec![];
+        let mut start = range.start - (range.start - self.memory.start) % SECTOR_SIZE;
+        while start < end {
+            sectors.push(start..start + SECTOR_SIZE);
+            start += SECTOR_SIZE;
+        }
+        Ok(sectors)
+    }
+
+    fn erase(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        range: Range<u64>,
+    ) -> Result<(), crate::Error> {
+        self.check_bounds(&range)?;
+        for sector in self.sectors(range)? {
+            tracing::debug!("Erasing SPI flash sector at {:#010x}", sector.start);
+            self.command_at(interface, CMD_SECTOR_ERASE, sector.start, &[])?;
+            self.wait_ready(interface, ERASE_TIMEOUT)?;
+        }
+        Ok(())
+    }
+
+    fn read(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        address: u64,
+        data: &mut [u8],
+    ) -> Result<(), crate::Error> {
+        self.check_bounds(&(address..address + data.len() as u64))?;
+        self.transfer(interface, &self.addressed(CMD_READ, address), data)
+    }
+
+    fn program(
+        &self,
+        interface: &mut Leon3CommunicationInterface,
+        address: u64,
+        data: &[u8],
+    ) -> Result<(), crate::Error> {
+        let end = address + data.len() as u64;
+        self.check_bounds(&(address..end))?;
+        let mut start = address;
+        while start < end {
+            // A page program wraps around at the end of the page.
+            let page_end = ((start / PAGE_SIZE + 1) * PAGE_SIZE).min(end);
+            let page = &data[(start - address) as usize..(page_end - address) as usize];
+            self.command_at(interface, CMD_PAGE_PROGRAM, start, page)?;
+            self.wait_ready(interface, PROGRAM_TIMEOUT)?;
+            start = page_end;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flash() -> SpiFlash {
+        SpiFlash {
+            registers: 0xFFF0_0100,
+            memory: 0x0200_0000..0x0220_0000,
+        }
+    }
+
+    #[test]
+    fn sectors_cover_range() {
+        assert_eq!(
+            flash().sectors(0x0201_8000..0x0203_0001).unwrap(),
+            vec![
+                0x0201_0000..0x0202_0000,
+                0x0202_0000..0x0203_0000,
+                0x0203_0000..0x0204_0000,
+            ]
+        );
+    }
+
+    #[test]
+    fn sectors_end_at_flash_end() {
+        assert_eq!(
+            flash().sectors(0x021F_0000..0x0300_0000).unwrap(),
+            vec![0x021F_0000..0x0220_0000]
+        );
+    }
+
+    #[test]
+    fn addressed_command_uses_flash_offset() {
+        assert_eq!(
+            flash().addressed(CMD_READ, 0x0212_3456),
+            vec![CMD_READ, 0x12, 0x34, 0x56]
+        );
+    }
+}
@@ -0,0 +1,433 @@
+//! DSU3 trace buffers.
+
+use std::time::{Duration, SystemTime};
+
+use crate::{MemoryInterface, memory_mapped_bitfield_register};
+
+/// Offset of the instruction trace buffer in the DSU address space of a core.
+pub(crate) const INSTRUCTION_TRACE_BUFFER_OFFSET: u64 = 0x10_0000;
+
+/// Offset of the AHB trace buffer in the DSU address space.
+pub(crate) const AHB_TRACE_BUFFER_OFFSET: u64 = 0x20_0000;
+
+/// Offset of the address register of the first AHB watchpoint. Each watchpoint has an address
+/// register followed by a mask register.
+pub(crate) const AHB_WATCHPOINT_OFFSET: u64 = 0x50;
+
+/// The number of AHB watchpoints of the DSU.
+pub const AHB_WATCHPOINTS: usize = 2;
+
+/// Mask of the implemented bits of the DSU time tag counter.
+const TIME_TAG_MASK: u32 = 0x3FFF_FFFF;
+
+/// The number of 32-bit words in a trace buffer entry.
+pub(crate) const TRACE_ENTRY_WORDS: usize = 4;
+
+memory_mapped_bitfield_register! {
+    /// Instruction Trace Control Register (GRLIB IP Core User's Manual, DSU3)
+    ///
+    /// Holds the position in the instruction trace buffer the next instruction is stored at.
+    pub struct InstructionTraceControl(u32);
+    0x11_0000, "instruction_trace_control",
+    impl From;
+    /// Instruction trace pointer (IT POINTER) - index of the next entry to be written. Only
+    /// the bits needed to address the implemented buffer size are writable.
+    pub u16, it_pointer, set_it_pointer: 15, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// AHB Trace Buffer Control Register (GRLIB IP Core User's Manual, DSU3)
+    ///
+    /// Controls recording of AHB transfers. The AHB trace buffer is shared by all cores.
+    pub struct AhbTraceControl(u32);
+    0x40, "ahb_trace_control",
+    impl From;
+    /// Trace buffer delay counter (DCNT) - the number of transfers to record after an AHB
+    /// watchpoint hit before tracing is stopped, if DM is set.
+    pub u16, dcnt, set_dcnt: 31, 16;
+    /// Break on AHB watchpoint (BR) - if set, the processors enter debug mode when an AHB
+    /// watchpoint hits.
+    pub br, set_br: 2;
+    /// Delay counter mode (DM) - if set, tracing stops DCNT transfers after an AHB
+    /// watchpoint hit.
+    pub dm, set_dm: 1;
+    /// Trace enable (EN) - if set, AHB transfers are recorded.
+    pub en, set_en: 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// AHB Trace Buffer Index Register (GRLIB IP Core User's Manual, DSU3)
+    ///
+    /// Holds the position in the AHB trace buffer the next transfer is stored at.
+    pub struct AhbTraceIndex(u32);
+    0x44, "ahb_trace_index",
+    impl From;
+    /// Trace buffer index (INDEX) - index of the next entry to be written. Only the bits needed
+    /// to address the implemented buffer size are writable.
+    pub u16, index, set_index: 15, 4;
+}
+
+memory_mapped_bitfield_register! {
+    /// AHB Trace Buffer Filter Control Register (GRLIB IP Core User's Manual, DSU3)
+    ///
+    /// Selects which AHB transfers are recorded.
+    pub struct AhbTraceFilterControl(u32);
+    0x48, "ahb_trace_filter_control",
+    impl From;
+    /// Address filter (AF) - if set, only transfers to addresses matching AHB watchpoint 2
+    /// are recorded.
+    pub af, set_af: 2;
+    /// Filter reads (FR) - if set, read transfers are not recorded.
+    pub fr, set_fr: 1;
+    /// Filter writes (FW) - if set, write transfers are not recorded.
+    pub fw, set_fw: 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// AHB Trace Buffer Filter Mask Register (GRLIB IP Core User's Manual, DSU3)
+    ///
+    /// Excludes transfers of individual masters and slaves from the AHB trace.
+    pub struct AhbTraceFilterMask(u32);
+    0x4C, "ahb_trace_filter_mask",
+    impl From;
+    /// Slave mask (SMASK) - transfers to slave `n` are not recorded if bit `n` is set.
+    pub u16, smask, set_smask: 31, 16;
+    /// Master mask (MMASK) - transfers of master `n` are not recorded if bit `n` is set.
+    pub u16, mmask, set_mmask: 15, 0;
+}
+
+/// Selects which AHB transfers are recorded in the AHB trace buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AhbTraceFilter {
+    /// Record read transfers.
+    pub reads: bool,
+    /// Record write transfers.
+    pub writes: bool,
+    /// Only record transfers to the address range of AHB watchpoint 2 (unit index 1).
+    pub watchpoint_range_only: bool,
+    /// Bus masters whose transfers are not recorded, bit `n` for master `n`.
+    pub ignored_masters: u16,
+    /// Slaves whose transfers are not recorded, bit `n` for slave `n`.
+    pub ignored_slaves: u16,
+}
+
+impl Default for AhbTraceFilter {
+    /// Record all transfers.
+    fn default() -> Self {
+        Self {
+            reads: true,
+            writes: true,
+            watchpoint_range_only: false,
+            ignored_masters: 0,
+            ignored_slaves: 0,
+        }
+    }
+}
+
+/// An instruction executed by the core, as recorded in the instruction trace buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstructionTraceEntry {
+    /// The value of the DSU time tag counter when the instruction completed.
+    pub time: u32,
+    /// The address of the instruction.
+    pub pc: u32,
+    /// The instruction.
+    pub instruction: u32,
+    /// The result of the instruction: the written register value, the data of a load or store,
+    /// or the address for multi-cycle loads and stores.
+    pub result: u32,
+    /// The instruction trapped.
+    pub trap: bool,
+    /// The core entered error mode on this instruction.
+    pub error_mode: bool,
+    /// This is an additional entry of an instruction that takes multiple cycles, e.g. LDD or STD.
+    pub multi_cycle: bool,
+}
+
+impl InstructionTraceEntry {
+    /// Decode an entry from the four words it is stored as, in address order.
+    pub(crate) fn from_words(words: [u32; TRACE_ENTRY_WORDS]) -> Self {
+        Self {
+            time: words[0] & TIME_TAG_MASK,
+            multi_cycle: words[0] & (1 << 30) != 0,
+            result: words[1],
+            pc: words[2] & !0b11,
+            trap: words[2] & (1 << 1) != 0,
+            error_mode: words[2] & (1 << 0) != 0,
+            instruction: words[3],
+        }
+    }
+}
+
+/// An AHB transfer, as recorded in the AHB trace buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AhbTraceEntry {
+    /// The value of the DSU time tag counter when the transfer completed.
+    pub time: u32,
+    /// An AHB watchpoint hit on this transfer.
+    pub watchpoint_hit: bool,
+    /// The pending interrupt requests (HIRQ).
+    pub irq: u16,
+    /// The transfer was a write (HWRITE).
+    pub write: bool,
+    /// The transfer type (HTRANS).
+    pub trans: u8,
+    /// The transfer size (HSIZE).
+    pub size: u8,
+    /// The burst type (HBURST).
+    pub burst: u8,
+    /// The bus master that performed the transfer (HMASTER).
+    pub master: u8,
+    /// The transfer was part of a locked sequence (HMASTLOCK).
+    pub locked: bool,
+    /// The slave response (HRESP).
+    pub response: u8,
+    /// The data that was read or written.
+    pub data: u32,
+    /// The address of the transfer.
+    pub address: u32,
+}
+
+impl AhbTraceEntry {
+    /// Decode an entry from the four words it is stored as, in address order.
+    pub(crate) fn from_words(words: [u32; TRACE_ENTRY_WORDS]) -> Self {
+        let control = words[1];
+        Self {
+            time: words[0] & TIME_TAG_MASK,
+            watchpoint_hit: words[0] & (1 << 31) != 0,
+            irq: (control >> 16) as u16,
+            write: control & (1 << 15) != 0,
+            trans: ((control >> 13) & 0b11) as u8,
+            size: ((control >> 10) & 0b111) as u8,
+            burst: ((control >> 7) & 0b111) as u8,
+            master: ((control >> 3) & 0b1111) as u8,
+            locked: control & (1 << 2) != 0,
+            response: (control & 0b11) as u8,
+            data: words[2],
+            address: words[3],
+        }
+    }
+}
+
+/// Samples the program counter of a running core from its instruction trace buffer.
+///
+/// The buffer records every instruction the core executes, so the entry before the trace
+/// pointer holds the instruction completed last. Reading it takes two AHB reads and does not
+/// halt the core. Created by [`Leon3::pc_sampler`](super::Leon3::pc_sampler).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PcSampler {
+    pointer_address: u64,
+    buffer_address: u64,
+    pointer_mask: u16,
+}
+
+impl PcSampler {
+    /// A sampler for the trace buffer at `buffer_address` with `entries` entries, whose trace
+    /// pointer is at `pointer_address`.
+    pub(crate) fn new(pointer_address: u64, buffer_address: u64, entries: usize) -> Self {
+        Self {
+            pointer_address,
+            buffer_address,
+            pointer_mask: (entries - 1) as u16,
+        }
+    }
+
+    /// The address of the instruction the core executed last.
+    ///
+    /// If the core is halted or powered down, this is the last instruction before it stopped.
+    pub fn sample(
+        &self,
+        memory: &mut (impl MemoryInterface + ?Sized),
+    ) -> Result<u32, crate::Error> {
+        let control = InstructionTraceControl(memory.read_word_32(self.pointer_address)?);
+        let index = control.it_pointer().wrapping_sub(1) & self.pointer_mask;
+        let entry = self.buffer_address + u64::from(index) * 4 * TRACE_ENTRY_WORDS as u64;
+        // The third word holds the PC, with the trap and error mode flags in the low bits.
+        Ok(memory.read_word_32(entry + 8)? & !0b11)
+    }
+}
+
+/// The number of clock cycles from time tag `earlier` to time tag `later`, taking the wrap
+/// around of the 30-bit counter into account.
+///
+/// Time tags are read with [`Leon3::read_time_tag`](super::Leon3::read_time_tag) or taken from
+/// trace buffer entries.
+pub fn time_tag_elapsed(earlier: u32, later: u32) -> u32 {
+    later.wrapping_sub(earlier) & TIME_TAG_MASK
+}
+
+/// Converts DSU time tags to wall-clock time, to merge trace entries with logs of the host.
+///
+/// The time tag counts the cycles of the system clock, so a time tag is converted by its
+/// distance to a reference time tag read at a known wall-clock time. Created by
+/// [`Leon3::trace_clock`](super::Leon3::trace_clock).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceClock {
+    reference_tag: u32,
+    reference_time: SystemTime,
+    frequency: u64,
+}
+
+impl TraceClock {
+    /// A clock on which the time tag `reference_tag` was read at `reference_time`, and which
+    /// counts `frequency` cycles per second.
+    pub fn new(reference_tag: u32, reference_time: SystemTime, frequency: u64) -> Self {
+        Self {
+            reference_tag,
+            reference_time,
+            frequency,
+        }
+    }
+
+    /// The frequency of the system clock in Hz.
+    pub fn frequency(&self) -> u64 {
+        self.frequency
+    }
+
+    /// The wall-clock time at which the time tag counter held `time_tag`.
+    ///
+    /// Trace entries are older than the reference, and the 30-bit counter wraps around, so
+    /// this is only correct for time tags less than one wrap around before the reference.
+    pub fn timestamp(&self, time_tag: u32) -> SystemTime {
+        let cycles = u128::from(time_tag_elapsed(time_tag, self.reference_tag));
+        let nanos = cycles * 1_000_000_000 / u128::from(self.frequency.max(1));
+        self.reference_time - Duration::from_nanos(nanos as u64)
+    }
+}
+
+/// Split the raw trace buffer into entries, oldest first.
+///
+/// `pointer` is the index of the entry that will be written next, which is the oldest entry once
+/// the buffer has wrapped around. Entries that have never been written are skipped.
+pub(crate) fn split_entries<T>(
+    words: &[u32],
+    pointer: usize,
+    decode: impl Fn([u32; TRACE_ENTRY_WORDS]) -> T,
+) -> Vec<T> {
+    let entries: Vec<[u32; TRACE_ENTRY_WORDS]> = words
+        .chunks_exact(TRACE_ENTRY_WORDS)
+        .map(|chunk| chunk.try_into().unwrap())
+        .collect();
+    let pointer = pointer % entries.len().max(1);
+
+    entries[pointer..]
+        .iter()
+        .chain(&entries[..pointer])
+        .filter(|entry| entry.iter().any(|&word| word != 0))
+        .map(|&entry| decode(entry))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::MockMemory;
+
+    #[test]
+    fn decode_instruction_trace_entry() {
+        let entry =
+            InstructionTraceEntry::from_words([0x4000_1234, 0xDEAD_BEEF, 0x4000_0102, 0x91D0_2001]);
+        assert_eq!(
+            entry,
+            InstructionTraceEntry {
+                time: 0x1234,
+                pc: 0x4000_0100,
+                instruction: 0x91D0_2001,
+                result: 0xDEAD_BEEF,
+                trap: true,
+                error_mode: false,
+                multi_cycle: true,
+            }
+        );
+    }
+
+    #[test]
+    fn decode_ahb_trace_entry() {
+        let control = (0x0004 << 16) | (1 << 15) | (0b10 << 13) | (0b010 << 10) | (3 << 3);
+        let entry = AhbTraceEntry::from_words([0x8000_0010, control, 0x1234_5678, 0x4000_0040]);
+        assert_eq!(
+            entry,
+            AhbTraceEntry {
+                time: 0x10,
+                watchpoint_hit: true,
+                irq: 0x0004,
+                write: true,
+                trans: 0b10,
+                size: 0b010,
+                burst: 0,
+                master: 3,
+                locked: false,
+                response: 0,
+                data: 0x1234_5678,
+                address: 0x4000_0040,
+            }
+        );
+    }
+
+    #[test]
+    fn sample_last_instruction() {
+        let memory = |pointer| {
+            let mut memory = MockMemory::new();
+            memory.add_word_range(0x9011_0000, &[pointer]);
+            memory.add_word_range(
+                0x9010_0000,
+                &[
+                    0,
+                    0,
+                    0x4000_0100,
+                    0, //
+                    0,
+                    0,
+                    0x4000_0104,
+                    0, //
+                    0,
+                    0,
+                    0x4000_0108,
+                    0, //
+                    0,
+                    0,
+                    0x4000_0202,
+                    0,
+                ],
+            );
+            memory
+        };
+        let sampler = PcSampler::new(0x9011_0000, 0x9010_0000, 4);
+        assert_eq!(sampler.sample(&mut memory(0)).unwrap(), 0x4000_0200);
+        assert_eq!(sampler.sample(&mut memory(2)).unwrap(), 0x4000_0104);
+    }
+
+    #[test]
+    fn time_tag_wraps_around() {
+        assert_eq!(time_tag_elapsed(10, 25), 15);
+        assert_eq!(time_tag_elapsed(0x3FFF_FFF0, 0x10), 0x20);
+    }
+
+    #[test]
+    fn trace_clock_timestamps() {
+        let reference = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let clock = TraceClock::new(0x100, reference, 50_000_000);
+        assert_eq!(clock.timestamp(0x100), reference);
+        assert_eq!(
+            clock.timestamp(0x100 - 50),
+            reference - Duration::from_micros(1)
+        );
+        // The entry was recorded before the counter wrapped around.
+        assert_eq!(
+            clock.timestamp(0x3FFF_FFFF - 49 + 0x100),
+            reference - Duration::from_micros(1)
+        );
+    }
+
+    #[test]
+    fn trace_entries_oldest_first() {
+        let words = [
+            3, 0, 0, 0, //
+            0, 0, 0, 0, //
+            1, 0, 0, 0, //
+            2, 0, 0, 0,
+        ];
+        let entries = split_entries(&words, 1, |entry| entry[0]);
+        assert_eq!(entries, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,230 @@
+//! The SPARC V8 trap table.
+
+use object::Object;
+
+use crate::architecture::leon3::{
+    communication_interface::Leon3Error,
+    instructions::{BranchKind, Instruction},
+};
+
+/// The number of entries of the trap table.
+pub const TRAP_TABLE_ENTRIES: usize = 256;
+
+/// The number of instructions of a trap table entry.
+pub(crate) const TRAP_ENTRY_WORDS: usize = 4;
+
+/// The address of the trap table entry of `trap_type` in the table the TBR `tbr` points to.
+pub(crate) fn entry_address(tbr: u32, trap_type: u8) -> u32 {
+    (tbr & 0xFFFF_F000) | (u32::from(trap_type) << 4)
+}
+
+/// The PSR after `rett` returns from a trap taken with `psr`.
+///
+/// The window of the trapped code is restored, S is restored from PS, and traps are enabled.
+pub(crate) fn psr_after_return(psr: u32, num_windows: u32) -> u32 {
+    let cwp = ((psr & 0x1F) + 1) % num_windows;
+    let supervisor = (psr >> 6) & 1;
+    (psr & !0x9F) | (supervisor << 7) | (1 << 5) | cwp
+}
+
+/// The name of a trap type, as used by the SPARC Architecture Manual and the LEON3 manual.
+pub fn trap_name(trap_type: u8) -> String {
+    let name = match trap_type {
+        0x00 => "reset",
+        0x01 => "instruction_access_exception",
+        0x02 => "illegal_instruction",
+        0x03 => "privileged_instruction",
+        0x04 => "fp_disabled",
+        0x05 => "window_overflow",
+        0x06 => "window_underflow",
+        0x07 => "mem_address_not_aligned",
+        0x08 => "fp_exception",
+        0x09 => "data_access_exception",
+        0x0A => "tag_overflow",
+        0x0B => "watchpoint_detected",
+        0x11..=0x1F => return format!("interrupt_level_{}", trap_type - 0x10),
+        0x20 => "r_register_access_error",
+        0x21 => "instruction_access_error",
+        0x24 => "cp_disabled",
+        0x25 => "unimplemented_FLUSH",
+        0x28 => "cp_exception",
+        0x29 => "data_access_error",
+        0x2A => "division_by_zero",
+        0x2B => "data_store_error",
+        0x2C => "data_access_MMU_miss",
+        0x3C => "instruction_access_MMU_miss",
+        0x60..=0x7F => "impl_dependent_exception",
+        0x80..=0xFF => return format!("trap_instruction (ta {})", trap_type - 0x80),
+        _ => "reserved",
+    };
+    name.to_string()
+}
+
+/// An entry of the trap table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrapTableEntry {
+    /// The trap type the entry is taken for.
+    pub trap_type: u8,
+    /// The address of the entry.
+    pub address: u32,
+    /// The four instructions of the entry.
+    pub instructions: [u32; TRAP_ENTRY_WORDS],
+    /// The address the entry jumps to, if it could be determined from its instructions.
+    pub handler: Option<u32>,
+    /// The symbol containing the handler, filled in by [`TrapTable::resolve_symbols`].
+    pub symbol: Option<String>,
+}
+
+impl TrapTableEntry {
+    pub(crate) fn new(trap_type: u8, address: u32, instructions: [u32; TRAP_ENTRY_WORDS]) -> Self {
+        Self {
+            trap_type,
+            address,
+            instructions,
+            handler: jump_target(address, &instructions),
+            symbol: None,
+        }
+    }
+
+    /// The name of the trap type of the entry.
+    pub fn name(&self) -> String {
+        trap_name(self.trap_type)
+    }
+}
+
+/// The trap table of a core, as pointed to by its trap base register.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrapTable {
+    /// The base address of the trap table.
+    pub base: u32,
+    /// The type of the last trap taken by the core, from the `tt` field of the TBR.
+    pub last_trap_type: u8,
+    /// The trap type that made the core enter debug mode, from the DSU trap register.
+    pub debug_trap_type: u8,
+    /// The entries of the table, indexed by trap type.
+    pub entries: Vec<TrapTableEntry>,
+}
+
+impl TrapTable {
+    /// The entry of the last trap taken by the core, whose handler is most likely running.
+    pub fn last_trap(&self) -> &TrapTableEntry {
+        &self.entries[usize::from(self.last_trap_type)]
+    }
+
+    /// Set the symbol of each entry with a known handler from the symbol table of an ELF file.
+    pub fn resolve_symbols(&mut self, elf: &[u8]) -> Result<(), Leon3Error> {
+        let file = object::File::parse(elf)?;
+        let symbols = file.symbol_map();
+        for entry in &mut self.entries {
+            entry.symbol = entry
+                .handler
+                .and_then(|handler| symbols.get(u64::from(handler)))
+                .map(|symbol| symbol.name().to_string());
+        }
+        Ok(())
+    }
+}
+
+/// The address the instructions of a trap table entry at `address` jump to.
+///
+/// Trap table entries usually load the address of the handler with `sethi` and `or`, or just
+/// `sethi`, and jump to it with `jmp`, or branch or call the handler directly. The first
+/// control transfer is followed, later instructions run in its delay slot.
+fn jump_target(address: u32, instructions: &[u32]) -> Option<u32> {
+    // The values of the registers loaded so far.
+    let mut registers: [Option<u32>; 32] = [None; 32];
+    registers[0] = Some(0);
+
+    for (n, &word) in instructions.iter().enumerate() {
+        let pc = address.wrapping_add(4 * n as u32);
+        let rd = ((word >> 25) & 0x1F) as usize;
+        let rs1 = ((word >> 14) & 0x1F) as usize;
+        // The second operand, a sign-extended immediate or the value of rs2.
+        let operand = if (word >> 13) & 1 == 1 {
+            Some(((word << 19) as i32 >> 19) as u32)
+        } else {
+            registers[(word & 0x1F) as usize]
+        };
+        let op3 = (word >> 19) & 0x3F;
+
+        match word >> 30 {
+            0 if (word >> 22) & 0b111 == 0b100 => {
+                // sethi
+                registers[rd] = Some(word << 10);
+            }
+            0 => {
+                if let Instruction::Branch {
+                    kind: BranchKind::Integer,
+                    cond: 8,
+                    displacement,
+                    ..
+                } = Instruction::decode(word)
+                {
+                    return Some(pc.wrapping_add_signed(displacement));
+                }
+            }
+            1 => return Some(pc.wrapping_add(word << 2)),
+            2 if op3 == 0x38 => {
+                // jmpl
+                return registers[rs1].zip(operand).map(|(a, b)| a.wrapping_add(b));
+            }
+            2 if op3 == 0x02 => {
+                // or, as in `or %l4, %lo(handler), %l4`
+                registers[rd] = registers[rs1].zip(operand).map(|(a, b)| a | b);
+            }
+            _ => registers[rd] = None,
+        }
+        registers[0] = Some(0);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(trap_name(0x05), "window_overflow");
+        assert_eq!(trap_name(0x1F), "interrupt_level_15");
+        assert_eq!(trap_name(0x81), "trap_instruction (ta 1)");
+        assert_eq!(trap_name(0x30), "reserved");
+    }
+
+    #[test]
+    fn return_from_trap() {
+        // Trapped from user mode in window 0 into window 7, traps disabled.
+        assert_eq!(psr_after_return(0xF300_0087, 8), 0xF300_0020);
+        // Trapped from supervisor mode in window 3 into window 2.
+        assert_eq!(psr_after_return(0xF340_00C2, 8), 0xF340_00E3);
+    }
+
+    #[test]
+    fn handler_loaded_with_sethi() {
+        // mov %psr, %l0; sethi %hi(0x40001800), %l4; jmp %l4 + 0x0; mov 0x5, %l3
+        let entry = [0xA148_0000, 0x2910_0006, 0x81C5_2000, 0xA610_2005];
+        assert_eq!(jump_target(0x4000_0050, &entry), Some(0x4000_1800));
+    }
+
+    #[test]
+    fn handler_loaded_with_set() {
+        // sethi %hi(0x40001234), %l4; or %l4, 0x234, %l4; jmp %l4; nop
+        let entry = [0x2910_0004, 0xA815_2234, 0x81C5_0000, 0x0100_0000];
+        assert_eq!(jump_target(0x4000_0000, &entry), Some(0x4000_1234));
+
+        // ld [%l4], %l4; jmp %l4; nop; nop
+        let entry = [0xE805_0000, 0x81C5_0000, 0x0100_0000, 0x0100_0000];
+        assert_eq!(jump_target(0x4000_0000, &entry), None);
+    }
+
+    #[test]
+    fn handler_reached_by_branch() {
+        // ba 0x40000010; nop
+        let entry = [0x1080_0004, 0x0100_0000, 0, 0];
+        assert_eq!(jump_target(0x4000_0000, &entry), Some(0x4000_0010));
+
+        // ta 0; nop; nop; nop
+        let entry = [0x91D0_2000, 0x0100_0000, 0x0100_0000, 0x0100_0000];
+        assert_eq!(jump_target(0x4000_0000, &entry), None);
+    }
+}
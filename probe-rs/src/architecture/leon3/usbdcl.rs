@@ -0,0 +1,318 @@
+//! USB DCL debug link, giving access to the AHB bus through the debug communication link of
+//! a GRUSBDC USB device controller.
+//!
+//! Commands are sent to the bulk OUT endpoint. Like the ADATA register of AHBJTAG, a command
+//! starts with the AHB address, followed by a control word holding the direction and the
+//! number of words of a sequential transfer. Write commands carry the data words, the data
+//! of read commands is returned on the bulk IN endpoint. All words are big-endian.
+
+use std::time::Duration;
+
+use nusb::{Interface, MaybeFuture as _};
+
+use crate::{
+    MemoryInterface,
+    architecture::leon3::{
+        ahbjtag::check_out_of_bounds,
+        communication_interface::Leon3Error,
+        word_access::{WordAccess, read_unaligned, write_unaligned},
+    },
+    memory::{InvalidDataLengthError, valid_32bit_address},
+    probe::{DebugProbeSelector, usb_util::InterfaceExt as _},
+};
+
+/// The interface of the USB DCL.
+const DCL_INTERFACE: u8 = 0;
+
+/// The bulk OUT endpoint commands are sent to.
+const EP_OUT: u8 = 0x01;
+
+/// The bulk IN endpoint read data is returned on.
+const EP_IN: u8 = 0x81;
+
+/// The maximum number of words of a single command. Sequential transfers also must not
+/// cross a 1 kB boundary.
+const MAX_WORDS: usize = 256;
+
+/// The default time to wait for a single bulk transfer.
+const DEFAULT_USB_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Errors of the USB DCL debug link.
+#[derive(thiserror::Error, Debug)]
+pub enum UsbDclError {
+    /// The device selector could not be parsed.
+    #[error("Invalid USB DCL device {0:?}, expected VID:PID[:serial]")]
+    InvalidSelector(String),
+    /// No matching USB device was found.
+    #[error("No USB DCL device matching {0:?} was found")]
+    NotFound(String),
+    /// A USB transfer failed.
+    #[error("USB DCL transfer failed")]
+    Usb(#[from] std::io::Error),
+    /// The device returned less data than requested.
+    #[error("USB DCL returned {received} of {expected} bytes")]
+    ShortRead {
+        /// The number of bytes returned.
+        received: usize,
+        /// The number of bytes requested.
+        expected: usize,
+    },
+}
+
+/// USB DCL driver used to access the AHB bus over USB.
+pub struct UsbDcl {
+    interface: Interface,
+    timeout: Duration,
+}
+
+impl std::fmt::Debug for UsbDcl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UsbDcl")
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl UsbDcl {
+    /// Opens the USB DCL device matching `selector`, given as `VID:PID[:serial]`.
+    pub fn open(selector: &str) -> Result<Self, UsbDclError> {
+        let parsed: DebugProbeSelector = selector
+            .parse()
+            .map_err(|_| UsbDclError::InvalidSelector(selector.to_string()))?;
+        let device = nusb::list_devices()
+            .wait()
+            .map_err(std::io::Error::from)?
+            .find(|device| parsed.matches(device))
+            .ok_or_else(|| UsbDclError::NotFound(selector.to_string()))?;
+
+        let interface = device
+            .open()
+            .wait()
+            .and_then(|handle| handle.claim_interface(DCL_INTERFACE).wait())
+            .map_err(std::io::Error::from)?;
+        tracing::info!("Using USB DCL {selector}");
+
+        Ok(Self {
+            interface,
+            timeout: DEFAULT_USB_TIMEOUT,
+        })
+    }
+
+    /// Returns how long to wait for a single bulk transfer.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Sets how long to wait for a single bulk transfer.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    fn read_chunk(&mut self, address: u32, data: &mut [u32]) -> Result<(), UsbDclError> {
+        let command = encode_command(address, false, data.len());
+        self.interface.write_bulk(EP_OUT, &command, self.timeout)?;
+
+        let mut response = vec![0u8; data.len() * 4];
+        let received = self
+            .interface
+            .read_bulk(EP_IN, &mut response, self.timeout)?;
+        if received != response.len() {
+            return Err(UsbDclError::ShortRead {
+                received,
+                expected: response.len(),
+            });
+        }
+        for (word, bytes) in data.iter_mut().zip(response.chunks_exact(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, address: u32, data: &[u32]) -> Result<(), UsbDclError> {
+        let mut command = encode_command(address, true, data.len());
+        command.extend(data.iter().flat_map(|word| word.to_be_bytes()));
+        self.interface.write_bulk(EP_OUT, &command, self.timeout)?;
+        Ok(())
+    }
+}
+
+/// Encodes the address and control word of a command for `num_words > 0` words.
+fn encode_command(address: u32, write: bool, num_words: usize) -> Vec<u8> {
+    let control = (u32::from(write) << 31) | (num_words as u32 - 1);
+    let mut command = Vec::with_capacity(8 + num_words * 4);
+    command.extend_from_slice(&address.to_be_bytes());
+    command.extend_from_slice(&control.to_be_bytes());
+    command
+}
+
+/// Returns how many words of a sequential transfer starting at `address` fit into a command.
+fn chunk_len(address: u32, remaining_words: usize) -> usize {
+    let words_to_boundary = (1024 - address % 1024) as usize / 4;
+    words_to_boundary.min(remaining_words).min(MAX_WORDS)
+}
+
+impl WordAccess for UsbDcl {
+    fn read_words(&mut self, address: u32, data: &mut [u32]) -> Result<(), Leon3Error> {
+        check_out_of_bounds(address, data.len() * 4)?;
+        let mut offset = 0;
+        while offset < data.len() {
+            let chunk_address = address + offset as u32 * 4;
+            let len = chunk_len(chunk_address, data.len() - offset);
+            self.read_chunk(chunk_address, &mut data[offset..][..len])?;
+            offset += len;
+        }
+        Ok(())
+    }
+
+    fn write_words(&mut self, address: u32, data: &[u32]) -> Result<(), Leon3Error> {
+        check_out_of_bounds(address, data.len() * 4)?;
+        let mut offset = 0;
+        while offset < data.len() {
+            let chunk_address = address + offset as u32 * 4;
+            let len = chunk_len(chunk_address, data.len() - offset);
+            self.write_chunk(chunk_address, &data[offset..][..len])?;
+            offset += len;
+        }
+        Ok(())
+    }
+}
+
+impl MemoryInterface for UsbDcl {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        false
+    }
+
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(8) {
+            return read_unaligned(self, address, data);
+        }
+        let address = valid_32bit_address(address)?;
+        let mut words = vec![0u32; data.len() * 2];
+        self.read_words(address, &mut words)?;
+        for (word64, pair) in data.iter_mut().zip(words.chunks_exact(2)) {
+            // The target is big-endian, the word at the lower address holds the upper half.
+            *word64 = (u64::from(pair[0]) << 32) | u64::from(pair[1]);
+        }
+        Ok(())
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(4) {
+            return read_unaligned(self, address, data);
+        }
+        let address = valid_32bit_address(address)?;
+        self.read_words(address, data)?;
+        Ok(())
+    }
+
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), crate::Error> {
+        let mut bytes = vec![0u8; data.len() * 2];
+        self.read_8(address, &mut bytes)?;
+        for (word16, bytes) in data.iter_mut().zip(bytes.chunks_exact(2)) {
+            *word16 = u16::from_be_bytes([bytes[0], bytes[1]]);
+        }
+        Ok(())
+    }
+
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        let address = valid_32bit_address(address)?;
+        self.read_bytes(address, data)?;
+        Ok(())
+    }
+
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(8) {
+            return write_unaligned(self, address, data);
+        }
+        let address = valid_32bit_address(address)?;
+        let words = data
+            .iter()
+            .flat_map(|word64| [(word64 >> 32) as u32, *word64 as u32])
+            .collect::<Vec<_>>();
+        self.write_words(address, &words)?;
+        Ok(())
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), crate::Error> {
+        if !address.is_multiple_of(4) {
+            return write_unaligned(self, address, data);
+        }
+        let address = valid_32bit_address(address)?;
+        self.write_words(address, data)?;
+        Ok(())
+    }
+
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), crate::Error> {
+        let bytes = data
+            .iter()
+            .flat_map(|word16| word16.to_be_bytes())
+            .collect::<Vec<_>>();
+        self.write_8(address, &bytes)
+    }
+
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        let address = valid_32bit_address(address)?;
+        self.write_bytes(address, data)?;
+        Ok(())
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, crate::Error> {
+        Ok(true)
+    }
+
+    fn flush(&mut self) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
+    fn read_mem_64bit(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(8) {
+            return Err(InvalidDataLengthError::new("read_mem_64bit", 8).into());
+        }
+        self.read_8(address, data)
+    }
+
+    fn read_mem_32bit(&mut self, address: u64, data: &mut [u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(4) {
+            return Err(InvalidDataLengthError::new("read_mem_32bit", 4).into());
+        }
+        self.read_8(address, data)
+    }
+
+    fn write_mem_64bit(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(8) {
+            return Err(InvalidDataLengthError::new("write_mem_64bit", 8).into());
+        }
+        self.write_8(address, data)
+    }
+
+    fn write_mem_32bit(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        if !data.len().is_multiple_of(4) {
+            return Err(InvalidDataLengthError::new("write_mem_32bit", 4).into());
+        }
+        self.write_8(address, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_commands() {
+        assert_eq!(
+            encode_command(0x4000_0100, false, 16),
+            [0x40, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x0F]
+        );
+        assert_eq!(
+            encode_command(0x9000_0000, true, 1),
+            [0x90, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn chunks_stop_at_1k_boundary() {
+        assert_eq!(chunk_len(0x4000_0000, 1000), 256);
+        assert_eq!(chunk_len(0x4000_03F0, 1000), 4);
+        assert_eq!(chunk_len(0x4000_0000, 3), 3);
+    }
+}
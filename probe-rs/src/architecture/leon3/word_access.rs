@@ -0,0 +1,221 @@
+//! Byte and unaligned accesses for debug links that transfer whole 32-bit words.
+
+use crate::{
+    MemoryInterface,
+    architecture::leon3::{ahbjtag::check_out_of_bounds, communication_interface::Leon3Error},
+};
+
+/// A debug link that reads and writes aligned 32-bit words of the AHB bus.
+pub(super) trait WordAccess {
+    /// Read 32-bit words, starting at the word aligned `address`.
+    fn read_words(&mut self, address: u32, data: &mut [u32]) -> Result<(), Leon3Error>;
+
+    /// Write 32-bit words, starting at the word aligned `address`.
+    fn write_words(&mut self, address: u32, data: &[u32]) -> Result<(), Leon3Error>;
+
+    /// Read bytes by reading the words covering them.
+    fn read_bytes(&mut self, address: u32, data: &mut [u8]) -> Result<(), Leon3Error> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        check_out_of_bounds(address, data.len())?;
+        let span = WordSpan::new(address, data.len());
+
+        let mut words = vec![0u32; span.num_words];
+        self.read_words(span.start, &mut words)?;
+
+        let bytes = words_to_bytes(&words);
+        data.copy_from_slice(&bytes[span.offset..][..data.len()]);
+        Ok(())
+    }
+
+    /// Write bytes by writing the words covering them.
+    ///
+    /// Partially written words at the start and end are read first, and their other
+    /// bytes are written back unchanged.
+    fn write_bytes(&mut self, address: u32, data: &[u8]) -> Result<(), Leon3Error> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        check_out_of_bounds(address, data.len())?;
+        let span = WordSpan::new(address, data.len());
+
+        let mut words = vec![0u32; span.num_words];
+        let last = span.num_words - 1;
+        let head_partial = span.offset != 0;
+        let tail_partial = !(span.offset + data.len()).is_multiple_of(4);
+        if head_partial || (tail_partial && last == 0) {
+            self.read_words(span.start, &mut words[..1])?;
+        }
+        if tail_partial && last > 0 {
+            self.read_words(span.start + last as u32 * 4, &mut words[last..])?;
+        }
+
+        let mut bytes = words_to_bytes(&words);
+        bytes[span.offset..][..data.len()].copy_from_slice(data);
+        for (word, bytes) in words.iter_mut().zip(bytes.chunks_exact(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+
+        self.write_words(span.start, &words)
+    }
+}
+
+/// The aligned 32-bit words covering a byte range.
+#[derive(Debug, PartialEq, Eq)]
+struct WordSpan {
+    /// Address of the first word.
+    start: u32,
+    /// Offset of the first byte within the first word.
+    offset: usize,
+    /// Number of words.
+    num_words: usize,
+}
+
+impl WordSpan {
+    /// The words covering `len > 0` bytes at `address`, which must not overflow.
+    fn new(address: u32, len: usize) -> Self {
+        let offset = (address % 4) as usize;
+        Self {
+            start: address - offset as u32,
+            offset,
+            num_words: (offset + len).div_ceil(4),
+        }
+    }
+}
+
+/// A value stored in target memory order (big-endian).
+pub(super) trait BigEndian: Copy {
+    /// The size of the value in bytes.
+    const SIZE: usize;
+
+    /// Converts `Self::SIZE` bytes to a value.
+    fn from_be_slice(bytes: &[u8]) -> Self;
+
+    /// Appends the bytes of the value.
+    fn extend_be(self, bytes: &mut Vec<u8>);
+}
+
+macro_rules! impl_big_endian {
+    ($($ty:ty),*) => {
+        $(impl BigEndian for $ty {
+            const SIZE: usize = size_of::<$ty>();
+
+            fn from_be_slice(bytes: &[u8]) -> Self {
+                <$ty>::from_be_bytes(bytes.try_into().unwrap())
+            }
+
+            fn extend_be(self, bytes: &mut Vec<u8>) {
+                bytes.extend_from_slice(&self.to_be_bytes());
+            }
+        })*
+    };
+}
+
+impl_big_endian!(u16, u32, u64);
+
+/// Emulates a read of values at an address not aligned to their size.
+///
+/// The bytes are read with `read_8`, which only issues aligned bus transfers, so
+/// consumers like the DWARF evaluator can read packed data.
+pub(super) fn read_unaligned<T: BigEndian>(
+    memory: &mut impl MemoryInterface,
+    address: u64,
+    data: &mut [T],
+) -> Result<(), crate::Error> {
+    let mut bytes = vec![0u8; data.len() * T::SIZE];
+    memory.read_8(address, &mut bytes)?;
+    for (value, bytes) in data.iter_mut().zip(bytes.chunks_exact(T::SIZE)) {
+        *value = T::from_be_slice(bytes);
+    }
+    Ok(())
+}
+
+/// Emulates a write of values at an address not aligned to their size, with `write_8`.
+pub(super) fn write_unaligned<T: BigEndian>(
+    memory: &mut impl MemoryInterface,
+    address: u64,
+    data: &[T],
+) -> Result<(), crate::Error> {
+    let mut bytes = Vec::with_capacity(data.len() * T::SIZE);
+    for value in data {
+        value.extend_be(&mut bytes);
+    }
+    memory.write_8(address, &bytes)
+}
+
+/// Converts words to the bytes in target memory order (big-endian).
+fn words_to_bytes(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_be_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Word memory at address 0, to check the merging of partial words.
+    struct Words(Vec<u32>);
+
+    impl WordAccess for Words {
+        fn read_words(&mut self, address: u32, data: &mut [u32]) -> Result<(), Leon3Error> {
+            let start = address as usize / 4;
+            data.copy_from_slice(&self.0[start..][..data.len()]);
+            Ok(())
+        }
+
+        fn write_words(&mut self, address: u32, data: &[u32]) -> Result<(), Leon3Error> {
+            let start = address as usize / 4;
+            self.0[start..][..data.len()].copy_from_slice(data);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn word_span_covers_unaligned_bytes() {
+        let span = |start, offset, num_words| WordSpan {
+            start,
+            offset,
+            num_words,
+        };
+        assert_eq!(WordSpan::new(0x4000_0000, 8), span(0x4000_0000, 0, 2));
+        assert_eq!(WordSpan::new(0x4000_0003, 2), span(0x4000_0000, 3, 2));
+        assert_eq!(WordSpan::new(0x4000_0001, 2), span(0x4000_0000, 1, 1));
+        assert_eq!(WordSpan::new(0xFFFF_FFFE, 2), span(0xFFFF_FFFC, 2, 1));
+    }
+
+    #[test]
+    fn words_are_big_endian_in_memory() {
+        assert_eq!(
+            words_to_bytes(&[0x0102_0304, 0xA0B0_C0D0]),
+            [0x01, 0x02, 0x03, 0x04, 0xA0, 0xB0, 0xC0, 0xD0]
+        );
+    }
+
+    #[test]
+    fn partial_words_are_merged() {
+        let mut memory = Words(vec![0x0011_2233, 0x4455_6677, 0x8899_AABB]);
+        memory
+            .write_bytes(3, &[0xF0, 0xF1, 0xF2, 0xF3, 0xF4])
+            .unwrap();
+        assert_eq!(memory.0, [0x0011_22F0, 0xF1F2_F3F4, 0x8899_AABB]);
+
+        memory.write_bytes(9, &[0xE0]).unwrap();
+        assert_eq!(memory.0[2], 0x88E0_AABB);
+
+        let mut bytes = [0; 3];
+        memory.read_bytes(2, &mut bytes).unwrap();
+        assert_eq!(bytes, [0x22, 0xF0, 0xF1]);
+    }
+
+    #[test]
+    fn big_endian_values_round_trip() {
+        let mut bytes = Vec::new();
+        0x1122_3344_5566_7788u64.extend_be(&mut bytes);
+        0xAABBu16.extend_be(&mut bytes);
+        assert_eq!(
+            bytes,
+            [0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0xAA, 0xBB]
+        );
+        assert_eq!(u32::from_be_slice(&bytes[3..7]), 0x4455_6677);
+    }
+}
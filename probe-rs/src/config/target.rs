@@ -120,7 +120,15 @@ impl Target {
                 Architecture::Arm => DebugSequence::Arm(DefaultArmSequence::create()),
                 Architecture::Riscv => DebugSequence::Riscv(DefaultRiscvSequence::create()),
                 Architecture::Xtensa => DebugSequence::Xtensa(DefaultXtensaSequence::create()),
-                Architecture::Sparc => DebugSequence::Leon3(DefaultLeon3Sequence::create()),
+                Architecture::Sparc => {
+                    let name = crate::vendor::leon3_sequence_name(chip);
+                    if name != chip.name {
+                        tracing::warn!(
+                            "No built-in LEON3 debug sequence for {name}, using the default sequence"
+                        );
+                    }
+                    DebugSequence::Leon3(DefaultLeon3Sequence::create())
+                }
             }
         });
 
@@ -167,6 +167,11 @@ pub trait CoreInterface: MemoryInterface {
         false
     }
 
+    /// Check if the core stores multi-byte values in big-endian byte order
+    fn is_big_endian(&self) -> bool {
+        false
+    }
+
     /// Spill registers into memory.
     fn spill_registers(&mut self) -> Result<(), Error> {
         // For most architectures, this is not necessary. Use cases include processors
@@ -546,6 +551,11 @@ impl<'probe> Core<'probe> {
         self.inner.is_64_bit()
     }
 
+    /// Check if the core stores multi-byte values in big-endian byte order
+    pub fn is_big_endian(&self) -> bool {
+        self.inner.is_big_endian()
+    }
+
     /// Spill registers into memory.
     pub fn spill_registers(&mut self) -> Result<(), Error> {
         self.inner.spill_registers()
@@ -680,6 +690,10 @@ impl CoreInterface for Core<'_> {
         self.is_64_bit()
     }
 
+    fn is_big_endian(&self) -> bool {
+        self.is_big_endian()
+    }
+
     fn spill_registers(&mut self) -> Result<(), Error> {
         self.spill_registers()
     }
@@ -700,7 +714,7 @@ pub enum ResolvedCoreOptions {
     },
     Sparc {
         sequence: Arc<dyn Leon3DebugSequence>,
-        options: Leon3CoreAccessOptions,
+        options: Box<Leon3CoreAccessOptions>,
     },
 }
 
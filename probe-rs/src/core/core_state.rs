@@ -1,3 +1,7 @@
+use std::sync::Arc;
+
+use probe_rs_target::MemoryRegion;
+
 use crate::{
     Core, CoreType, Error, Target,
     architecture::{
@@ -6,7 +10,10 @@ use crate::{
             core::{CortexAState, CortexMState},
             dp::DpAddress,
         },
-        leon3::{Leon3CoreState, communication_interface::Leon3CommunicationInterface},
+        leon3::{
+            Leon3, Leon3CoreState, communication_interface::Leon3CommunicationInterface,
+            sequences::Leon3DebugSequence,
+        },
         riscv::{RiscvCoreState, communication_interface::RiscvCommunicationInterface},
         xtensa::{XtensaCoreState, communication_interface::XtensaCommunicationInterface},
     },
@@ -224,7 +231,46 @@ impl CombinedCoreState {
         interface: Leon3CommunicationInterface<'probe>,
     ) -> Result<Core<'probe>, Error> {
         let name = &target.cores[self.id].name;
+        let id = self.id;
+
+        Ok(Core::new(id, name, target, self.leon3_core(interface)?))
+    }
+
+    /// Pass the executable memory regions of a LEON3 core to its state.
+    pub(crate) fn set_leon3_code_ranges(&mut self, target: &Target) {
+        let SpecificCoreState::Leon3(state) = &mut self.specific_state else {
+            return;
+        };
+        let name = &target.cores[self.id].name;
+        let ranges = target
+            .memory_map
+            .iter()
+            .filter(|region| region.cores().contains(name))
+            .filter(|region| match region {
+                MemoryRegion::Ram(region) => region.is_executable(),
+                MemoryRegion::Nvm(region) => region.is_executable(),
+                MemoryRegion::Generic(region) => region.is_executable(),
+            })
+            .map(MemoryRegion::address_range)
+            .collect();
+        state.set_code_ranges(ranges);
+    }
+
+    /// Pass the core access options of a LEON3 core to its state.
+    pub(crate) fn apply_leon3_options(&mut self) {
+        let (SpecificCoreState::Leon3(state), ResolvedCoreOptions::Sparc { options, .. }) = (
+            &mut self.specific_state,
+            &self.core_state.core_access_options,
+        ) else {
+            return;
+        };
+        state.apply_options(options);
+    }
 
+    pub(crate) fn leon3_core<'probe>(
+        &'probe mut self,
+        interface: Leon3CommunicationInterface<'probe>,
+    ) -> Result<Leon3<'probe>, Error> {
         let ResolvedCoreOptions::Sparc { sequence, .. } = &self.core_state.core_access_options
         else {
             unreachable!(
@@ -241,12 +287,16 @@ impl CombinedCoreState {
             );
         };
 
-        Ok(Core::new(
-            self.id,
-            name,
-            target,
-            crate::architecture::leon3::Leon3::new(self.id, interface, s, debug_sequence)?,
-        ))
+        Leon3::new(interface.core_index(), interface, s, debug_sequence)
+    }
+
+    /// Replace the debug sequence of a LEON3 core.
+    pub(crate) fn set_leon3_sequence(&mut self, new_sequence: Arc<dyn Leon3DebugSequence>) {
+        if let ResolvedCoreOptions::Sparc { sequence, .. } =
+            &mut self.core_state.core_access_options
+        {
+            *sequence = new_sequence;
+        }
     }
 
     /// Get the memory AP for this core.
@@ -315,7 +365,7 @@ pub enum SpecificCoreState {
     /// The state of an Xtensa core.
     Xtensa(XtensaCoreState),
     /// The state of a Leon3 core.
-    Leon3(Leon3CoreState),
+    Leon3(Box<Leon3CoreState>),
 }
 
 impl SpecificCoreState {
@@ -329,7 +379,7 @@ impl SpecificCoreState {
             CoreType::Armv8m => SpecificCoreState::Armv8m(CortexMState::new()),
             CoreType::Riscv => SpecificCoreState::Riscv(RiscvCoreState::new()),
             CoreType::Xtensa => SpecificCoreState::Xtensa(XtensaCoreState::new()),
-            CoreType::Sparc => SpecificCoreState::Leon3(Leon3CoreState::new()),
+            CoreType::Sparc => SpecificCoreState::Leon3(Box::new(Leon3CoreState::new())),
         }
     }
 
@@ -7,7 +7,7 @@ pub enum CoreStatus {
     Running,
     /// The core is currently halted. This also specifies the reason as a payload.
     Halted(HaltReason),
-    /// This is a Cortex-M specific status, and will not be set or handled by RISC-V code.
+    /// The core is locked up, e.g. a Cortex-M in lockup state or a LEON3 in error mode.
     LockedUp,
     /// The core is currently sleeping.
     Sleeping,
@@ -5,22 +5,28 @@ use crate::architecture::arm::core::registers::aarch64::AARCH64_CORE_REGISTERS;
 use crate::architecture::arm::core::registers::cortex_m::{
     CORTEX_M_CORE_REGISTERS, CORTEX_M_WITH_FP_CORE_REGISTERS,
 };
-use crate::architecture::leon3::registers::LEON3_CORE_REGISTERS;
+use crate::architecture::leon3::communication_interface::Leon3Error;
+use crate::architecture::leon3::registers::{
+    IuCoreReg, IuSpecialReg, LEON3_CORE_REGISTERS, Leon3RegisterId,
+};
 use crate::architecture::riscv::registers::{RISCV_CORE_REGISTERS, RISCV_WITH_FP_CORE_REGISTERS};
 use crate::architecture::xtensa::arch::{Register as XtensaRegister, SpecialRegister};
 use crate::architecture::xtensa::registers::XTENSA_CORE_REGISTERS;
 use crate::{Core, CoreRegisters, CoreType, Error, InstructionSet, MemoryInterface};
 use crate::{RegisterId, RegisterValue};
-use object::elf::PT_NOTE;
+use object::elf::{
+    ELFCLASS32, ELFDATA2LSB, ELFDATA2MSB, ELFOSABI_NONE, EM_RISCV, EM_SPARC, EM_XTENSA, ET_CORE,
+    EV_CURRENT, NT_PRSTATUS, PF_R, PF_W, PF_X, PT_LOAD, PT_NOTE,
+};
 use object::read::elf::ProgramHeader;
-use object::{Object, ObjectSegment};
+use object::{Endianness, Object, ObjectSegment};
 use probe_rs_target::MemoryRange;
 use scroll::Cread;
 use serde::{Deserialize, Serialize};
 use std::array;
 use std::sync::LazyLock;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, hash_map::Entry},
     fs::OpenOptions,
     ops::Range,
     path::{Path, PathBuf},
@@ -38,6 +44,14 @@ trait Processor {
         false
     }
 
+    /// Returns the byte order of the register data.
+    fn endianness(&self) -> Endianness {
+        Endianness::Little
+    }
+
+    /// Returns the `e_machine` value of ELF core files of the processor.
+    fn elf_machine(&self) -> u16;
+
     /// Returns the length of the register data in bytes.
     fn register_data_len(&self) -> usize;
 
@@ -63,7 +77,11 @@ trait Processor {
     /// Reads a single register value from the note data. The register is addressed by its
     /// position in the .elf note data.
     fn read_register(&self, note_data: &[u8], idx: usize) -> Result<RegisterValue, CoreDumpError> {
-        let value = u32::from_le_bytes(note_data[idx * 4..][..4].try_into().unwrap());
+        let bytes = note_data[idx * 4..][..4].try_into().unwrap();
+        let value = match self.endianness() {
+            Endianness::Little => u32::from_le_bytes(bytes),
+            Endianness::Big => u32::from_be_bytes(bytes),
+        };
         Ok(RegisterValue::U32(value))
     }
 }
@@ -76,6 +94,9 @@ impl Processor for XtensaProcessor {
     fn core_type(&self) -> CoreType {
         CoreType::Xtensa
     }
+    fn elf_machine(&self) -> u16 {
+        EM_XTENSA
+    }
     fn register_data_len(&self) -> usize {
         128 * 4
     }
@@ -118,6 +139,9 @@ impl Processor for RiscvProcessor {
     fn core_type(&self) -> CoreType {
         CoreType::Riscv
     }
+    fn elf_machine(&self) -> u16 {
+        EM_RISCV
+    }
     fn register_data_len(&self) -> usize {
         32 * 4
     }
@@ -139,6 +163,64 @@ impl Processor for RiscvProcessor {
     }
 }
 
+/// The SPARC special registers in the order of Linux core files, following the integer
+/// registers.
+const SPARC_SPECIAL_REGISTERS: [IuSpecialReg; 6] = [
+    IuSpecialReg::PSR,
+    IuSpecialReg::PC,
+    IuSpecialReg::NPC,
+    IuSpecialReg::Y,
+    IuSpecialReg::WIM,
+    IuSpecialReg::TBR,
+];
+
+struct SparcProcessor;
+impl Processor for SparcProcessor {
+    fn instruction_set(&self) -> InstructionSet {
+        InstructionSet::Sparc
+    }
+    fn core_type(&self) -> CoreType {
+        CoreType::Sparc
+    }
+    fn endianness(&self) -> Endianness {
+        Endianness::Big
+    }
+    fn elf_machine(&self) -> u16 {
+        EM_SPARC
+    }
+    fn register_data_len(&self) -> usize {
+        38 * 4
+    }
+    fn register_map(&self) -> &[(usize, RegisterId)] {
+        static REGS: LazyLock<[(usize, RegisterId); 38]> = LazyLock::new(|| {
+            let core_regs = &LEON3_CORE_REGISTERS;
+
+            array::from_fn(|idx| {
+                // %g0-%g7, %o0-%o7, %l0-%l7 and %i0-%i7 come first.
+                let regid = match idx {
+                    0..32 => core_regs.core_register(idx).id(),
+                    _ => Leon3RegisterId::IuSpecial(SPARC_SPECIAL_REGISTERS[idx - 32]).into(),
+                };
+                (idx, regid)
+            })
+        });
+        &*REGS
+    }
+}
+
+/// Returns the processor of the given core type, if ELF core files are supported for it.
+fn processor(core_type: CoreType) -> Option<Box<dyn Processor>> {
+    match core_type {
+        CoreType::Riscv => Some(Box::new(RiscvProcessor)),
+        CoreType::Xtensa => Some(Box::new(XtensaProcessor)),
+        CoreType::Sparc => Some(Box::new(SparcProcessor)),
+        _ => None,
+    }
+}
+
+/// The size of the CORE note data before the registers.
+const CORE_NOTE_HEADER_SIZE: usize = 72;
+
 /// A snapshot representation of a core state.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CoreDump {
@@ -172,6 +254,9 @@ impl CoreDump {
             let value = core.read_core_reg(register.id())?;
             registers.insert(register.id(), value);
         }
+        if core.core_type() == CoreType::Sparc {
+            Self::dump_sparc_registers(core, &mut registers)?;
+        }
 
         let mut data = Vec::new();
         for range in ranges {
@@ -191,6 +276,37 @@ impl CoreDump {
         })
     }
 
+    /// Read the SPARC special registers and the locals and ins of all register windows, which
+    /// are not part of the core registers.
+    fn dump_sparc_registers(
+        core: &mut Core<'_>,
+        registers: &mut HashMap<RegisterId, RegisterValue>,
+    ) -> Result<(), Error> {
+        for register in SPARC_SPECIAL_REGISTERS {
+            let id = Leon3RegisterId::IuSpecial(register).into();
+            if let Entry::Vacant(entry) = registers.entry(id) {
+                entry.insert(core.read_core_reg(id)?);
+            }
+        }
+
+        // Windows past the implemented ones are rejected as invalid registers.
+        for window in 0..32 {
+            for index in 16..32 {
+                let id = Leon3RegisterId::IuWindow {
+                    window,
+                    reg: IuCoreReg::from_index(index),
+                }
+                .into();
+                match core.read_core_reg(id) {
+                    Ok(value) => registers.insert(id, value),
+                    Err(Error::Leon3(Leon3Error::InvalidRegisterId(_))) => return Ok(()),
+                    Err(error) => return Err(error),
+                };
+            }
+        }
+        Ok(())
+    }
+
     /// Store the dumped core to a file.
     pub fn store(&self, path: &Path) -> Result<(), CoreDumpError> {
         let mut file = OpenOptions::new()
@@ -205,6 +321,127 @@ impl CoreDump {
         Ok(())
     }
 
+    /// Store the dumped core to a file in the ELF core file format, which GDB can open.
+    pub fn store_elf(&self, path: &Path) -> Result<(), CoreDumpError> {
+        let elf = self.to_elf()?;
+        std::fs::write(path, elf).map_err(|e| {
+            CoreDumpError::CoreDumpFileWrite(e, dunce::canonicalize(path).unwrap_or_default())
+        })
+    }
+
+    /// Encode the dumped core as an ELF core file.
+    ///
+    /// The registers are stored in an `NT_PRSTATUS` note, laid out like in Linux core files,
+    /// and each dumped memory range in a load segment. Floating-point registers are not
+    /// included.
+    pub fn to_elf(&self) -> Result<Vec<u8>, CoreDumpError> {
+        const EHDR_SIZE: usize = 52;
+        const PHDR_SIZE: usize = 32;
+        /// The offset of `pr_cursig` in the CORE note.
+        const CURSIG_OFFSET: usize = 12;
+        const SIGTRAP: u16 = 5;
+
+        let processor = processor(self.core_type)
+            .ok_or(CoreDumpError::UnsupportedElfCoreDump(self.core_type))?;
+        let big_endian = processor.endianness() == Endianness::Big;
+        let u16_bytes = |value: u16| {
+            if big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            }
+        };
+        let u32_bytes = |value: u32| {
+            if big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            }
+        };
+
+        // The CORE note holds the registers between a header and `pr_fpvalid`.
+        let mut status = vec![0; CORE_NOTE_HEADER_SIZE + processor.register_data_len() + 4];
+        status[CURSIG_OFFSET..][..2].copy_from_slice(&u16_bytes(SIGTRAP));
+        for (idx, id) in processor.register_map() {
+            let value = self
+                .registers
+                .get(id)
+                .and_then(|value| TryInto::<u32>::try_into(*value).ok())
+                .unwrap_or(0);
+            status[CORE_NOTE_HEADER_SIZE + idx * 4..][..4].copy_from_slice(&u32_bytes(value));
+        }
+        let mut note = Vec::new();
+        note.extend(u32_bytes(5));
+        note.extend(u32_bytes(status.len() as u32));
+        note.extend(u32_bytes(NT_PRSTATUS));
+        note.extend(b"CORE\0\0\0\0");
+        note.extend(status);
+        note.resize(note.len().next_multiple_of(4), 0);
+
+        let program_header =
+            |p_type: u32, offset: usize, address: u64, size: usize, flags: u32, align: u32| {
+                let address = address as u32;
+                let size = size as u32;
+                [
+                    p_type,
+                    offset as u32,
+                    address,
+                    address,
+                    size,
+                    size,
+                    flags,
+                    align,
+                ]
+                .into_iter()
+                .flat_map(u32_bytes)
+            };
+
+        let phnum = 1 + self.data.len();
+        let mut elf = vec![
+            0x7F,
+            b'E',
+            b'L',
+            b'F',
+            ELFCLASS32,
+            if big_endian { ELFDATA2MSB } else { ELFDATA2LSB },
+            EV_CURRENT,
+            ELFOSABI_NONE,
+        ];
+        elf.resize(16, 0);
+        elf.extend(u16_bytes(ET_CORE));
+        elf.extend(u16_bytes(processor.elf_machine()));
+        elf.extend(u32_bytes(EV_CURRENT.into()));
+        elf.extend(u32_bytes(0)); // e_entry
+        elf.extend(u32_bytes(EHDR_SIZE as u32)); // e_phoff
+        elf.extend(u32_bytes(0)); // e_shoff
+        elf.extend(u32_bytes(0)); // e_flags
+        elf.extend(u16_bytes(EHDR_SIZE as u16));
+        elf.extend(u16_bytes(PHDR_SIZE as u16));
+        elf.extend(u16_bytes(phnum as u16));
+        elf.extend([0; 6]); // e_shentsize, e_shnum and e_shstrndx
+
+        let mut offset = EHDR_SIZE + phnum * PHDR_SIZE;
+        elf.extend(program_header(PT_NOTE, offset, 0, note.len(), PF_R, 4));
+        offset += note.len();
+        for (range, memory) in &self.data {
+            let flags = PF_R | PF_W | PF_X;
+            elf.extend(program_header(
+                PT_LOAD,
+                offset,
+                range.start,
+                memory.len(),
+                flags,
+                1,
+            ));
+            offset += memory.len();
+        }
+        elf.extend(note);
+        for (_, memory) in &self.data {
+            elf.extend(memory);
+        }
+        Ok(elf)
+    }
+
     /// Load the dumped core from a file.
     pub fn load(path: &Path) -> Result<Self, CoreDumpError> {
         let file_contents = std::fs::read(path).map_err(|e| {
@@ -233,6 +470,7 @@ impl CoreDump {
         let processor: Box<dyn Processor> = match elf.architecture() {
             object::Architecture::Riscv32 => Box::new(RiscvProcessor),
             object::Architecture::Xtensa => Box::new(XtensaProcessor),
+            object::Architecture::Sparc => Box::new(SparcProcessor),
             other => {
                 return Err(CoreDumpError::DecodingElfCoreDump(format!(
                     "Unsupported architecture: {other:?}",
@@ -280,7 +518,6 @@ impl CoreDump {
             // We only care about the registers, so let's cut off the rest. If we decide to use
             // the other information, we can do that later, most likely without
             // architecture-specific processing code.
-            const CORE_NOTE_HEADER_SIZE: usize = 72;
             let note_length = processor.register_data_len();
 
             if note.desc().len() < CORE_NOTE_HEADER_SIZE + note_length {
@@ -345,9 +582,13 @@ impl CoreDump {
             self.get_memory_from_coredump(address, (std::mem::size_of_val(data)) as u64)?;
 
         let value_size = std::mem::size_of::<T>();
+        let endian = match self.core_type {
+            CoreType::Sparc => scroll::BE,
+            _ => scroll::LE,
+        };
 
         for (n, data) in data.iter_mut().enumerate() {
-            *data = memory.cread_with::<T>(n * value_size, scroll::LE);
+            *data = memory.cread_with::<T>(n * value_size, endian);
         }
         Ok(())
     }
@@ -506,7 +747,41 @@ pub enum CoreDumpError {
     /// Decoding the coredump .elf failed.
     #[error("Decoding the coredump .elf failed.")]
     DecodingElfCoreDump(String),
+    /// ELF core files are not supported for the core type.
+    #[error("ELF core dumps are not supported for {0:?} cores.")]
+    UnsupportedElfCoreDump(CoreType),
     /// Invalid ELF file.
     #[error("Invalid ELF file.")]
     ElfCoreDumpFormat(#[from] object::read::Error),
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sparc_elf_round_trip() {
+        let mut registers = HashMap::new();
+        for (idx, id) in SparcProcessor.register_map() {
+            registers.insert(*id, RegisterValue::U32(0x1000 + *idx as u32));
+        }
+        let dump = CoreDump {
+            registers,
+            data: vec![(0x4000_0000..0x4000_0008, vec![1, 2, 3, 4, 5, 6, 7, 8])],
+            instruction_set: InstructionSet::Sparc,
+            supports_native_64bit_access: false,
+            core_type: CoreType::Sparc,
+            fpu_support: false,
+            floating_point_register_count: None,
+        };
+
+        let elf = dump.to_elf().unwrap();
+        let mut loaded = CoreDump::load_raw(&elf).unwrap();
+
+        assert_eq!(loaded.core_type(), CoreType::Sparc);
+        assert_eq!(loaded.data, dump.data);
+        let pc = LEON3_CORE_REGISTERS.pc().unwrap().id();
+        assert_eq!(loaded.registers[&pc], RegisterValue::U32(0x1000 + 33));
+        assert_eq!(loaded.read_word_32(0x4000_0004).unwrap(), 0x0506_0708);
+    }
+}
@@ -44,6 +44,10 @@ pub struct IdfOptions {
 pub struct ElfOptions {
     /// Sections to skip flashing
     pub skip_sections: Vec<String>,
+    /// Write the sections that are linked to RAM into the PROM of a LEON3 target instead,
+    /// behind a boot loader that configures the memory controller, copies them to RAM and
+    /// starts the image.
+    pub prom_boot: bool,
 }
 
 /// A finite list of all the available binary formats probe-rs understands.
@@ -55,6 +59,8 @@ pub enum FormatKind {
     Bin,
     /// Marks a file in [Intel HEX](https://en.wikipedia.org/wiki/Intel_HEX) format.
     Hex,
+    /// Marks a file in [Motorola S-record](https://en.wikipedia.org/wiki/SREC_(file_format)) format.
+    Srec,
     /// Marks a file in the [ELF](https://en.wikipedia.org/wiki/Executable_and_Linkable_Format) format.
     #[default]
     Elf,
@@ -84,6 +90,7 @@ impl FromStr for FormatKind {
         match &s.to_lowercase()[..] {
             "bin" | "binary" => Ok(Self::Bin),
             "hex" | "ihex" | "intelhex" => Ok(Self::Hex),
+            "srec" | "s-record" | "s19" | "s28" | "s37" | "mot" => Ok(Self::Srec),
             "elf" => Ok(Self::Elf),
             "uf2" => Ok(Self::Uf2),
             "idf" | "esp-idf" | "espidf" => Ok(Self::Idf),
@@ -101,6 +108,8 @@ pub enum Format {
     Bin(BinOptions),
     /// Marks a file in [Intel HEX](https://en.wikipedia.org/wiki/Intel_HEX) format.
     Hex,
+    /// Marks a file in [Motorola S-record](https://en.wikipedia.org/wiki/SREC_(file_format)) format.
+    Srec,
     /// Marks a file in the [ELF](https://en.wikipedia.org/wiki/Executable_and_Linkable_Format) format.
     Elf(ElfOptions),
     /// Marks a file in the [ESP-IDF bootloader](https://docs.espressif.com/projects/esp-idf/en/latest/esp32/api-reference/system/app_image_format.html#app-image-structures) format.
@@ -121,6 +130,7 @@ impl From<FormatKind> for Format {
         match kind {
             FormatKind::Bin => Format::Bin(BinOptions::default()),
             FormatKind::Hex => Format::Hex,
+            FormatKind::Srec => Format::Srec,
             FormatKind::Elf => Format::Elf(ElfOptions::default()),
             FormatKind::Uf2 => Format::Uf2,
             FormatKind::Idf => Format::Idf(IdfOptions::default()),
@@ -143,6 +153,14 @@ pub enum FileDownloadError {
     /// Failed to read or decode the IHEX file.
     IhexRead(#[from] ihex::ReaderError),
 
+    /// Failed to decode line {line} of the S-record file: {reason}.
+    SrecRead {
+        /// The line number, starting at 1.
+        line: usize,
+        /// What is wrong with the record.
+        reason: &'static str,
+    },
+
     /// An IO error has occurred while reading the firmware file.
     IO(#[from] std::io::Error),
 
@@ -444,6 +462,8 @@ mod tests {
         assert_eq!(FormatKind::from_str("intelhex"), Ok(FormatKind::Hex));
         assert_eq!(FormatKind::from_str("intelHex"), Ok(FormatKind::Hex));
         assert_eq!(FormatKind::from_str("Intelhex"), Ok(FormatKind::Hex));
+        assert_eq!(FormatKind::from_str("srec"), Ok(FormatKind::Srec));
+        assert_eq!(FormatKind::from_str("S37"), Ok(FormatKind::Srec));
         assert_eq!(FormatKind::from_str("bin"), Ok(FormatKind::Bin));
         assert_eq!(FormatKind::from_str("Bin"), Ok(FormatKind::Bin));
         assert_eq!(FormatKind::from_str("binary"), Ok(FormatKind::Bin));
@@ -1,8 +1,11 @@
 use std::collections::HashMap;
+use std::ops::Range;
+use std::time::{Duration, Instant};
 
 use probe_rs_target::{MemoryRange, MemoryRegion, NvmRegion};
 
 use crate::Session;
+use crate::architecture::leon3::prom::PromProgress;
 use crate::flashing::progress::ProgressOperation;
 use crate::flashing::{FlashError, FlashLoader, flasher::Flasher};
 use crate::flashing::{FlashLayout, FlashSector};
@@ -14,6 +17,93 @@ struct FlasherWithRegions {
     regions: Vec<NvmRegion>,
 }
 
+/// The erase sectors of an NVM region that the host erases itself, without a flash algorithm.
+struct HostErase {
+    core_index: usize,
+    sectors: Vec<Range<u64>>,
+}
+
+impl HostErase {
+    /// Find the erase sectors of `region` overlapping `range`, halting the core that
+    /// accesses the region.
+    fn new(
+        session: &mut Session,
+        region: &NvmRegion,
+        range: Range<u64>,
+    ) -> Result<Self, FlashError> {
+        let core_name = region
+            .cores
+            .first()
+            .ok_or_else(|| FlashError::NoNvmCoreAccess(region.clone()))?;
+        let core_index = session.target().core_index_by_name(core_name).unwrap();
+
+        let mut core = session.core(core_index).map_err(FlashError::Core)?;
+        if !core.core_halted().map_err(FlashError::Core)? {
+            core.halt(Duration::from_millis(500))
+                .map_err(FlashError::Core)?;
+        }
+        drop(core);
+
+        let sectors = session
+            .host_programmed_nvm_sectors(core_index, range)
+            .map_err(FlashError::Core)?;
+        Ok(Self {
+            core_index,
+            sectors,
+        })
+    }
+
+    /// The erase sectors as flash sectors.
+    fn layout(&self) -> FlashLayout {
+        let mut layout = FlashLayout::default();
+        layout.sectors = self
+            .sectors
+            .iter()
+            .map(|sector| FlashSector {
+                address: sector.start,
+                size: sector.end - sector.start,
+            })
+            .collect();
+        layout
+    }
+
+    fn size(&self) -> u64 {
+        self.sectors
+            .iter()
+            .map(|sector| sector.end - sector.start)
+            .sum()
+    }
+
+    /// Erase the sectors.
+    fn run(
+        self,
+        session: &mut Session,
+        progress: &mut FlashProgress<'_>,
+    ) -> Result<(), FlashError> {
+        progress.started_erasing();
+        let result = self.sectors.into_iter().try_for_each(|sector| {
+            tracing::debug!(
+                "    sector: {:#010x}-{:#010x} ({} bytes)",
+                sector.start,
+                sector.end,
+                sector.end - sector.start
+            );
+            let mut start = Instant::now();
+            session.erase_host_programmed_nvm(self.core_index, sector, |event| {
+                if let PromProgress::Erased(size) = event {
+                    progress.sector_erased(size, start.elapsed());
+                    start = Instant::now();
+                }
+            })
+        });
+        match result.is_ok() {
+            true => progress.finished_erasing(),
+            false => progress.failed_erasing(),
+        }
+        result.map_err(FlashError::Core)
+    }
+}
+
 /// Mass-erase all nonvolatile memory.
 ///
 /// The optional progress will only be used to emit RTT messages.
@@ -28,6 +118,7 @@ pub fn erase_all(
     // TODO: this first loop is pretty much identical to FlashLoader::prepare_plan - can we simplify?
 
     let mut algos = Vec::<FlasherWithRegions>::new();
+    let mut host_regions = vec![];
     tracing::debug!("Regions:");
     for region in session
         .target()
@@ -45,6 +136,12 @@ pub fn erase_all(
             region.range.end - region.range.start
         );
 
+        if FlashLoader::is_host_programmed(region, session.target()) {
+            tracing::debug!("     -- erased by the host");
+            host_regions.push(region.clone());
+            continue;
+        }
+
         let region = region.clone();
 
         // Get the first core that can access the region
@@ -76,7 +173,12 @@ pub fn erase_all(
     // No longer needs to be mutable.
     let algos = algos;
 
-    let mut do_chip_erase = true;
+    let host_erases = host_regions
+        .iter()
+        .map(|region| HostErase::new(session, region, region.range.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut do_chip_erase = !algos.is_empty();
 
     let mut phases = vec![];
 
@@ -116,6 +218,10 @@ pub fn erase_all(
             progress.add_progress_bar(ProgressOperation::Erase, Some(sector_size));
         }
     }
+    for host_erase in host_erases.iter() {
+        progress.add_progress_bar(ProgressOperation::Erase, Some(host_erase.size()));
+        phases.push(host_erase.layout());
+    }
     progress.initialized(phases);
 
     for el in algos {
@@ -159,6 +265,11 @@ pub fn erase_all(
         }
     }
 
+    for host_erase in host_erases {
+        tracing::debug!("Erasing from the host");
+        host_erase.run(session, progress)?;
+    }
+
     Ok(())
 }
 
@@ -176,6 +287,7 @@ pub fn erase(
     let address_range = address_start..address_end;
 
     let mut algos: HashMap<(String, String), Vec<NvmRegion>> = HashMap::new();
+    let mut host_regions = vec![];
     tracing::debug!("Regions:");
     for region in session
         .target()
@@ -198,6 +310,12 @@ pub fn erase(
             continue;
         }
 
+        if FlashLoader::is_host_programmed(region, session.target()) {
+            tracing::debug!("     -- erased by the host");
+            host_regions.push(region.clone());
+            continue;
+        }
+
         // Get the first core that can access the region
         let core_name = region
             .cores
@@ -257,6 +375,18 @@ pub fn erase(
         })?;
     }
 
+    for region in host_regions {
+        tracing::debug!("Erasing from the host");
+        let range = region.range.start.max(address_start)..region.range.end.min(address_end);
+        let mut host_erase = HostErase::new(session, &region, range)?;
+
+        // Like with flash algorithms, only whole sectors inside the range are erased.
+        host_erase
+            .sectors
+            .retain(|sector| address_range.contains_range(sector));
+        host_erase.run(session, progress)?;
+    }
+
     Ok(())
 }
 
@@ -295,6 +425,11 @@ pub fn run_blank_check(
             continue;
         }
 
+        if FlashLoader::is_host_programmed(region, session.target()) {
+            tracing::debug!("     -- programmed by the host, ignoring!");
+            continue;
+        }
+
         // Get the first core that can access the region
         let core_name = region
             .cores
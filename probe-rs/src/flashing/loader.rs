@@ -2,25 +2,43 @@ use espflash::flasher::{FlashData, FlashSettings, FlashSize};
 use espflash::image_format::idf::{IdfBootloaderFormat, check_idf_bootloader};
 use ihex::Record;
 use itertools::Itertools as _;
+use object::Object as _;
 use probe_rs_target::{
-    InstructionSet, MemoryRange, MemoryRegion, NvmRegion, RawFlashAlgorithm,
+    CoreAccessOptions, InstructionSet, MemoryRange, MemoryRegion, NvmRegion, RawFlashAlgorithm,
     TargetDescriptionSource,
 };
 use std::io::{Read, Seek, SeekFrom};
 use std::ops::Range;
 use std::str::FromStr;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use super::builder::FlashBuilder;
+use super::srec;
 use super::{
     BinOptions, DownloadOptions, ElfOptions, FileDownloadError, FlashError, Flasher, IdfOptions,
-    extract_from_elf,
+    download::ExtractedFlashData, extract_from_elf,
+};
+use crate::architecture::leon3::{
+    crc::{CRC32_ROUTINE, crc32},
+    prom::{PromProgress, PromWriteOptions},
+    sequences::default_stack,
 };
-use crate::Target;
 use crate::flashing::progress::ProgressOperation;
 use crate::flashing::{FlashLayout, FlashProgress, Format};
 use crate::memory::MemoryInterface;
 use crate::session::Session;
+use crate::{Architecture, Target};
+
+/// Data blocks smaller than this are read back for verification, which is about as fast as
+/// running the CRC routine.
+const CRC_VERIFY_MIN_SIZE: usize = 1024;
+
+/// How long the CRC routine may run, plus [`CRC_VERIFY_TIMEOUT_PER_BYTE`] for each byte.
+const CRC_VERIFY_TIMEOUT: Duration = Duration::from_secs(1);
+const CRC_VERIFY_TIMEOUT_PER_BYTE: Duration = Duration::from_micros(10);
+
+/// The amount of data written to the RAM of LEON3 targets between progress reports.
+const RAM_PROGRESS_CHUNK: usize = 64 * 1024;
 
 /// Helper trait for object safety.
 pub trait ImageReader: Read + Seek {}
@@ -50,6 +68,7 @@ impl ImageLoader for Format {
             Format::Bin(options) => BinLoader(options.clone()).load(flash_loader, session, file),
             Format::Elf(options) => ElfLoader(options.clone()).load(flash_loader, session, file),
             Format::Hex => HexLoader.load(flash_loader, session, file),
+            Format::Srec => SrecLoader.load(flash_loader, session, file),
             Format::Idf(options) => IdfLoader(options.clone()).load(flash_loader, session, file),
             Format::Uf2 => Uf2Loader.load(flash_loader, session, file),
         }
@@ -128,6 +147,20 @@ impl ImageLoader for ElfLoader {
             );
         }
 
+        if self.0.prom_boot {
+            let entry = object::File::parse(&*elf_buffer)?.entry();
+            return add_prom_boot_image(flash_loader, session, entry, extracted_data);
+        }
+
+        // SPARC images have no vector table section. Their trap table is at the entry point,
+        // which is where an image downloaded to RAM is started.
+        if flash_loader.vector_table_addr.is_none()
+            && session.target().architecture() == Architecture::Sparc
+        {
+            let entry = object::File::parse(&*elf_buffer)?.entry();
+            flash_loader.set_vector_table_addr(entry);
+        }
+
         for data in extracted_data {
             flash_loader.add_data(data.address.into(), data.data)?;
         }
@@ -136,6 +169,58 @@ impl ImageLoader for ElfLoader {
     }
 }
 
+/// Add the sections of an ELF image that are linked to RAM to the PROM of a LEON3 target,
+/// behind a boot loader that copies them to RAM and starts the image at `entry`. The other
+/// sections are added as they are.
+fn add_prom_boot_image(
+    flash_loader: &mut FlashLoader,
+    session: &mut Session,
+    entry: u64,
+    extracted_data: Vec<ExtractedFlashData<'_>>,
+) -> Result<(), FileDownloadError> {
+    let target = session.target();
+    if target.architecture() != Architecture::Sparc {
+        return Err(FileDownloadError::Other(crate::Error::NotImplemented(
+            "PROM boot images on targets other than LEON3",
+        )));
+    }
+
+    let (ram, other): (Vec<_>, Vec<_>) = extracted_data.into_iter().partition(|data| {
+        target
+            .memory_region_by_address(data.address.into())
+            .is_some_and(MemoryRegion::is_ram)
+    });
+    let ram_boot = match &target.cores[0].core_access_options {
+        CoreAccessOptions::Leon3(options) => options.ram_boot.clone(),
+        _ => None,
+    };
+    let stack = ram_boot
+        .and_then(|ram_boot| ram_boot.stack)
+        .or_else(|| default_stack(target, entry))
+        .ok_or_else(|| {
+            FileDownloadError::Other(crate::Error::Other(format!(
+                "No RAM region holds the entry point {entry:#010x}"
+            )))
+        })?;
+
+    let sections: Vec<_> = ram.iter().map(|data| (data.address, data.data)).collect();
+    let (prom_base, image) = session
+        .get_leon3_core(0)
+        .and_then(|mut core| core.prom_boot_image(&sections, entry as u32, stack as u32))
+        .map_err(FileDownloadError::Other)?;
+    tracing::info!(
+        "    PROM boot image at {:#010X} ({} bytes)",
+        prom_base,
+        image.len()
+    );
+
+    flash_loader.add_data(prom_base, &image)?;
+    for data in other {
+        flash_loader.add_data(data.address.into(), data.data)?;
+    }
+    Ok(())
+}
+
 /// Reads the HEX data segments and adds them as loadable data blocks to the loader.
 /// This does not create any flash loader instructions yet.
 struct HexLoader;
@@ -174,6 +259,46 @@ impl ImageLoader for HexLoader {
     }
 }
 
+/// Reads the data records of a Motorola S-record file and adds them as loadable data blocks
+/// to the loader. This does not create any flash loader instructions yet.
+struct SrecLoader;
+
+impl ImageLoader for SrecLoader {
+    fn load(
+        &self,
+        flash_loader: &mut FlashLoader,
+        session: &mut Session,
+        file: &mut dyn ImageReader,
+    ) -> Result<(), FileDownloadError> {
+        let mut data = String::new();
+        file.read_to_string(&mut data)?;
+
+        for (index, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let record =
+                srec::parse_record(line).map_err(|reason| FileDownloadError::SrecRead {
+                    line: index + 1,
+                    reason,
+                })?;
+            match record {
+                srec::Record::Data { address, data } => flash_loader.add_data(address, &data)?,
+                // Like the entry point of an ELF file, the start address of a SPARC image is
+                // its trap table.
+                srec::Record::Start(address)
+                    if session.target().architecture() == Architecture::Sparc =>
+                {
+                    flash_loader.set_vector_table_addr(address);
+                }
+                srec::Record::Header | srec::Record::Count | srec::Record::Start(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Prepares the data sections that have to be loaded into flash from an UF2 file.
 /// This will validate the UF2 file and transform all its data into sections but no flash loader commands yet.
 struct Uf2Loader;
@@ -496,6 +621,10 @@ impl FlashLoader {
             }
             progress.add_progress_bar(ProgressOperation::Verify, Some(program_size));
         }
+        let (nvm_size, ram_size) = self.host_write_sizes(session.target());
+        if nvm_size + ram_size > 0 {
+            progress.add_progress_bar(ProgressOperation::Verify, Some(nvm_size + ram_size));
+        }
 
         // Iterate all flash algorithms we need to use and do the flashing.
         for mut flasher in algos {
@@ -509,7 +638,7 @@ impl FlashLoader {
             }
         }
 
-        self.verify_ram(session)?;
+        self.verify_ram(session, progress)?;
 
         Ok(())
     }
@@ -574,6 +703,8 @@ impl FlashLoader {
             )?;
         }
 
+        self.commit_host_programmed(session, &mut options)?;
+
         tracing::debug!("Committing RAM!");
 
         if let BootInfo::FromRam { cores_to_reset, .. } = self.boot_info() {
@@ -598,6 +729,36 @@ impl FlashLoader {
         }
 
         // Commit RAM last, because NVM flashing overwrites RAM
+        let (_, ram_size) = self.host_write_sizes(session.target());
+        let report_progress = ram_size > 0;
+        if report_progress {
+            options.progress.started_programming();
+        }
+        let result = self.commit_ram(session, &mut options.progress, report_progress);
+        if report_progress {
+            match result.is_ok() {
+                true => options.progress.finished_programming(),
+                false => options.progress.failed_programming(),
+            }
+        }
+        result?;
+
+        if options.verify {
+            self.verify_ram(session, &mut options.progress)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the data of the RAM regions, reporting the progress in chunks if
+    /// `report_progress` is set.
+    fn commit_ram(
+        &self,
+        session: &mut Session,
+        progress: &mut FlashProgress<'_>,
+        report_progress: bool,
+    ) -> Result<(), FlashError> {
+        let mut cores_with_ram_set_up = vec![];
         for region in self
             .memory_map
             .iter()
@@ -639,6 +800,19 @@ impl FlashLoader {
                     .map_err(FlashError::Core)?;
             }
 
+            if !cores_with_ram_set_up.contains(&region_core_index) {
+                drop(core);
+                session
+                    .setup_ram(region_core_index)
+                    .map_err(FlashError::Core)?;
+                cores_with_ram_set_up.push(region_core_index);
+                core = session.core(region_core_index).map_err(FlashError::Core)?;
+            }
+
+            let written: Vec<_> = ranges_in_region
+                .iter()
+                .map(|(address, data)| *address..*address + data.len() as u64)
+                .collect();
             for (address, data) in ranges_in_region {
                 tracing::debug!(
                     "     -- writing: {:#010X}..{:#010X} ({} bytes)",
@@ -647,17 +821,168 @@ impl FlashLoader {
                     data.len()
                 );
                 // Write data to memory.
-                core.write(address, data).map_err(FlashError::Core)?;
+                if !report_progress {
+                    core.write(address, data).map_err(FlashError::Core)?;
+                    continue;
+                }
+                for (offset, chunk) in (0..)
+                    .step_by(RAM_PROGRESS_CHUNK)
+                    .zip(data.chunks(RAM_PROGRESS_CHUNK))
+                {
+                    let start = Instant::now();
+                    core.write(address + offset, chunk)
+                        .map_err(FlashError::Core)?;
+                    progress.page_programmed(chunk.len() as u64, start.elapsed());
+                }
             }
+            drop(core);
+
+            session
+                .flush_ram(region_core_index, &written)
+                .map_err(FlashError::Core)?;
         }
 
-        if options.verify {
-            self.verify_ram(session)?;
+        Ok(())
+    }
+
+    /// Program the NVM regions that the host programs without a flash algorithm.
+    fn commit_host_programmed(
+        &self,
+        session: &mut Session,
+        options: &mut DownloadOptions,
+    ) -> Result<(), FlashError> {
+        let (nvm_size, _) = self.host_write_sizes(session.target());
+        if nvm_size == 0 {
+            return Ok(());
+        }
+        let erase = !options.skip_erase;
+        if erase {
+            options.progress.started_erasing();
+        }
+        options.progress.started_programming();
+
+        let result = self.program_host_nvm(session, options);
+
+        if erase {
+            match result.is_ok() {
+                true => options.progress.finished_erasing(),
+                false => options.progress.failed_erasing(),
+            }
+        }
+        match result.is_ok() {
+            true => options.progress.finished_programming(),
+            false => options.progress.failed_programming(),
+        }
+        result
+    }
+
+    fn program_host_nvm(
+        &self,
+        session: &mut Session,
+        options: &mut DownloadOptions,
+    ) -> Result<(), FlashError> {
+        for region in self
+            .memory_map
+            .iter()
+            .filter_map(MemoryRegion::as_nvm_region)
+        {
+            if !Self::is_host_programmed(region, session.target()) {
+                continue;
+            }
+
+            let ranges_in_region: Vec<_> = self.builder.data_in_range(&region.range).collect();
+            if ranges_in_region.is_empty() {
+                continue;
+            }
+
+            tracing::debug!(
+                "Programming region from the host: {:#010X?} ({} bytes)",
+                region.range,
+                region.range.end - region.range.start
+            );
+
+            let Some(core_name) = region.cores.first() else {
+                return Err(FlashError::NoNvmCoreAccess(region.clone()));
+            };
+            let core_index = session.target().core_index_by_name(core_name).unwrap();
+
+            let mut core = session.core(core_index).map_err(FlashError::Core)?;
+            if !core.core_halted().map_err(FlashError::Core)? {
+                core.halt(Duration::from_millis(500))
+                    .map_err(FlashError::Core)?;
+            }
+            drop(core);
+
+            let write_options = PromWriteOptions {
+                erase: !options.skip_erase,
+                keep_unwritten_bytes: options.keep_unwritten_bytes,
+            };
+            for (address, data) in ranges_in_region {
+                tracing::debug!(
+                    "     -- programming: {:#010X}..{:#010X} ({} bytes)",
+                    address,
+                    address + data.len() as u64,
+                    data.len()
+                );
+                let progress = &mut options.progress;
+                let mut start = Instant::now();
+                session
+                    .write_host_programmed_nvm(core_index, address, data, write_options, |event| {
+                        match event {
+                            PromProgress::Erased(size) => {
+                                progress.sector_erased(size, start.elapsed())
+                            }
+                            PromProgress::Programmed(size) => {
+                                progress.page_programmed(size, start.elapsed())
+                            }
+                        }
+                        start = Instant::now();
+                    })
+                    .map_err(FlashError::Core)?;
+            }
         }
 
         Ok(())
     }
 
+    /// The sizes of the data the host writes itself on LEON3 targets, to the NVM regions
+    /// without a flash algorithm and to the RAM regions.
+    fn host_write_sizes(&self, target: &Target) -> (u64, u64) {
+        if target.architecture() != Architecture::Sparc {
+            return (0, 0);
+        }
+        let size = |range: &Range<u64>| {
+            self.builder
+                .data_in_range(range)
+                .map(|(_, data)| data.len() as u64)
+                .sum::<u64>()
+        };
+        let mut nvm_size = 0;
+        let mut ram_size = 0;
+        for region in &self.memory_map {
+            match region {
+                MemoryRegion::Nvm(region) if Self::is_host_programmed(region, target) => {
+                    nvm_size += size(&region.range)
+                }
+                MemoryRegion::Ram(region) => ram_size += size(&region.range),
+                _ => {}
+            }
+        }
+        (nvm_size, ram_size)
+    }
+
+    /// Whether the host programs `region` itself instead of running a flash algorithm, which
+    /// is the case for the PROM of LEON3 targets without a flash algorithm for it.
+    pub(crate) fn is_host_programmed(region: &NvmRegion, target: &Target) -> bool {
+        target.architecture() == Architecture::Sparc
+            && !target.flash_algorithms.iter().any(|algorithm| {
+                algorithm
+                    .flash_properties
+                    .address_range
+                    .intersects_range(&region.range)
+            })
+    }
+
     fn prepare_plan(
         &self,
         session: &mut Session,
@@ -725,6 +1050,11 @@ impl FlashLoader {
                 continue;
             }
 
+            if Self::is_host_programmed(region, session.target()) {
+                tracing::debug!("     -- programmed by the host");
+                continue;
+            }
+
             let region = region.clone();
 
             let Some(core_name) = region.cores.first() else {
@@ -831,12 +1161,60 @@ impl FlashLoader {
             phases.push(phase_layout);
         }
 
+        // The progress bars of the data the host writes itself follow those of the flash
+        // algorithms, in the order in which `commit` writes the data.
+        let (nvm_size, ram_size) = self.host_write_sizes(session.target());
+        if nvm_size > 0 {
+            if !options.skip_erase {
+                options
+                    .progress
+                    .add_progress_bar(ProgressOperation::Erase, None);
+            }
+            options
+                .progress
+                .add_progress_bar(ProgressOperation::Program, Some(nvm_size));
+        }
+        if ram_size > 0 {
+            options
+                .progress
+                .add_progress_bar(ProgressOperation::Program, Some(ram_size));
+        }
+        if options.verify && nvm_size + ram_size > 0 {
+            options
+                .progress
+                .add_progress_bar(ProgressOperation::Verify, Some(nvm_size + ram_size));
+        }
+
         options.progress.initialized(phases);
 
         Ok(())
     }
 
-    fn verify_ram(&self, session: &mut Session) -> Result<(), FlashError> {
+    fn verify_ram(
+        &self,
+        session: &mut Session,
+        progress: &mut FlashProgress<'_>,
+    ) -> Result<(), FlashError> {
+        let (nvm_size, ram_size) = self.host_write_sizes(session.target());
+        if nvm_size + ram_size == 0 {
+            return self.verify_ram_data(session, None);
+        }
+        progress.started_verifying();
+        let result = self.verify_ram_data(session, Some(progress));
+        match result.is_ok() {
+            true => progress.finished_verifying(),
+            false => progress.failed_verifying(),
+        }
+        result
+    }
+
+    /// Verify the data of the RAM regions and of the NVM regions programmed by the host,
+    /// reporting the progress to `progress` if given.
+    fn verify_ram_data(
+        &self,
+        session: &mut Session,
+        mut progress: Option<&mut FlashProgress<'_>>,
+    ) -> Result<(), FlashError> {
         tracing::debug!("Verifying RAM!");
         for (&address, data) in &self.builder.data {
             tracing::debug!(
@@ -848,15 +1226,44 @@ impl FlashLoader {
 
             let associated_region = session.target().memory_region_by_address(address).unwrap();
 
-            // We verified NVM regions before, in flasher.program().
-            if !associated_region.is_ram() {
+            // We verified NVM regions before, in flasher.program(), except for those programmed
+            // by the host.
+            let host_programmed = associated_region
+                .as_nvm_region()
+                .is_some_and(|region| Self::is_host_programmed(region, session.target()));
+            if !associated_region.is_ram() && !host_programmed {
                 continue;
             }
 
             let core_name = associated_region.cores().first().unwrap();
             let core_index = session.target().core_index_by_name(core_name).unwrap();
-            let mut core = session.core(core_index).map_err(FlashError::Core)?;
+            let start = Instant::now();
+
+            // Reading the data back is slow on LEON3 debug links, so the core computes a CRC
+            // of the data instead, if it has RAM to run the CRC routine from.
+            if session.target().architecture() == Architecture::Sparc
+                && data.len() >= CRC_VERIFY_MIN_SIZE
+                && let Some(scratch) = self.crc_scratch(core_name)
+            {
+                let timeout = CRC_VERIFY_TIMEOUT + CRC_VERIFY_TIMEOUT_PER_BYTE * data.len() as u32;
+                let crc = session.get_leon3_core(core_index).and_then(|mut core| {
+                    core.crc32(scratch, address as u32, data.len() as u32, timeout)
+                });
+                match crc {
+                    Ok(crc) if crc == crc32(data) => {
+                        if let Some(progress) = progress.as_deref_mut() {
+                            progress.page_verified(data.len() as u64, start.elapsed());
+                        }
+                        continue;
+                    }
+                    Ok(_) => return Err(FlashError::Verify),
+                    Err(error) => {
+                        tracing::warn!("CRC verification failed, reading the data back: {error}")
+                    }
+                }
+            }
 
+            let mut core = session.core(core_index).map_err(FlashError::Core)?;
             let mut written_data = vec![0; data.len()];
             core.read(address, &mut written_data)
                 .map_err(FlashError::Core)?;
@@ -864,11 +1271,34 @@ impl FlashLoader {
             if data != &written_data {
                 return Err(FlashError::Verify);
             }
+            if let Some(progress) = progress.as_deref_mut() {
+                progress.page_verified(data.len() as u64, start.elapsed());
+            }
         }
 
         Ok(())
     }
 
+    /// A RAM area to run the CRC routine from, which holds no data of the image and is
+    /// accessible by `core_name`.
+    fn crc_scratch(&self, core_name: &str) -> Option<u32> {
+        let size = 4 * CRC32_ROUTINE.len() as u64;
+        self.memory_map
+            .iter()
+            .filter_map(MemoryRegion::as_ram_region)
+            .filter(|region| region.accessible_by(core_name))
+            .flat_map(|region| {
+                let end = region.range.end.checked_sub(size).map(|start| start & !0x7);
+                [Some(region.range.start), end]
+                    .into_iter()
+                    .flatten()
+                    .map(|start| start..start + size)
+                    .filter(|scratch| region.range.contains_range(scratch))
+            })
+            .find(|scratch| scratch.end <= 1 << 32 && !self.builder.has_data_in_range(scratch))
+            .map(|scratch| scratch.start as u32)
+    }
+
     /// Try to find a flash algorithm for the given NvmRegion.
     /// Errors when:
     /// - there's no algo for the region.
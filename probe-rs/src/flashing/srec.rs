@@ -0,0 +1,122 @@
+//! Parsing of [Motorola S-record](https://en.wikipedia.org/wiki/SREC_(file_format)) files.
+
+/// A decoded S-record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Record {
+    /// An S0 header record.
+    Header,
+    /// An S1, S2 or S3 record with data to load at an address.
+    Data {
+        /// The address of the first byte.
+        address: u64,
+        /// The bytes to load.
+        data: Vec<u8>,
+    },
+    /// An S5 or S6 record with the number of data records.
+    Count,
+    /// An S7, S8 or S9 termination record with the start address of the image.
+    Start(u64),
+}
+
+/// Decode a single line of an S-record file.
+pub(super) fn parse_record(line: &str) -> Result<Record, &'static str> {
+    let Some(record) = line.strip_prefix('S') else {
+        return Err("record does not start with 'S'");
+    };
+    let mut chars = record.chars();
+    let kind = chars.next().ok_or("missing record type")?;
+    let hex = chars.as_str();
+    if !hex.is_ascii() || hex.len() % 2 != 0 {
+        return Err("odd number of hex digits");
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&hex[index..index + 2], 16))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "invalid hex digit")?;
+
+    let (&count, rest) = bytes.split_first().ok_or("missing byte count")?;
+    if rest.len() != usize::from(count) {
+        return Err("byte count does not match the record length");
+    }
+    let (&checksum, payload) = rest.split_last().ok_or("missing checksum")?;
+    let sum = bytes[..bytes.len() - 1]
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    if !sum != checksum {
+        return Err("checksum mismatch");
+    }
+
+    let address_size = match kind {
+        '0' | '1' | '5' | '9' => 2,
+        '2' | '6' | '8' => 3,
+        '3' | '7' => 4,
+        _ => return Err("unknown record type"),
+    };
+    if payload.len() < address_size {
+        return Err("record too short for its address");
+    }
+    let (address, data) = payload.split_at(address_size);
+    let address = address
+        .iter()
+        .fold(0u64, |address, byte| (address << 8) | u64::from(*byte));
+
+    Ok(match kind {
+        '0' => Record::Header,
+        '1' | '2' | '3' => Record::Data {
+            address,
+            data: data.to_vec(),
+        },
+        '5' | '6' => Record::Count,
+        _ => Record::Start(address),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_records() {
+        assert_eq!(
+            parse_record("S00F000068656C6C6F202020202000003C"),
+            Ok(Record::Header)
+        );
+        assert_eq!(
+            parse_record("S1137AF00A0A0D0000000000000000000000000061"),
+            Ok(Record::Data {
+                address: 0x7AF0,
+                data: vec![0x0A, 0x0A, 0x0D, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            })
+        );
+        assert_eq!(
+            parse_record("S30900000000DEADBEEFBE"),
+            Ok(Record::Data {
+                address: 0,
+                data: vec![0xDE, 0xAD, 0xBE, 0xEF],
+            })
+        );
+        assert_eq!(parse_record("S5030003F9"), Ok(Record::Count));
+        assert_eq!(
+            parse_record("S70540000000BA"),
+            Ok(Record::Start(0x4000_0000))
+        );
+    }
+
+    #[test]
+    fn reject_invalid_records() {
+        assert_eq!(
+            parse_record("S1137AF00A0A0D0000000000000000000000000062"),
+            Err("checksum mismatch")
+        );
+        assert_eq!(
+            parse_record("S1047AF00A"),
+            Err("byte count does not match the record length")
+        );
+        assert_eq!(
+            parse_record(":020000040800F2"),
+            Err("record does not start with 'S'")
+        );
+        assert_eq!(parse_record("S4030000FC"), Err("unknown record type"));
+    }
+}
@@ -77,6 +77,7 @@ mod core;
 mod error;
 pub mod flashing;
 pub mod integration;
+pub mod live_watch;
 mod memory;
 pub mod probe;
 pub mod rtt;
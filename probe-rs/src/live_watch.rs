@@ -0,0 +1,254 @@
+//! Live watch of memory locations while the core runs.
+//!
+//! Cores whose memory can be accessed while they run, like LEON3 cores through the DSU or Arm
+//! cores through the MEM-AP, can be observed without halting them. A [`LiveWatch`] holds a set
+//! of locations, given by address or by ELF symbol, and reports which of them changed each time
+//! it is polled.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use probe_rs::live_watch::LiveWatch;
+//! use probe_rs::probe::list::Lister;
+//! use probe_rs::Permissions;
+//!
+//! let lister = Lister::new();
+//! let probe = lister.list_all()[0].open()?;
+//! let mut session = probe.attach("somechip", Permissions::default())?;
+//! let mut core = session.core(0)?;
+//!
+//! let elf = std::fs::read("firmware.elf")?;
+//! let mut watch = LiveWatch::new(Duration::from_millis(100));
+//! watch.add_symbol(&elf, "TICKS")?;
+//! watch.add("status", 0x8000_0100, 4);
+//!
+//! loop {
+//!     for change in watch.poll(&mut core)? {
+//!         println!("{} changed to {:02x?}", change.name, change.value);
+//!     }
+//!     std::thread::sleep(watch.time_to_next_poll());
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::time::{Duration, Instant};
+
+use object::{Object, ObjectSymbol};
+
+use crate::MemoryInterface;
+
+/// Error type for live watch operations.
+#[derive(thiserror::Error, Debug, docsplay::Display)]
+pub enum LiveWatchError {
+    /// The ELF file has no symbol named {0}.
+    SymbolNotFound(String),
+
+    /// The symbol {0} has no size. Use `LiveWatch::add` with an explicit size.
+    UnsizedSymbol(String),
+
+    /// Cannot read the symbol table of the ELF file.
+    InvalidElf(#[from] object::read::Error),
+
+    /// Error communicating with the probe.
+    Probe(#[from] crate::Error),
+}
+
+/// Identifies a location of a [`LiveWatch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WatchId(usize);
+
+/// A change of a watched location, reported by [`LiveWatch::poll`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchChange {
+    /// The location that changed.
+    pub id: WatchId,
+    /// The name the location was added with.
+    pub name: String,
+    /// The address of the location.
+    pub address: u64,
+    /// The value before the change, or `None` on the first poll after the location was added.
+    pub previous: Option<Vec<u8>>,
+    /// The value read from the target, in target byte order.
+    pub value: Vec<u8>,
+    /// When the value was read.
+    pub time: Instant,
+}
+
+#[derive(Debug)]
+struct WatchedLocation {
+    id: WatchId,
+    name: String,
+    address: u64,
+    size: usize,
+    value: Option<Vec<u8>>,
+}
+
+/// A set of memory locations that are read at a fixed rate while the core runs.
+///
+/// The locations are read by [`LiveWatch::poll`], which the caller calls from its own loop, for
+/// example next to polling RTT. It reads the locations at most once per interval, and reports
+/// those whose value differs from the previous read.
+#[derive(Debug)]
+pub struct LiveWatch {
+    interval: Duration,
+    last_poll: Option<Instant>,
+    locations: Vec<WatchedLocation>,
+    next_id: usize,
+}
+
+impl LiveWatch {
+    /// Creates an empty watch that reads its locations every `interval`.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_poll: None,
+            locations: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// The time between two reads of the locations.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Changes the time between two reads of the locations.
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// Watches `size` bytes at `address`, which are reported under `name`.
+    pub fn add(&mut self, name: impl Into<String>, address: u64, size: usize) -> WatchId {
+        let id = WatchId(self.next_id);
+        self.next_id += 1;
+        self.locations.push(WatchedLocation {
+            id,
+            name: name.into(),
+            address,
+            size,
+            value: None,
+        });
+        id
+    }
+
+    /// Watches the variable of the given symbol of an ELF file, with the size of the symbol.
+    pub fn add_symbol(&mut self, elf: &[u8], symbol: &str) -> Result<WatchId, LiveWatchError> {
+        let file = object::File::parse(elf)?;
+        let symbol_entry = file
+            .symbols()
+            .find(|entry| entry.name() == Ok(symbol))
+            .ok_or_else(|| LiveWatchError::SymbolNotFound(symbol.to_string()))?;
+        if symbol_entry.size() == 0 {
+            return Err(LiveWatchError::UnsizedSymbol(symbol.to_string()));
+        }
+        Ok(self.add(symbol, symbol_entry.address(), symbol_entry.size() as usize))
+    }
+
+    /// Stops watching a location. Returns `false` if it was not watched.
+    pub fn remove(&mut self, id: WatchId) -> bool {
+        let count = self.locations.len();
+        self.locations.retain(|location| location.id != id);
+        self.locations.len() != count
+    }
+
+    /// The value of a location at the last poll, or `None` if it was not read yet.
+    pub fn value(&self, id: WatchId) -> Option<&[u8]> {
+        self.locations
+            .iter()
+            .find(|location| location.id == id)
+            .and_then(|location| location.value.as_deref())
+    }
+
+    /// The time until the next poll reads the locations.
+    pub fn time_to_next_poll(&self) -> Duration {
+        match self.last_poll {
+            Some(last_poll) => self.interval.saturating_sub(last_poll.elapsed()),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Reads all locations if the interval elapsed since the last read, and returns those that
+    /// changed. Returns nothing if it is too early.
+    ///
+    /// The first read of a location is reported as a change without a previous value.
+    pub fn poll(
+        &mut self,
+        memory: &mut (impl MemoryInterface + ?Sized),
+    ) -> Result<Vec<WatchChange>, LiveWatchError> {
+        if !self.time_to_next_poll().is_zero() {
+            return Ok(Vec::new());
+        }
+        self.last_poll = Some(Instant::now());
+
+        let mut changes = Vec::new();
+        for location in &mut self.locations {
+            let mut value = vec![0; location.size];
+            memory.read(location.address, &mut value)?;
+            let time = Instant::now();
+            if location.value.as_ref() == Some(&value) {
+                continue;
+            }
+            let previous = location.value.replace(value.clone());
+            changes.push(WatchChange {
+                id: location.id,
+                name: location.name.clone(),
+                address: location.address,
+                previous,
+                value,
+                time,
+            });
+        }
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::MockMemory;
+
+    #[test]
+    fn reports_changes() {
+        let mut watch = LiveWatch::new(Duration::ZERO);
+        let counter = watch.add("counter", 0x2000_0000, 4);
+        let flag = watch.add("flag", 0x2000_0004, 1);
+
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![1, 0, 0, 0, 7, 0, 0, 0]);
+        let changes = watch.poll(&mut memory).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].id, counter);
+        assert_eq!(changes[0].previous, None);
+        assert_eq!(changes[1].value, vec![7]);
+
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![2, 0, 0, 0, 7, 0, 0, 0]);
+        let changes = watch.poll(&mut memory).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].name, "counter");
+        assert_eq!(changes[0].previous, Some(vec![1, 0, 0, 0]));
+        assert_eq!(changes[0].value, vec![2, 0, 0, 0]);
+        assert_eq!(watch.value(flag), Some(&[7][..]));
+
+        assert!(watch.remove(counter));
+        assert!(!watch.remove(counter));
+        assert!(watch.poll(&mut memory).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rate_limited() {
+        let mut watch = LiveWatch::new(Duration::from_secs(3600));
+        watch.add("counter", 0x2000_0000, 4);
+
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![1, 0, 0, 0]);
+        assert_eq!(watch.poll(&mut memory).unwrap().len(), 1);
+
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![2, 0, 0, 0]);
+        assert!(watch.poll(&mut memory).unwrap().is_empty());
+        assert!(!watch.time_to_next_poll().is_zero());
+    }
+}
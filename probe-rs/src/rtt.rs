@@ -180,7 +180,12 @@ impl RttControlBlockHeader {
         self.header_size() + channel_size * total_number_of_channels
     }
 
-    pub fn parse_channel_buffers(&self, mem: &[u8]) -> Result<Vec<RttChannelBuffer>, Error> {
+    /// Parses the channel buffers from words read in the byte order of the target.
+    pub fn parse_channel_buffers(
+        &self,
+        mem: &[u8],
+        big_endian: bool,
+    ) -> Result<Vec<RttChannelBuffer>, Error> {
         let buffers = match self {
             RttControlBlockHeader::Header32(_) => {
                 <[RttChannelBufferInner<u32>]>::ref_from_bytes(mem)
@@ -195,7 +200,7 @@ impl RttControlBlockHeader {
                     .map_err(|_| Error::ControlBlockNotFound)?
                     .iter()
                     .cloned()
-                    .map(RttChannelBuffer::from)
+                    .map(|buffer| RttChannelBuffer::from(buffer.with_target_byte_order(big_endian)))
                     .collect::<Vec<RttChannelBuffer>>()
             }
         };
@@ -227,6 +232,7 @@ impl Rtt {
         ptr: u64,
     ) -> Result<Rtt, Error> {
         let is_64_bit = core.is_64_bit();
+        let is_big_endian = core.is_big_endian();
 
         let mut mem = [0u32; RttControlBlockHeader::minimal_header_size() / 4];
         // Read the magic value first as unordered data, and read the subsequent pointers
@@ -274,12 +280,14 @@ impl Rtt {
         let up_channels_start = 0;
         let up_channels_len = max_up_channels * channel_buffer_size;
         let up_channels_raw_buffer = &mem.as_bytes()[up_channels_start..][..up_channels_len];
-        let up_channels_buffer = rtt_header.parse_channel_buffers(up_channels_raw_buffer)?;
+        let up_channels_buffer =
+            rtt_header.parse_channel_buffers(up_channels_raw_buffer, is_big_endian)?;
 
         let down_channels_start = up_channels_start + up_channels_len;
         let down_channels_len = max_down_channels * channel_buffer_size;
         let down_channels_raw_buffer = &mem.as_bytes()[down_channels_start..][..down_channels_len];
-        let down_channels_buffer = rtt_header.parse_channel_buffers(down_channels_raw_buffer)?;
+        let down_channels_buffer =
+            rtt_header.parse_channel_buffers(down_channels_raw_buffer, is_big_endian)?;
 
         let mut offset = ptr + rtt_header.header_size() as u64 + up_channels_start as u64;
         for (channel_index, buffer) in up_channels_buffer.into_iter().enumerate() {
@@ -551,4 +559,22 @@ mod test {
             "Multiple control blocks found in target memory: 0x00002000, 0x00003000."
         );
     }
+
+    #[test]
+    fn parse_64_bit_channel_buffers_of_big_endian_target() {
+        let header = RttControlBlockHeader::try_from_header(true, &[0; 24]).unwrap();
+        // The words of the pointers are stored most significant word first.
+        let words: [u32; 8] = [0x1, 0x2000_0000, 0x3, 0x4000_0000, 1024, 0, 0, 0];
+
+        let buffers = header
+            .parse_channel_buffers(words.as_bytes(), true)
+            .unwrap();
+
+        assert_eq!(buffers[0].buffer_start_pointer(), 0x3_4000_0000);
+        assert_eq!(
+            buffers[0].standard_name_pointer().map(|ptr| ptr.get()),
+            Some(0x1_2000_0000)
+        );
+        assert_eq!(buffers[0].size_of_buffer(), 1024);
+    }
 }
@@ -49,6 +49,18 @@ impl<T> RttChannelBufferInner<T> {
     }
 }
 
+impl RttChannelBufferInner<u64> {
+    /// Corrects the pointers for the byte order of the target. They are read as two words each,
+    /// which are combined in the byte order of the host.
+    pub fn with_target_byte_order(mut self, big_endian: bool) -> Self {
+        if big_endian != cfg!(target_endian = "big") {
+            self.standard_name_pointer = self.standard_name_pointer.rotate_left(32);
+            self.buffer_start_pointer = self.buffer_start_pointer.rotate_left(32);
+        }
+        self
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) enum RttChannelBuffer {
     Buffer32(RttChannelBufferInner<u32>),
@@ -120,31 +132,25 @@ impl RttChannelBuffer {
         ptr: u64,
         buffer_ptr: u64,
     ) -> Result<(), Error> {
-        match self {
-            RttChannelBuffer::Buffer32(h32) => {
-                core.write_word_32(
-                    ptr + h32.write_buffer_ptr_offset() as u64,
-                    buffer_ptr.try_into().unwrap(),
-                )?;
-            }
-            RttChannelBuffer::Buffer64(h64) => {
-                core.write_word_64(ptr + h64.write_buffer_ptr_offset() as u64, buffer_ptr)?;
-            }
+        // The offsets are 32 bits wide in both layouts.
+        let offset = match self {
+            RttChannelBuffer::Buffer32(h32) => h32.write_buffer_ptr_offset(),
+            RttChannelBuffer::Buffer64(h64) => h64.write_buffer_ptr_offset(),
         };
+        core.write_word_32(ptr + offset as u64, buffer_ptr.try_into().unwrap())?;
         Ok(())
     }
 
     fn write_read_buffer_ptr_operation(&self, ptr: u64, buffer_ptr: u64) -> Operation<'_> {
-        match self {
-            RttChannelBuffer::Buffer32(h32) => Operation::new(
-                ptr + h32.read_buffer_ptr_offset() as u64,
-                OperationKind::WriteWord32(buffer_ptr.try_into().unwrap()),
-            ),
-            RttChannelBuffer::Buffer64(h64) => Operation::new(
-                ptr + h64.read_buffer_ptr_offset() as u64,
-                OperationKind::WriteWord64(buffer_ptr),
-            ),
-        }
+        // The offsets are 32 bits wide in both layouts.
+        let offset = match self {
+            RttChannelBuffer::Buffer32(h32) => h32.read_buffer_ptr_offset(),
+            RttChannelBuffer::Buffer64(h64) => h64.read_buffer_ptr_offset(),
+        };
+        Operation::new(
+            ptr + offset as u64,
+            OperationKind::WriteWord32(buffer_ptr.try_into().unwrap()),
+        )
     }
 
     pub fn read_flags(&self, core: &mut Core, ptr: u64) -> Result<u64, Error> {
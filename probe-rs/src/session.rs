@@ -1,5 +1,5 @@
 use crate::{
-    Core, CoreType, Error, MemoryInterface,
+    Core, CoreInterface, CoreType, Error, MemoryInterface,
     architecture::{
         arm::{
             ArmError, SwoReader,
@@ -10,8 +10,20 @@ use crate::{
             sequences::{ArmDebugSequence, DefaultArmSequence},
         },
         leon3::{
+            Leon3,
             ahbjtag::AhbJtag,
-            communication_interface::{Leon3CommunicationInterface, Leon3DebugInterfaceState},
+            ahbuart::AhbUart,
+            communication_interface::{
+                DebugUnit, DsuCoreGroup, Leon3CommunicationInterface, Leon3DebugInterfaceState,
+                Leon3Error,
+            },
+            dsu3,
+            edcl::Edcl,
+            pci::PciBus,
+            plugnplay::{PlugnPlayState, Record},
+            prom::{PromProgress, PromWriteOptions},
+            sequences::Leon3DebugSequence,
+            usbdcl::UsbDcl,
         },
         riscv::communication_interface::{
             RiscvCommunicationInterface, RiscvDebugInterfaceState, RiscvError,
@@ -20,7 +32,10 @@ use crate::{
             XtensaCommunicationInterface, XtensaDebugInterfaceState, XtensaError,
         },
     },
-    config::{CoreExt, DebugSequence, RegistryError, Target, TargetSelector, registry::Registry},
+    config::{
+        CoreExt, DebugSequence, MemoryRange, RegistryError, Target, TargetSelector,
+        registry::Registry,
+    },
     core::{Architecture, CombinedCoreState},
     memory::CoreMemoryInterface,
     probe::{
@@ -29,7 +44,7 @@ use crate::{
     },
 };
 use std::ops::DerefMut;
-use std::{fmt, sync::Arc, time::Duration};
+use std::{fmt, ops::Range, sync::Arc, time::Duration};
 
 /// The `Session` struct represents an active debug session.
 ///
@@ -104,6 +119,10 @@ impl fmt::Debug for JtagInterface {
 #[derive(Debug)]
 pub enum BusAccess {
     AhbJtag(AhbJtag),
+    AhbUart(AhbUart),
+    Edcl(Edcl),
+    Pci(PciBus),
+    UsbDcl(UsbDcl),
 }
 
 impl BusAccess {
@@ -114,9 +133,44 @@ impl BusAccess {
         Ok(Self::AhbJtag(AhbJtag::new(probe, config)))
     }
 
-    fn as_probe(&mut self) -> &mut Probe {
+    fn new_ahbuart(port: &str) -> Result<Self, Error> {
+        let ahbuart = AhbUart::open(port).map_err(Leon3Error::from)?;
+        Ok(Self::AhbUart(ahbuart))
+    }
+
+    fn new_edcl(address: &str) -> Result<Self, Error> {
+        let edcl = Edcl::connect(address).map_err(Leon3Error::from)?;
+        Ok(Self::Edcl(edcl))
+    }
+
+    fn new_pci(config: &probe_rs_target::Leon3PciAccess) -> Result<Self, Error> {
+        let pci = PciBus::open(config).map_err(Leon3Error::from)?;
+        Ok(Self::Pci(pci))
+    }
+
+    fn new_usbdcl(selector: &str) -> Result<Self, Error> {
+        let usbdcl = UsbDcl::open(selector).map_err(Leon3Error::from)?;
+        Ok(Self::UsbDcl(usbdcl))
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
         match self {
-            BusAccess::AhbJtag(ahb_jtag) => ahb_jtag.as_probe(),
+            BusAccess::AhbJtag(ahb_jtag) => ahb_jtag.set_timeout(timeout),
+            BusAccess::AhbUart(ahbuart) => ahbuart.set_timeout(timeout),
+            BusAccess::Edcl(edcl) => edcl.set_timeout(timeout),
+            // Memory mapped accesses complete synchronously.
+            BusAccess::Pci(_) => {}
+            BusAccess::UsbDcl(usbdcl) => usbdcl.set_timeout(timeout),
+        }
+    }
+
+    pub(crate) fn as_probe(&mut self) -> Option<&mut Probe> {
+        match self {
+            BusAccess::AhbJtag(ahb_jtag) => Some(ahb_jtag.as_probe()),
+            BusAccess::AhbUart(_)
+            | BusAccess::Edcl(_)
+            | BusAccess::Pci(_)
+            | BusAccess::UsbDcl(_) => None,
         }
     }
 }
@@ -127,18 +181,26 @@ impl CoreMemoryInterface for BusAccess {
     fn memory(&self) -> &dyn MemoryInterface<Self::ErrorType> {
         match self {
             BusAccess::AhbJtag(ahb_jtag) => ahb_jtag,
+            BusAccess::AhbUart(ahbuart) => ahbuart,
+            BusAccess::Edcl(edcl) => edcl,
+            BusAccess::Pci(pci) => pci,
+            BusAccess::UsbDcl(usbdcl) => usbdcl,
         }
     }
 
     fn memory_mut(&mut self) -> &mut dyn MemoryInterface<Self::ErrorType> {
         match self {
             BusAccess::AhbJtag(ahb_jtag) => ahb_jtag,
+            BusAccess::AhbUart(ahbuart) => ahbuart,
+            BusAccess::Edcl(edcl) => edcl,
+            BusAccess::Pci(pci) => pci,
+            BusAccess::UsbDcl(usbdcl) => usbdcl,
         }
     }
 }
 
 enum SystemBusInterface {
-    Leon3(Leon3DebugInterfaceState),
+    Leon3(Box<Leon3DebugInterfaceState>),
 }
 
 impl SystemBusInterface {
@@ -525,27 +587,248 @@ impl Session {
     }
 
     fn attach_system_bus(
-        mut probe: Probe,
-        target: Target,
-        _attach_method: AttachMethod,
+        probe: Probe,
+        mut target: Target,
+        attach_method: AttachMethod,
         _permissions: Permissions,
-        cores: Vec<CombinedCoreState>,
+        mut cores: Vec<CombinedCoreState>,
     ) -> Result<Self, Error> {
-        // currently only AHBJTAG is supported, but others may be added in the future
-        // e.g., Ethernet via EDCL.
-        let Some(jtag) = target.jtag.as_ref() else {
-            return Err(Error::Other(
-                "System bus interface requires a JTAG target configuration".into(),
-            ));
+        let options = target
+            .cores
+            .iter()
+            .find_map(|core| match &core.core_access_options {
+                probe_rs_target::CoreAccessOptions::Leon3(options) => {
+                    Some(options.as_ref().clone())
+                }
+                _ => None,
+            });
+
+        let interfaces = match target.architecture() {
+            Architecture::Sparc => {
+                let (bus_access, mut iface) = Self::attach_leon3_debug_link(
+                    probe,
+                    &target,
+                    &cores,
+                    options.as_ref(),
+                    attach_method,
+                )?;
+                Self::add_leon3_cores(&mut target, &mut cores, iface.num_cores())?;
+                let dsu_of_core: Vec<usize> = target
+                    .cores
+                    .iter()
+                    .map(|core| match &core.core_access_options {
+                        probe_rs_target::CoreAccessOptions::Leon3(options) => {
+                            options.dsu.unwrap_or(0)
+                        }
+                        _ => 0,
+                    })
+                    .collect();
+                iface.map_cores(&dsu_of_core)?;
+                Self::add_plugnplay_memory_regions(&mut target, iface.plugnplay());
+                for core in &mut cores {
+                    core.set_leon3_code_ranges(&target);
+                    core.apply_leon3_options();
+                }
+                ArchitectureInterface::SystemBus(
+                    bus_access,
+                    SystemBusInterface::Leon3(Box::new(iface)),
+                )
+            }
+            arch => {
+                return Err(Error::Probe(DebugProbeError::Other(format!(
+                    "Unsupported system bus core architecture {arch:?}",
+                ))));
+            }
+        };
+
+        let mut session = Session {
+            target,
+            interfaces,
+            cores,
+            configured_trace_sink: None,
+        };
+
+        if attach_method == AttachMethod::UnderReset {
+            // The system bus is held in reset together with the cores, so they can only be
+            // halted once the reset is released. Put them back at the reset address, so the
+            // firmware starts from the beginning when they are resumed.
+            for (core_id, _) in session.list_cores() {
+                session.get_leon3_core(core_id)?.halt_at_reset()?;
+            }
+        }
+
+        Ok(session)
+    }
+
+    /// Open the first LEON3 debug link through which the debug support unit responds.
+    ///
+    /// The links of the `debug_links` core access option are tried by priority. Without
+    /// them, the single link selected by the `edcl`, `pci` or `usbdcl` options is used,
+    /// falling back to AHBJTAG.
+    fn attach_leon3_debug_link(
+        probe: Probe,
+        target: &Target,
+        cores: &[CombinedCoreState],
+        options: Option<&probe_rs_target::Leon3CoreAccessOptions>,
+        attach_method: AttachMethod,
+    ) -> Result<(BusAccess, Leon3DebugInterfaceState), Error> {
+        let links = Self::leon3_debug_links(options);
+        let debug_unit = match &target.debug_sequence {
+            DebugSequence::Leon3(sequence) => sequence.debug_unit(),
+            _ => None,
         };
-        let Some(ahbjtag_config) = jtag.ahbjtag.as_ref() else {
+
+        // Only AHBJTAG needs the probe.
+        let mut probe = Some(probe);
+        let mut last_error = None;
+        for (index, link) in links.iter().enumerate() {
+            tracing::debug!("Trying LEON3 debug link {link:?}");
+            let result = match link {
+                probe_rs_target::Leon3DebugLinkKind::Ahbjtag => match probe.take() {
+                    Some(probe) => Self::attach_ahbjtag_link(
+                        probe,
+                        target,
+                        cores,
+                        options,
+                        debug_unit,
+                        attach_method,
+                    ),
+                    None => Err(Error::Other(
+                        "The probe was already used by another AHBJTAG debug link".into(),
+                    )),
+                },
+                // Only the probe can hold the system in reset.
+                _ if attach_method == AttachMethod::UnderReset => {
+                    Err(Leon3Error::ProbeResetUnavailable.into())
+                }
+                probe_rs_target::Leon3DebugLinkKind::Ahbuart(port) => BusAccess::new_ahbuart(port),
+                probe_rs_target::Leon3DebugLinkKind::Edcl(address) => BusAccess::new_edcl(address),
+                probe_rs_target::Leon3DebugLinkKind::Pci(pci) => BusAccess::new_pci(pci),
+                probe_rs_target::Leon3DebugLinkKind::Usbdcl(device) => {
+                    BusAccess::new_usbdcl(device)
+                }
+            }
+            .and_then(|mut bus_access| {
+                let iface = Leon3DebugInterfaceState::try_attach(
+                    &mut bus_access,
+                    debug_unit,
+                    options.and_then(|options| options.dsu_base),
+                    options.and_then(|options| options.plugnplay_base),
+                )?;
+                Ok((bus_access, iface))
+            });
+
+            match result {
+                Ok(attached) => return Ok(attached),
+                Err(error) if index + 1 < links.len() => {
+                    tracing::warn!("LEON3 debug link {link:?} is not usable: {error}");
+                    last_error = Some(error);
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| Error::Other("No LEON3 debug link configured".into())))
+    }
+
+    /// Open the AHBJTAG debug link.
+    ///
+    /// When attaching under reset, the reset line is asserted before the probe connects to
+    /// the JTAG chain and released once the connection is established. If the address of the
+    /// debug support unit is known from `dsu_base` or `debug_unit`, the first bus access after
+    /// the release halts all processors, so the firmware does not run during the plug&play
+    /// scan.
+    fn attach_ahbjtag_link(
+        mut probe: Probe,
+        target: &Target,
+        cores: &[CombinedCoreState],
+        options: Option<&probe_rs_target::Leon3CoreAccessOptions>,
+        debug_unit: Option<(DebugUnit, u64)>,
+        attach_method: AttachMethod,
+    ) -> Result<BusAccess, Error> {
+        if attach_method == AttachMethod::UnderReset {
+            probe.target_reset_assert()?;
+        }
+
+        let mut bus_access = Self::attach_ahbjtag(probe, target, cores, options)?;
+        if let Some(timeout) = options.and_then(|o| o.ahbjtag_timeout) {
+            bus_access.set_timeout(Duration::from_millis(u64::from(timeout)));
+        }
+
+        if attach_method == AttachMethod::UnderReset
+            && let Some(probe) = bus_access.as_probe()
+        {
+            probe.target_reset_deassert()?;
+
+            let dsu_base = options
+                .and_then(|options| options.dsu_base)
+                .or(debug_unit.map(|(_, base)| base));
+            match dsu_base {
+                Some(base) => dsu3::break_now(&mut bus_access, base, target.cores.len())?,
+                None => tracing::warn!(
+                    "The DSU address is not known before the plug&play scan, so the firmware \
+                    runs until the cores are halted"
+                ),
+            }
+        }
+        Ok(bus_access)
+    }
+
+    /// The LEON3 debug links to try, in order.
+    fn leon3_debug_links(
+        options: Option<&probe_rs_target::Leon3CoreAccessOptions>,
+    ) -> Vec<probe_rs_target::Leon3DebugLinkKind> {
+        if let Some(options) = options
+            && !options.debug_links.is_empty()
+        {
+            let mut links = options.debug_links.clone();
+            // The sort is stable, which keeps the listed order for the same priority.
+            links.sort_by_key(|link| std::cmp::Reverse(link.priority));
+            return links.into_iter().map(|link| link.link).collect();
+        }
+
+        let link = if let Some(edcl) = options.and_then(|o| o.edcl.clone()) {
+            probe_rs_target::Leon3DebugLinkKind::Edcl(edcl)
+        } else if let Some(pci) = options.and_then(|o| o.pci.clone()) {
+            probe_rs_target::Leon3DebugLinkKind::Pci(pci)
+        } else if let Some(usbdcl) = options.and_then(|o| o.usbdcl.clone()) {
+            probe_rs_target::Leon3DebugLinkKind::Usbdcl(usbdcl)
+        } else {
+            probe_rs_target::Leon3DebugLinkKind::Ahbjtag
+        };
+        vec![link]
+    }
+
+    /// Set up the probe's JTAG scan chain and open the AHBJTAG system bus access.
+    ///
+    /// All cores share the AHB bus, so they have to use the same AHBJTAG TAP. Its
+    /// instruction codes may be overridden in the core access options.
+    fn attach_ahbjtag(
+        mut probe: Probe,
+        target: &Target,
+        cores: &[CombinedCoreState],
+        options: Option<&probe_rs_target::Leon3CoreAccessOptions>,
+    ) -> Result<BusAccess, Error> {
+        let jtag = target.jtag.as_ref();
+        let Some(ahbjtag_config) = options
+            .and_then(|options| options.ahbjtag.as_ref())
+            .or_else(|| jtag.and_then(|jtag| jtag.ahbjtag.as_ref()))
+        else {
             return Err(Error::Other(
                 "System bus interface requires an AHBJTAG target configuration".into(),
             ));
         };
 
+        let tap = cores.first().map_or(0, |core| core.jtag_tap_index());
+        if let Some(core) = cores.iter().find(|core| core.jtag_tap_index() != tap) {
+            return Err(Error::Other(format!(
+                "All LEON3 cores have to use the same AHBJTAG TAP, but core {} uses TAP {} instead of {tap}",
+                core.id(),
+                core.jtag_tap_index()
+            )));
+        }
+
         // TODO(darsor): much of this is copied from attach_jtag. Pull out into separate method?
-        if let Some(scan_chain) = jtag.scan_chain.clone()
+        if let Some(scan_chain) = jtag.and_then(|jtag| jtag.scan_chain.clone())
             && let Some(probe) = probe.try_as_jtag_probe()
         {
             probe.set_scan_chain(&scan_chain)?;
@@ -556,30 +839,79 @@ impl Session {
             && let Ok(chain) = probe.scan_chain()
             && !chain.is_empty()
         {
-            for core in &cores {
-                probe.select_target(core.jtag_tap_index())?;
+            if tap >= chain.len() {
+                return Err(Error::Other(format!(
+                    "AHBJTAG TAP {tap} is not on the scan chain, which has {} TAPs",
+                    chain.len()
+                )));
             }
+            tracing::debug!("Using AHBJTAG TAP {tap} of {}", chain.len());
+            probe.select_target(tap)?;
         }
 
-        let interfaces = match target.architecture() {
-            Architecture::Sparc => {
-                let mut bus_access = BusAccess::new_ahbjtag(probe, ahbjtag_config.clone())?;
-                let iface = Leon3DebugInterfaceState::try_attach(&mut bus_access)?;
-                ArchitectureInterface::SystemBus(bus_access, SystemBusInterface::Leon3(iface))
-            }
-            arch => {
-                return Err(Error::Probe(DebugProbeError::Other(format!(
-                    "Unsupported system bus core architecture {arch:?}",
-                ))));
-            }
+        BusAccess::new_ahbjtag(probe, ahbjtag_config.clone())
+    }
+
+    /// Match the cores of the target description with the `num_cores` LEON3 processors found
+    /// on the target. Processors missing in the target description are added as copies of its
+    /// last core.
+    fn add_leon3_cores(
+        target: &mut Target,
+        cores: &mut Vec<CombinedCoreState>,
+        num_cores: usize,
+    ) -> Result<(), Error> {
+        if num_cores == 0 {
+            tracing::warn!("No LEON3 processors found in the plug&play area");
+            return Ok(());
+        }
+        if cores.len() > num_cores {
+            return Err(Error::Other(format!(
+                "The target description has {} cores, but only {num_cores} LEON3 processors were found",
+                cores.len()
+            )));
+        }
+
+        let Some(template) = target.cores.last().cloned() else {
+            return Ok(());
         };
+        for id in cores.len()..num_cores {
+            tracing::info!("Adding LEON3 processor {id} that is missing in the target description");
+            let core = probe_rs_target::Core {
+                name: format!("core{id}"),
+                ..template.clone()
+            };
+            cores.push(Core::create_state(
+                id,
+                core.core_access_options.clone(),
+                target,
+                core.core_type,
+            ));
+            target.cores.push(core);
+        }
+        Ok(())
+    }
 
-        Ok(Session {
-            target,
-            interfaces,
-            cores,
-            configured_trace_sink: None,
-        })
+    /// Add the RAM and ROM areas of the memory controllers and the I/O areas found in the
+    /// plug&play area to the memory map of the target. Regions of the target description take
+    /// precedence over overlapping regions found in the plug&play area.
+    fn add_plugnplay_memory_regions(target: &mut Target, plugnplay: &PlugnPlayState) {
+        let cores: Vec<String> = target.cores.iter().map(|core| core.name.clone()).collect();
+        for region in plugnplay
+            .memory_regions(&cores)
+            .into_iter()
+            .chain(plugnplay.io_regions(&cores))
+        {
+            let range = region.address_range();
+            if target
+                .memory_map
+                .iter()
+                .any(|existing| existing.address_range().intersects_range(&range))
+            {
+                continue;
+            }
+            tracing::info!("Adding memory region {region:x?} found in the plug&play area");
+            target.memory_map.push(region);
+        }
     }
 
     /// Automatically open a probe with the given session config.
@@ -646,6 +978,10 @@ impl Session {
         &mut self,
         f: impl FnOnce(&mut Self) -> Result<R, Error>,
     ) -> Result<R, Error> {
+        if self.is_leon3() {
+            return self.leon3_halted_access(f);
+        }
+
         let mut resume_state = vec![];
         for (core, _) in self.list_cores() {
             let mut c = match self.core(core) {
@@ -807,6 +1143,169 @@ impl Session {
         Err(XtensaError::NoXtensaTarget.into())
     }
 
+    /// Get the LEON3 core interface, for functionality that is specific to LEON3 cores.
+    pub fn get_leon3_core(&mut self, core_id: usize) -> Result<Leon3<'_>, Error> {
+        let combined_state = self
+            .cores
+            .get_mut(core_id)
+            .ok_or(Error::CoreNotFound(core_id))?;
+        if let ArchitectureInterface::SystemBus(probe, SystemBusInterface::Leon3(state)) =
+            &mut self.interfaces
+        {
+            let iface = Leon3CommunicationInterface::try_attach(core_id, probe, state)?;
+            return combined_state.leon3_core(iface);
+        }
+        Err(Leon3Error::NoLeon3Target.into())
+    }
+
+    /// Set how long to wait for a single word transaction on the system bus, overriding the
+    /// `ahbjtag_timeout` of the target description.
+    pub fn set_system_bus_timeout(&mut self, timeout: Duration) -> Result<(), Error> {
+        if let ArchitectureInterface::SystemBus(bus_access, _) = &mut self.interfaces {
+            bus_access.set_timeout(timeout);
+            return Ok(());
+        }
+        Err(Leon3Error::NoLeon3Target.into())
+    }
+
+    /// Get the debug support units of a LEON3 target, with the cores each of them controls.
+    pub fn leon3_core_groups(&self) -> Result<Vec<DsuCoreGroup>, Error> {
+        if let ArchitectureInterface::SystemBus(_, SystemBusInterface::Leon3(state)) =
+            &self.interfaces
+        {
+            return Ok(state.core_groups());
+        }
+        Err(Leon3Error::NoLeon3Target.into())
+    }
+
+    /// Get the AMBA plug&play records of a LEON3 target, which describe the devices of the
+    /// GRLIB design.
+    pub fn plugnplay_records(&self) -> Result<&[Record], Error> {
+        if let ArchitectureInterface::SystemBus(_, SystemBusInterface::Leon3(state)) =
+            &self.interfaces
+        {
+            return Ok(state.plugnplay().records());
+        }
+        Err(Leon3Error::NoLeon3Target.into())
+    }
+
+    /// Make all LEON3 cores halt together: when one core enters debug mode, for example
+    /// because of a breakpoint, the DSU forces all other cores into debug mode as well.
+    pub fn set_leon3_synchronous_halt(&mut self, enabled: bool) -> Result<(), Error> {
+        for (core_id, _) in self.list_cores() {
+            let mut core = self.get_leon3_core(core_id)?;
+            core.set_halt_with_other_cores(enabled)?;
+            core.set_halt_other_cores(enabled)?;
+        }
+        Ok(())
+    }
+
+    /// Use a user-provided debug sequence for all LEON3 cores, for custom designs like FPGA
+    /// systems that need special handling.
+    ///
+    /// The sequence replaces the built-in one selected from the target description. Hooks
+    /// used while attaching, like [`Leon3DebugSequence::debug_unit`], only take effect if the
+    /// sequence is set as the [`Target::debug_sequence`] before attaching.
+    pub fn set_leon3_debug_sequence(
+        &mut self,
+        sequence: Arc<dyn Leon3DebugSequence>,
+    ) -> Result<(), Error> {
+        if !self.is_leon3() {
+            return Err(Leon3Error::NoLeon3Target.into());
+        }
+        self.target.debug_sequence = DebugSequence::Leon3(sequence.clone());
+        for core in &mut self.cores {
+            core.set_leon3_sequence(sequence.clone());
+        }
+        Ok(())
+    }
+
+    fn is_leon3(&self) -> bool {
+        matches!(
+            self.interfaces,
+            ArchitectureInterface::SystemBus(_, SystemBusInterface::Leon3(_))
+        )
+    }
+
+    /// LEON3 version of [`Session::halted_access`], which halts and resumes the cores at the
+    /// same time.
+    fn leon3_halted_access<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let mut resume_state = vec![];
+        for (core, _) in self.list_cores() {
+            if !self.get_leon3_core(core)?.core_halted()? {
+                resume_state.push(core);
+            }
+        }
+        self.halt_leon3_cores(&resume_state, Duration::from_millis(100))?;
+
+        let r = f(self);
+
+        self.resume_leon3_cores(&resume_state)?;
+        r
+    }
+
+    /// Halt the given LEON3 cores at the same time, by requesting break-now for all of them in
+    /// a single DSU write.
+    fn halt_leon3_cores(&mut self, cores: &[usize], timeout: Duration) -> Result<(), Error> {
+        if cores.is_empty() {
+            return Ok(());
+        }
+        tracing::info!("Halting cores {cores:?}...");
+        for &core in cores {
+            self.get_leon3_core(core)?.prepare_halt()?;
+        }
+        self.set_leon3_break_now(cores, true)?;
+        for &core in cores {
+            self.get_leon3_core(core)?.finish_halt(timeout)?;
+        }
+        Ok(())
+    }
+
+    /// Resume the given LEON3 cores at the same time, by clearing break-now for all of them in
+    /// a single DSU write. Cores that are already running are left alone.
+    fn resume_leon3_cores(&mut self, cores: &[usize]) -> Result<(), Error> {
+        let mut halted = vec![];
+        for &core in cores {
+            if self.get_leon3_core(core)?.prepare_run()? {
+                halted.push(core);
+            }
+        }
+        if halted.is_empty() {
+            return Ok(());
+        }
+        tracing::debug!("Resuming cores {halted:?}...");
+        self.set_leon3_break_now(&halted, false)
+    }
+
+    /// Set or clear break-now for the given LEON3 cores, with one write to each debug support
+    /// unit.
+    fn set_leon3_break_now(&mut self, cores: &[usize], enabled: bool) -> Result<(), Error> {
+        for group in self.leon3_core_groups()? {
+            let members: Vec<usize> = group
+                .cores
+                .iter()
+                .copied()
+                .filter(|core| cores.contains(core))
+                .collect();
+            let Some(&first) = members.first() else {
+                continue;
+            };
+            let mut leon3 = self.get_leon3_core(first)?;
+            let local: Vec<usize> = group
+                .cores
+                .iter()
+                .enumerate()
+                .filter(|(_, core)| members.contains(core))
+                .map(|(index, _)| index)
+                .collect();
+            leon3.set_break_now(&local, enabled)?;
+        }
+        Ok(())
+    }
+
     #[tracing::instrument(skip_all)]
     fn reattach_arm_interface(
         interface: &mut Box<dyn ArmDebugInterface>,
@@ -845,12 +1344,94 @@ impl Session {
         Ok(())
     }
 
+    /// Prepare the RAM of a core for downloading an image, for example by initializing the
+    /// SDRAM controller of a LEON3 target. Does nothing for other architectures.
+    pub(crate) fn setup_ram(&mut self, core_index: usize) -> Result<(), Error> {
+        if self.is_leon3() {
+            self.get_leon3_core(core_index)?.setup_ram()?;
+        }
+        Ok(())
+    }
+
+    /// Make data downloaded to the RAM of a core visible to all bus masters, for example by
+    /// writing back the L2 cache lines of a LEON3 target. Does nothing for other architectures.
+    pub(crate) fn flush_ram(
+        &mut self,
+        core_index: usize,
+        ranges: &[Range<u64>],
+    ) -> Result<(), Error> {
+        if !self.is_leon3() {
+            return Ok(());
+        }
+        let mut core = self.get_leon3_core(core_index)?;
+        if core.has_l2_cache() {
+            for range in ranges {
+                core.flush_l2_cache(Some(range.clone()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Program NVM that is programmed by the host without a flash algorithm, like the PROM
+    /// of a LEON3 target. The core must be halted.
+    pub(crate) fn write_host_programmed_nvm(
+        &mut self,
+        core_index: usize,
+        address: u64,
+        data: &[u8],
+        options: PromWriteOptions,
+        progress: impl FnMut(PromProgress),
+    ) -> Result<(), Error> {
+        if !self.is_leon3() {
+            return Err(Error::NotImplemented(
+                "host programming of NVM on non-LEON3 targets",
+            ));
+        }
+        self.get_leon3_core(core_index)?
+            .write_prom_with_progress(address, data, options, progress)
+    }
+
+    /// The erase sectors of NVM that is programmed by the host, overlapping `range`. The core
+    /// must be halted.
+    pub(crate) fn host_programmed_nvm_sectors(
+        &mut self,
+        core_index: usize,
+        range: Range<u64>,
+    ) -> Result<Vec<Range<u64>>, Error> {
+        if !self.is_leon3() {
+            return Err(Error::NotImplemented(
+                "host programming of NVM on non-LEON3 targets",
+            ));
+        }
+        self.get_leon3_core(core_index)?.prom_sectors(range)
+    }
+
+    /// Erase the sectors of NVM that is programmed by the host, overlapping `range`. The core
+    /// must be halted.
+    pub(crate) fn erase_host_programmed_nvm(
+        &mut self,
+        core_index: usize,
+        range: Range<u64>,
+        progress: impl FnMut(PromProgress),
+    ) -> Result<(), Error> {
+        if !self.is_leon3() {
+            return Err(Error::NotImplemented(
+                "host programming of NVM on non-LEON3 targets",
+            ));
+        }
+        self.get_leon3_core(core_index)?
+            .erase_prom_with_progress(range, progress)
+    }
+
     /// This function can be used to set up an application which was flashed to RAM.
     pub fn prepare_running_on_ram(&mut self, vector_table_addr: u64) -> Result<(), crate::Error> {
         match &self.target.debug_sequence.clone() {
             crate::config::DebugSequence::Arm(arm) => {
                 arm.prepare_running_on_ram(vector_table_addr, self)
             }
+            crate::config::DebugSequence::Leon3(leon3) => {
+                leon3.prepare_running_on_ram(vector_table_addr, self)
+            }
             _ => Err(crate::Error::NotImplemented(
                 "ram flash non-ARM architectures",
             )),
@@ -1002,9 +1583,7 @@ impl Session {
                     Architecture::Xtensa
                 }
             }
-            ArchitectureInterface::SystemBus(_, iface) => match iface {
-                SystemBusInterface::Leon3(_) => Architecture::Sparc,
-            },
+            ArchitectureInterface::SystemBus(_, iface) => iface.architecture(),
         }
     }
 
@@ -1025,6 +1604,11 @@ impl Session {
 
     /// Resume all cores
     pub fn resume_all_cores(&mut self) -> Result<(), Error> {
+        if self.is_leon3() {
+            let cores: Vec<usize> = (0..self.cores.len()).collect();
+            return self.resume_leon3_cores(&cores);
+        }
+
         // Resume cores
         for core_id in 0..self.cores.len() {
             match self.core(core_id) {
@@ -1161,3 +1745,48 @@ impl Permissions {
 #[derive(Debug, Clone, thiserror::Error)]
 #[error("An operation could not be performed because it lacked the permission to do so: {0}")]
 pub struct MissingPermissions(pub String);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::architecture::leon3::mock_ahbjtag::{LinkEvent, MockAhbJtag};
+
+    #[test]
+    fn leon3_cores_break_before_plugnplay_scan() {
+        let target = Registry::from_builtin_families()
+            .get_target_by_name("GR740")
+            .unwrap();
+        let link = MockAhbJtag::new();
+
+        Session::attach_leon3_debug_link(
+            link.probe(),
+            &target,
+            &[],
+            None,
+            AttachMethod::UnderReset,
+        )
+        .unwrap();
+
+        let events = link.events();
+        let released = events
+            .iter()
+            .position(|event| *event == LinkEvent::ResetDeassert)
+            .unwrap();
+        assert_eq!(events[..released], [LinkEvent::ResetAssert]);
+        // Break-now for the four processors is the first access, then BW is set for each.
+        assert_eq!(events[released + 1], LinkEvent::Write(0xE000_0020, 0xF));
+        let dsu = 0xE000_0000..0xF000_0000;
+        let scan = events
+            .iter()
+            .position(|event| matches!(event, LinkEvent::Read(addr) if !dsu.contains(addr)))
+            .unwrap();
+        for core_index in 0..4 {
+            let ctrl = 0xE000_0000 + (core_index << 24);
+            let bw_set = events
+                .iter()
+                .position(|event| *event == LinkEvent::Write(ctrl, 0x4))
+                .unwrap();
+            assert!(bw_set < scan);
+        }
+    }
+}
@@ -221,9 +221,27 @@ fn create_core(processor: &Processor) -> Result<ProbeCore> {
             Architecture::Xtensa => {
                 CoreAccessOptions::Xtensa(XtensaCoreAccessOptions { jtag_tap: None })
             }
-            Architecture::Sparc => {
-                CoreAccessOptions::Leon3(Leon3CoreAccessOptions { jtag_tap: None })
-            }
+            Architecture::Sparc => CoreAccessOptions::Leon3(Box::new(Leon3CoreAccessOptions {
+                jtag_tap: None,
+                ahbjtag: None,
+                dsu_base: None,
+                plugnplay_base: None,
+                dsu: None,
+                ahbjtag_timeout: None,
+                edcl: None,
+                pci: None,
+                usbdcl: None,
+                memory_controller_init: vec![],
+                sdram: None,
+                prom: Default::default(),
+                ram_boot: None,
+                debug_links: vec![],
+                debug_sequence: None,
+                reset: Default::default(),
+                reset_address: None,
+                ungate_clocks: vec![],
+                semihosting_trap: None,
+            })),
         },
     })
 }